@@ -0,0 +1,75 @@
+//! Alternate files of a file, like the header belonging to a C source, switched to with `:A`.
+use std::path::{Path, PathBuf};
+
+/// Default of the `alternates` option
+pub const DEFAULT_RULES: &str = "c:h,cc:h,cpp:h,cpp:hpp,cxx:hxx";
+
+/// Returns the paths which may be the alternate of `path`, in the order they are preferred.
+///
+/// `rules` are comma separated pairs of extensions like `c:h`, a file with either extension
+/// alternates with the file of the same name with the other one. Rust modules alternate between
+/// `name.rs` and `name/mod.rs`.
+pub fn candidates(path: &Path, rules: &str) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    let Some(extension) = path.extension().and_then(|extension| extension.to_str()) else {
+        return candidates;
+    };
+
+    if extension == "rs" {
+        if path.file_name().is_some_and(|name| name == "mod.rs") {
+            let module = path
+                .parent()
+                .and_then(|dir| dir.file_name().map(|name| (dir, name)));
+            if let Some((dir, name)) = module {
+                candidates.push(dir.with_file_name(name).with_extension("rs"));
+            }
+        } else {
+            candidates.push(path.with_extension("").join("mod.rs"));
+        }
+    }
+    for (first, second) in rules
+        .split(',')
+        .filter_map(|rule| rule.trim().split_once(':'))
+    {
+        let other = match extension {
+            extension if extension == first => second,
+            extension if extension == second => first,
+            _ => continue,
+        };
+        let candidate = path.with_extension(other);
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(
+            candidates(Path::new("src/main.c"), DEFAULT_RULES),
+            [PathBuf::from("src/main.h")]
+        );
+        assert_eq!(
+            candidates(Path::new("lib/list.h"), DEFAULT_RULES),
+            ["lib/list.c", "lib/list.cc", "lib/list.cpp"].map(PathBuf::from)
+        );
+        assert_eq!(
+            candidates(Path::new("src/buffer.rs"), ""),
+            [PathBuf::from("src/buffer/mod.rs")]
+        );
+        assert_eq!(
+            candidates(Path::new("src/buffer/mod.rs"), ""),
+            [PathBuf::from("src/buffer.rs")]
+        );
+        assert_eq!(
+            candidates(Path::new("notes.txt"), "txt:md"),
+            [PathBuf::from("notes.md")]
+        );
+        assert!(candidates(Path::new("Makefile"), DEFAULT_RULES).is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{LineCol, Modal, Result};
+use crate::{theme::Highlight, LineCol, Modal, Result};
 use crossterm::{
     execute,
     style::{self, Color},
@@ -14,10 +14,15 @@ pub const INFO_BAR_Y_LOCATION: u16 = 1;
 pub const NOTIFICATION_BAR_Y_LOCATION: u16 = 0;
 pub const INFO_BAR_LINEWIDTH_INDICATOR_X_LOCATION_NEGATIVE: u16 = 1;
 pub const INFO_BAR_MODAL_INDICATOR_X_LOCATION: u16 = 1;
+/// Spaces between the keys of a pending command and the cursor position in the info bar
+pub const INFO_BAR_PENDING_COMMAND_GAP: usize = 4;
 pub const NOTIFICATION_BAR_TEXT_X_LOCATION: u16 = 1;
+/// Splits a `statusline` format into the part aligned left and the part aligned right
+pub const STATUS_LINE_SEPARATOR: &str = "%=";
+/// Name shown by `%f` for a buffer without a file
+pub const NO_NAME: &str = "[No Name]";
 pub const DEFAULT_FG: Color = Color::Reset;
 pub const DEFAULT_BG: Color = Color::Reset;
-pub const INFO_BAR_BG: Color = Color::DarkGrey;
 
 pub const NOTIFICATION_BAR: BarInfo = BarInfo::new(
     NOTIFICATION_BAR_Y_LOCATION,
@@ -26,12 +31,15 @@ pub const NOTIFICATION_BAR: BarInfo = BarInfo::new(
     DEFAULT_BG,
 );
 
-pub const INFO_BAR: BarInfo = BarInfo::new(
-    INFO_BAR_Y_LOCATION,
-    INFO_BAR_MODAL_INDICATOR_X_LOCATION,
-    DEFAULT_FG,
-    INFO_BAR_BG,
-);
+/// The info bar drawn in the colors of the theme's `StatusLine`
+pub const fn info_bar(colors: Highlight) -> BarInfo {
+    BarInfo::new(
+        INFO_BAR_Y_LOCATION,
+        INFO_BAR_MODAL_INDICATOR_X_LOCATION,
+        colors.fg,
+        colors.bg,
+    )
+}
 
 pub const COMMAND_BAR: BarInfo =
     BarInfo::new(NOTIFICATION_BAR_Y_LOCATION, 0, DEFAULT_FG, DEFAULT_BG);
@@ -200,6 +208,46 @@ pub fn force_notif_bar_content(s: String) {
     get_debug_messages().lock().unwrap().push_front(s);
 }
 
+/// The file being edited, as shown by the placeholders of a `statusline` format
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileStatus {
+    /// Format of the info bar set with `:set statusline`, empty for the default one
+    pub format: String,
+    pub path: Option<String>,
+    pub filetype: Option<String>,
+    pub modified: bool,
+    pub line_count: usize,
+}
+
+/// Substitutes the placeholders of a `statusline` format with the status of the file and the
+/// cursor at `pos`, counted from one. Unknown placeholders are left as they are.
+fn expand_status_format(format: &str, status: &FileStatus, pos: LineCol) -> String {
+    let mut expanded = String::new();
+    let mut chars = format.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            expanded.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('f') => expanded.push_str(status.path.as_deref().unwrap_or(NO_NAME)),
+            Some('l') => expanded.push_str(&pos.line.to_string()),
+            Some('c') => expanded.push_str(&pos.col.to_string()),
+            Some('m') if status.modified => expanded.push_str("[+]"),
+            Some('m') => (),
+            Some('p') => {
+                let percent = pos.line * 100 / status.line_count.max(1);
+                expanded.push_str(&percent.min(100).to_string());
+            }
+            Some('y') => expanded.extend(status.filetype.as_deref()),
+            Some('%') => expanded.push('%'),
+            Some(other) => expanded.extend(['%', other]),
+            None => expanded.push('%'),
+        }
+    }
+    expanded
+}
+
 /// Draws the information bar at the bottom of the editor.
 ///
 /// This function renders an information bar that displays the current cursor position
@@ -207,9 +255,11 @@ pub fn force_notif_bar_content(s: String) {
 ///
 /// # Display Characteristics
 /// - Location: Positioned `INFO_BAR_Y_LOCATION` lines from the bottom of the terminal.
-/// - Background: Dark grey
-/// - Text Color: White
-/// - Content: Displays the cursor position, starting at `INFO_BAR_LINEWIDTH_INDICATOR_X_LOCATION`
+/// - Colors: The `info_bar` colors of the theme, white on dark grey by default
+/// - Content: Displays the cursor position, starting at `INFO_BAR_LINEWIDTH_INDICATOR_X_LOCATION`,
+///   preceded by the keys of a command still being typed, like vim's `showcmd`. The mode is
+///   followed by `recording @{reg}` while a macro is recorded.
+/// - A `statusline` format replaces the content, the text following `%=` in it aligned right.
 ///
 /// # Returns
 /// `Ok(())` if the info bar is successfully drawn, or an error if any terminal operation fails.
@@ -220,17 +270,43 @@ pub fn force_notif_bar_content(s: String) {
 /// - Cursor movement fails
 /// - Writing to stdout fails
 /// - Color setting or resetting fails
-pub fn get_info_bar_content(term_width: usize, mode: &Modal, pos: LineCol) -> String {
+pub fn get_info_bar_content(
+    term_width: usize,
+    mode: &Modal,
+    recording: Option<char>,
+    pending_command: &str,
+    pos: LineCol,
+    status: &FileStatus,
+) -> String {
     let mut pos = pos;
-    let modal_string = format!("{mode}");
     pos.line += 1;
-    let pos_string = format!("{pos}");
+    let (modal_string, pos_string) = match status.format.split_once(STATUS_LINE_SEPARATOR) {
+        _ if status.format.is_empty() => {
+            let modal_string = match recording {
+                Some(register) => format!("{mode}  recording @{register}"),
+                None => format!("{mode}"),
+            };
+            let pos_string = match pending_command {
+                "" => format!("{pos}"),
+                keys => format!("{keys}{}{pos}", " ".repeat(INFO_BAR_PENDING_COMMAND_GAP)),
+            };
+            (modal_string, pos_string)
+        }
+        Some((left, right)) => (
+            expand_status_format(left, status, pos),
+            expand_status_format(right, status, pos),
+        ),
+        None => (
+            expand_status_format(&status.format, status, pos),
+            String::new(),
+        ),
+    };
 
     let middle_space = term_width
-        - INFO_BAR_MODAL_INDICATOR_X_LOCATION as usize
-        - modal_string.len()
-        - pos_string.len()
-        - INFO_BAR_LINEWIDTH_INDICATOR_X_LOCATION_NEGATIVE as usize;
+        .saturating_sub(INFO_BAR_MODAL_INDICATOR_X_LOCATION as usize)
+        .saturating_sub(modal_string.chars().count())
+        .saturating_sub(pos_string.chars().count())
+        .saturating_sub(INFO_BAR_LINEWIDTH_INDICATOR_X_LOCATION_NEGATIVE as usize);
 
     #[allow(clippy::repeat_once)]
     let loc_neg = " ".repeat(INFO_BAR_LINEWIDTH_INDICATOR_X_LOCATION_NEGATIVE as usize);
@@ -242,3 +318,36 @@ pub fn get_info_bar_content(term_width: usize, mode: &Modal, pos: LineCol) -> St
         loc_neg
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_line_format() {
+        let status = FileStatus {
+            format: "%f%m %y %q%=%l:%c %p%%".to_string(),
+            path: Some("src/main.rs".to_string()),
+            filetype: Some("rust".to_string()),
+            modified: true,
+            line_count: 40,
+        };
+        let pos = LineCol { line: 9, col: 3 };
+        let bar = get_info_bar_content(40, &Modal::Normal, None, "", pos, &status);
+        assert_eq!(
+            bar,
+            format!("src/main.rs[+] rust %q{}10:3 25% ", " ".repeat(8))
+        );
+        assert_eq!(bar.len(), 40 - INFO_BAR_MODAL_INDICATOR_X_LOCATION as usize);
+
+        let status = FileStatus {
+            format: "%f%m".to_string(),
+            ..FileStatus::default()
+        };
+        let bar = get_info_bar_content(20, &Modal::Normal, None, "", pos, &status);
+        assert!(bar.starts_with("[No Name] "));
+
+        let bar = get_info_bar_content(20, &Modal::Normal, None, "", pos, &FileStatus::default());
+        assert!(bar.starts_with("NORMAL ") && bar.ends_with("10:3 "));
+    }
+}
@@ -4,11 +4,11 @@ use crate::{
     editor::Lazy, viewport::FIND_MODE_DIRECTION_SYMBOL_GAP, BaseAction, Component, Error,
     FindDirection, LineCol, Modal, Result,
 };
-use std::{collections::VecDeque, fmt::Debug};
+use std::{collections::VecDeque, fmt::Debug, ops::Range};
 
 /// Trait defining the interface for a text buffer
 #[allow(clippy::module_name_repetitions)]
-pub trait TextBuffer {
+pub trait TextBuffer: Default {
     fn verify_lazy_values<T: Clone>(&self, l: &Lazy<T>) -> Result<T> {
         if l.is_evaluated() {
             Ok(l.clone_inner())
@@ -34,7 +34,9 @@ pub trait TextBuffer {
     /// Delete text in the specified range
     fn delete_selection(&mut self, from: LineCol, to: LineCol) -> Result<()>;
 
-    /// Delete the symbol at the specified position
+    /// Delete the symbol before the specified position, joining the line with the previous one at
+    /// its start. Returns the position the deleted symbol was at, the start of the buffer has
+    /// nothing before it and is returned unchanged.
     fn delete(&mut self, at: LineCol) -> Result<LineCol>;
 
     /// Replace text in the specified range with new text
@@ -63,6 +65,16 @@ pub trait TextBuffer {
     /// Redo the last undone operation
     fn redo(&mut self, at: LineCol) -> Result<()>;
 
+    /// Record the current state of the normal buffer as an undo step before it gets edited
+    fn snapshot(&mut self, at: LineCol);
+
+    /// Start grouping edits into a single undo step, until `end_undo_group`. Only the first edit
+    /// within the group records the state before it. Groups nest, the outermost one is the step.
+    fn begin_undo_group(&mut self);
+
+    /// Stop grouping edits, the following edits record undo steps of their own again
+    fn end_undo_group(&mut self);
+
     /// Get the entire text for the current buffer
     fn get_entire_text(&self) -> &[String];
     /// Get the entire text for the normal buffer
@@ -71,24 +83,37 @@ pub trait TextBuffer {
     /// Get partial window to the normal buffer, ranging from -> to
     fn get_buffer_window(&self, from: Option<LineCol>, to: Option<LineCol>) -> Result<Vec<String>>;
 
-    /// Get the entire text for the terminal buffer
-    fn get_terminal_text(&self) -> &str;
+    /// Get the entire text for the terminal buffer, its last line being the prompt
+    fn get_terminal_text(&self) -> &[String];
+    /// Append lines of shell output to the terminal buffer and start a new, empty prompt line
+    fn append_terminal_output(&mut self, lines: Vec<String>);
     /// Get the entire text for the command buffer
     fn get_command_text(&self) -> &str;
     /// Get the entire text for the command buffer
     fn replace_command_text(&mut self, new: impl Into<String>);
 
-    /// Get maximum line bound for the current buffer
+    /// Get maximum line bound for the normal buffer
     fn max_line(&self) -> usize;
-    /// Get maximum column bound for the current buffer
+    /// Get maximum column bound of a line of the plane currently being edited, which is the
+    /// command line while typing a command or search and the terminal in terminal mode
     fn max_col(&self, at: usize) -> usize;
+    /// Get maximum column bound of a line of the normal buffer, regardless of the plane being
+    /// edited. Positions within the text, like the cursor position kept while typing a command,
+    /// are bound by this one.
     fn max_normal_col(&self, at: usize) -> usize;
     fn is_command_empty(&self) -> bool;
     fn clear_command(&mut self);
     /// Adjust column for Modal requirements
     fn adjust_col(&self, col: usize) -> usize;
     fn max_linecol(&self) -> LineCol;
+    /// Position of the byte `offset` into the normal text as it is written to a file, every line
+    /// ended by a newline. The newline of a line is at its end, `None` if the file is shorter.
+    fn linecol_at_offset(&self, offset: usize) -> Option<LineCol>;
     fn delete_line(&mut self, at: usize);
+    /// Replace the whole normal buffer with new text, discarding its undo history
+    fn load(&mut self, text: Vec<String>);
+    /// Replace a range of lines of the normal buffer with new lines
+    fn splice_lines(&mut self, range: Range<usize>, lines: Vec<String>) -> Result<()>;
     fn get_full_lines_buffer_window(
         &self,
         from: Option<LineCol>,
@@ -125,6 +150,11 @@ impl Stack {
         self.truncate();
     }
 
+    /// Removes all elements from the stack.
+    pub fn clear(&mut self) {
+        self.content.clear();
+    }
+
     /// Checks if the stack is empty.
     /// Returns true if the stack contains no elements, false otherwise.
     pub fn is_empty(&self) -> bool {
@@ -147,6 +177,10 @@ pub struct VecBuffer {
     /// The current state of the normal text buffer, stored as a vector of lines.
     text: Vec<String>,
     /// The current state of the terminal buffer, stored as a vector of lines.
+    ///
+    /// Entered with `:terminal` and left with Esc, its contents persist between visits. The last
+    /// line is the prompt being typed into, commands run in the shell leave the prompt behind
+    /// followed by their output and a fresh prompt line. Edits of this plane are never undone.
     terminal: Vec<String>,
     /// The current state of the command bar buffer, stored as a vector of a single line.
     command: Vec<String>,
@@ -154,6 +188,10 @@ pub struct VecBuffer {
     past: Stack,
     /// Stack to store future states for redo operations.
     future: Stack,
+    /// Whether an undo group is open, and if so whether its first edit recorded an undo step
+    undo_group: Option<bool>,
+    /// Undo groups opened within the open one
+    nested_undo_groups: usize,
     plane: BufferPlane,
 }
 
@@ -163,7 +201,8 @@ enum BufferPlane {
     Normal,
     Terminal,
     Command,
-    Find,
+    /// The command line while typing a search, behind the symbol of its direction
+    Find(FindDirection),
 }
 
 impl Default for VecBuffer {
@@ -174,6 +213,8 @@ impl Default for VecBuffer {
             command: vec![String::new()],
             past: Stack::default(),
             future: Stack::default(),
+            undo_group: None,
+            nested_undo_groups: 0,
             plane: BufferPlane::Normal,
         }
     }
@@ -182,6 +223,18 @@ impl Default for VecBuffer {
 impl<T: TextBuffer + Debug> Component for T {
     #[instrument]
     fn execute_action(&mut self, a: &crate::BaseAction) -> Result<()> {
+        match a {
+            BaseAction::InsertAt(lc, _)
+            | BaseAction::DeleteAt(lc, _)
+            | BaseAction::InsertLineAt(lc, _)
+            | BaseAction::DeleteLineAt(lc, _) => self.snapshot(self.verify_lazy_values(lc)?),
+            BaseAction::ReplaceLines(range, _) => self.snapshot(LineCol {
+                line: range.start,
+                col: 0,
+            }),
+            _ => (),
+        }
+
         match a {
             BaseAction::InsertAt(lc, ch) => self.insert(lc.clone_inner(), *ch),
             BaseAction::DeleteAt(lc, rep) => {
@@ -203,10 +256,26 @@ impl<T: TextBuffer + Debug> Component for T {
                 self.insert_newline(start);
                 Ok(())
             }
+            BaseAction::ReplaceLines(range, lines) => {
+                self.splice_lines(range.clone(), lines.clone())
+            }
+            BaseAction::AppendTerminalOutput(lines) => {
+                self.append_terminal_output(lines.clone());
+                Ok(())
+            }
             BaseAction::ChangeMode(modal) => {
                 self.set_plane(modal);
                 Ok(())
             }
+            // Running out of history is not an error worth interrupting the editor for
+            BaseAction::Undo(steps) => {
+                let _ = (0..*steps).try_for_each(|_| self.undo(LineCol::default()));
+                Ok(())
+            }
+            BaseAction::Redo(steps) => {
+                let _ = (0..*steps).try_for_each(|_| self.redo(LineCol::default()));
+                Ok(())
+            }
             _ => Ok(()),
         }
     }
@@ -220,6 +289,8 @@ impl VecBuffer {
             command: vec![String::new()],
             past: Stack::default(),
             future: Stack::default(),
+            undo_group: None,
+            nested_undo_groups: 0,
             plane: BufferPlane::Normal,
         }
     }
@@ -227,21 +298,21 @@ impl VecBuffer {
         match &self.plane {
             BufferPlane::Normal => &mut self.text,
             BufferPlane::Terminal => &mut self.terminal,
-            BufferPlane::Command | BufferPlane::Find => &mut self.command,
+            BufferPlane::Command | BufferPlane::Find(_) => &mut self.command,
         }
     }
     fn get_buffer(&self) -> &[String] {
         match &self.plane {
             BufferPlane::Normal => &self.text,
             BufferPlane::Terminal => &self.terminal,
-            BufferPlane::Command | BufferPlane::Find => &self.command,
+            BufferPlane::Command | BufferPlane::Find(_) => &self.command,
         }
     }
 }
 
 impl TextBuffer for VecBuffer {
     fn adjust_col(&self, col: usize) -> usize {
-        if matches!(self.plane, BufferPlane::Find) {
+        if matches!(self.plane, BufferPlane::Find(_)) {
             col + FIND_MODE_DIRECTION_SYMBOL_GAP as usize
         } else {
             col
@@ -300,6 +371,34 @@ impl TextBuffer for VecBuffer {
     fn delete_line(&mut self, at: usize) {
         let _ = self.text.remove(at);
     }
+    fn load(&mut self, text: Vec<String>) {
+        self.text = if text.is_empty() {
+            vec![String::new()]
+        } else {
+            text
+        };
+        self.past.clear();
+        self.future.clear();
+    }
+    /// Replaces the lines within `range` of the normal buffer with `lines`.
+    ///
+    /// An empty range inserts the lines before `range.start`, while an empty `lines` deletes the
+    /// range. The buffer always keeps at least one (possibly empty) line.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidLineNumber` if the range reaches past the end of the buffer or is
+    /// reversed.
+    fn splice_lines(&mut self, range: Range<usize>, lines: Vec<String>) -> Result<()> {
+        if range.start > range.end || range.end > self.text.len() {
+            return Err(Error::InvalidLineNumber);
+        }
+        self.text.splice(range, lines);
+        if self.text.is_empty() {
+            self.text.push(String::new());
+        }
+        Ok(())
+    }
     fn clear_command(&mut self) {
         self.command[0] = String::new()
     }
@@ -309,14 +408,20 @@ impl TextBuffer for VecBuffer {
     fn set_plane(&mut self, modal: &Modal) {
         self.plane = match modal {
             Modal::Command => BufferPlane::Command,
+            // The symbol replaces the command line, switching planes never leaves a stale one
             Modal::Find(direction) => {
-                match direction {
-                    FindDirection::Forwards => self.command[0].push('/'),
-                    FindDirection::Backwards => self.command[0].push('?'),
-                };
-                BufferPlane::Find
+                self.replace_command_text(direction.symbol());
+                BufferPlane::Find(*direction)
             }
-            Modal::Normal | Modal::Insert | Modal::Visual | Modal::VisualLine => {
+            Modal::Terminal => {
+                self.clear_command();
+                BufferPlane::Terminal
+            }
+            Modal::Normal
+            | Modal::Insert
+            | Modal::Visual
+            | Modal::VisualLine
+            | Modal::VisualBlock => {
                 self.clear_command();
                 BufferPlane::Normal
             }
@@ -337,13 +442,28 @@ impl TextBuffer for VecBuffer {
         let col = buf[line].len();
         LineCol { line, col }
     }
+    fn linecol_at_offset(&self, offset: usize) -> Option<LineCol> {
+        let mut start = 0;
+        for (line, text) in self.get_normal_text().iter().enumerate() {
+            if offset <= start + text.len() {
+                // Offsets within a character belong to the character
+                let mut col = offset - start;
+                while !text.is_char_boundary(col) {
+                    col -= 1;
+                }
+                return Some(LineCol { line, col });
+            }
+            start += text.len() + 1;
+        }
+        None
+    }
     fn insert_newline(&mut self, at: LineCol) {
         self.get_mut_buffer().insert(at.line + 1, String::new());
     }
     fn insert(&mut self, at: LineCol, ch: char) -> Result<()> {
         match self.plane {
             BufferPlane::Command => self.command[0].insert(at.col, ch),
-            BufferPlane::Find => {
+            BufferPlane::Find(_) => {
                 self.command[0].insert(at.col + FIND_MODE_DIRECTION_SYMBOL_GAP as usize, ch)
             }
             _ => {
@@ -389,6 +509,38 @@ impl TextBuffer for VecBuffer {
         Ok(())
     }
 
+    /// Pushes the current normal text onto the undo stack, invalidating everything undone so far.
+    /// Edits of the command and terminal planes are not tracked.
+    fn snapshot(&mut self, at: LineCol) {
+        if self.plane != BufferPlane::Normal {
+            return;
+        }
+        match &mut self.undo_group {
+            Some(true) => return,
+            Some(recorded) => *recorded = true,
+            None => (),
+        }
+        self.past.push(StateCapsule {
+            content: self.text.clone(),
+            loc: at,
+        });
+        self.future.clear();
+    }
+
+    fn begin_undo_group(&mut self) {
+        match self.undo_group {
+            Some(_) => self.nested_undo_groups += 1,
+            None => self.undo_group = Some(false),
+        }
+    }
+
+    fn end_undo_group(&mut self) {
+        match self.nested_undo_groups {
+            0 => self.undo_group = None,
+            _ => self.nested_undo_groups -= 1,
+        }
+    }
+
     fn len(&self) -> usize {
         // Currently length of the entire file seems unnecessary to implement. If I realize it
         // needs to be implemented it might be as a counter at the level of a struct attribute.
@@ -655,6 +807,10 @@ impl TextBuffer for VecBuffer {
 
         if from.col == 0 && to.col >= buf[to.line].len() {
             buf.drain(from.line..=to.line);
+            // Every plane keeps at least one (possibly empty) line
+            if buf.is_empty() {
+                buf.push(String::new());
+            }
             return Ok(());
         }
 
@@ -687,8 +843,12 @@ impl TextBuffer for VecBuffer {
     fn get_command_text(&self) -> &str {
         &self.command[0]
     }
-    fn get_terminal_text(&self) -> &str {
-        &self.terminal[0]
+    fn get_terminal_text(&self) -> &[String] {
+        &self.terminal
+    }
+    fn append_terminal_output(&mut self, lines: Vec<String>) {
+        self.terminal.extend(lines);
+        self.terminal.push(String::new());
     }
     fn delete(&mut self, mut at: LineCol) -> Result<LineCol> {
         let buf = self.get_mut_buffer();
@@ -697,7 +857,7 @@ impl TextBuffer for VecBuffer {
         }
         if at.col == 0 {
             if at.line == 0 {
-                return Err(Error::ImATeacup);
+                return Ok(at);
             }
 
             let line_content = buf.remove(at.line);
@@ -732,10 +892,60 @@ mod tests {
             },
             command: vec![],
             terminal: vec![],
+            undo_group: None,
+            nested_undo_groups: 0,
             plane: BufferPlane::Normal,
         }
     }
 
+    #[test]
+    fn test_set_plane_keeps_find_direction() {
+        let mut buf = new_test_buffer();
+        buf.command = vec![String::new()];
+        buf.set_plane(&Modal::Find(FindDirection::Forwards));
+        buf.set_plane(&Modal::Find(FindDirection::Forwards));
+        assert_eq!(buf.get_command_text(), "/");
+
+        buf.set_plane(&Modal::Find(FindDirection::Backwards));
+        assert_eq!(buf.plane, BufferPlane::Find(FindDirection::Backwards));
+        assert_eq!(buf.get_command_text(), "?");
+
+        buf.set_plane(&Modal::Normal);
+        assert!(buf.is_command_empty());
+    }
+
+    #[test]
+    fn test_max_col_follows_plane_and_max_normal_col_ignores_it() {
+        let mut buf = new_test_buffer();
+        buf.command = vec!["s/a/b".to_string()];
+        buf.terminal = vec!["$ ls".to_string(), "$ ".to_string()];
+        assert_eq!((buf.max_col(0), buf.max_normal_col(0)), (10, 10));
+
+        buf.plane = BufferPlane::Command;
+        assert_eq!((buf.max_col(0), buf.max_normal_col(0)), (5, 10));
+        buf.plane = BufferPlane::Find(FindDirection::Forwards);
+        assert_eq!(buf.max_col(0), 5);
+
+        buf.plane = BufferPlane::Terminal;
+        assert_eq!(buf.max_col(1), 2);
+        assert_eq!((buf.max_normal_col(1), buf.max_normal_col(2)), (11, 10));
+        assert_eq!(buf.max_line(), 2);
+    }
+
+    #[test]
+    fn test_delete_before_position() {
+        let mut buf = new_test_buffer();
+        let start = LineCol { line: 0, col: 0 };
+        assert_eq!(buf.delete(start).unwrap(), start);
+        assert_eq!(buf.text[0], "First line");
+
+        let joined = buf.delete(LineCol { line: 1, col: 0 }).unwrap();
+        assert_eq!(joined, LineCol { line: 0, col: 10 });
+        assert_eq!(buf.text[0], "First lineSecond line");
+        assert_eq!(buf.delete(LineCol { line: 0, col: 1 }).unwrap(), start);
+        assert_eq!(buf.text[0], "irst lineSecond line");
+    }
+
     #[test]
     fn test_replace_within_single_line() {
         let mut buf = new_test_buffer();
@@ -843,6 +1053,8 @@ mod tests {
             },
             command: vec![],
             terminal: vec![],
+            undo_group: None,
+            nested_undo_groups: 0,
             plane: BufferPlane::Normal,
         }
     }
@@ -867,6 +1079,8 @@ mod tests {
             },
             command: vec![],
             terminal: vec![],
+            undo_group: None,
+            nested_undo_groups: 0,
             plane: BufferPlane::Normal,
         }
     }
@@ -1061,6 +1275,99 @@ mod tests {
             .unwrap();
         assert_eq!(buffer.text.last().unwrap(), "New last line");
     }
+    #[test]
+    fn test_splice_lines() {
+        let mut buffer = new_test_buffer();
+        buffer
+            .splice_lines(1..2, vec!["New".to_string(), "lines".to_string()])
+            .unwrap();
+        assert_eq!(
+            buffer.text,
+            vec!["First line", "New", "lines", "Third line"]
+        );
+        buffer.splice_lines(0..4, vec![]).unwrap();
+        assert_eq!(buffer.text, vec![""]);
+        assert!(buffer.splice_lines(0..2, vec![]).is_err());
+    }
+
+    #[test]
+    fn test_undo_redo_snapshots() {
+        let mut buffer = new_test_buffer();
+        let at = LineCol::default();
+        buffer
+            .execute_action(&BaseAction::ReplaceLines(0..1, vec!["Changed".to_string()]))
+            .unwrap();
+        buffer.execute_action(&BaseAction::Undo(1)).unwrap();
+        assert_eq!(buffer.text[0], "First line");
+        buffer.execute_action(&BaseAction::Redo(1)).unwrap();
+        assert_eq!(buffer.text[0], "Changed");
+
+        buffer.undo(at).unwrap();
+        buffer.snapshot(at);
+        assert!(buffer.redo(at).is_err());
+        assert!(buffer.execute_action(&BaseAction::Undo(5)).is_ok());
+    }
+
+    #[test]
+    fn test_linecol_at_offset() {
+        let mut buffer = new_test_buffer();
+        assert_eq!(
+            buffer.linecol_at_offset(0),
+            Some(LineCol { line: 0, col: 0 })
+        );
+        assert_eq!(
+            buffer.linecol_at_offset(10),
+            Some(LineCol { line: 0, col: 10 })
+        );
+        assert_eq!(
+            buffer.linecol_at_offset(11),
+            Some(LineCol { line: 1, col: 0 })
+        );
+        assert_eq!(
+            buffer.linecol_at_offset(33),
+            Some(LineCol { line: 2, col: 10 })
+        );
+        assert_eq!(buffer.linecol_at_offset(34), None);
+
+        buffer.load(vec!["äb".to_string()]);
+        assert_eq!(
+            buffer.linecol_at_offset(1),
+            Some(LineCol { line: 0, col: 0 })
+        );
+        assert_eq!(
+            buffer.linecol_at_offset(2),
+            Some(LineCol { line: 0, col: 2 })
+        );
+    }
+
+    #[test]
+    fn test_undo_group() {
+        let mut buffer = new_test_buffer();
+        buffer.begin_undo_group();
+        for line in 0..3 {
+            buffer
+                .execute_action(&BaseAction::ReplaceLines(
+                    line..line + 1,
+                    vec![String::new()],
+                ))
+                .unwrap();
+        }
+        buffer.begin_undo_group();
+        buffer.end_undo_group();
+        buffer
+            .execute_action(&BaseAction::ReplaceLines(1..2, vec!["Nested".to_string()]))
+            .unwrap();
+        buffer.end_undo_group();
+        buffer
+            .execute_action(&BaseAction::ReplaceLines(0..1, vec!["After".to_string()]))
+            .unwrap();
+
+        buffer.execute_action(&BaseAction::Undo(1)).unwrap();
+        assert_eq!(buffer.text, ["", "Nested", ""]);
+        buffer.execute_action(&BaseAction::Undo(1)).unwrap();
+        assert_eq!(buffer.text, ["First line", "Second line", "Third line"]);
+    }
+
     #[test]
     fn test_set_plane_and_buffer_operations() {
         let mut buffer = VecBuffer::default();
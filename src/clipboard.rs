@@ -0,0 +1,111 @@
+//! The system clipboard, reached through the `"+` register and, with `clipboard=unnamedplus`,
+//! through every yank, delete and paste without a register. The clipboard is only touched by
+//! these, running the clipboard tool of the platform like `pbcopy`, `wl-copy` or `xclip`.
+use std::{
+    fmt::Debug,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use crate::register::RegisterContent;
+
+/// Register holding the content of the system clipboard
+pub const CLIPBOARD_REGISTER: char = '+';
+/// Value of the `clipboard` option routing the unnamed register through the system clipboard
+pub const UNNAMEDPLUS: &str = "unnamedplus";
+
+/// Programs writing their input to the clipboard, the first one available is used
+const COPY_TOOLS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+/// Programs printing the content of the clipboard, the first one available is used
+const PASTE_TOOLS: &[(&str, &[&str])] = &[
+    ("pbpaste", &[]),
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-out"]),
+    ("xsel", &["--clipboard", "--output"]),
+];
+
+pub trait Clipboard: Debug {
+    /// Text on the clipboard, `None` if it can't be read
+    fn read(&mut self) -> Option<String>;
+    /// Puts `text` on the clipboard, silently leaving it as it was if it can't be written
+    fn write(&mut self, text: &str);
+}
+
+/// The clipboard of the desktop the editor runs in
+#[derive(Debug, Default)]
+pub struct SystemClipboard;
+
+impl Clipboard for SystemClipboard {
+    fn read(&mut self) -> Option<String> {
+        PASTE_TOOLS.iter().find_map(|(program, args)| {
+            let output = Command::new(program)
+                .args(*args)
+                .stderr(Stdio::null())
+                .output()
+                .ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+        })
+    }
+
+    fn write(&mut self, text: &str) {
+        for (program, args) in COPY_TOOLS {
+            let Ok(mut child) = Command::new(program)
+                .args(*args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+            else {
+                continue;
+            };
+            let written = child
+                .stdin
+                .take()
+                .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+            if written && child.wait().is_ok_and(|status| status.success()) {
+                return;
+            }
+        }
+    }
+}
+
+/// The text register content is put on the clipboard as, linewise content ending in a newline
+pub fn to_text(content: &RegisterContent) -> String {
+    let mut text = content.lines.join("\n");
+    if content.linewise {
+        text.push('\n');
+    }
+    text
+}
+
+/// Register content holding text taken from the clipboard, linewise if the text ends in a newline
+pub fn from_text(text: &str) -> RegisterContent {
+    match text.strip_suffix('\n') {
+        Some(lines) => RegisterContent::new(lines.split('\n').map(String::from).collect(), true),
+        None => RegisterContent::new(text.split('\n').map(String::from).collect(), false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_conversion() {
+        let lines = RegisterContent::new(vec!["a".to_string(), "b".to_string()], true);
+        assert_eq!(to_text(&lines), "a\nb\n");
+        assert_eq!(from_text("a\nb\n"), lines);
+
+        let words = RegisterContent::new(vec!["a".to_string(), "b".to_string()], false);
+        assert_eq!(to_text(&words), "a\nb");
+        assert_eq!(from_text("a\nb"), words);
+    }
+}
@@ -1,11 +1,21 @@
 pub(crate) use crate::error::{Error, Result};
-use crate::{cursor::Cursor, editor::Lazy};
+use crate::{
+    cursor::Cursor,
+    editor::Lazy,
+    ex::{SortOptions, Substitution},
+    keymap::{MapMode, Mapping},
+};
+use crossterm::event::KeyEvent;
 use std::{
     borrow::Cow,
     cmp::Ordering,
     fmt::{Debug, Display},
+    ops::Range,
+    path::PathBuf,
 };
 
+/// Part of the editor carrying out the base actions the editor resolved. Extensions registered with
+/// `Editor::register_extension` are components too.
 pub trait Component {
     fn execute_action(&mut self, a: &BaseAction) -> Result<()>;
 }
@@ -23,6 +33,8 @@ pub enum BaseAction {
     SetCursor(LineCol),
 
     ChangeMode(Modal),
+    ScrollUp(usize),
+    ScrollDown(usize),
 
     Yank,
     Paste(char, usize),
@@ -31,6 +43,10 @@ pub enum BaseAction {
     InsertLineAt(Lazy<LineCol>, usize),
     DeleteAt(Lazy<LineCol>, usize),
     DeleteLineAt(Lazy<LineCol>, usize),
+    // Replaces the given range of lines of the normal buffer with new lines
+    ReplaceLines(Range<usize>, Vec<String>),
+    // Appends the output of a shell command to the terminal buffer, followed by a fresh prompt line
+    AppendTerminalOutput(Vec<String>),
 
     ExecuteCommand(Command),
 
@@ -66,6 +82,20 @@ impl BaseAction {
             _ => None,
         }
     }
+    /// Whether the action changes the text of the plane it is performed on
+    pub const fn is_edit(&self) -> bool {
+        matches!(
+            self,
+            Self::Paste(..)
+                | Self::InsertAt(..)
+                | Self::InsertLineAt(..)
+                | Self::DeleteAt(..)
+                | Self::DeleteLineAt(..)
+                | Self::ReplaceLines(..)
+                | Self::Undo(_)
+                | Self::Redo(_)
+        )
+    }
 }
 
 pub trait Pattern {
@@ -78,6 +108,34 @@ pub trait Pattern {
     /// Thus find and rfind will require to be split at the cursor
     fn find_pattern(&self, haystack: &[String]) -> Option<LineCol>;
     fn rfind_pattern(&self, haystack: &[String]) -> Option<LineCol>;
+    /// Whether matches depend on the text around them, like for patterns anchored to the start or
+    /// end of the line. Such patterns are given whole lines instead of the lines split at the
+    /// cursor.
+    fn is_anchored(&self) -> bool {
+        false
+    }
+    /// Positions of all matches within the haystack, in order. Unlike `find_pattern`, the whole
+    /// haystack is searched, so the lines are not split at the cursor. Matches may overlap, the
+    /// search going on one character after the start of the previous match.
+    fn all_matches(&self, haystack: &[String]) -> Vec<LineCol> {
+        let mut matches = Vec::new();
+        for (line, content) in haystack.iter().enumerate() {
+            let mut col = 0;
+            while let Some(rest) = content.get(col..) {
+                let Some(found) = self.find_pattern(&[rest.to_string()]) else {
+                    break;
+                };
+                let found = col + found.col;
+                matches.push(LineCol { line, col: found });
+                // Anchored patterns match once per line at most, and only against whole lines
+                if self.is_anchored() {
+                    break;
+                }
+                col = found + content[found..].chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        matches
+    }
 }
 
 impl Pattern for &str {
@@ -104,6 +162,20 @@ impl Pattern for &str {
                 })
             })
     }
+    fn all_matches(&self, haystack: &[String]) -> Vec<LineCol> {
+        haystack
+            .iter()
+            .enumerate()
+            .flat_map(|(line, content)| {
+                let mut col = 0;
+                std::iter::from_fn(move || {
+                    let found = col + content.get(col..)?.find(self)?;
+                    col = found + content[found..].chars().next().map_or(1, char::len_utf8);
+                    Some(LineCol { line, col: found })
+                })
+            })
+            .collect()
+    }
 }
 
 // impl<F> Pattern for F
@@ -130,6 +202,9 @@ impl Pattern for String {
     fn rfind_pattern(&self, haystack: &[String]) -> Option<LineCol> {
         self.as_str().rfind_pattern(haystack)
     }
+    fn all_matches(&self, haystack: &[String]) -> Vec<LineCol> {
+        self.as_str().all_matches(haystack)
+    }
 }
 
 impl Pattern for Cow<'_, str> {
@@ -139,6 +214,9 @@ impl Pattern for Cow<'_, str> {
     fn rfind_pattern(&self, haystack: &[String]) -> Option<LineCol> {
         self.as_ref().rfind_pattern(haystack)
     }
+    fn all_matches(&self, haystack: &[String]) -> Vec<LineCol> {
+        self.as_ref().all_matches(haystack)
+    }
 }
 
 impl Pattern for char {
@@ -165,6 +243,17 @@ impl Pattern for char {
                 })
             })
     }
+    fn all_matches(&self, haystack: &[String]) -> Vec<LineCol> {
+        haystack
+            .iter()
+            .enumerate()
+            .flat_map(|(line, content)| {
+                content
+                    .match_indices(*self)
+                    .map(move |(col, _)| LineCol { line, col })
+            })
+            .collect()
+    }
 }
 
 impl<F> Pattern for F
@@ -176,12 +265,13 @@ where
             .iter()
             .enumerate()
             .find_map(|(line_num, line_content)| {
-                line_content.chars().position(self).map(|col| LineCol {
+                line_content.find(self).map(|col| LineCol {
                     line: line_num,
                     col,
                 })
             })
     }
+    /// Unlike the other patterns, returns the column right after the matching character
     fn rfind_pattern(&self, haystack: &[String]) -> Option<LineCol> {
         haystack
             .iter()
@@ -189,12 +279,12 @@ where
             .rev()
             .find_map(|(line_num, line_content)| {
                 line_content
-                    .chars()
+                    .char_indices()
                     .rev()
-                    .position(self)
-                    .map(|rcol| LineCol {
+                    .find(|&(_, ch)| self(ch))
+                    .map(|(col, ch)| LineCol {
                         line: line_num,
-                        col: line_content.len() - rcol,
+                        col: col + ch.len_utf8(),
                     })
             })
     }
@@ -246,6 +336,10 @@ impl Selection {
         };
         self
     }
+    /// Columns covered by the selection in visual block mode, from its left to its right edge
+    pub fn block_cols(&self) -> Range<usize> {
+        self.start.col.min(self.end.col)..self.start.col.max(self.end.col) + 1
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Debug, Copy)]
@@ -254,6 +348,16 @@ pub enum FindDirection {
     Backwards,
 }
 
+impl FindDirection {
+    /// The key starting a search in this direction, shown in front of the search pattern
+    pub const fn symbol(self) -> char {
+        match self {
+            Self::Forwards => '/',
+            Self::Backwards => '?',
+        }
+    }
+}
+
 /// Contains the main modal variants of the editor.
 #[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
 pub enum Modal {
@@ -262,8 +366,10 @@ pub enum Modal {
     Insert,
     Visual,
     VisualLine,
+    VisualBlock,
     Find(FindDirection),
     Command,
+    Terminal,
 }
 
 impl Display for Modal {
@@ -272,7 +378,9 @@ impl Display for Modal {
             Self::Insert => "INSERT",
             Self::Visual => "VISUAL",
             Self::VisualLine => "VISUAL_LINE",
+            Self::VisualBlock => "VISUAL_BLOCK",
             Self::Command => "COMMAND",
+            Self::Terminal => "TERMINAL",
             Self::Normal => "NORMAL",
             Self::Find(FindDirection::Forwards) => "FORWARD FIND",
             Self::Find(FindDirection::Backwards) => "BACKWARD FIND",
@@ -294,9 +402,22 @@ impl Modal {
     pub fn is_visual_line(&self) -> bool {
         matches!(&self, Modal::VisualLine)
     }
+    pub fn is_visual_block(&self) -> bool {
+        matches!(&self, Modal::VisualBlock)
+    }
+    /// Whether the mode selects text, in any of the visual modes
+    pub fn is_selecting(&self) -> bool {
+        matches!(
+            &self,
+            Modal::Visual | Modal::VisualLine | Modal::VisualBlock
+        )
+    }
     pub fn is_command(&self) -> bool {
         matches!(&self, Modal::Command)
     }
+    pub fn is_terminal(&self) -> bool {
+        matches!(&self, Modal::Terminal)
+    }
     pub fn is_find(&self) -> bool {
         matches!(&self, Modal::Find(_))
     }
@@ -306,13 +427,84 @@ impl Modal {
     pub fn is_backwards_find(&self) -> bool {
         matches!(&self, Modal::Find(FindDirection::Backwards))
     }
+    /// Whether the mode works on the text, rather than on the command line or the terminal
+    pub fn is_text(&self) -> bool {
+        !matches!(&self, Modal::Command | Modal::Find(_) | Modal::Terminal)
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Command {
     Find(String),
     Rfind(String),
-    Exit,
+    /// `:set` with its arguments
+    Set(String),
+    /// `:highlight` with its arguments
+    Highlight(String),
+    /// `:help` with its topic
+    Help(String),
+    /// `:recover`, restoring the text kept in the swap file
+    Recover,
+    /// `:script`, running the rest of the line as a script
+    Script(String),
+    /// `:source`, running the script in a file
+    Source(PathBuf),
+    /// A command defined by a script with its arguments
+    User(String, String),
+    /// `:edit`, replacing the buffer with the contents of a file
+    Edit(PathBuf),
+    /// `:Explore`, listing the given directory or the one of the buffer's file
+    Explore(Option<PathBuf>),
+    /// `:w`, writing the given lines or the whole buffer to a file or the buffer's own. Existing
+    /// files other than its own and partial writes to its own need `force`, `:w!`. With `append`,
+    /// `:w >>`, the lines are added to the end of the existing file instead.
+    Write {
+        range: Option<Range<usize>>,
+        path: Option<PathBuf>,
+        force: bool,
+        append: bool,
+    },
+    /// `:A`, editing the alternate file of the current file
+    Alternate,
+    /// `:terminal`, switching to the terminal plane
+    Terminal,
+    /// A bare line range, jumping to its last line
+    Goto(usize),
+    /// `:goto`, jumping to a byte offset into the file, counted from one
+    GotoByte(usize),
+    /// `:s`, substituting within the given lines
+    Substitute(Range<usize>, Substitution),
+    /// `:ab` defining an abbreviation from a trigger to its expansion, or listing them without one
+    Abbreviate(Option<(String, String)>),
+    /// `:unab` removing the abbreviation of a trigger
+    Unabbreviate(String),
+    /// `:map` and its variants mapping a lhs to a rhs in the modes, or listing the mappings of
+    /// the modes without one
+    Map(&'static [MapMode], Option<(Vec<KeyEvent>, Mapping)>),
+    /// `:unmap` and its variants removing the mapping of a lhs from the modes
+    Unmap(&'static [MapMode], Vec<KeyEvent>),
+    /// `:todo`, placing signs on the lines with `TODO`, `FIXME` or `XXX` comments
+    Todo,
+    /// `:sort`, sorting the given lines
+    Sort(Range<usize>, SortOptions),
+    /// `:align` or `:Tabularize`, aligning the given lines on the first occurrence of a delimiter
+    Align(Range<usize>, String),
+    /// `:number!`, prefixing the given lines with their line numbers and a separator
+    Number(Range<usize>, String),
+    /// `:squeeze`, collapsing runs of blank lines within the given lines into one
+    Squeeze(Range<usize>),
+    /// `:trimblank`, removing the blank lines at the end of the buffer
+    TrimBlank,
+    /// `:changeall`, replacing every occurrence of the word under the cursor
+    ChangeAll(String),
+    /// `:diff`, showing the unsaved changes as a unified diff against the file on disk
+    Diff,
+    /// A command which couldn't be parsed, holding the message to report
+    Invalid(String),
+    /// `:q`, or `:q!` with `force` quitting without confirmation
+    Exit {
+        force: bool,
+    },
     None,
 }
 
@@ -379,6 +571,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_all_matches() {
+        let buffer = vec![
+            "abab ab".to_string(),
+            "".to_string(),
+            "aaa".to_string(),
+            "b ab".to_string(),
+        ];
+        let at = |line, col| LineCol { line, col };
+        assert_eq!(
+            "ab".all_matches(&buffer),
+            [at(0, 0), at(0, 2), at(0, 5), at(3, 2)]
+        );
+        assert_eq!("aa".all_matches(&buffer), [at(2, 0), at(2, 1)]);
+        assert_eq!(
+            'b'.all_matches(&buffer),
+            [at(0, 1), at(0, 3), at(0, 6), at(3, 0), at(3, 3)]
+        );
+        assert!("c".to_string().all_matches(&buffer).is_empty());
+
+        // The default built on `find_pattern`
+        let pattern = |c: char| c == 'a';
+        assert_eq!(
+            pattern.all_matches(&buffer),
+            [
+                at(0, 0),
+                at(0, 2),
+                at(0, 5),
+                at(2, 0),
+                at(2, 1),
+                at(2, 2),
+                at(3, 2)
+            ]
+        );
+    }
+
     #[test]
     fn test_pattern_not_found() {
         let buffer = create_test_buffer();
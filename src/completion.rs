@@ -0,0 +1,313 @@
+//! Tab completion of the command line, completing Ex command names and the file paths passed to
+//! the commands taking one, and `Ctrl-N`/`Ctrl-P` completion of words in insert mode.
+use std::{fs, path::Path};
+
+use crate::{textobject::is_keyword_char, LineCol};
+
+/// Ex commands known to the command line
+pub const COMMANDS: &[&str] = &[
+    "A",
+    "abbreviate",
+    "align",
+    "changeall",
+    "diff",
+    "edit",
+    "Explore",
+    "goto",
+    "help",
+    "highlight",
+    "imap",
+    "inoremap",
+    "iunmap",
+    "map",
+    "nmap",
+    "nnoremap",
+    "noremap",
+    "number!",
+    "nunmap",
+    "quit",
+    "recover",
+    "script",
+    "set",
+    "sort",
+    "source",
+    "squeeze",
+    "substitute",
+    "Tabularize",
+    "terminal",
+    "todo",
+    "trimblank",
+    "unabbreviate",
+    "unmap",
+    "vmap",
+    "vnoremap",
+    "vunmap",
+    "write",
+];
+
+/// Commands whose argument is a file path
+const PATH_COMMANDS: &[&str] = &["e", "edit", "w", "write", "Ex", "Explore"];
+
+/// Candidates for the last word of a command line, cycled through by repeatedly pressing Tab.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Completion {
+    /// The command line in front of the completed word
+    head: String,
+    pub candidates: Vec<String>,
+    index: usize,
+}
+
+impl Completion {
+    /// Computes the candidates for the last word of `command_line`, `None` if there are none.
+    pub fn new(command_line: &str) -> Option<Self> {
+        let (head, candidates) = match command_line.split_once(' ') {
+            None => (
+                String::new(),
+                COMMANDS
+                    .iter()
+                    .filter(|command| command.starts_with(command_line))
+                    .map(ToString::to_string)
+                    .collect(),
+            ),
+            Some((command, path)) if PATH_COMMANDS.contains(&command) => {
+                let path = path.trim_start();
+                let head_len = command_line.len() - path.len();
+                (command_line[..head_len].to_string(), complete_path(path))
+            }
+            Some(_) => return None,
+        };
+        (!candidates.is_empty()).then_some(Self {
+            head,
+            candidates,
+            index: 0,
+        })
+    }
+
+    /// The command line with the selected candidate filled in
+    pub fn current(&self) -> String {
+        format!("{}{}", self.head, self.candidates[self.index])
+    }
+
+    /// Selects the next candidate, wrapping around after the last one
+    pub fn advance(&mut self) {
+        self.index = (self.index + 1) % self.candidates.len();
+    }
+}
+
+/// Candidates for the word in front of the cursor in insert mode, taken from the words of the
+/// buffer and cycled through with `Ctrl-N` and `Ctrl-P`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WordCompletion {
+    /// Start of the completed word
+    pub start: LineCol,
+    /// The word as it was typed, shown again after the last candidate
+    prefix: String,
+    /// Words starting with the prefix, the ones following the cursor first
+    pub candidates: Vec<String>,
+    /// The selected candidate, `None` while the typed word is shown
+    index: Option<usize>,
+}
+
+impl WordCompletion {
+    /// Collects the words of `lines` starting with the word in front of `pos`, each once and in
+    /// the order they follow the cursor, wrapping around the end of the buffer. The word being
+    /// typed and the typed word itself aren't candidates. `None` if there are none.
+    pub fn new(lines: &[String], pos: LineCol, iskeyword: &str) -> Option<Self> {
+        let line = lines.get(pos.line)?;
+        let before = line.get(..pos.col)?;
+        let start = before
+            .char_indices()
+            .rev()
+            .take_while(|&(_, ch)| is_keyword_char(ch, iskeyword))
+            .last()
+            .map_or(pos.col, |(col, _)| col);
+        let prefix = &before[start..];
+
+        let mut candidates: Vec<String> = Vec::new();
+        let order = (pos.line..lines.len()).chain(0..=pos.line);
+        for (pass, line_number) in order.enumerate() {
+            for (col, word) in words(&lines[line_number], iskeyword) {
+                let position = LineCol {
+                    line: line_number,
+                    col,
+                };
+                // The cursor line is scanned after the cursor first, then in front of it
+                let typed = LineCol { col: start, ..pos };
+                let after_cursor = position > typed;
+                let in_pass = match line_number == pos.line {
+                    true => (pass == 0) == after_cursor,
+                    false => true,
+                };
+                if in_pass
+                    && position != typed
+                    && word != prefix
+                    && word.starts_with(prefix)
+                    && !candidates.iter().any(|candidate| candidate == word)
+                {
+                    candidates.push(word.to_string());
+                }
+            }
+        }
+        (!candidates.is_empty()).then(|| Self {
+            start: LineCol { col: start, ..pos },
+            prefix: prefix.to_string(),
+            candidates,
+            index: None,
+        })
+    }
+
+    /// The selected candidate, or the typed word once the candidates have been cycled through
+    pub fn current(&self) -> &str {
+        self.index
+            .map_or(&self.prefix, |index| &self.candidates[index])
+    }
+
+    /// Selects the next candidate, or the previous one unless `forwards`, passing through the
+    /// typed word between the last and the first candidate
+    pub fn advance(&mut self, forwards: bool) {
+        let last = self.candidates.len() - 1;
+        self.index = match (self.index, forwards) {
+            (None, true) => Some(0),
+            (None, false) => Some(last),
+            (Some(index), true) if index < last => Some(index + 1),
+            (Some(index), false) if index > 0 => Some(index - 1),
+            _ => None,
+        };
+    }
+
+    /// Which candidate is selected, like `match 2 of 5`
+    pub fn report(&self) -> String {
+        match self.index {
+            Some(index) => format!("match {} of {}", index + 1, self.candidates.len()),
+            None => "Back at original".to_string(),
+        }
+    }
+}
+
+/// The words of `line` along with the columns they start at
+fn words<'a>(line: &'a str, iskeyword: &'a str) -> impl Iterator<Item = (usize, &'a str)> {
+    let mut chars = line.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, _) = chars.find(|&(_, ch)| is_keyword_char(ch, iskeyword))?;
+        let end = loop {
+            match chars.next_if(|&(_, ch)| is_keyword_char(ch, iskeyword)) {
+                Some(_) => continue,
+                None => break chars.peek().map_or(line.len(), |&(col, _)| col),
+            }
+        };
+        Some((start, &line[start..end]))
+    })
+}
+
+/// Returns the paths starting with `partial`, directories ending with a slash. Hidden entries
+/// are only offered when `partial` names them explicitly.
+fn complete_path(partial: &str) -> Vec<String> {
+    let (dir, prefix) = match partial.rfind('/') {
+        Some(i) => partial.split_at(i + 1),
+        None => ("", partial),
+    };
+    let read_from = if dir.is_empty() { "." } else { dir };
+    let Ok(entries) = fs::read_dir(Path::new(read_from)) else {
+        return Vec::new();
+    };
+
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{dir}{name}{slash}"))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_complete_command_names() {
+        let mut completion = Completion::new("se").unwrap();
+        assert_eq!(completion.current(), "set");
+        completion.advance();
+        assert_eq!(completion.current(), "set");
+
+        let mut completion = Completion::new("").unwrap();
+        assert_eq!(completion.candidates, COMMANDS);
+        completion.advance();
+        assert_eq!(completion.current(), COMMANDS[1]);
+
+        assert_eq!(Completion::new("di").unwrap().current(), "diff");
+        assert_eq!(Completion::new("go").unwrap().current(), "goto");
+        assert_eq!(Completion::new("ch").unwrap().current(), "changeall");
+        assert_eq!(Completion::new("al").unwrap().current(), "align");
+        assert_eq!(Completion::new("Ta").unwrap().current(), "Tabularize");
+        assert_eq!(Completion::new("A").unwrap().current(), "A");
+        assert_eq!(Completion::new("Ex").unwrap().current(), "Explore");
+        assert_eq!(Completion::new("xyz"), None);
+        assert_eq!(Completion::new("set mo"), None);
+    }
+
+    #[test]
+    fn test_complete_buffer_words() {
+        let lines = ["alpha beta", "alp", "gamma alphabet beta alpine"].map(String::from);
+        let pos = LineCol { line: 1, col: 3 };
+        let mut completion = WordCompletion::new(&lines, pos, "_").unwrap();
+        assert_eq!(completion.candidates, ["alphabet", "alpine", "alpha"]);
+        assert_eq!(completion.start, LineCol { line: 1, col: 0 });
+        assert_eq!(completion.current(), "alp");
+
+        completion.advance(true);
+        assert_eq!(
+            (completion.current(), completion.report().as_str()),
+            ("alphabet", "match 1 of 3")
+        );
+        completion.advance(false);
+        assert_eq!(
+            (completion.current(), completion.report().as_str()),
+            ("alp", "Back at original")
+        );
+        completion.advance(false);
+        assert_eq!(completion.current(), "alpha");
+
+        assert_eq!(
+            WordCompletion::new(&lines, LineCol { line: 0, col: 3 }, "_")
+                .unwrap()
+                .candidates,
+            ["alphabet", "alpine"]
+        );
+        assert_eq!(
+            WordCompletion::new(&lines, LineCol { line: 0, col: 7 }, "_")
+                .unwrap()
+                .candidates,
+            ["beta"]
+        );
+        assert_eq!(
+            WordCompletion::new(&lines, LineCol { line: 2, col: 2 }, "_"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_complete_file_paths() {
+        let temp = TempDir::new("completion");
+        fs::create_dir_all(temp.join("src")).unwrap();
+        fs::write(temp.join("script.sh"), "").unwrap();
+        fs::write(temp.join(".hidden"), "").unwrap();
+        let dir = temp.to_str().unwrap();
+
+        let mut completion = Completion::new(&format!("e {dir}/s")).unwrap();
+        assert_eq!(completion.current(), format!("e {dir}/script.sh"));
+        completion.advance();
+        assert_eq!(completion.current(), format!("e {dir}/src/"));
+        let candidates = |line: String| Completion::new(&line).unwrap().candidates;
+        assert_eq!(candidates(format!("w {dir}/")).len(), 2);
+        assert_eq!(candidates(format!("w {dir}/.")), [format!("{dir}/.hidden")]);
+    }
+}
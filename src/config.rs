@@ -0,0 +1,493 @@
+//! User configurable options of the editor, adjustable at runtime through `:set`.
+use std::path::Path;
+
+use crate::{
+    alternate, clipboard::UNNAMEDPLUS, encoding, filetype, keymap, textobject::is_keyword_char,
+    theme::Theme, Error, Result,
+};
+
+/// Comment prefix for files whose extension has no known comment syntax
+const DEFAULT_COMMENT_STRING: &str = "# ";
+/// Width `gq` and `gw` rewrap lines to while `textwidth` is zero
+pub const DEFAULT_TEXT_WIDTH: usize = 80;
+/// Key duplicating lines unless `duplicatekey` names another one
+const DEFAULT_DUPLICATE_KEY: &str = "<M-d>";
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Prefix used by comment toggling, overriding the one derived from the file extension
+    pub comment_string: Option<String>,
+    /// Whether mouse events are captured to click and scroll within the text
+    pub mouse: bool,
+    /// Lines scrolled by `Ctrl-U` and `Ctrl-D`, zero scrolls half the window
+    pub scroll: usize,
+    /// Whether searches continue at the other end of the buffer once they hit its end
+    pub wrapscan: bool,
+    /// Whether `f`, `F`, `t` and `T` continue on the following (or preceding) lines if the
+    /// character isn't on the cursor line. Off by default, the search stays within the line.
+    pub findacrosslines: bool,
+    /// Whether the cursor moves past the end of lines into virtual space, text inserted there
+    /// padding the line with spaces. Off by default, the cursor stays on the text.
+    pub virtualedit: bool,
+    /// Whether `Home` goes to the first non-blank character of the line, and to its start when
+    /// the cursor is already there. Off by default, it always goes to the start like `_`.
+    pub smarthome: bool,
+    /// Whether scrolling with `Ctrl-D`, `Ctrl-U`, `Ctrl-F` and `Ctrl-B` moves the cursor to the
+    /// first non-blank character of its new line. Off by default, it keeps its column.
+    pub startofline: bool,
+    /// Whether the line of a search match is scrolled to the middle of the window, like `zz` does
+    pub searchcenter: bool,
+    /// Whether the matches of the last search are highlighted
+    pub hlsearch: bool,
+    /// Whether the line the cursor is on and its number are highlighted
+    pub cursorline: bool,
+    /// Whether unsaved changes are kept in a swap file next to the edited file
+    pub swapfile: bool,
+    /// Milliseconds without input after which a modified buffer is written, zero disables it
+    pub autosave: u64,
+    /// Key substituted for `<leader>` in the lhs and rhs of mappings as they are defined
+    pub mapleader: char,
+    /// Key duplicating the cursor line, or the selected lines in visual modes, written in key
+    /// notation like `<M-d>`. Empty leaves lines to be duplicated through mappings only.
+    pub duplicatekey: String,
+    /// Milliseconds to wait for the next key of a mapping before deciding on the typed keys
+    pub timeoutlen: u64,
+    /// Width of the column in front of the line numbers marking folds, zero hides it
+    pub foldcolumn: usize,
+    /// Width of the column in front of the line numbers showing signs, zero shows them in the
+    /// separator following the line numbers
+    pub signcolumn: usize,
+    /// Whether lines differing from the file on disk are marked by `+`, `~` and `-` signs
+    pub changesigns: bool,
+    /// Whether `>` and `<` in visual modes keep the shifted lines selected for shifting them
+    /// again, instead of returning to normal mode like vim
+    pub indentkeepselection: bool,
+    /// Spaces a line is shifted by with `>` and `<`, and indented by per level with `=`
+    pub shiftwidth: usize,
+    /// Width lines typed in insert mode are broken at, and `gq` and `gw` rewrap lines to. Zero
+    /// leaves typed lines alone and rewraps to `DEFAULT_TEXT_WIDTH`.
+    pub textwidth: usize,
+    /// Column tinted on every line to show a line length limit, zero disables it
+    pub colorcolumn: usize,
+    /// Characters besides letters and digits which words consist of, for word motions, the `iw`
+    /// text object and abbreviations
+    pub iskeyword: String,
+    /// Comma separated pairs of extensions like `c:h` whose files are alternates of each other,
+    /// switched between with `:A`
+    pub alternates: String,
+    /// Whether lines of only whitespace count as blank for `:squeeze` and `:trimblank`, otherwise
+    /// only empty lines do
+    pub whitespaceblank: bool,
+    /// Whether `gc` in visual block mode pads lines ending left of the block with spaces to comment
+    /// them at the block's column as well. Off by default, such lines are left alone.
+    pub blockpad: bool,
+    /// Whether `:w` over another existing file and `:q` with unsaved changes ask for confirmation
+    /// instead of refusing or proceeding. Off by default.
+    pub confirm: bool,
+    /// Encoding files are read and written in, like `utf-8` or `latin1`
+    pub fileencoding: String,
+    /// `unnamedplus` to yank, delete and paste through the system clipboard without naming the `+`
+    /// register, empty to keep them within the editor
+    pub clipboard: String,
+    /// Format of the info bar, `%f`, `%l`, `%c`, `%m`, `%p` and `%y` standing for the file name,
+    /// line, column, modified flag, percentage through the file and file type, and `%=` splitting
+    /// it into a part aligned left and a part aligned right. Empty for the default info bar.
+    pub statusline: String,
+    /// Kind of the file being edited like `rust`, detected as it is opened. The comment prefix and
+    /// `%y` of the `statusline` follow it.
+    pub filetype: Option<String>,
+    /// Colors of the editor, assigned with `:highlight`
+    pub theme: Theme,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            comment_string: None,
+            mouse: true,
+            scroll: 0,
+            wrapscan: true,
+            findacrosslines: false,
+            virtualedit: false,
+            smarthome: false,
+            startofline: false,
+            searchcenter: false,
+            hlsearch: false,
+            cursorline: false,
+            swapfile: true,
+            autosave: 0,
+            mapleader: ' ',
+            duplicatekey: DEFAULT_DUPLICATE_KEY.to_string(),
+            timeoutlen: 1000,
+            foldcolumn: 0,
+            signcolumn: 0,
+            changesigns: false,
+            indentkeepselection: false,
+            shiftwidth: 4,
+            textwidth: 0,
+            colorcolumn: 0,
+            iskeyword: "_".to_string(),
+            alternates: alternate::DEFAULT_RULES.to_string(),
+            whitespaceblank: true,
+            blockpad: false,
+            confirm: false,
+            fileencoding: encoding::DEFAULT_ENCODING.to_string(),
+            clipboard: String::new(),
+            statusline: String::new(),
+            filetype: None,
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Returns the line comment prefix for the file at `path`, following the `filetype` or, when
+    /// it isn't set, the filetype of the path.
+    pub fn comment_string(&self, path: Option<&Path>) -> &str {
+        if let Some(comment_string) = &self.comment_string {
+            return comment_string;
+        }
+        let filetype = match &self.filetype {
+            Some(filetype) => Some(filetype.clone()),
+            None => path.and_then(|path| filetype::detect(path, None)),
+        };
+        filetype
+            .as_deref()
+            .and_then(filetype::comment_string)
+            .unwrap_or(DEFAULT_COMMENT_STRING)
+    }
+
+    /// Width `gq` and `gw` rewrap lines to
+    pub fn reflow_width(&self) -> usize {
+        match self.textwidth {
+            0 => DEFAULT_TEXT_WIDTH,
+            width => width,
+        }
+    }
+
+    /// Whether words include `ch`
+    pub fn is_keyword(&self, ch: char) -> bool {
+        is_keyword_char(ch, &self.iskeyword)
+    }
+
+    /// Applies the arguments of a `:set` command.
+    ///
+    /// Boolean options are switched on with `name`, off with `noname` and toggled with `invname`
+    /// or `name!`. Other options are assigned with `name=value`, and `name?` queries the current
+    /// value. `name+=value` and `name-=value` add characters to and remove them from `iskeyword`.
+    /// Arguments are separated by whitespace unless it is escaped with a backslash.
+    ///
+    /// # Returns
+    ///
+    /// The values of the queried options, if any were queried.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UnknownOption` for options that don't exist or don't accept the given form,
+    /// and `Error::InvalidInput` for values the option can't take.
+    pub fn set(&mut self, args: &str) -> Result<Option<String>> {
+        let mut reports = Vec::new();
+        for arg in split_args(args) {
+            if let Some(name) = arg.strip_suffix('?') {
+                reports.push(self.query(name)?);
+            } else if let Some((name, value)) = arg.split_once('=') {
+                if let Some(name) = name.strip_suffix('+') {
+                    self.adjust(name, value, true)?;
+                } else if let Some(name) = name.strip_suffix('-') {
+                    self.adjust(name, value, false)?;
+                } else {
+                    self.assign(name, value)?;
+                }
+            } else if let Some(flag) = self.flag(&arg) {
+                *flag = true;
+            } else if let Some(flag) = arg.strip_prefix("no").and_then(|name| self.flag(name)) {
+                *flag = false;
+            } else if let Some(flag) = arg
+                .strip_prefix("inv")
+                .or_else(|| arg.strip_suffix('!'))
+                .and_then(|name| self.flag(name))
+            {
+                *flag = !*flag;
+            } else {
+                // Like in vim, naming a non-boolean option shows its value
+                reports.push(self.query(&arg)?);
+            }
+        }
+        Ok((!reports.is_empty()).then(|| reports.join(" ")))
+    }
+
+    /// Returns the boolean option called `name`
+    fn flag(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "mouse" => Some(&mut self.mouse),
+            "wrapscan" | "ws" => Some(&mut self.wrapscan),
+            "findacrosslines" | "fal" => Some(&mut self.findacrosslines),
+            "virtualedit" | "ve" => Some(&mut self.virtualedit),
+            "smarthome" | "sh" => Some(&mut self.smarthome),
+            "startofline" | "sol" => Some(&mut self.startofline),
+            "searchcenter" => Some(&mut self.searchcenter),
+            "hlsearch" | "hls" => Some(&mut self.hlsearch),
+            "cursorline" | "cul" => Some(&mut self.cursorline),
+            "swapfile" | "swf" => Some(&mut self.swapfile),
+            "whitespaceblank" | "wsb" => Some(&mut self.whitespaceblank),
+            "blockpad" | "bp" => Some(&mut self.blockpad),
+            "confirm" | "cf" => Some(&mut self.confirm),
+            "changesigns" | "chs" => Some(&mut self.changesigns),
+            "indentkeepselection" | "iks" => Some(&mut self.indentkeepselection),
+            _ => None,
+        }
+    }
+
+    fn assign(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "commentstring" => {
+                self.comment_string = (!value.is_empty()).then(|| value.to_string());
+            }
+            // Mouse support is all or nothing, any mode list enables it
+            "mouse" => self.mouse = !value.is_empty(),
+            "scroll" | "scr" => self.scroll = value.parse().map_err(|_| Error::InvalidInput)?,
+            "mapleader" => {
+                let mut chars = value.chars();
+                self.mapleader = match (chars.next(), chars.next()) {
+                    (Some(leader), None) => leader,
+                    _ => return Err(Error::InvalidInput),
+                };
+            }
+            "timeoutlen" | "tm" => {
+                self.timeoutlen = value.parse().map_err(|_| Error::InvalidInput)?;
+            }
+            "duplicatekey" | "dupk" => match keymap::parse_keys(value, self.mapleader).len() {
+                0 | 1 => self.duplicatekey = value.to_string(),
+                _ => return Err(Error::InvalidInput),
+            },
+            "foldcolumn" | "fdc" => {
+                self.foldcolumn = value.parse().map_err(|_| Error::InvalidInput)?;
+            }
+            "signcolumn" | "scl" => {
+                self.signcolumn = value.parse().map_err(|_| Error::InvalidInput)?;
+            }
+            "autosave" => self.autosave = value.parse().map_err(|_| Error::InvalidInput)?,
+            "shiftwidth" | "sw" => {
+                self.shiftwidth = value.parse().map_err(|_| Error::InvalidInput)?;
+            }
+            "textwidth" | "tw" => {
+                self.textwidth = value.parse().map_err(|_| Error::InvalidInput)?;
+            }
+            "iskeyword" | "isk" => self.iskeyword = value.to_string(),
+            "alternates" => self.alternates = value.to_string(),
+            "statusline" | "stl" => self.statusline = value.to_string(),
+            "filetype" | "ft" => self.filetype = (!value.is_empty()).then(|| value.to_string()),
+            "fileencoding" | "fenc" => match encoding::lookup(value) {
+                Some(_) => self.fileencoding = value.to_ascii_lowercase(),
+                None => return Err(Error::InvalidInput),
+            },
+            "clipboard" | "cb" => match value {
+                "" | UNNAMEDPLUS => self.clipboard = value.to_string(),
+                _ => return Err(Error::InvalidInput),
+            },
+            // Like in vim, an empty value clears it
+            "colorcolumn" | "cc" => {
+                self.colorcolumn = match value {
+                    "" => 0,
+                    value => value.parse().map_err(|_| Error::InvalidInput)?,
+                };
+            }
+            _ => return Err(Error::UnknownOption(name.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Adds the characters of `value` to a character set option or removes them from it
+    fn adjust(&mut self, name: &str, value: &str, add: bool) -> Result<()> {
+        let set = match name {
+            "iskeyword" | "isk" => &mut self.iskeyword,
+            _ => return Err(Error::UnknownOption(name.to_string())),
+        };
+        for ch in value.chars() {
+            match add {
+                true if !set.contains(ch) => set.push(ch),
+                false => set.retain(|kept| kept != ch),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn query(&mut self, name: &str) -> Result<String> {
+        if let Some(flag) = self.flag(name) {
+            let prefix = if *flag { "" } else { "no" };
+            return Ok(format!("{prefix}{name}"));
+        }
+        let value = match name {
+            "commentstring" => self.comment_string.clone().unwrap_or_default(),
+            "scroll" | "scr" => self.scroll.to_string(),
+            "mapleader" => self.mapleader.to_string(),
+            "duplicatekey" | "dupk" => self.duplicatekey.clone(),
+            "timeoutlen" | "tm" => self.timeoutlen.to_string(),
+            "foldcolumn" | "fdc" => self.foldcolumn.to_string(),
+            "signcolumn" | "scl" => self.signcolumn.to_string(),
+            "shiftwidth" | "sw" => self.shiftwidth.to_string(),
+            "textwidth" | "tw" => self.textwidth.to_string(),
+            "autosave" => self.autosave.to_string(),
+            "iskeyword" | "isk" => self.iskeyword.clone(),
+            "alternates" => self.alternates.clone(),
+            "statusline" | "stl" => self.statusline.clone(),
+            "filetype" | "ft" => self.filetype.clone().unwrap_or_default(),
+            "fileencoding" | "fenc" => self.fileencoding.clone(),
+            "clipboard" | "cb" => self.clipboard.clone(),
+            "colorcolumn" | "cc" => match self.colorcolumn {
+                0 => String::new(),
+                column => column.to_string(),
+            },
+            _ => return Err(Error::UnknownOption(name.to_string())),
+        };
+        Ok(format!("{name}={value}"))
+    }
+}
+
+/// Splits `:set` arguments on whitespace, a backslash escapes the character following it
+fn split_args(args: &str) -> Vec<String> {
+    let mut split = Vec::new();
+    let mut current = String::new();
+    let mut chars = args.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => current.extend(chars.next()),
+            ch if ch.is_whitespace() => {
+                if !current.is_empty() {
+                    split.push(std::mem::take(&mut current));
+                }
+            }
+            ch => current.push(ch),
+        }
+    }
+    if !current.is_empty() {
+        split.push(current);
+    }
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comment_string_by_extension_and_override() {
+        let mut config = Config::default();
+        assert_eq!(config.comment_string(Some(Path::new("src/main.rs"))), "// ");
+        assert_eq!(config.comment_string(Some(Path::new("init.lua"))), "-- ");
+        assert_eq!(config.comment_string(Some(Path::new("notes"))), "# ");
+        assert_eq!(config.comment_string(None), "# ");
+
+        config.set("filetype=lua").unwrap();
+        assert_eq!(config.comment_string(Some(Path::new("src/main.rs"))), "-- ");
+        assert_eq!(config.set("ft?").unwrap().as_deref(), Some("ft=lua"));
+
+        config.comment_string = Some("; ".to_string());
+        assert_eq!(config.comment_string(Some(Path::new("src/main.rs"))), "; ");
+    }
+
+    #[test]
+    fn test_set_boolean_option() {
+        let mut config = Config::default();
+        config.set("nomouse").unwrap();
+        assert!(!config.mouse);
+        config.set("invmouse").unwrap();
+        assert!(config.mouse);
+        config.set("mouse!").unwrap();
+        assert_eq!(config.set("mouse?").unwrap(), Some("nomouse".to_string()));
+        config.set("mouse=a").unwrap();
+        assert!(config.mouse);
+        config.set("nows").unwrap();
+        assert!(!config.wrapscan);
+    }
+
+    #[test]
+    fn test_set_value_with_escaped_whitespace() {
+        let mut config = Config::default();
+        config.set(r"commentstring=//\ ").unwrap();
+        assert_eq!(config.comment_string, Some("// ".to_string()));
+        assert_eq!(
+            config.set("commentstring").unwrap(),
+            Some("commentstring=// ".to_string())
+        );
+    }
+
+    #[test]
+    fn test_set_numeric_option() {
+        let mut config = Config::default();
+        config.set("scr=12").unwrap();
+        assert_eq!(config.scroll, 12);
+        assert_eq!(
+            config.set("scroll?").unwrap(),
+            Some("scroll=12".to_string())
+        );
+        assert!(matches!(
+            config.set("scroll=half"),
+            Err(Error::InvalidInput)
+        ));
+        config.set("tm=300 fdc=1 sw=2 cc=80").unwrap();
+        assert_eq!(config.set("cc?").unwrap(), Some("cc=80".to_string()));
+        config.set("colorcolumn=").unwrap();
+        assert_eq!(config.colorcolumn, 0);
+        assert_eq!(
+            (config.timeoutlen, config.foldcolumn, config.shiftwidth),
+            (300, 1, 2)
+        );
+    }
+
+    #[test]
+    fn test_set_iskeyword() {
+        let mut config = Config::default();
+        assert!(config.is_keyword('_') && !config.is_keyword('-'));
+        config.set("iskeyword+=-").unwrap();
+        assert!(config.is_keyword('-'));
+        config.set("isk+=-$ isk-=_").unwrap();
+        assert_eq!(
+            config.set("iskeyword?").unwrap(),
+            Some("iskeyword=-$".to_string())
+        );
+        assert!(matches!(
+            config.set("scroll+=1"),
+            Err(Error::UnknownOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_set_mapleader() {
+        let mut config = Config::default();
+        assert_eq!(config.mapleader, ' ');
+        config.set("mapleader=,").unwrap();
+        assert_eq!(config.mapleader, ',');
+        config.set(r"mapleader=\ ").unwrap();
+        assert_eq!(config.mapleader, ' ');
+        assert!(matches!(
+            config.set("mapleader=ab"),
+            Err(Error::InvalidInput)
+        ));
+    }
+
+    #[test]
+    fn test_set_duplicatekey() {
+        let mut config = Config::default();
+        assert_eq!(config.duplicatekey, "<M-d>");
+        config.set("dupk=<C-y>").unwrap();
+        assert_eq!(
+            config.set("duplicatekey?").unwrap().as_deref(),
+            Some("duplicatekey=<C-y>")
+        );
+        assert!(matches!(config.set("dupk=ab"), Err(Error::InvalidInput)));
+        assert_eq!(config.duplicatekey, "<C-y>");
+        config.set("dupk=").unwrap();
+        assert_eq!(config.duplicatekey, "");
+    }
+
+    #[test]
+    fn test_set_unknown_option() {
+        let mut config = Config::default();
+        assert!(matches!(
+            config.set("nonsense"),
+            Err(Error::UnknownOption(name)) if name == "nonsense"
+        ));
+    }
+}
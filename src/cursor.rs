@@ -27,6 +27,8 @@ pub struct Cursor {
     pos_initial: LineCol,
     plane: CursorPlane,
     pub last_text_mode_pos: LineCol,
+    /// While selecting, `last_text_mode_pos` is frozen and acts as the anchor of the selection
+    anchored: bool,
 }
 
 #[derive(Debug)]
@@ -68,7 +70,7 @@ impl Component for ShadowCursor {
 impl Component for Cursor {
     #[instrument]
     fn execute_action(&mut self, a: &BaseAction) -> crate::Result<()> {
-        if self.plane.text() {
+        if self.plane.text() && !self.anchored {
             self.last_text_mode_pos = self.pos
         }
         notif_bar!(self.last_text_mode_pos;);
@@ -92,6 +94,7 @@ impl Default for Cursor {
             pos_initial: LineCol::default(),
             plane: CursorPlane::Text,
             last_text_mode_pos: LineCol::default(),
+            anchored: false,
         }
     }
 }
@@ -160,9 +163,24 @@ impl Cursor {
                 self.plane = CursorPlane::CommandBar;
                 self.pos = LineCol { line: 0, col: 0 };
             }
-            Modal::Normal | Modal::Insert | Modal::Visual | Modal::VisualLine => {
+            // The editor places the cursor on the prompt line right after switching planes
+            Modal::Terminal => {
+                self.plane = CursorPlane::Terminal;
+                self.pos = LineCol { line: 0, col: 0 };
+            }
+            Modal::Visual | Modal::VisualLine | Modal::VisualBlock => {
+                if !self.plane.text() {
+                    self.pos = self.last_text_mode_pos;
+                }
+                self.plane = CursorPlane::Text;
+                self.anchored = true;
+            }
+            Modal::Normal | Modal::Insert => {
+                if !self.plane.text() {
+                    self.pos = self.last_text_mode_pos;
+                }
                 self.plane = CursorPlane::Text;
-                self.pos = self.last_text_mode_pos;
+                self.anchored = false;
             }
         }
         self.pos_initial = LineCol {
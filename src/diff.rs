@@ -0,0 +1,74 @@
+//! Unified diffs of the buffer against the file it was loaded from, shown by `:diff`, and the
+//! change signs placed with `changesigns`.
+use similar::{DiffTag, TextDiff};
+
+use crate::sign::SignKind;
+
+/// Lines of the unified diff turning `saved` into `current`, headed by `name`. Empty if the two
+/// don't differ.
+pub fn unified(name: &str, saved: &str, current: &str) -> Vec<String> {
+    let diff = TextDiff::from_lines(saved, current);
+    if diff.ops().iter().all(|op| op.tag() == DiffTag::Equal) {
+        return Vec::new();
+    }
+    diff.unified_diff()
+        .context_radius(3)
+        .header(name, name)
+        .to_string()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+/// The lines of `current` differing from `saved` along with the sign marking how. A line replacing
+/// one of `saved` is changed, the lines beyond those replaced are added. Deleted lines mark the line
+/// in front of them, or the first line when they were at the top.
+pub fn line_changes(saved: &str, current: &str) -> Vec<(usize, SignKind)> {
+    let diff = TextDiff::from_lines(saved, current);
+    let mut changes = Vec::new();
+    for op in diff.ops() {
+        let (tag, old, new) = op.as_tag_tuple();
+        match tag {
+            DiffTag::Equal => (),
+            DiffTag::Delete => changes.push((new.start.saturating_sub(1), SignKind::Removed)),
+            DiffTag::Insert | DiffTag::Replace => changes.extend(new.clone().map(|line| {
+                match tag == DiffTag::Replace && line - new.start < old.len() {
+                    true => (line, SignKind::Changed),
+                    false => (line, SignKind::Added),
+                }
+            })),
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_changes() {
+        assert!(line_changes("a\nb\n", "a\nb\n").is_empty());
+        assert_eq!(
+            line_changes("a\nb\nc\nd\n", "a\nB\nB2\nc\n"),
+            [
+                (1, SignKind::Changed),
+                (2, SignKind::Added),
+                (3, SignKind::Removed)
+            ]
+        );
+        assert_eq!(
+            line_changes("a\nb\n", "b\nc\n"),
+            [(0, SignKind::Removed), (1, SignKind::Added)]
+        );
+    }
+
+    #[test]
+    fn test_unified() {
+        assert!(unified("f", "a\nb\n", "a\nb\n").is_empty());
+        assert_eq!(
+            unified("f", "a\nb\nc\n", "a\nB\nc\n"),
+            ["--- f", "+++ f", "@@ -1,3 +1,3 @@", " a", "-b", "+B", " c"]
+        );
+    }
+}
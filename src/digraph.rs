@@ -0,0 +1,152 @@
+//! Digraphs, two character sequences typed after `Ctrl-K` in insert mode to enter characters
+//! missing from the keyboard. The sequences follow the ones of vim (RFC 1345).
+
+/// The supported subset of digraphs
+const DIGRAPHS: &[(&str, char)] = &[
+    // Latin letters with diacritics
+    ("a:", 'ä'),
+    ("e:", 'ë'),
+    ("i:", 'ï'),
+    ("o:", 'ö'),
+    ("u:", 'ü'),
+    ("y:", 'ÿ'),
+    ("A:", 'Ä'),
+    ("E:", 'Ë'),
+    ("I:", 'Ï'),
+    ("O:", 'Ö'),
+    ("U:", 'Ü'),
+    ("a'", 'á'),
+    ("e'", 'é'),
+    ("i'", 'í'),
+    ("o'", 'ó'),
+    ("u'", 'ú'),
+    ("y'", 'ý'),
+    ("A'", 'Á'),
+    ("E'", 'É'),
+    ("I'", 'Í'),
+    ("O'", 'Ó'),
+    ("U'", 'Ú'),
+    ("a!", 'à'),
+    ("e!", 'è'),
+    ("i!", 'ì'),
+    ("o!", 'ò'),
+    ("u!", 'ù'),
+    ("A!", 'À'),
+    ("E!", 'È'),
+    ("I!", 'Ì'),
+    ("O!", 'Ò'),
+    ("U!", 'Ù'),
+    ("a>", 'â'),
+    ("e>", 'ê'),
+    ("i>", 'î'),
+    ("o>", 'ô'),
+    ("u>", 'û'),
+    ("A>", 'Â'),
+    ("E>", 'Ê'),
+    ("I>", 'Î'),
+    ("O>", 'Ô'),
+    ("U>", 'Û'),
+    ("a?", 'ã'),
+    ("o?", 'õ'),
+    ("n?", 'ñ'),
+    ("N?", 'Ñ'),
+    ("c,", 'ç'),
+    ("C,", 'Ç'),
+    ("c<", 'č'),
+    ("s<", 'š'),
+    ("z<", 'ž'),
+    ("C<", 'Č'),
+    ("S<", 'Š'),
+    ("Z<", 'Ž'),
+    ("aa", 'å'),
+    ("AA", 'Å'),
+    ("ae", 'æ'),
+    ("AE", 'Æ'),
+    ("o/", 'ø'),
+    ("O/", 'Ø'),
+    ("ss", 'ß'),
+    // Greek letters
+    ("a*", 'α'),
+    ("b*", 'β'),
+    ("g*", 'γ'),
+    ("d*", 'δ'),
+    ("e*", 'ε'),
+    ("l*", 'λ'),
+    ("m*", 'μ'),
+    ("p*", 'π'),
+    ("s*", 'σ'),
+    ("D*", 'Δ'),
+    ("S*", 'Σ'),
+    ("W*", 'Ω'),
+    // Symbols
+    ("Eu", '€'),
+    ("Pd", '£'),
+    ("Ye", '¥'),
+    ("Ct", '¢'),
+    ("Co", '©'),
+    ("Rg", '®'),
+    ("SE", '§'),
+    ("PI", '¶'),
+    ("DG", '°'),
+    ("+-", '±'),
+    ("*X", '×'),
+    ("-:", '÷'),
+    ("<<", '«'),
+    (">>", '»'),
+    ("!I", '¡'),
+    ("?I", '¿'),
+    ("12", '½'),
+    ("14", '¼'),
+    ("34", '¾'),
+    ("1S", '¹'),
+    ("2S", '²'),
+    ("3S", '³'),
+    (".M", '·'),
+    ("NS", '\u{a0}'),
+    ("->", '→'),
+    ("<-", '←'),
+    ("-!", '↑'),
+    ("-v", '↓'),
+    ("=>", '⇒'),
+    ("!=", '≠'),
+    ("=<", '≤'),
+    (">=", '≥'),
+    ("00", '∞'),
+    ("OK", '✓'),
+    ("XX", '✗'),
+];
+
+/// Returns the character entered by the digraph `first` `second`. Like in vim, the two characters
+/// may also be typed in reverse order.
+pub fn lookup(first: char, second: char) -> Option<char> {
+    let find = |a: char, b: char| {
+        DIGRAPHS.iter().find_map(|&(digraph, ch)| {
+            let mut chars = digraph.chars();
+            (chars.next() == Some(a) && chars.next() == Some(b)).then_some(ch)
+        })
+    };
+    find(first, second).or_else(|| find(second, first))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(lookup('a', ':'), Some('ä'));
+        assert_eq!(lookup(':', 'a'), Some('ä'));
+        assert_eq!(lookup('E', 'u'), Some('€'));
+        assert_eq!(lookup('q', 'q'), None);
+    }
+
+    #[test]
+    fn test_digraphs_are_unique() {
+        for (i, (digraph, _)) in DIGRAPHS.iter().enumerate() {
+            assert!(
+                DIGRAPHS[i + 1..].iter().all(|(other, _)| other != digraph),
+                "{digraph} is defined twice"
+            );
+        }
+    }
+}
@@ -1,16 +1,63 @@
-use std::{borrow::Cow, collections::VecDeque, fmt::Debug};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    fs::{self, OpenOptions},
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 use crate::{
-    bars::force_notif_bar_content,
+    alternate,
+    bars::{force_notif_bar_content, FileStatus},
     buffer::TextBuffer,
+    clipboard::{SystemClipboard, UNNAMEDPLUS},
+    completion::{Completion, WordCompletion},
+    config::Config,
     cursor::{Cursor, ShadowCursor},
-    viewport::ViewPort,
-    BaseAction, Command, Component, Error, LineCol, Modal, Pattern, Result,
+    diff, digraph, encoding,
+    ex::{self, AddressContext},
+    explore, expression, filetype,
+    fold::{FoldView, Folds},
+    grapheme,
+    keymap::{self, Keymap, MapMode, Mapping},
+    multicursor::{self, CursorEdit},
+    register::{RegisterContent, Registers, UNNAMED_REGISTER},
+    script::{self, ScriptHost},
+    search::SearchPattern,
+    sign::{SignKind, Signs},
+    surround, swap, terminal,
+    textobject::{self, TextObject, TextRange},
+    transform,
+    viewport::{ViewPort, COMMAND_MODE_PROMPT},
+    BaseAction, Command, Component, Error, FindDirection, LineCol, Modal, Pattern, Result,
+    Selection,
+};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
 };
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use tracing::{info, instrument, span, warn, Level};
 
-const JUMP_DIST: usize = 25;
+/// Lines scrolled by a single step of the mouse wheel
+const MOUSE_SCROLL_LINES: usize = 3;
+/// Lines of the previous page still visible after scrolling a full page
+const PAGE_OVERLAP: usize = 2;
+/// Words marking the comments `:todo` places signs on
+const TODO_KEYWORDS: &[&str] = &["TODO", "FIXME", "XXX"];
+/// Range the command line is prefilled with when entering it from visual mode
+const VISUAL_RANGE: &str = "'<,'>";
+/// Command line `gr` opens, followed by the replacement of the word under the cursor
+const CHANGE_ALL_PROMPT: &str = "changeall ";
+/// Nesting depth at which expanding recursive mappings is aborted
+const MAX_MAP_DEPTH: usize = 100;
+/// Size of the viewport of an editor which isn't attached to a terminal
+const HEADLESS_SIZE: (u16, u16) = (80, 24);
+/// Longest time the event loop waits for an event before checking its timers again
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Idle time after an edit before the change signs are brought up to date
+const CHANGE_SIGNS_DELAY: Duration = Duration::from_millis(500);
 
 impl<Buff: TextBuffer> Debug for Editor<Buff> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -25,9 +72,114 @@ pub struct Editor<Buff: TextBuffer> {
     action_history: Vec<Action>,
     action_queue: VecDeque<BaseAction>,
     repeat_action: usize,
+    /// Count typed in front of a command, taken over by `repeat_action` once the command resolves
+    count: Option<usize>,
+    /// Count typed in front of a pending operator, multiplied with the count typed after it, so
+    /// `2d3w` deletes six words
+    operator_count: Option<usize>,
     previous_key: Option<char>,
+    /// Operator waiting for the text object or motion it acts upon
+    pending_operator: Option<Operator>,
+    /// Surround command waiting for its delimiter characters
+    pending_surround: Option<PendingSurround>,
+    /// Insert mode key sequence waiting for the keys completing it
+    pending_insert: Option<PendingInsert>,
+    /// Register selected with `"` for the next command
+    register: Option<char>,
+    registers: Registers,
+    /// Insert mode abbreviations defined with `:ab`, mapping triggers to their expansions
+    abbreviations: HashMap<String, String>,
+    /// Key mappings defined with `:map` and its variants
+    keymap: Keymap,
+    /// Scripts run so far, along with the commands they defined
+    scripts: ScriptHost,
+    /// Folds of the text which are closed
+    folds: Folds,
+    /// Signs shown in the gutter next to the line numbers
+    signs: Signs,
+    /// Whether `:todo` ran, after which its signs follow the edits of the buffer
+    todo_signs: bool,
+    /// Typed keys which start the lhs of a mapping, held back until the mapping is decided
+    pending_keys: Vec<KeyEvent>,
+    /// Keys typed so far for a command which isn't complete yet, like a count or an operator
+    /// waiting for its motion, shown in the info bar
+    typed_command: Vec<KeyEvent>,
+    /// Keys of the change being typed in normal mode, followed by the keys of the insert mode it
+    /// enters, if any
+    change_keys: Vec<KeyEvent>,
+    /// Register a macro is recorded into with `q`, along with the keys typed since recording
+    /// started
+    recording: Option<(char, Vec<KeyEvent>)>,
+    /// Register of the macro played last, played again by `@@`
+    last_macro: Option<char>,
+    /// Number of macros being played within each other, a macro playing itself stops at
+    /// `MAX_MAP_DEPTH`
+    macro_depth: usize,
+    /// Prompt of a command waiting for `y` to carry it out, any other key cancels it
+    confirmation: Option<(String, Command)>,
+    /// Whether the insert mode was entered by a change, its keys being part of it
+    recording_change: bool,
+    /// Keys of the last change, replayed by `.`
+    last_change: Vec<KeyEvent>,
+    /// Edits performed on the text so far, telling whether a command changed it
+    edits: usize,
+    /// Edits made when the change signs were last brought up to date
+    change_signs_edits: usize,
+    /// Where insert mode was entered, `Ctrl-U` deletes the text inserted after it on its line
+    insert_start: LineCol,
+    /// Cursors added with `Ctrl-N` besides the primary one, text typed in insert mode is edited at
+    /// all of them
+    extra_cursors: Vec<LineCol>,
+    /// Column the cursor returns to while scrolling, once scrolled past lines shorter than it
+    desired_col: Option<usize>,
+    /// The previous visual selection, whose lines are addressed by `'<` and `'>`
+    last_visual: Option<Selection>,
+    /// Selections grown with `+` in visual mode, each paired with the one it grew into, which `-`
+    /// shrinks back to
+    expansions: Vec<(TextRange, TextRange)>,
+    /// Pattern and direction of the previous search, repeated by `n` and `N`
+    last_search: Option<(String, FindDirection)>,
+    /// Count typed in front of `/` or `?`, applied once the search is executed
+    search_count: usize,
+    /// Selection a search started from visual mode is confined to, until the search completes
+    search_scope: Option<Selection>,
+    /// Match previewed while typing a search, the cursor only jumps to it once the search runs
+    search_preview: Option<LineCol>,
+    /// First line of the view when the search being typed started, returned to without a match
+    search_preview_top: Option<usize>,
+    /// Marks set with `m`, along with the special marks `` ` ``, the position before the latest
+    /// jump, and `.`, the position of the last change
+    marks: HashMap<char, LineCol>,
+    /// What the help buffer, or another read-only buffer like the one of `:diff`, replaced while it
+    /// is open, restored once it is closed
+    help: Option<HelpReturn<Buff>>,
+    /// Directory listed by the read-only buffer of `:Explore`, Enter opens the entry at the cursor
+    explored: Option<PathBuf>,
+    /// Candidates of the last Tab completion of the command line
+    completion: Option<Completion>,
+    /// Whether the undo group of an insert session is open, taking in every edit from the one
+    /// entering insert mode until insert mode is left
+    insert_undo_group: bool,
+    /// Candidates of the `Ctrl-N`/`Ctrl-P` completion of the word in front of the cursor, kept
+    /// while the keys are pressed one after another in insert mode
+    word_completion: Option<WordCompletion>,
+    /// Text position the left mouse button was pressed on, while it is held down
+    drag_start: Option<LineCol>,
+    /// File the buffer was read from
+    path: Option<PathBuf>,
+    /// Edits of the text made since the swap file was last written
+    unswapped_edits: usize,
+    /// Whether the text changed since it was read from or written to its file
+    modified: bool,
+    /// Whether the file held bytes invalid in its encoding, which were replaced when it was read.
+    /// Writing the text back to the file takes a `!` until `fileencoding` changes.
+    conversion_error: bool,
+    /// When the last terminal event arrived, timers are due after idling from then on
+    last_event: Instant,
+    config: Config,
     cursor: Cursor,
     shadow_cursor: ShadowCursor,
+    /// Components registered with `register_extension`, handed every base action after the editor
     extensions: Vec<Box<dyn Component>>,
 }
 
@@ -59,127 +211,1165 @@ macro_rules! lazy_eval {
 }
 
 impl<Buff: TextBuffer + Debug> Editor<Buff> {
+    /// Creates an editor over the buffer. An editor `without_target` never touches the terminal,
+    /// which allows driving it from tests or embedding it.
     pub fn new(buff: Buff, without_target: bool) -> Self {
+        let viewport = if without_target {
+            ViewPort::headless(HEADLESS_SIZE.0, HEADLESS_SIZE.1)
+        } else {
+            ViewPort::default()
+        };
         Self {
             buffer: buff,
-            viewport: ViewPort::default(),
+            viewport,
             modal: Modal::Normal,
             action_history: Vec::new(),
             action_queue: VecDeque::new(),
             repeat_action: 1,
+            count: None,
+            operator_count: None,
             previous_key: None,
+            pending_operator: None,
+            pending_surround: None,
+            pending_insert: None,
+            register: None,
+            registers: match without_target {
+                true => Registers::default(),
+                false => Registers::with_clipboard(Box::new(SystemClipboard)),
+            },
+            abbreviations: HashMap::new(),
+            keymap: Keymap::default(),
+            scripts: ScriptHost::default(),
+            folds: Folds::default(),
+            signs: Signs::default(),
+            todo_signs: false,
+            pending_keys: Vec::new(),
+            typed_command: Vec::new(),
+            change_keys: Vec::new(),
+            recording: None,
+            last_macro: None,
+            macro_depth: 0,
+            confirmation: None,
+            recording_change: false,
+            last_change: Vec::new(),
+            edits: 0,
+            change_signs_edits: 0,
+            desired_col: None,
+            extra_cursors: Vec::new(),
+            insert_start: LineCol::default(),
+            last_visual: None,
+            expansions: Vec::new(),
+            last_search: None,
+            marks: HashMap::new(),
+            help: None,
+            explored: None,
+            search_count: 1,
+            search_scope: None,
+            search_preview: None,
+            search_preview_top: None,
+            completion: None,
+            word_completion: None,
+            insert_undo_group: false,
+            drag_start: None,
+            path: None,
+            unswapped_edits: 0,
+            modified: false,
+            conversion_error: false,
+            last_event: Instant::now(),
+            config: Config::default(),
             cursor: Cursor::default(),
             extensions: Vec::new(),
             shadow_cursor: ShadowCursor { line: 0, col: 0 },
         }
     }
+    /// Associates the buffer with the file it was read from
+    pub fn set_path(&mut self, path: PathBuf) {
+        let text = self.buffer.get_normal_text();
+        self.config.filetype = filetype::detect(&path, text.first().map(String::as_str));
+        self.path = Some(path);
+        self.notify_swap_file();
+    }
+    /// Notes that the text read from the file lost bytes invalid in its encoding
+    pub fn set_conversion_error(&mut self) {
+        self.conversion_error = true;
+    }
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+    pub const fn cursor_position(&self) -> LineCol {
+        self.cursor.pos
+    }
+    /// Registers an extension, which observes every base action the editor carries out from now
+    /// on, after the buffer, viewport and cursor did. Extensions keep whatever state they need
+    /// themselves, an error returned by one aborts the action like an error of the editor would.
+    pub fn register_extension(&mut self, ext: Box<dyn Component>) {
+        self.extensions.push(ext);
+    }
+    /// Places a sign on `line`, replacing the sign already there. Signs stay on their line number
+    /// while lines are inserted or deleted above them.
+    pub fn place_sign(&mut self, line: usize, kind: SignKind) {
+        self.signs.place(line, kind);
+    }
+    /// Removes every sign of the given kinds
+    pub fn clear_signs(&mut self, kinds: &[SignKind]) {
+        self.signs.clear(kinds);
+    }
+    /// Moves the cursor to `pos`, clamped to the bounds of the buffer, and scrolls it into view.
+    pub fn goto(&mut self, pos: LineCol) -> Result<()> {
+        let line = pos.line.min(self.buffer.max_line());
+        let col = pos.col.min(self.buffer.max_normal_col(line));
+        self.perform_action(BaseAction::SetCursor(LineCol { line, col }))?;
+        self.shadow_cursor.update(self.cursor.pos);
+        self.viewport.scroll_into_view(line);
+        Ok(())
+    }
     pub fn run_event_loop(&mut self) -> Result<()> {
         let span = span!(Level::INFO, "event_loop");
         let _guard = span.enter();
+        let mut redraw = true;
         loop {
-            let command_buf = self.buffer.get_command_text();
-            if !command_buf.is_empty() {
-                force_notif_bar_content(command_buf.to_string());
-            }
-            self.viewport
-                .update_viewport(self.buffer.get_normal_text(), &self.cursor)?;
-            if let Event::Key(key_event) = event::read()? {
-                info!("Interpreting event: {:?}", key_event);
-                let action = match self.modal {
-                    Modal::Normal => self.interpret_normal_event(key_event),
-                    Modal::Insert => self.interpret_insert_event(key_event),
-                    Modal::Command | Modal::Find(_) => self.interpret_command_event(key_event),
-                    _ => continue,
-                }?;
-
-                self.action_history.push(action.clone());
+            if redraw {
+                self.redraw()?;
+            }
+            // Waking up regularly lets timers run without input, only redrawing if they did
+            if !event::poll(self.poll_timeout())? {
+                redraw = self.service_timers()?;
+                continue;
+            }
+            self.last_event = Instant::now();
+            match event::read()? {
+                Event::Key(key_event) => self.process_key(key_event)?,
+                Event::Mouse(mouse_event) if self.config.mouse => {
+                    self.process_mouse(mouse_event)?
+                }
+                Event::Resize(width, height) => {
+                    self.viewport.resize(width, height, self.cursor.line())
+                }
+                // Mouse, focus and paste events don't affect the editor, the loop simply redraws
+                _ => (),
+            }
+            redraw = true;
+        }
+    }
+    /// The command line shown in the notification bar, followed by the completion candidates.
+    /// Only the command line and find modes show it, text left behind never lingers on screen.
+    fn command_line_content(&self) -> Option<String> {
+        if !self.modal.is_command() && !self.modal.is_find() {
+            return None;
+        }
+        let command_buf = self.buffer.get_command_text();
+        match &self.completion {
+            Some(completion) if completion.candidates.len() > 1 => Some(format!(
+                "{command_buf}    {}",
+                completion.candidates.join("  ")
+            )),
+            _ if !command_buf.is_empty() => Some(command_buf.to_string()),
+            _ => None,
+        }
+    }
+    fn redraw(&mut self) -> Result<()> {
+        match self.command_line_content() {
+            Some(command_line) if self.modal.is_command() => {
+                force_notif_bar_content(format!("{COMMAND_MODE_PROMPT}{command_line}"))
+            }
+            None if self.modal.is_command() => {
+                force_notif_bar_content(COMMAND_MODE_PROMPT.to_string())
+            }
+            Some(command_line) => force_notif_bar_content(command_line),
+            None => (),
+        }
+        let text = if self.modal.is_terminal() {
+            self.buffer.get_terminal_text()
+        } else {
+            self.buffer.get_normal_text()
+        };
+        if let Some(hint) = self.pending_keys_hint() {
+            force_notif_bar_content(hint);
+        }
+        if let Some((prompt, _)) = &self.confirmation {
+            force_notif_bar_content(prompt.clone());
+        }
+        self.viewport.set_pending_command(self.pending_command());
+        self.viewport.set_extra_cursors(self.extra_cursors.clone());
+        self.viewport
+            .set_recording(self.recording.as_ref().map(|&(register, _)| register));
+        self.viewport.set_status(FileStatus {
+            format: self.config.statusline.clone(),
+            path: self.path.as_ref().map(|path| path.display().to_string()),
+            filetype: self.config.filetype.clone(),
+            modified: self.modified,
+            line_count: text.len(),
+        });
+        let folds = if self.modal.is_terminal() {
+            FoldView::default()
+        } else {
+            self.folds.view(text)
+        };
+        let search = match &self.last_search {
+            Some((pattern, _)) if self.config.hlsearch => Some(pattern.as_str()),
+            _ => None,
+        };
+        self.viewport
+            .update_viewport(text, &self.cursor, &folds, &self.signs, search)
+    }
+    /// Timers running while no events arrive, each due after idling for its duration
+    fn timers(&self) -> Vec<(Timer, Duration)> {
+        let mut timers = Vec::new();
+        // Keys held back for a mapping are decided on if the next one doesn't follow in time
+        if !self.pending_keys.is_empty() {
+            let timeout = Duration::from_millis(self.config.timeoutlen);
+            timers.push((Timer::PendingKeys, timeout));
+        }
+        if self.config.autosave > 0 && self.modified {
+            let idle = Duration::from_millis(self.config.autosave);
+            timers.push((Timer::Autosave, idle));
+        }
+        if self.config.changesigns && self.edits != self.change_signs_edits {
+            timers.push((Timer::ChangeSigns, CHANGE_SIGNS_DELAY));
+        }
+        timers
+    }
+    /// How long to wait for the next event, until the next timer is due but never longer than
+    /// `POLL_INTERVAL`
+    fn poll_timeout(&self) -> Duration {
+        let idle = self.last_event.elapsed();
+        self.timers()
+            .into_iter()
+            .map(|(_, due)| due.saturating_sub(idle))
+            .fold(POLL_INTERVAL, Duration::min)
+    }
+    /// Runs the timers which are due.
+    ///
+    /// # Returns
+    ///
+    /// Whether any timer ran, requiring a redraw.
+    fn service_timers(&mut self) -> Result<bool> {
+        let idle = self.last_event.elapsed();
+        let mut ran = false;
+        for (timer, due) in self.timers() {
+            if idle < due {
+                continue;
+            }
+            match timer {
+                Timer::PendingKeys => self.timeout_pending_keys()?,
+                Timer::Autosave => self.autosave(),
+                Timer::ChangeSigns => self.refresh_change_signs(),
+            }
+            ran = true;
+        }
+        Ok(ran)
+    }
+    /// Interprets a single key event and performs the resulting actions, exactly as if the key
+    /// was typed into the terminal. Keys are remapped by the mappings of the current mode.
+    pub fn process_key(&mut self, key_event: KeyEvent) -> Result<()> {
+        if let Some((_, keys)) = &mut self.recording {
+            keys.push(keymap::normalize(key_event));
+        }
+        if let Some((_, command)) = self.confirmation.take() {
+            return self.confirm(command, key_event);
+        }
+        let result = self.feed_key(key_event, 0);
+        self.abort_recursive_mapping(result)
+    }
+    /// Carries out the command waiting for confirmation if `key_event` is `y`
+    fn confirm(&mut self, command: Command, key_event: KeyEvent) -> Result<()> {
+        if key_event.code != KeyCode::Char('y') {
+            force_notif_bar_content("Cancelled".to_string());
+            return Ok(());
+        }
+        self.repeat_action = 1;
+        let actions = self.resolve_command_action(command)?;
+        self.action_queue.extend(actions);
+        self.consume_action_queue()
+    }
+    /// Decides on the keys held back for a mapping once no further key arrived in time, as if
+    /// none of the longer mappings they start could follow.
+    pub fn timeout_pending_keys(&mut self) -> Result<()> {
+        let result = match self.map_mode() {
+            Some(mode) if !self.pending_keys.is_empty() => self.apply_pending_keys(mode, 0),
+            _ => Ok(()),
+        };
+        self.abort_recursive_mapping(result)
+    }
+    /// Reports a mapping expanding into itself instead of failing with it, and ends a macro which
+    /// stopped playing
+    fn abort_recursive_mapping(&mut self, result: Result<()>) -> Result<()> {
+        match result {
+            Err(Error::RecursiveMapping) => {
+                self.pending_keys.clear();
+                force_notif_bar_content("Recursive mapping".to_string());
+                Ok(())
+            }
+            Err(Error::MacroAborted) => {
+                self.pending_keys.clear();
+                Ok(())
+            }
+            result => result,
+        }
+    }
+    /// Collects keys until they either form the lhs of a mapping, which is replaced by its rhs,
+    /// or can't start one anymore, in which case the first key is interpreted unmapped and the
+    /// remaining ones are looked up again.
+    fn feed_key(&mut self, key_event: KeyEvent, depth: usize) -> Result<()> {
+        let Some(mode) = self.map_mode() else {
+            return self.process_unmapped_key(key_event);
+        };
+        self.pending_keys.push(key_event);
+        if self.keymap.is_pending(mode, &self.pending_keys) {
+            return Ok(());
+        }
+        self.apply_pending_keys(mode, depth)
+    }
+    /// Replaces the longest lhs the pending keys start with by its rhs, or interprets the first
+    /// key unmapped if there is none, and feeds the remaining keys again
+    fn apply_pending_keys(&mut self, mode: MapMode, depth: usize) -> Result<()> {
+        let (len, mapping) = match self.keymap.lookup(mode, &self.pending_keys) {
+            Some((len, mapping)) => (len, Some(mapping.clone())),
+            None => (1, None),
+        };
+        let rest = self.pending_keys.split_off(len);
+        let keys = std::mem::take(&mut self.pending_keys);
+        match mapping {
+            Some(mapping) => self.expand_mapping(&keys, mapping, depth)?,
+            None => self.process_unmapped_key(keys[0])?,
+        }
+        for key in rest {
+            self.feed_key(key, depth)?;
+        }
+        Ok(())
+    }
+    /// Feeds the rhs of a mapping, looking up mappings within it again unless it is a noremap one
+    fn expand_mapping(&mut self, lhs: &[KeyEvent], mapping: Mapping, depth: usize) -> Result<()> {
+        if depth >= MAX_MAP_DEPTH {
+            return Err(Error::RecursiveMapping);
+        }
+        // Like in vim, the first key of a rhs starting with its own lhs isn't remapped
+        let lhs: Vec<_> = lhs.iter().map(|&key| keymap::normalize(key)).collect();
+        let starts_with_lhs = mapping.rhs.starts_with(&lhs);
+        for (i, key) in mapping.rhs.into_iter().enumerate() {
+            if mapping.noremap || (i == 0 && starts_with_lhs) {
+                self.process_unmapped_key(key)?;
+            } else {
+                self.feed_key(key, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+    /// Shows the keys held back for a mapping followed by the keys which may complete them
+    fn pending_keys_hint(&self) -> Option<String> {
+        let mode = self.map_mode().filter(|_| !self.pending_keys.is_empty())?;
+        let continuations: Vec<_> = self
+            .keymap
+            .continuations(mode, &self.pending_keys)
+            .into_iter()
+            .map(|(rest, mapping)| {
+                format!(
+                    "{} → {}",
+                    keymap::key_notation(rest),
+                    keymap::key_notation(&mapping.rhs)
+                )
+            })
+            .collect();
+        Some(format!(
+            "{}    {}",
+            keymap::key_notation(&self.pending_keys),
+            continuations.join("  ")
+        ))
+    }
+    /// Mode whose mappings apply to the next key, `None` while the key completes a pending command
+    fn map_mode(&self) -> Option<MapMode> {
+        if self.awaits_completion() {
+            return None;
+        }
+        MapMode::of(self.modal)
+    }
+    /// Whether the next key completes a command started by the previous ones
+    const fn awaits_completion(&self) -> bool {
+        self.pending_operator.is_some()
+            || self.pending_surround.is_some()
+            || self.pending_insert.is_some()
+            || self.previous_key.is_some()
+    }
+    /// Whether the keys typed so far form an incomplete command, including a count or register
+    const fn is_command_incomplete(&self) -> bool {
+        self.awaits_completion() || self.count.is_some() || self.register.is_some()
+    }
+    /// The keys of the command being typed followed by those held back for a mapping, empty if
+    /// there are none
+    fn pending_command(&self) -> String {
+        let keys: Vec<_> = self
+            .typed_command
+            .iter()
+            .chain(&self.pending_keys)
+            .map(|&key| keymap::normalize(key))
+            .collect();
+        keymap::key_notation(&keys)
+    }
+    fn process_unmapped_key(&mut self, key_event: KeyEvent) -> Result<()> {
+        info!("Interpreting event: {:?}", key_event);
+        let (modal, edits) = (self.modal, self.edits);
+        let action = match self.modal {
+            Modal::Normal => self.interpret_normal_event(key_event),
+            Modal::Insert => self.interpret_insert_event(key_event),
+            Modal::Visual | Modal::VisualLine | Modal::VisualBlock => {
+                self.interpret_visual_event(key_event)
+            }
+            Modal::Command | Modal::Find(_) => self.interpret_command_event(key_event),
+            Modal::Terminal => Ok(Self::interpret_terminal_event(key_event)),
+        }?;
+
+        self.action_history.push(action.clone());
+        // Like in vim, a macro stops at the first motion or deletion which can't be carried out
+        let must_move = self.macro_depth > 0 && action.fails_in_place();
+        let pos = self.cursor.pos;
+        // Only typing edits the text at every cursor, other edits leave the additional ones behind
+        let types = self.modal.is_insert()
+            && matches!(
+                action,
+                Action::InsertCharAtCursor(_) | Action::DeleteBeforeCursor
+            );
+        match action {
+            Action::RepeatChange => {
+                self.change_keys.clear();
+                self.repeat_change()?;
+            }
+            Action::PlayMacro(register) => self.play_macro(register)?,
+            action => {
                 self.add_to_action_queue(action)?;
                 self.consume_action_queue()?;
+                self.record_change(key_event, modal, edits);
+            }
+        }
+        if self.edits > edits && !types {
+            self.extra_cursors.clear();
+        }
+        if self.modal.is_insert() && !modal.is_insert() {
+            self.insert_start = self.cursor.pos;
+        }
+        if modal.is_insert() && !self.modal.is_insert() {
+            let _ = self.marks.insert('^', self.cursor.pos);
+        }
 
-                self.shadow_cursor.update(self.cursor.pos)
+        // The typed keys are kept for as long as they form an incomplete command
+        if self.is_command_incomplete() && self.modal.is_text() {
+            self.typed_command.push(key_event);
+        } else {
+            self.typed_command.clear();
+        }
+
+        self.shadow_cursor.update(self.cursor.pos);
+        if must_move && self.cursor.pos == pos && self.edits == edits {
+            return Err(Error::MacroAborted);
+        }
+        Ok(())
+    }
+    /// Collects the keys of changes for `.`: the keys of a normal mode command which edits the text
+    /// or enters insert mode, and in the latter case the keys typed until insert mode is left.
+    /// `modal` is the mode the key was typed in and `edits` the edits performed before it.
+    fn record_change(&mut self, key_event: KeyEvent, modal: Modal, edits: usize) {
+        match modal {
+            Modal::Normal => {
+                self.change_keys.push(key_event);
+                if self.is_command_incomplete() {
+                    return;
+                }
+                if self.modal.is_insert() {
+                    self.recording_change = true;
+                } else if self.edits > edits {
+                    self.last_change = std::mem::take(&mut self.change_keys);
+                } else {
+                    self.change_keys.clear();
+                }
+            }
+            Modal::Insert if self.recording_change => {
+                self.change_keys.push(key_event);
+                if !self.modal.is_insert() {
+                    self.recording_change = false;
+                    self.last_change = std::mem::take(&mut self.change_keys);
+                }
+            }
+            _ => (),
+        }
+    }
+    /// Replays the last change `count` times, as a single undo step. Every repetition starts at
+    /// the cursor the previous one left.
+    fn repeat_change(&mut self) -> Result<()> {
+        let count = self.count.take().unwrap_or(1);
+        let keys = self.last_change.clone();
+        self.buffer.begin_undo_group();
+        let mut replayed = Ok(());
+        for _ in 0..count {
+            replayed = keys
+                .iter()
+                .try_for_each(|&key| self.process_unmapped_key(key));
+            if replayed.is_err() {
+                break;
+            }
+        }
+        self.buffer.end_undo_group();
+        replayed
+    }
+    /// Plays the keys recorded into `register` `count` times, as if they were typed. `@` plays the
+    /// macro played last. Playing stops at the first key which fails, including those of the
+    /// macros played within it.
+    fn play_macro(&mut self, register: char) -> Result<()> {
+        let count = self.count.take().unwrap_or(1);
+        let register = match (register, self.last_macro) {
+            ('@', Some(last)) => last,
+            ('@', None) => {
+                force_notif_bar_content("No previously used register".to_string());
+                return Ok(());
             }
+            (register, _) => register,
+        };
+        let Some(content) = self.registers.get(register) else {
+            force_notif_bar_content(format!("Register {register} is empty"));
+            return Ok(());
+        };
+        let keys = keymap::parse_keys(&content.lines.join("\n"), self.config.mapleader);
+        self.last_macro = Some(register);
+        if self.macro_depth >= MAX_MAP_DEPTH {
+            force_notif_bar_content("Recursive macro".to_string());
+            return Err(Error::MacroAborted);
         }
+        self.macro_depth += 1;
+        let played =
+            (0..count).try_for_each(|_| keys.iter().try_for_each(|&key| self.feed_key(key, 0)));
+        self.macro_depth -= 1;
+        played
     }
+    /// Performs the queued actions. However many edits a command repeated by a count makes, a
+    /// single undo takes them back.
     fn consume_action_queue(&mut self) -> Result<()> {
         info!("Contents of Action Queue: {:?}", self.action_queue);
         let actions: Vec<_> = self.action_queue.drain(..).collect();
+        // Edits made on the way into insert mode, like the deletion of `cw`, are undone with the
+        // typed text
+        let enters_insert = actions
+            .iter()
+            .any(|action| matches!(action, BaseAction::ChangeMode(Modal::Insert)));
+        if enters_insert && !self.modal.is_insert() {
+            self.begin_insert_undo_group();
+        }
+        if self.repeat_action == 1 {
+            return self.perform_actions(actions);
+        }
+        self.buffer.begin_undo_group();
+        let performed = self.perform_actions(actions);
+        self.buffer.end_undo_group();
+        performed
+    }
+    fn begin_insert_undo_group(&mut self) {
+        if !self.insert_undo_group {
+            self.buffer.begin_undo_group();
+            self.insert_undo_group = true;
+        }
+    }
+    fn perform_actions(&mut self, actions: Vec<BaseAction>) -> Result<()> {
+        let line_before = self.cursor.line();
+        let mut edited = false;
         for action in actions {
+            // Edits of the command line and terminal aren't kept in the swap file
+            if action.is_edit() && self.modal.is_text() && self.help.is_none() {
+                self.unswapped_edits += 1;
+                self.modified = true;
+            }
+            let edit = action.is_edit() && self.modal.is_text();
+            if edit && self.help.is_some() {
+                force_notif_bar_content("This buffer is read-only".to_string());
+                continue;
+            }
             self.perform_action(action)?;
+            if edit {
+                let _ = self.marks.insert('.', self.cursor.pos);
+                self.edits += 1;
+                edited = true;
+            }
+        }
+        self.update_swap_file();
+        if edited && self.todo_signs {
+            let _ = self.place_todo_signs();
+        }
+
+        if self.modal.is_find() {
+            self.update_search_preview();
+        }
+        // However far the actions moved the cursor or shrank the text, the cursor stays on screen
+        if self.modal.is_text() {
+            self.skip_closed_folds(line_before)?;
+            self.viewport.scroll_into_view(self.cursor.line());
+        }
+        Ok(())
+    }
+    /// Previews the match of the search typed so far, found from where the search started like
+    /// once it runs. The match is scrolled into view while the cursor stays on the command line,
+    /// without a match the view returns to where it was.
+    fn update_search_preview(&mut self) {
+        let Modal::Find(direction) = self.modal else {
+            return;
+        };
+        let buf = self.buffer.get_command_text();
+        let pattern = buf.strip_prefix(direction.symbol()).unwrap_or(buf);
+        let from = self.cursor.last_text_mode_pos;
+        let forwards = direction == FindDirection::Forwards;
+        let target = match pattern.is_empty() {
+            true => None,
+            false => self
+                .search_matches(pattern, from, forwards, self.search_count)
+                .ok(),
+        };
+        let found = target.and_then(|at| {
+            let line = &self.buffer.get_normal_text()[at.line];
+            Some((
+                at.line,
+                SearchPattern::parse(pattern).match_at(line, at.col)?,
+            ))
+        });
+
+        let top = *self
+            .search_preview_top
+            .get_or_insert(self.viewport.top_border());
+        self.viewport.scroll_to(top);
+        if let Some(at) = target {
+            self.viewport.scroll_into_view(at.line);
         }
+        self.viewport.set_search_preview(found);
+        self.search_preview = target;
+    }
+    /// Drops the preview of the search once it runs or is cancelled, returning to the view it
+    /// started from
+    fn end_search_preview(&mut self) {
+        self.search_preview = None;
+        self.viewport.set_search_preview(None);
+        if let Some(top) = self.search_preview_top.take() {
+            self.viewport.scroll_to(top);
+        }
+    }
+    /// Replaces the `Todo` signs with signs on every line containing one of `TODO_KEYWORDS`, lines
+    /// with another sign keep it. Returns the number of lines found.
+    fn place_todo_signs(&mut self) -> usize {
+        self.signs.clear(SignKind::TODOS);
+        let lines = self.buffer.get_normal_text();
+        let mut found = 0;
+        for line in 0..lines.len() {
+            if TODO_KEYWORDS
+                .iter()
+                .any(|keyword| keyword.find_pattern(&lines[line..=line]).is_some())
+            {
+                if self.signs.get(line).is_none() {
+                    self.signs.place(line, SignKind::Todo);
+                }
+                found += 1;
+            }
+        }
+        found
+    }
+    /// Inserts `text` at the cursor as a single edit, splitting the cursor line at its newlines.
+    /// Returns the edit along with the position behind the inserted text.
+    fn resolve_insert_text(&self, text: &str) -> (BaseAction, LineCol) {
+        let pos = self.cursor.pos;
+        let line = &self.buffer.get_normal_text()[pos.line];
+        let (before, after) = line.split_at(pos.col.min(line.len()));
+        let inserted = format!("{before}{text}");
+        let lines: Vec<String> = format!("{inserted}{after}")
+            .split('\n')
+            .map(String::from)
+            .collect();
+        let last = inserted.rsplit('\n').next().unwrap_or_default();
+        let end = LineCol {
+            line: pos.line + lines.len() - 1,
+            col: last.len(),
+        };
+        (BaseAction::ReplaceLines(pos.line..pos.line + 1, lines), end)
+    }
+    /// Returns to normal mode and carries out the requests of a script in order, reporting the
+    /// failure of the script instead if it failed
+    fn perform_script_requests(
+        &mut self,
+        requests: Result<Vec<script::Request>>,
+    ) -> Result<Vec<BaseAction>> {
+        self.perform_action(BaseAction::ChangeMode(Modal::Normal))?;
+        let requests = match requests {
+            Ok(requests) => requests,
+            Err(Error::Script(message)) => {
+                force_notif_bar_content(format!("Script error: {message}"));
+                return ok_vec!();
+            }
+            Err(Error::Io(e)) => {
+                force_notif_bar_content(format!("Can't read script: {e}"));
+                return ok_vec!();
+            }
+            Err(e) => return Err(e),
+        };
+        for request in requests {
+            match request {
+                script::Request::InsertText(text) => {
+                    let (replace, end) = self.resolve_insert_text(&text);
+                    self.perform_action(replace)?;
+                    self.goto(end)?;
+                }
+                script::Request::Goto(target) => self.goto(target)?,
+                script::Request::Notify(message) => force_notif_bar_content(message),
+                script::Request::Map { mode, lhs, rhs } => {
+                    let command = self.parse_ex_command(&format!("{mode}noremap {lhs} {rhs}"));
+                    for action in self.resolve_command_action(command)? {
+                        self.perform_action(action)?;
+                    }
+                }
+            }
+        }
+        ok_vec!()
+    }
+    /// Writes the text to the swap file once an edit is completed by leaving insert mode, or
+    /// after many edits within insert mode
+    fn update_swap_file(&mut self) {
+        let Some(path) = self.path.as_deref().filter(|_| self.config.swapfile) else {
+            return;
+        };
+        let completed = !self.modal.is_insert() || self.unswapped_edits >= swap::UPDATE_COUNT;
+        if self.unswapped_edits == 0 || !completed {
+            return;
+        }
+        if let Err(e) = swap::write(path, self.buffer.get_normal_text()) {
+            force_notif_bar_content(format!("Can't write swap file: {e}"));
+        }
+        self.unswapped_edits = 0;
+    }
+    /// Places the signs of the lines differing from the file on disk, replacing the previous ones.
+    /// Other signs keep their lines, and without `changesigns` the change signs are removed.
+    pub fn refresh_change_signs(&mut self) {
+        self.signs.clear(SignKind::CHANGES);
+        self.change_signs_edits = self.edits;
+        let path = self.path.as_deref().filter(|_| self.help.is_none());
+        let Some(saved) = path
+            .filter(|_| self.config.changesigns)
+            .and_then(|path| fs::read(path).ok())
+        else {
+            return;
+        };
+        let (saved, _) = encoding::decode(&saved, &self.config.fileencoding);
+        let mut current = self.buffer.get_normal_text().join("\n");
+        current.push('\n');
+        for (line, kind) in diff::line_changes(&saved, &current) {
+            if self.signs.get(line).is_none() {
+                self.signs.place(line, kind);
+            }
+        }
+    }
+    /// Writes the modified text to its file once the editor has been idle for `autosave`
+    pub fn autosave(&mut self) {
+        // Writing would replace the invalid bytes of the file, which takes an explicit `:w!`
+        if !self.modified || self.conversion_error {
+            return;
+        }
+        match self.write_file() {
+            Ok(Some(path)) => {
+                self.refresh_change_signs();
+                force_notif_bar_content(format!("\"{}\" written", path.display()))
+            }
+            Ok(None) => (),
+            Err(e) => force_notif_bar_content(format!("Can't autosave: {e}")),
+        }
+    }
+    /// Writes the text to the file it was read from, whose swap file is then no longer needed.
+    ///
+    /// # Returns
+    ///
+    /// The path written to, `None` if the buffer isn't associated with a file.
+    fn write_file(&mut self) -> Result<Option<PathBuf>> {
+        let Some(path) = self.path.clone() else {
+            return Ok(None);
+        };
+        let lines = self.buffer.get_normal_text();
+        write_lines(&path, lines, false, &self.config.fileencoding)?;
+        self.modified = false;
+        self.discard_swap_file();
+        Ok(Some(path))
+    }
+    /// Removes the swap file of the edited file, once its changes no longer need to be kept
+    fn discard_swap_file(&mut self) {
+        if let Some(path) = &self.path {
+            if let Err(e) = swap::remove(path) {
+                warn!("Couldn't remove swap file: {:?}", e);
+            }
+        }
+        self.unswapped_edits = 0;
+    }
+    /// Warns about a swap file left behind for the edited file, which `:recover` restores
+    fn notify_swap_file(&self) {
+        match &self.path {
+            Some(path) if self.config.swapfile && swap::exists(path) => {
+                force_notif_bar_content(format!(
+                    "Found swap file {}, :recover restores it",
+                    swap::swap_path(path).display()
+                ))
+            }
+            _ => (),
+        }
+    }
+    /// Moves the cursor out of the lines hidden by a closed fold, past the fold if it moved down
+    /// into it from `line_before` and onto the line the fold is displayed as otherwise
+    fn skip_closed_folds(&mut self, line_before: usize) -> Result<()> {
+        let folds = self.folds.view(self.buffer.get_normal_text());
+        let Some(fold) = folds
+            .closed_fold(self.cursor.line())
+            .filter(|fold| fold.start != self.cursor.line())
+        else {
+            return Ok(());
+        };
+        let moved_down = line_before <= fold.start;
+        let line = match moved_down && fold.end <= self.buffer.max_line() {
+            true => fold.end,
+            false => fold.start,
+        };
+        let col = self.cursor.col().min(self.buffer.max_normal_col(line));
+        self.perform_action(BaseAction::SetCursor(LineCol { line, col }))
+    }
+
+    /// Moves the cursor to the clicked text position and scrolls on mouse wheel events. Dragging
+    /// selects the text in visual mode. Clicks leave visual mode and are ignored while typing into
+    /// the command bar. The terminal plane doesn't react to the mouse.
+    pub fn process_mouse(&mut self, mouse_event: MouseEvent) -> Result<()> {
+        if self.modal.is_terminal() {
+            return Ok(());
+        }
+        let action = match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let text = self.buffer.get_normal_text();
+                let folds = self.folds.view(text);
+                let target = self.viewport.buffer_position(
+                    mouse_event.column,
+                    mouse_event.row,
+                    text,
+                    &folds,
+                );
+                let (Some(target), false) =
+                    (target, self.modal.is_command() || self.modal.is_find())
+                else {
+                    return Ok(());
+                };
+                // Clicking a marker of the fold column toggles its fold
+                let in_fold_column = (mouse_event.column as usize) < self.config.foldcolumn;
+                if in_fold_column && folds.marker(target.line).is_some() {
+                    self.add_to_action_queue(Action::ToggleFold(target.line))?;
+                    self.consume_action_queue()?;
+                    self.shadow_cursor.update(self.cursor.pos);
+                    return Ok(());
+                }
+                if self.modal.is_selecting() {
+                    self.perform_action(BaseAction::ChangeMode(Modal::Normal))?;
+                }
+                self.goto(target)?;
+                self.drag_start = Some(self.cursor.pos);
+                return Ok(());
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                return self.drag_to(mouse_event.column, mouse_event.row)
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                self.drag_start = None;
+                return Ok(());
+            }
+            MouseEventKind::ScrollUp => Action::ScrollLineUp,
+            MouseEventKind::ScrollDown => Action::ScrollLineDown,
+            _ => return Ok(()),
+        };
+        self.add_to_action_queue(action)?;
+        self.consume_action_queue()?;
+        self.shadow_cursor.update(self.cursor.pos);
         Ok(())
     }
 
+    /// Extends the selection to the dragged to screen position, entering visual mode anchored at
+    /// the press position on the first movement. Dragging onto the top row or past the last text
+    /// row scrolls the view.
+    fn drag_to(&mut self, column: u16, row: u16) -> Result<()> {
+        let Some(start) = self.drag_start else {
+            return Ok(());
+        };
+        if !self.modal.is_visual() {
+            self.goto(start)?;
+            self.perform_action(BaseAction::ChangeMode(Modal::Visual))?;
+        }
+
+        let last_row = self.viewport.text_rows().saturating_sub(1) as u16;
+        let top = self.viewport.top_border();
+        if row >= last_row && top < self.buffer.max_line() {
+            self.perform_action(BaseAction::ScrollDown(1))?;
+        } else if row == 0 && top > 0 {
+            self.perform_action(BaseAction::ScrollUp(1))?;
+        }
+        let text = self.buffer.get_normal_text();
+        let folds = self.folds.view(text);
+        match self
+            .viewport
+            .buffer_position(column, row.min(last_row), text, &folds)
+        {
+            Some(target) => self.goto(target),
+            None => Ok(()),
+        }
+    }
+
+    /// Maps keys moving the cursor through the text, shared by the normal and visual interpreters
+    fn interpret_motion(key_event: KeyEvent) -> Option<Action> {
+        Binding::lookup(MOTIONS, key_event)
+    }
+
+    /// Accumulates a typed digit into the pending count.
+    /// Returns false if the key doesn't continue a count, a leading `0` never starts one.
+    fn accumulate_count(&mut self, key_event: KeyEvent) -> bool {
+        let KeyCode::Char(ch @ '0'..='9') = key_event.code else {
+            return false;
+        };
+        if ch == '0' && self.count.is_none() {
+            return false;
+        }
+        let digit = ch.to_digit(10).expect("Matched on ascii digits") as usize;
+        self.count = Some(
+            self.count
+                .unwrap_or(0)
+                .saturating_mul(10)
+                .saturating_add(digit),
+        );
+        true
+    }
+
+    /// Interprets the keys following an operator, which are either a text object or the operator
+    /// key again to act on whole lines. An `s` turns `y`, `d` and `c` into the surround commands.
+    fn interpret_operator_pending(&mut self, op: Operator, key_event: KeyEvent) -> Action {
+        let action = if let Some(prev) = self.previous_key.take() {
+            let inner = prev == 'i';
+            match key_event.code {
+                KeyCode::Char('w') => Action::Operate(op, TextObject::Word { inner }),
+                KeyCode::Char('p') => Action::Operate(op, TextObject::Paragraph { inner }),
+                KeyCode::Char('t') => Action::Operate(op, TextObject::Tag { inner }),
+                KeyCode::Char('i') => Action::Operate(op, TextObject::Indent { inner }),
+                _ => Action::Nothing,
+            }
+        } else if self.accumulate_count(key_event) {
+            return Action::Nothing;
+        } else {
+            match key_event.code {
+                KeyCode::Char(c) if c == op.key() => Action::Operate(op, TextObject::Line),
+                KeyCode::Char(c @ ('w' | 'W')) => {
+                    Action::Operate(op, TextObject::NextWord { big: c == 'W' })
+                }
+                KeyCode::Char(c @ ('j' | 'k')) => {
+                    Action::Operate(op, TextObject::Vertical { up: c == 'k' })
+                }
+                KeyCode::Char('%') if self.count.or(self.operator_count).is_some() => {
+                    Action::Operate(op, TextObject::Percent)
+                }
+                KeyCode::Char('s')
+                    if matches!(op, Operator::Yank | Operator::Delete | Operator::Change) =>
+                {
+                    self.merge_operator_count();
+                    self.pending_operator = None;
+                    match op {
+                        Operator::Yank => self.pending_operator = Some(Operator::Surround),
+                        Operator::Delete => self.pending_surround = Some(PendingSurround::Delete),
+                        _ => self.pending_surround = Some(PendingSurround::Change(None)),
+                    }
+                    return Action::Nothing;
+                }
+                KeyCode::Char(c @ ('i' | 'a')) => {
+                    self.previous_key = Some(c);
+                    return Action::Nothing;
+                }
+                _ => Action::Nothing,
+            }
+        };
+
+        self.pending_operator = None;
+        self.merge_operator_count();
+        if let Action::Operate(Operator::Surround, object) = action {
+            self.pending_surround = Some(PendingSurround::Add(object));
+            return Action::Nothing;
+        }
+        if matches!(action, Action::Nothing) {
+            self.count = None;
+            self.register = None;
+        }
+        action
+    }
+
+    /// Combines the counts typed in front of and after an operator into the count of the command
+    fn merge_operator_count(&mut self) {
+        self.count = match (self.operator_count.take(), self.count) {
+            (Some(before), Some(after)) => Some(before.saturating_mul(after)),
+            (before, after) => before.or(after),
+        };
+    }
+
+    /// Interprets the delimiter characters completing a surround command
+    fn interpret_surround_pending(
+        &mut self,
+        pending: PendingSurround,
+        key_event: KeyEvent,
+    ) -> Action {
+        let KeyCode::Char(ch) = key_event.code else {
+            self.count = None;
+            return Action::Nothing;
+        };
+        match pending {
+            PendingSurround::Add(object) => Action::Surround(object, ch),
+            PendingSurround::Delete => Action::DeleteSurround(ch),
+            PendingSurround::Change(None) => {
+                self.pending_surround = Some(PendingSurround::Change(Some(ch)));
+                Action::Nothing
+            }
+            PendingSurround::Change(Some(old)) => Action::ChangeSurround(old, ch),
+        }
+    }
+
     fn interpret_normal_event(&mut self, key_event: KeyEvent) -> Result<Action> {
+        if let Some(op) = self.pending_operator {
+            return Ok(self.interpret_operator_pending(op, key_event));
+        }
+        if let Some(pending) = self.pending_surround.take() {
+            return Ok(self.interpret_surround_pending(pending, key_event));
+        }
         let action = if let Some(prev) = self.previous_key.take() {
             match (prev, key_event.code) {
-                ('t', KeyCode::Char(c)) => Action::FindChar(c),
-                ('T', KeyCode::Char(c)) => Action::ReverseFindChar(c),
+                ('t', KeyCode::Char(c)) => Action::ToChar(c),
+                ('T', KeyCode::Char(c)) => Action::ReverseToChar(c),
                 ('f', KeyCode::Char(c)) => Action::FindChar(c),
                 ('F', KeyCode::Char(c)) => Action::ReverseFindChar(c),
                 ('r', KeyCode::Char(c)) => Action::Replace(c),
-                ('p', KeyCode::Char(c)) => Action::Paste(c),
-                ('P', KeyCode::Char(c)) => Action::PasteAbove(c),
+                ('m', KeyCode::Char(c)) => Action::SetMark(c),
+                ('`', KeyCode::Char(mark)) => Action::JumpToMark {
+                    mark,
+                    linewise: false,
+                },
+                ('\'', KeyCode::Char(mark)) => Action::JumpToMark {
+                    mark,
+                    linewise: true,
+                },
+                ('g', KeyCode::Char('g')) => Action::JumpSOF,
+                ('g', KeyCode::Char('f')) => Action::OpenFile,
+                ('g', KeyCode::Char('r')) => Action::ChangeAllPrompt,
+                ('g', KeyCode::Char('i')) => Action::ResumeInsert,
+                ('g', KeyCode::Char('?')) => {
+                    Action::ExecuteCommand(Command::Help(HELP_KEYS.to_string()))
+                }
+                ('z', KeyCode::Char('a')) => Action::ToggleFold(self.cursor.line()),
+                ('z', KeyCode::Char('A')) => Action::ToggleFoldRecursively(self.cursor.line()),
+                ('z', KeyCode::Char('M')) => Action::CloseAllFolds,
+                ('z', KeyCode::Char('R')) => Action::OpenAllFolds,
+                ('z', KeyCode::Char('z')) => Action::CenterLine,
+                (bracket @ (']' | '['), KeyCode::Char(c)) if c == bracket => {
+                    Action::JumpToSection {
+                        forwards: bracket == ']',
+                    }
+                }
+                (bracket @ (']' | '['), KeyCode::Char('d')) => Action::JumpToSign {
+                    forwards: bracket == ']',
+                    kinds: SignKind::DIAGNOSTICS,
+                },
+                (bracket @ (']' | '['), KeyCode::Char('t')) => Action::JumpToSign {
+                    forwards: bracket == ']',
+                    kinds: SignKind::TODOS,
+                },
+                ('g', KeyCode::Char('c')) => {
+                    self.pending_operator = Some(Operator::Comment);
+                    Action::Nothing
+                }
+                ('g', KeyCode::Char(c @ ('q' | 'w'))) => {
+                    self.pending_operator = Some(Operator::Reflow {
+                        keep_cursor: c == 'w',
+                    });
+                    Action::Nothing
+                }
+                ('"', KeyCode::Char(c)) => {
+                    self.register = Some(c);
+                    Action::Nothing
+                }
+                ('q', KeyCode::Char(c @ ('a'..='z' | '0'..='9'))) => Action::StartRecording(c),
+                ('@', KeyCode::Char(c)) => Action::PlayMacro(c),
                 _ => Action::Nothing,
             }
+        } else if self.accumulate_count(key_event) {
+            Action::Nothing
+        } else if self.is_duplicate_key(key_event) {
+            Action::DuplicateLines
+        } else if let Some(motion) = Self::interpret_motion(key_event) {
+            motion
+        } else if let Some(action) = Binding::lookup(NORMAL_KEYS, key_event) {
+            action
         } else {
             match (key_event.code, key_event.modifiers) {
-                // Cursor Movement
-                (KeyCode::Char('k'), KeyModifiers::NONE) => Action::BumpUp,
-                (KeyCode::Char('j'), KeyModifiers::NONE) => Action::BumpDown,
-                (KeyCode::Char('h'), KeyModifiers::NONE) => Action::BumpLeft,
-                (KeyCode::Char('l'), KeyModifiers::NONE) => Action::BumpRight,
-                (KeyCode::Char('u'), KeyModifiers::CONTROL) => Action::JumpUp,
-                (KeyCode::Char('d'), KeyModifiers::CONTROL) => Action::JumpDown,
-
-                (KeyCode::Char('W'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
-                    Action::JumpToNextWord
-                }
-                (KeyCode::Char('w'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
-                    Action::JumpToNextSymbol
-                }
-                (KeyCode::Char('B'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
-                    Action::ReverseJumpToNextWord
-                }
-                (KeyCode::Char('b'), KeyModifiers::NONE) => Action::ReverseJumpToNextSymbol,
-                (KeyCode::Char('_'), KeyModifiers::NONE) => Action::JumpSOL,
-                (KeyCode::Home, KeyModifiers::NONE) => Action::JumpSOL,
-                (KeyCode::Char('$'), KeyModifiers::NONE) => Action::JumpEOL,
-                (KeyCode::End, KeyModifiers::NONE) => Action::JumpEOL,
-                (KeyCode::Char('g'), KeyModifiers::NONE) => Action::JumpSOF,
-                (KeyCode::Char('G'), KeyModifiers::NONE | KeyModifiers::SHIFT) => Action::JumpEOF,
-
-                // Mode Changes
-                (KeyCode::Char('i'), KeyModifiers::NONE) => Action::ChangeMode(Modal::Insert),
-                (KeyCode::Char('v'), KeyModifiers::NONE) => Action::ChangeMode(Modal::Visual),
-                (KeyCode::Char('V'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
-                    Action::ChangeMode(Modal::VisualLine)
+                (KeyCode::Esc, _) if !self.extra_cursors.is_empty() => Action::ClearCursors,
+                (KeyCode::Esc, _) => {
+                    self.count = None;
+                    self.register = None;
+                    Action::Nothing
                 }
-                (KeyCode::Char(':'), KeyModifiers::NONE) => Action::ChangeMode(Modal::Command),
-                (KeyCode::Char('A'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
-                    Action::InsertModeEOL
+
+                (KeyCode::Char('%'), KeyModifiers::NONE | KeyModifiers::SHIFT)
+                    if self.count.is_some() =>
+                {
+                    Action::JumpToPercent
                 }
 
                 // Text Search
                 (KeyCode::Char('/'), KeyModifiers::NONE) => {
-                    Action::ChangeMode(Modal::Find(crate::FindDirection::Forwards))
+                    self.search_count = self.count.take().unwrap_or(1);
+                    Action::ChangeMode(Modal::Find(FindDirection::Forwards))
                 }
                 (KeyCode::Char('?'), KeyModifiers::NONE) => {
-                    Action::ChangeMode(Modal::Find(crate::FindDirection::Backwards))
+                    self.search_count = self.count.take().unwrap_or(1);
+                    Action::ChangeMode(Modal::Find(FindDirection::Backwards))
                 }
 
-                // Text Manipulation
-                (KeyCode::Char('o'), KeyModifiers::NONE) => Action::InsertModeBelow,
-                (KeyCode::Char('O'), KeyModifiers::NONE) => Action::InsertModeAbove,
-                (KeyCode::Char('X'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
-                    Action::DeleteBeforeCursor
+                // Operators
+                (KeyCode::Char('d'), KeyModifiers::NONE) => {
+                    self.pending_operator = Some(Operator::Delete);
+                    Action::Nothing
+                }
+                (KeyCode::Char('c'), KeyModifiers::NONE) => {
+                    self.pending_operator = Some(Operator::Change);
+                    Action::Nothing
+                }
+                (KeyCode::Char('y'), KeyModifiers::NONE) => {
+                    self.pending_operator = Some(Operator::Yank);
+                    Action::Nothing
+                }
+                (KeyCode::Char('='), KeyModifiers::NONE) => {
+                    self.pending_operator = Some(Operator::Reindent);
+                    Action::Nothing
                 }
-                (KeyCode::Char('x'), KeyModifiers::NONE) => Action::DeleteAtCursor,
 
-                // Undo/Redo
-                (KeyCode::Char('u'), KeyModifiers::NONE) => Action::Undo(1),
-                (KeyCode::Char('r'), KeyModifiers::CONTROL) => Action::Redo,
+                // Clipboard Operations
+                (KeyCode::Char('p'), KeyModifiers::NONE) => {
+                    Action::Paste(self.register.unwrap_or(UNNAMED_REGISTER))
+                }
+                (KeyCode::Char('P'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                    Action::PasteAbove(self.register.unwrap_or(UNNAMED_REGISTER))
+                }
+                (KeyCode::Char('q'), KeyModifiers::NONE) if self.recording.is_some() => {
+                    Action::StopRecording
+                }
+                (KeyCode::Enter, KeyModifiers::NONE) if self.explored.is_some() => {
+                    Action::OpenEntry
+                }
                 (KeyCode::Char(otherwise), _) => {
-                    if matches!(otherwise, 'f' | 'F' | 't' | 'T' | 'p' | 'P' | 'r') {
+                    if matches!(
+                        otherwise,
+                        'f' | 'F'
+                            | 'q'
+                            | '@'
+                            | 't'
+                            | 'T'
+                            | 'r'
+                            | 'g'
+                            | 'z'
+                            | '"'
+                            | 'm'
+                            | '`'
+                            | '\''
+                            | ']'
+                            | '['
+                    ) {
                         self.previous_key = Some(otherwise);
                     }
                     Action::Nothing
@@ -187,15 +1377,143 @@ impl<Buff: TextBuffer + Debug> Editor<Buff> {
                 _ => Action::Nothing,
             }
         };
+        // The count typed after the operator starts out anew
+        if self.pending_operator.is_some() {
+            self.operator_count = self.count.take();
+        }
 
         Ok(action)
     }
-    fn interpret_insert_event(&self, key_event: KeyEvent) -> Result<Action> {
-        let action = match key_event.code {
+    /// Whether the key is the one `duplicatekey` binds to duplicating lines
+    fn is_duplicate_key(&self, key_event: KeyEvent) -> bool {
+        let key = keymap::parse_keys(&self.config.duplicatekey, self.config.mapleader);
+        key == [keymap::normalize(key_event)]
+    }
+    fn interpret_visual_event(&mut self, key_event: KeyEvent) -> Result<Action> {
+        let ctrl = key_event.modifiers == KeyModifiers::CONTROL;
+        let action = if let Some(prev) = self.previous_key.take() {
+            match (prev, key_event.code) {
+                ('g', KeyCode::Char('g')) => Action::JumpSOF,
+                ('g', KeyCode::Char('a')) if ctrl => Action::SequentialIncrementSelection(1),
+                ('g', KeyCode::Char('x')) if ctrl => Action::SequentialIncrementSelection(-1),
+                ('g', KeyCode::Char('c')) => Action::CommentSelection,
+                _ => Action::Nothing,
+            }
+        } else if self.accumulate_count(key_event) {
+            Action::Nothing
+        } else if self.is_duplicate_key(key_event) {
+            Action::DuplicateLines
+        } else if let Some(motion) = Self::interpret_motion(key_event) {
+            motion
+        } else {
+            match (key_event.code, key_event.modifiers) {
+                (KeyCode::Esc, _) => {
+                    self.count = None;
+                    Action::ChangeMode(Modal::Normal)
+                }
+                (KeyCode::Char('v'), KeyModifiers::NONE) if self.modal.is_visual() => {
+                    Action::ChangeMode(Modal::Normal)
+                }
+                (KeyCode::Char('v'), KeyModifiers::NONE) => Action::ChangeMode(Modal::Visual),
+                (KeyCode::Char('V'), KeyModifiers::NONE | KeyModifiers::SHIFT)
+                    if self.modal.is_visual_line() =>
+                {
+                    Action::ChangeMode(Modal::Normal)
+                }
+                (KeyCode::Char('V'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                    Action::ChangeMode(Modal::VisualLine)
+                }
+                (KeyCode::Char('v'), KeyModifiers::CONTROL) if self.modal.is_visual_block() => {
+                    Action::ChangeMode(Modal::Normal)
+                }
+                (KeyCode::Char('v'), KeyModifiers::CONTROL) => {
+                    Action::ChangeMode(Modal::VisualBlock)
+                }
+
+                // Text Manipulation
+                (KeyCode::Char('a'), KeyModifiers::CONTROL) => Action::IncrementSelection(1),
+                (KeyCode::Char('x'), KeyModifiers::CONTROL) => Action::IncrementSelection(-1),
+                (KeyCode::Char('g'), KeyModifiers::NONE) => {
+                    self.previous_key = Some('g');
+                    Action::Nothing
+                }
+                (KeyCode::Char(':'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                    Action::CommandOnSelection
+                }
+                // Searches confined to the selection
+                (KeyCode::Char('/'), KeyModifiers::NONE) => {
+                    self.search_count = self.count.take().unwrap_or(1);
+                    Action::ChangeMode(Modal::Find(FindDirection::Forwards))
+                }
+                (KeyCode::Char('?'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                    self.search_count = self.count.take().unwrap_or(1);
+                    Action::ChangeMode(Modal::Find(FindDirection::Backwards))
+                }
+                (KeyCode::Char('J'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                    Action::JoinSelection
+                }
+                (KeyCode::Char('>'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                    Action::ShiftSelection { right: true }
+                }
+                (KeyCode::Char('<'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                    Action::ShiftSelection { right: false }
+                }
+                (KeyCode::Char('%'), KeyModifiers::NONE | KeyModifiers::SHIFT)
+                    if self.count.is_some() =>
+                {
+                    Action::JumpToPercent
+                }
+                (KeyCode::Char('='), KeyModifiers::NONE) => Action::ReindentSelection,
+                (KeyCode::Char('+'), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                    Action::ExpandSelection
+                }
+                (KeyCode::Char('j'), KeyModifiers::ALT) => Action::MoveLineDown,
+                (KeyCode::Char('k'), KeyModifiers::ALT) => Action::MoveLineUp,
+                (KeyCode::Char('-'), KeyModifiers::NONE) => Action::ShrinkSelection,
+                _ => Action::Nothing,
+            }
+        };
+
+        Ok(action)
+    }
+    fn interpret_insert_event(&mut self, key_event: KeyEvent) -> Result<Action> {
+        if let Some(pending) = self.pending_insert.take() {
+            return Ok(self.interpret_insert_pending(pending, key_event));
+        }
+        let ctrl = key_event.modifiers == KeyModifiers::CONTROL;
+        if !(ctrl && matches!(key_event.code, KeyCode::Char('n' | 'p'))) {
+            self.word_completion = None;
+        }
+        let action = match key_event.code {
+            KeyCode::Char(c @ ('n' | 'p')) if ctrl => Action::CompleteWord { forwards: c == 'n' },
+            KeyCode::Char('k') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.pending_insert = Some(PendingInsert::Digraph(None));
+                Action::Nothing
+            }
+            KeyCode::Char('v') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.pending_insert = Some(PendingInsert::Literal);
+                Action::Nothing
+            }
+            KeyCode::Char('r') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.pending_insert = Some(PendingInsert::Register);
+                Action::Nothing
+            }
+            KeyCode::Char('w') if key_event.modifiers == KeyModifiers::CONTROL => {
+                Action::DeleteWordBeforeCursor
+            }
+            KeyCode::Char('u') if key_event.modifiers == KeyModifiers::CONTROL => {
+                Action::DeleteInsertedBeforeCursor
+            }
             KeyCode::Char(c) => Action::InsertCharAtCursor(c),
             KeyCode::Enter => Action::InsertNewLine,
             KeyCode::Esc => Action::ChangeMode(Modal::Normal),
             KeyCode::Backspace => Action::DeleteBeforeCursor,
+            KeyCode::Left if key_event.modifiers == KeyModifiers::CONTROL => {
+                Action::ReverseJumpToNextSymbol
+            }
+            KeyCode::Right if key_event.modifiers == KeyModifiers::CONTROL => {
+                Action::JumpToNextSymbol
+            }
             KeyCode::Left => Action::BumpLeft,
             KeyCode::Right => Action::BumpRight,
             KeyCode::Up => Action::BumpUp,
@@ -204,35 +1522,244 @@ impl<Buff: TextBuffer + Debug> Editor<Buff> {
         };
         Ok(action)
     }
+    /// Completes a digraph, literal, register or expression insertion, any key which can't continue
+    /// it cancels it
+    fn interpret_insert_pending(&mut self, pending: PendingInsert, key_event: KeyEvent) -> Action {
+        let ctrl = key_event.modifiers == KeyModifiers::CONTROL;
+        match (pending, key_event.code) {
+            (PendingInsert::Digraph(None), KeyCode::Char(first)) => {
+                self.pending_insert = Some(PendingInsert::Digraph(Some(first)));
+                Action::Nothing
+            }
+            (PendingInsert::Digraph(Some(first)), KeyCode::Char(second)) => {
+                match digraph::lookup(first, second) {
+                    Some(ch) => Action::InsertCharAtCursor(ch),
+                    None => {
+                        force_notif_bar_content(format!("Unknown digraph: {first}{second}"));
+                        Action::Nothing
+                    }
+                }
+            }
+            // Control characters are inserted as the raw codes the keys stand for
+            (PendingInsert::Literal, KeyCode::Char(c)) if ctrl && c.is_ascii_alphabetic() => {
+                Action::InsertCharAtCursor((c.to_ascii_uppercase() as u8 & 0x1f) as char)
+            }
+            (PendingInsert::Literal, KeyCode::Char(c)) => Action::InsertCharAtCursor(c),
+            (PendingInsert::Literal, KeyCode::Tab) => Action::InsertCharAtCursor('\t'),
+            (PendingInsert::Literal, KeyCode::Esc) => Action::InsertCharAtCursor('\x1b'),
+            (PendingInsert::Register, KeyCode::Char('=')) => self.pending_expression(String::new()),
+            (PendingInsert::Register, KeyCode::Char(name)) => Action::InsertRegister(name),
+            (PendingInsert::Expression(mut expression), KeyCode::Char(ch)) => {
+                expression.push(ch);
+                self.pending_expression(expression)
+            }
+            (PendingInsert::Expression(mut expression), KeyCode::Backspace) => {
+                match expression.pop() {
+                    Some(_) => self.pending_expression(expression),
+                    None => Action::Nothing,
+                }
+            }
+            (PendingInsert::Expression(expression), KeyCode::Enter) => {
+                Action::InsertExpression(expression)
+            }
+            _ => Action::Nothing,
+        }
+    }
+    /// Keeps typing the expression of `Ctrl-R =`, showing it in the notification bar
+    fn pending_expression(&mut self, expression: String) -> Action {
+        force_notif_bar_content(format!("={expression}"));
+        self.pending_insert = Some(PendingInsert::Expression(expression));
+        Action::Nothing
+    }
     fn parse_out_command(&self) -> Command {
         let buf = self.buffer.get_command_text();
         info!("Parsing out command: {}", buf);
-        let first_ch = buf.chars().next();
-
-        // Parse Command Type
-        if let Some(prefix) = first_ch {
-            let rest = &buf[1..];
-            match prefix {
-                '/' => Command::Find(rest.to_string()),
-                '?' => Command::Rfind(rest.to_string()),
-                // Interpret Command
-                _ => match buf {
-                    "q" => Command::Exit,
-                    _ => Command::None,
-                },
+
+        // The mode tells searches apart, the command line only shows their direction
+        match self.modal {
+            Modal::Find(direction) => {
+                let pattern = buf.strip_prefix(direction.symbol()).unwrap_or(buf);
+                match direction {
+                    FindDirection::Forwards => Command::Find(pattern.to_string()),
+                    FindDirection::Backwards => Command::Rfind(pattern.to_string()),
+                }
             }
-        } else {
-            Command::None
+            _ if buf.is_empty() => Command::None,
+            _ => self.parse_ex_command(buf),
+        }
+    }
+    /// Parses an Ex command, preceded by an optional line range
+    fn parse_ex_command(&self, buf: &str) -> Command {
+        let current = self.cursor.last_text_mode_pos.line;
+        let ctx = AddressContext {
+            current,
+            last: self.buffer.max_line(),
+            visual: self.last_visual.map(|s| (s.start.line, s.end.line)),
+        };
+        let (range, command) = match ex::parse_range(buf, &ctx) {
+            Ok(parsed) => parsed,
+            Err(Error::InvalidLineNumber) => return Command::Invalid("Invalid range".to_string()),
+            Err(_) => return Command::Invalid("Invalid address".to_string()),
+        };
+
+        let substitute_args = ["substitute", "s"]
+            .iter()
+            .find_map(|name| command.strip_prefix(name))
+            .filter(|args| args.starts_with(|ch: char| !ch.is_alphanumeric()));
+        if let Some(args) = substitute_args {
+            return match ex::parse_substitution(args) {
+                Ok(sub) => Command::Substitute(range.unwrap_or(current..current + 1), sub),
+                Err(_) => Command::Invalid(format!("Invalid substitution: {args}")),
+            };
+        }
+        let sort_args = ["sort", "sor"]
+            .iter()
+            .find_map(|name| command.strip_prefix(name))
+            .filter(|args| !args.starts_with(|ch: char| ch.is_alphabetic()));
+        if let Some(args) = sort_args {
+            let whole_buffer = 0..self.buffer.max_line() + 1;
+            return match ex::parse_sort_options(args) {
+                Ok(options) => Command::Sort(range.unwrap_or(whole_buffer), options),
+                Err(_) => Command::Invalid(format!("Invalid sort options: {args}")),
+            };
+        }
+        let write_args = ["write", "w"]
+            .iter()
+            .find_map(|name| command.strip_prefix(name))
+            .filter(|args| args.is_empty() || args.starts_with(['!', ' ', '>']));
+        if let Some(args) = write_args {
+            let (force, args) = match args.strip_prefix('!') {
+                Some(args) => (true, args.trim()),
+                None => (false, args.trim()),
+            };
+            let (append, path) = match args.strip_prefix(">>") {
+                Some(path) => (true, path.trim()),
+                None => (false, args),
+            };
+            return Command::Write {
+                range,
+                path: (!path.is_empty()).then(|| PathBuf::from(path)),
+                force,
+                append,
+            };
+        }
+        let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+        if let Some((modes, noremap)) = keymap::map_command(name) {
+            let args = args.trim_start();
+            return match args.split_once(char::is_whitespace) {
+                _ if args.is_empty() => Command::Map(modes, None),
+                Some((lhs, rhs)) if !rhs.trim().is_empty() => {
+                    let leader = self.config.mapleader;
+                    let lhs = keymap::parse_keys(lhs, leader);
+                    let rhs = keymap::parse_keys(rhs.trim_start(), leader);
+                    Command::Map(modes, Some((lhs, Mapping { rhs, noremap })))
+                }
+                _ => Command::Invalid(format!("Missing rhs for {}", args.trim())),
+            };
+        }
+        if let Some(modes) = keymap::unmap_command(name) {
+            return match args.trim() {
+                "" => Command::Invalid("Argument required".to_string()),
+                lhs => Command::Unmap(modes, keymap::parse_keys(lhs, self.config.mapleader)),
+            };
+        }
+        if self.scripts.has_command(name) {
+            return Command::User(name.to_string(), args.trim().to_string());
+        }
+        match (range, command.split_once(' ')) {
+            (Some(range), _) if command.is_empty() => Command::Goto(range.end - 1),
+            (_, Some(("script", script))) => Command::Script(script.to_string()),
+            (_, Some(("so" | "source", path))) if !path.trim().is_empty() => {
+                Command::Source(PathBuf::from(path.trim()))
+            }
+            (_, Some(("set" | "se", args))) => Command::Set(args.to_string()),
+            (_, Some(("hi" | "highlight", args))) => Command::Highlight(args.to_string()),
+            _ if command == "rec" || command == "recover" => Command::Recover,
+            _ if command == "h" || command == "help" => Command::Help(HELP_KEYS.to_string()),
+            (_, Some(("h" | "help", topic))) => Command::Help(topic.trim().to_string()),
+            (_, Some(("edit" | "e", path))) if !path.trim().is_empty() => {
+                Command::Edit(PathBuf::from(path.trim()))
+            }
+            _ if command == "Explore" || command == "Ex" => Command::Explore(None),
+            (_, Some(("Explore" | "Ex", dir))) => Command::Explore(Some(PathBuf::from(dir.trim()))),
+            _ if command == "edit" || command == "e" => {
+                Command::Invalid("No file name".to_string())
+            }
+            (_, Some(("ab" | "abbr" | "abbreviate" | "iab", args))) => {
+                match args.trim().split_once(' ') {
+                    Some((trigger, expansion)) => Command::Abbreviate(Some((
+                        trigger.to_string(),
+                        expansion.trim().to_string(),
+                    ))),
+                    None => Command::Invalid(format!("Missing expansion for {}", args.trim())),
+                }
+            }
+            _ if matches!(command, "ab" | "abbr" | "abbreviate" | "iab") => {
+                Command::Abbreviate(None)
+            }
+            (_, Some(("una" | "unab" | "unabbreviate" | "iuna", trigger))) => {
+                Command::Unabbreviate(trigger.trim().to_string())
+            }
+            (range, Some(("align" | "Tabularize" | "Tab", args))) => {
+                // The delimiter may be written like a pattern, `/=/` or `/=`
+                let args = args.trim();
+                let delimiter = args.strip_prefix('/').map_or(args, |delimiter| {
+                    delimiter.strip_suffix('/').unwrap_or(delimiter)
+                });
+                match delimiter {
+                    "" => Command::Invalid("Argument required".to_string()),
+                    delimiter => {
+                        let lines = self.buffer.get_normal_text();
+                        let paragraph = textobject::paragraph(lines, current, true);
+                        let range = range.unwrap_or(paragraph.start.line..paragraph.end.line + 1);
+                        Command::Align(range, delimiter.to_string())
+                    }
+                }
+            }
+            _ if command == "go" || command == "goto" => Command::GotoByte(1),
+            (_, Some(("go" | "goto", count))) => match count.trim().parse() {
+                Ok(count) => Command::GotoByte(count),
+                Err(_) => Command::Invalid(format!("Invalid byte count: {}", count.trim())),
+            },
+            _ if command == "q" || command == "quit" => Command::Exit { force: false },
+            _ if command == "q!" || command == "quit!" => Command::Exit { force: true },
+            _ if command == "terminal" || command == "term" => Command::Terminal,
+            _ if command == "todo" => Command::Todo,
+            (range, _) if command == "squeeze" => {
+                Command::Squeeze(range.unwrap_or(0..self.buffer.max_line() + 1))
+            }
+            _ if command == "trimblank" => Command::TrimBlank,
+            _ if command == "diff" => Command::Diff,
+            (_, Some(("changeall", replacement))) if !replacement.trim().is_empty() => {
+                Command::ChangeAll(replacement.trim().to_string())
+            }
+            _ if command == "changeall" => Command::Invalid("Argument required".to_string()),
+            // Without the bang vim only prints the lines, which the notification bar can't hold
+            (range, _) if command.starts_with("number!") || command.starts_with("nu!") => {
+                let separator = command.split_once('!').map_or("", |(_, rest)| rest.trim());
+                let separator = match separator {
+                    "" => " ",
+                    separator => separator,
+                };
+                let whole_buffer = 0..self.buffer.max_line() + 1;
+                Command::Number(range.unwrap_or(whole_buffer), separator.to_string())
+            }
+            _ if command == "A" => Command::Alternate,
+            _ => Command::None,
         }
     }
 
-    fn interpret_command_event(&self, key_event: KeyEvent) -> Result<Action> {
+    fn interpret_command_event(&mut self, key_event: KeyEvent) -> Result<Action> {
+        if key_event.code != KeyCode::Tab {
+            self.completion = None;
+        }
         let action = match key_event.code {
             // Enter will execute different commands based on the parsing of the executecommand/find and rfind
             KeyCode::Enter => {
                 let command = self.parse_out_command();
                 Action::ExecuteCommand(command)
             }
+            KeyCode::Tab if self.modal.is_command() => Action::CompleteCommand,
             KeyCode::Char(c) => Action::InsertCharAtCursor(c),
             KeyCode::Up => Action::BumpUp,
             KeyCode::Down => Action::BumpDown,
@@ -244,6 +1771,18 @@ impl<Buff: TextBuffer + Debug> Editor<Buff> {
         };
         Ok(action)
     }
+    /// Typing edits the prompt line, Enter runs it in the shell and Esc returns to the text
+    fn interpret_terminal_event(key_event: KeyEvent) -> Action {
+        match key_event.code {
+            KeyCode::Enter => Action::RunTerminalPrompt,
+            KeyCode::Char(c) => Action::InsertCharAtCursor(c),
+            KeyCode::Backspace => Action::DeleteBeforeCursor,
+            KeyCode::Left => Action::BumpLeft,
+            KeyCode::Right => Action::BumpRight,
+            KeyCode::Esc => Action::ChangeMode(Modal::Normal),
+            _ => Action::Nothing,
+        }
+    }
     // Decides on how to delegate a given base action
     #[instrument]
     fn perform_action(&mut self, action: BaseAction) -> Result<()> {
@@ -254,9 +1793,37 @@ impl<Buff: TextBuffer + Debug> Editor<Buff> {
             | BaseAction::MoveLeft(_)
             | BaseAction::MoveRight(_) => self.delegate_action_bound_checked(&action),
             chm @ BaseAction::ChangeMode(mode) => {
+                if self.modal.is_selecting() && !mode.is_selecting() {
+                    self.last_visual = Some(Selection::from(&self.cursor).normalized());
+                }
+                if self.modal.is_find() && !mode.is_find() {
+                    self.end_search_preview();
+                }
+                self.search_scope = match mode {
+                    Modal::Find(_) if self.modal.is_selecting() => self.selection_scope(),
+                    Modal::Find(_) => self.search_scope,
+                    _ => None,
+                };
+                // The additional cursors only take part in typing
+                if !mode.is_normal() && !mode.is_insert() {
+                    self.extra_cursors.clear();
+                }
+                // An insert session is a single undo step, changing modes is never one itself
+                match (self.modal.is_insert(), mode.is_insert()) {
+                    (false, true) => self.begin_insert_undo_group(),
+                    (true, false) if self.insert_undo_group => {
+                        self.buffer.end_undo_group();
+                        self.insert_undo_group = false;
+                    }
+                    _ => (),
+                }
                 self.modal = mode;
                 self.delegate_action(&chm)
             }
+            history @ (BaseAction::Undo(_) | BaseAction::Redo(_)) => {
+                self.delegate_action(&history)?;
+                self.goto(self.cursor.pos)
+            }
             otherwise => self.delegate_action(&otherwise),
         }
     }
@@ -297,8 +1864,13 @@ impl<Buff: TextBuffer + Debug> Editor<Buff> {
 
         let mut altered = false;
 
-        // Line bound checking
-        if self.shadow_cursor.line > self.buffer.max_line() as i64 {
+        // Line bound checking, the terminal plane has lines of its own
+        let max_line = if self.modal.is_terminal() {
+            self.buffer.get_terminal_text().len() - 1
+        } else {
+            self.buffer.max_line()
+        };
+        if self.shadow_cursor.line > max_line as i64 {
             warn!("Exceeding maximum line, altering action...");
             self.shadow_cursor.line = self.cursor.pos.line as i64;
             let actions = self.resolve_action(Action::JumpEOF)?;
@@ -324,8 +1896,10 @@ impl<Buff: TextBuffer + Debug> Editor<Buff> {
             altered = true;
         }
 
-        // Col bound checking
-        if self.shadow_cursor.col > self.buffer.max_col(self.shadow_cursor.line as usize) as i64 {
+        // Col bound checking, with `virtualedit` the cursor may pass the end of the text
+        let virtual_space = self.config.virtualedit && self.modal.is_text();
+        let max_col = self.buffer.max_col(self.shadow_cursor.line as usize) as i64;
+        if self.shadow_cursor.col > max_col && !virtual_space {
             warn!("Exceeding maximum col, altering action...");
             self.shadow_cursor.col = self.cursor.pos.col as i64;
             let actions = self.resolve_action(Action::JumpEOL)?;
@@ -348,55 +1922,169 @@ impl<Buff: TextBuffer + Debug> Editor<Buff> {
             self.delegate_action(action)?
         };
 
+        // The column kept by a vertical move may fall within a cluster of the new line
+        if matches!(action, BaseAction::MoveUp(_) | BaseAction::MoveDown(_)) && self.modal.is_text()
+        {
+            let pos = self.cursor.pos;
+            let line = &self.buffer.get_normal_text()[pos.line];
+            self.cursor.set_col(grapheme::cluster_start(line, pos.col));
+            self.shadow_cursor.update(self.cursor.pos);
+        }
+
         Ok(())
     }
     fn resolve_action(&mut self, api_action: Action) -> Result<Vec<BaseAction>> {
         match api_action {
             // No-op and exit actions
-            Action::Nothing => ok_vec!(),
-            Action::Quit => Err(Error::ExitCall),
+            Action::Nothing | Action::RepeatChange | Action::PlayMacro(_) => ok_vec!(),
+            Action::StartRecording(register) => {
+                self.recording = Some((register, Vec::new()));
+                ok_vec!()
+            }
+            Action::StopRecording => {
+                if let Some((register, mut keys)) = self.recording.take() {
+                    // The `q` stopping the recording isn't part of the macro
+                    let _ = keys.pop();
+                    let content = RegisterContent::new(vec![keymap::key_notation(&keys)], false);
+                    self.registers.yank(Some(register), content);
+                }
+                ok_vec!()
+            }
+            Action::Quit => {
+                self.discard_swap_file();
+                Err(Error::ExitCall)
+            }
 
             // Basic cursor movements
             Action::BumpUp => ok_vec![BaseAction::MoveUp(1)],
             Action::BumpDown => ok_vec![BaseAction::MoveDown(1)],
+            // Within the text the cursor moves over grapheme clusters as a whole
+            Action::BumpLeft | Action::BumpRight if self.modal.is_text() => {
+                let forwards = matches!(api_action, Action::BumpRight);
+                let pos = self.cursor.pos;
+                let line = &self.buffer.get_normal_text()[pos.line];
+                let virtual_space = self.config.virtualedit;
+                let col = (0..self.repeat_action).fold(pos.col, |col, _| match forwards {
+                    true if virtual_space && col >= line.len() => col + 1,
+                    true => grapheme::next_boundary(line, col),
+                    false if col > line.len() => col - 1,
+                    false => grapheme::previous_boundary(line, col),
+                });
+                ok_vec![BaseAction::SetCursor(LineCol { col, ..pos })]
+            }
             Action::BumpLeft => ok_vec![BaseAction::MoveLeft(1)],
             Action::BumpRight => ok_vec![BaseAction::MoveRight(1)],
 
             // Larger cursor movements
-            Action::JumpUp => ok_vec![BaseAction::MoveUp(JUMP_DIST)],
-            Action::JumpDown => ok_vec![BaseAction::MoveDown(JUMP_DIST)],
+            Action::JumpUp | Action::JumpDown => {
+                let down = matches!(api_action, Action::JumpDown);
+                // Like in vim, a count replaces the configured distance
+                let dist = if self.repeat_action > 1 {
+                    self.repeat_action
+                } else {
+                    self.half_page()
+                };
+                Ok(self.resolve_scroll(down, dist, dist))
+            }
+            Action::PageUp | Action::PageDown => {
+                let down = matches!(api_action, Action::PageDown);
+                let page = self
+                    .viewport
+                    .text_rows()
+                    .saturating_sub(PAGE_OVERLAP)
+                    .max(1);
+                let dist = page * self.repeat_action;
+                Ok(self.resolve_scroll(down, dist, dist))
+            }
             Action::JumpSOL => ok_vec![BaseAction::MoveLeft(self.cursor.col())],
+            Action::SmartHome if self.config.smarthome && self.modal.is_text() => {
+                let first = self.first_non_blank(self.cursor.line());
+                match self.cursor.pos == first {
+                    true => ok_vec![BaseAction::MoveLeft(self.cursor.col())],
+                    false => ok_vec![BaseAction::SetCursor(first)],
+                }
+            }
+            Action::SmartHome => ok_vec![BaseAction::MoveLeft(self.cursor.col())],
             Action::JumpEOL => ok_vec![
                 BaseAction::MoveLeft(self.cursor.col()),
                 BaseAction::MoveRight(self.buffer.max_col(self.cursor.line()))
             ],
             Action::JumpSOF => ok_vec![BaseAction::MoveUp(self.cursor.line())],
+            Action::JumpNextParagraph | Action::JumpPrevParagraph => {
+                let forwards = matches!(api_action, Action::JumpNextParagraph);
+                let line = (0..self.repeat_action).fold(self.cursor.line(), |line, _| {
+                    textobject::paragraph_boundary(self.buffer.get_normal_text(), line, forwards)
+                });
+                ok_vec![BaseAction::SetCursor(LineCol { line, col: 0 })]
+            }
+            Action::CenterLine => ok_vec![self.resolve_center(self.cursor.line())],
+            Action::ScrollLineUp | Action::ScrollLineDown => {
+                let down = matches!(api_action, Action::ScrollLineDown);
+                Ok(self.resolve_scroll(down, MOUSE_SCROLL_LINES * self.repeat_action, 0))
+            }
+            Action::JumpToSign { forwards, kinds } => {
+                let mut line = self.cursor.line();
+                for _ in 0..self.repeat_action {
+                    match self.signs.next(line, forwards, kinds) {
+                        Some(next) => line = next,
+                        None => break,
+                    }
+                }
+                match line == self.cursor.line() {
+                    true => ok_vec!(),
+                    false => ok_vec![BaseAction::SetCursor(self.first_non_blank(line))],
+                }
+            }
+            Action::JumpToSection { forwards } => {
+                let lines = self.buffer.get_normal_text();
+                let mut line = self.cursor.line();
+                for _ in 0..self.repeat_action {
+                    match textobject::section_start(lines, line, forwards) {
+                        Some(next) => line = next,
+                        None => break,
+                    }
+                }
+                match line == self.cursor.line() {
+                    true => ok_vec!(),
+                    false => ok_vec![BaseAction::SetCursor(LineCol { line, col: 0 })],
+                }
+            }
+            Action::JumpToPercent => ok_vec![BaseAction::SetCursor(
+                self.first_non_blank(self.percent_line())
+            )],
             Action::JumpEOF => ok_vec![
                 BaseAction::MoveUp(self.cursor.line()),
                 BaseAction::MoveDown(self.buffer.max_line())
             ],
 
             // Word and symbol navigation
-            Action::JumpToNextWord => ok_vec![self.jump_two_boundaries(
-                Direction::Forward,
-                char::is_whitespace,
-                |ch| !char::is_whitespace(ch),
-            )?],
-            Action::JumpToNextSymbol => ok_vec![self.jump_two_boundaries(
+            Action::JumpToNextWord => {
+                self.resolve_word_motion(Direction::Forward, char::is_whitespace, |ch| {
+                    !char::is_whitespace(ch)
+                })
+            }
+            // Ctrl-Right and Ctrl-Left jump to the starts of words while typing
+            Action::JumpToNextSymbol if self.modal.is_insert() => {
+                Ok(self.resolve_word_start_motion(Direction::Forward))
+            }
+            Action::JumpToNextSymbol => self.resolve_word_motion(
                 Direction::Forward,
                 |ch| !char::is_whitespace(ch),
-                |ch| !char::is_alphanumeric(ch),
-            )?],
-            Action::ReverseJumpToNextWord => ok_vec![self.jump_two_boundaries(
-                Direction::Backward,
-                char::is_whitespace,
-                |ch| !char::is_whitespace(ch),
-            )?],
-            Action::ReverseJumpToNextSymbol => ok_vec![self.jump_two_boundaries(
+                |ch| !self.config.is_keyword(ch),
+            ),
+            Action::ReverseJumpToNextWord => {
+                self.resolve_word_motion(Direction::Backward, char::is_whitespace, |ch| {
+                    !char::is_whitespace(ch)
+                })
+            }
+            Action::ReverseJumpToNextSymbol if self.modal.is_insert() => {
+                Ok(self.resolve_word_start_motion(Direction::Backward))
+            }
+            Action::ReverseJumpToNextSymbol => self.resolve_word_motion(
                 Direction::Backward,
                 |ch| !char::is_whitespace(ch),
-                |ch| !char::is_alphanumeric(ch),
-            )?],
+                |ch| !self.config.is_keyword(ch),
+            ),
 
             // Find and search actions
             Action::Find(pat) => {
@@ -405,22 +2093,28 @@ impl<Buff: TextBuffer + Debug> Editor<Buff> {
             Action::ReverseFind(pat) => {
                 ok_vec![self.resolve_find(|p, pos| self.rfind(p, pos), pat)?]
             }
-            Action::FindChar(ch) => self.resolve_action(Action::Find(ch.to_string())),
-            Action::ReverseFindChar(ch) => self.resolve_action(Action::ReverseFind(ch.to_string())),
-            Action::ToChar(ch) => {
-                let mut actions = self.resolve_action(Action::FindChar(ch))?;
-                actions.push(BaseAction::MoveLeft(1));
-                Ok(actions)
-            }
-            Action::ReverseToChar(ch) => {
-                let mut actions = self.resolve_action(Action::ReverseFindChar(ch))?;
-                actions.push(BaseAction::MoveRight(1));
-                Ok(actions)
-            }
+            Action::FindChar(ch) => Ok(self.resolve_find_char(ch, true, false)),
+            Action::ReverseFindChar(ch) => Ok(self.resolve_find_char(ch, false, false)),
+            Action::ToChar(ch) => Ok(self.resolve_find_char(ch, true, true)),
+            Action::ReverseToChar(ch) => Ok(self.resolve_find_char(ch, false, true)),
 
             // Mode change actions
             Action::ChangeMode(mode) => {
-                ok_vec![BaseAction::ChangeMode(mode)]
+                let mut actions = self.resolve_abbreviation();
+                actions.push(BaseAction::ChangeMode(mode));
+                Ok(actions)
+            }
+            Action::ResumeInsert => {
+                let Some(&pos) = self.marks.get(&'^') else {
+                    return ok_vec![BaseAction::ChangeMode(Modal::Insert)];
+                };
+                // The text may have shrunk since insert mode was left
+                let line = pos.line.min(self.buffer.max_line());
+                let col = pos.col.min(self.buffer.max_col(line));
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Insert),
+                    BaseAction::SetCursor(LineCol { line, col }),
+                ]
             }
             Action::InsertModeEOL => {
                 let dist = self.buffer.max_col(self.cursor.line()) - self.cursor.col();
@@ -436,8 +2130,6 @@ impl<Buff: TextBuffer + Debug> Editor<Buff> {
                 BaseAction::ChangeMode(Modal::Insert),
             ],
             Action::InsertModeAbove => {
-                let mut pos = self.cursor.pos;
-                pos.line -= 1;
                 ok_vec![
                     BaseAction::InsertLineAt(lazy!(self.cursor.pos), 1),
                     BaseAction::MoveUp(1),
@@ -449,446 +2141,5098 @@ impl<Buff: TextBuffer + Debug> Editor<Buff> {
             Action::Save => ok_vec![BaseAction::Save],
             Action::Yank => ok_vec![BaseAction::Yank],
             Action::Redo => ok_vec![BaseAction::Redo(1)],
-            Action::DeleteAtCursor => ok_vec![BaseAction::DeleteAt(lazy!(), 1),],
+            Action::DeleteAtCursor => Ok(self.resolve_delete_chars(false)),
             Action::Replace(char) => {
+                let pos = self.cursor.pos;
+                if pos.col < self.buffer.get_normal_text()[pos.line].len() {
+                    return ok_vec![
+                        BaseAction::DeleteAt(lazy!(), 1),
+                        BaseAction::InsertAt(lazy!(), char),
+                    ];
+                }
+                // Past the end of the line, only virtual space holds a character to replace
+                if !self.config.virtualedit {
+                    return ok_vec!();
+                }
+                let mut actions = self.resolve_virtual_space_padding();
+                actions.push(BaseAction::InsertAt(lazy!(), char));
+                Ok(actions)
+            }
+            Action::DeleteBeforeCursor
+                if self.modal.is_insert() && !self.extra_cursors.is_empty() =>
+            {
+                Ok(self.resolve_multicursor_edit(CursorEdit::DeleteBefore))
+            }
+            Action::DeleteBeforeCursor if self.modal.is_normal() => {
+                Ok(self.resolve_delete_chars(true))
+            }
+            // There is nothing before the start of the buffer, or of the command and prompt lines
+            Action::DeleteBeforeCursor if self.cursor.col() == 0 => {
+                let line = self.cursor.line();
+                if line == 0 || !self.modal.is_insert() {
+                    return ok_vec!();
+                }
+                let lines = self.buffer.get_normal_text();
+                let joined = format!("{}{}", lines[line - 1], lines[line]);
+                let col = lines[line - 1].len();
                 ok_vec![
-                    BaseAction::DeleteAt(lazy!(), 1),
-                    BaseAction::InsertAt(lazy!(), char),
+                    BaseAction::ReplaceLines(line - 1..line + 1, vec![joined]),
+                    BaseAction::SetCursor(LineCol {
+                        line: line - 1,
+                        col
+                    })
                 ]
             }
             Action::DeleteBeforeCursor => {
                 ok_vec![BaseAction::MoveLeft(1), BaseAction::DeleteAt(lazy!(), 1)]
             }
+            Action::DeleteWordBeforeCursor | Action::DeleteInsertedBeforeCursor => {
+                let pos = self.cursor.pos;
+                let line = &self.buffer.get_normal_text()[pos.line];
+                let col = pos.col.min(line.len());
+                let start = match api_action {
+                    Action::DeleteWordBeforeCursor => {
+                        textobject::previous_word_start(line, col, &self.config.iskeyword)
+                    }
+                    // Once the inserted text is gone, the indentation and then the rest goes
+                    _ if self.insert_start.line == pos.line && self.insert_start.col < col => {
+                        self.insert_start.col
+                    }
+                    _ => match self.first_non_blank(pos.line).col {
+                        indent if indent < col => indent,
+                        _ => 0,
+                    },
+                };
+                let remaining = format!("{}{}", &line[..start], &line[col..]);
+                ok_vec![
+                    BaseAction::ReplaceLines(pos.line..pos.line + 1, vec![remaining]),
+                    BaseAction::SetCursor(LineCol { col: start, ..pos }),
+                ]
+            }
+            Action::CompleteWord { forwards } => Ok(self.resolve_word_completion(forwards)),
+            Action::AddCursorAtNextMatch => Ok(self.resolve_add_cursor()),
+            Action::ClearCursors => {
+                self.extra_cursors.clear();
+                ok_vec!()
+            }
             Action::Undo(steps) => ok_vec![BaseAction::Undo(steps.into())],
+            // Columns are byte offsets, the cursor has to skip the whole encoded character
+            Action::InsertCharAtCursor(ch)
+                if self.modal.is_insert() && !self.extra_cursors.is_empty() =>
+            {
+                Ok(self.resolve_multicursor_edit(CursorEdit::Insert(ch)))
+            }
             Action::InsertCharAtCursor(ch) => {
-                ok_vec![BaseAction::InsertAt(lazy!(), ch), BaseAction::MoveRight(1)]
+                let mut actions = if self.config.is_keyword(ch) {
+                    Vec::new()
+                } else {
+                    self.resolve_abbreviation()
+                };
+                actions.extend(self.resolve_virtual_space_padding());
+                if actions.is_empty() && !ch.is_whitespace() && self.config.textwidth > 0 {
+                    if let Some(wrapped) = self.resolve_auto_wrap(ch) {
+                        return Ok(wrapped);
+                    }
+                }
+                actions.extend([
+                    BaseAction::InsertAt(lazy!(), ch),
+                    BaseAction::MoveRight(ch.len_utf8()),
+                ]);
+                Ok(actions)
+            }
+            Action::Increment(delta) => self.resolve_increment(delta),
+            Action::IncrementSelection(delta) => self.resolve_selection_increment(delta, false),
+            Action::SequentialIncrementSelection(delta) => {
+                self.resolve_selection_increment(delta, true)
+            }
+            Action::CommentSelection => {
+                let selection = Selection::from(&self.cursor).normalized();
+                let range = selection.start.line..selection.end.line + 1;
+                let mut actions = match self.modal.is_visual_block() {
+                    true => self.resolve_block_comment(range, selection.block_cols().start),
+                    false => self.resolve_comment(range),
+                };
+                actions.insert(0, BaseAction::ChangeMode(Modal::Normal));
+                Ok(actions)
+            }
+            Action::JoinSelection => {
+                let selection = Selection::from(&self.cursor).normalized();
+                // Like `J`, a selection of a single line is joined with the line below it
+                let last = match selection.start.line == selection.end.line {
+                    true => (selection.end.line + 1).min(self.buffer.max_line()),
+                    false => selection.end.line,
+                };
+                let range = selection.start.line..last + 1;
+                let (joined, col) =
+                    transform::join_lines(&self.buffer.get_normal_text()[range.clone()]);
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::ReplaceLines(range, vec![joined]),
+                    BaseAction::SetCursor(LineCol {
+                        line: selection.start.line,
+                        col,
+                    }),
+                ]
+            }
+            Action::ShiftSelection { right } => {
+                let width = self.config.shiftwidth * self.repeat_action;
+                let actions = self.resolve_selection_rewrite(|lines, _| {
+                    transform::shift_lines(lines, width, right)
+                });
+                match self.config.indentkeepselection {
+                    true => Ok(self.keep_selection(actions)),
+                    false => Ok(actions),
+                }
+            }
+            Action::ReindentSelection => {
+                let width = self.config.shiftwidth;
+                Ok(self.resolve_selection_rewrite(|lines, previous| {
+                    transform::reindent_lines(lines, previous, width)
+                }))
+            }
+            Action::ExpandSelection => Ok(self.resolve_expand_selection()),
+            Action::DuplicateLines => {
+                let (first, last) = match self.modal.is_selecting() {
+                    true => {
+                        let selection = Selection::from(&self.cursor).normalized();
+                        (selection.start.line, selection.end.line)
+                    }
+                    false => (self.cursor.line(), self.cursor.line()),
+                };
+                let copied = &self.buffer.get_normal_text()[first..=last];
+                let copies = vec![copied; self.repeat_action].concat();
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::ReplaceLines(last + 1..last + 1, copies),
+                    BaseAction::SetCursor(LineCol {
+                        line: last + 1,
+                        col: self.cursor.col(),
+                    }),
+                ]
+            }
+            Action::MoveLineDown | Action::MoveLineUp => {
+                Ok(self.resolve_move_lines(matches!(api_action, Action::MoveLineDown)))
+            }
+            Action::ShrinkSelection => {
+                let current = self.selection_range();
+                match self.expansions.pop() {
+                    Some((previous, expanded)) if expanded == current => {
+                        Ok(self.resolve_select(previous))
+                    }
+                    _ => {
+                        self.expansions.clear();
+                        ok_vec!()
+                    }
+                }
+            }
+
+            // Operators
+            // Like in vim, `cw` on a word changes up to its end, keeping the whitespace after it
+            Action::Operate(Operator::Change, object @ TextObject::NextWord { .. })
+                if !self.char_under_cursor().is_some_and(char::is_whitespace) =>
+            {
+                match self.text_object_target(object)? {
+                    Some(range) => {
+                        let lines = self.buffer.get_normal_text();
+                        let range = textobject::trim_trailing_whitespace(lines, range);
+                        self.apply_operator(Operator::Change, range)
+                    }
+                    None => ok_vec!(),
+                }
             }
+            Action::Operate(op, object) => match self.text_object_target(object)? {
+                Some(range) => self.apply_operator(op, range),
+                None => ok_vec!(),
+            },
+            Action::Surround(object, ch) => match self.text_object_target(object)? {
+                Some(range) => Ok(self.resolve_add_surround(range, ch)),
+                None => ok_vec!(),
+            },
+            Action::DeleteSurround(ch) => self.resolve_replace_surround(ch, None),
+            Action::ChangeSurround(old, new) => self.resolve_replace_surround(old, Some(new)),
 
             // Paste actions
-            Action::Paste(reg) => ok_vec![BaseAction::Paste(reg, 1)],
-            Action::PasteAbove(reg) => ok_vec![BaseAction::Paste(reg, 1)],
+            Action::Paste(reg) => {
+                let reg = self.registers.paste_source(reg);
+                self.resolve_paste(reg, false)
+            }
+            Action::PasteAbove(reg) => {
+                let reg = self.registers.paste_source(reg);
+                self.resolve_paste(reg, true)
+            }
             Action::PasteNewline(reg) => {
+                let reg = self.registers.paste_source(reg);
                 ok_vec![BaseAction::MoveDown(1), BaseAction::Paste(reg, 1)]
             }
 
             // Miscellaneous actions
-            Action::OpenFile => ok_vec![BaseAction::OpenFile],
-            Action::InsertNewLine => ok_vec![
-                BaseAction::InsertLineAt(lazy!(), 1),
-                BaseAction::MoveDown(1)
-            ],
-            Action::FetchFromHistory => ok_vec![BaseAction::FetchFromHistory],
-            Action::ExecuteCommand(c) => self.resolve_command_action(c),
-        }
-    }
-    fn resolve_command_action(&self, c: Command) -> Result<Vec<BaseAction>> {
-        match c {
-            Command::Exit => Err(Error::ExitCall),
-            Command::None => ok_vec![BaseAction::ChangeMode(Modal::Normal)],
-            Command::Find(s) => {
-                let lc = self.find(s, self.cursor.last_text_mode_pos);
-                info!("Found match for find on {:?}", lc);
-
-                match lc {
-                    Err(Error::PatternNotFound) => ok_vec!(BaseAction::ChangeMode(Modal::Normal)),
-                    Ok(target) => self.calculate_jump_actions(target),
+            Action::OpenFile => {
+                let line = &self.buffer.get_normal_text()[self.cursor.line()];
+                let Some(range) = textobject::path(line, self.cursor.col()) else {
+                    force_notif_bar_content("No file name under cursor".to_string());
+                    return ok_vec!();
+                };
+                // Relative paths are relative to the directory of the current file
+                let dir = self.path.as_deref().and_then(Path::parent);
+                let path = dir.unwrap_or(Path::new("")).join(&line[range]);
+                self.resolve_command_action(Command::Edit(path))
+            }
+            Action::OpenEntry => {
+                let Some(dir) = &self.explored else {
+                    return ok_vec!();
+                };
+                let entry = &self.buffer.get_normal_text()[self.cursor.line()];
+                let path = dir.join(entry.trim_end_matches('/'));
+                self.resolve_command_action(Command::Edit(path))
+            }
+            Action::SetMark(mark) => {
+                let mark = match mark {
+                    'a'..='z' => mark,
+                    '`' | '\'' => '`',
+                    _ => return ok_vec!(),
+                };
+                let _ = self.marks.insert(mark, self.cursor.pos);
+                ok_vec!()
+            }
+            Action::JumpToMark { mark, linewise } => {
+                let mark = if mark == '\'' { '`' } else { mark };
+                let Some(&pos) = self.marks.get(&mark) else {
+                    force_notif_bar_content("Mark not set".to_string());
+                    return ok_vec!();
+                };
+                // The text may have shrunk since the mark was set
+                let line = pos.line.min(self.buffer.max_line());
+                let target = match linewise {
+                    true => self.first_non_blank(line),
+                    false => LineCol {
+                        line,
+                        col: pos.col.min(self.buffer.max_normal_col(line)),
+                    },
+                };
+                ok_vec![BaseAction::SetCursor(target)]
+            }
+            Action::RepeatSearch { reverse } => {
+                let Some((pattern, direction)) = self.last_search.clone() else {
+                    force_notif_bar_content("No previous search pattern".to_string());
+                    return ok_vec!();
+                };
+                let forwards = (direction == FindDirection::Forwards) != reverse;
+                match self.search_matches(&pattern, self.cursor.pos, forwards, self.repeat_action) {
+                    Ok(target) => Ok(self.resolve_search_jump(target)),
+                    Err(Error::PatternNotFound) => ok_vec!(),
                     Err(e) => Err(e),
                 }
             }
-            Command::Rfind(s) => {
-                let lc = self.rfind(s, self.cursor.last_text_mode_pos);
-                info!("Found match for rfind on {:?}", lc);
-
-                match lc {
-                    Err(Error::PatternNotFound) => ok_vec!(BaseAction::ChangeMode(Modal::Normal)),
-                    Ok(target) => self.calculate_jump_actions(target),
-                    Err(e) => Err(e),
+            // Moving the cursor out of a closed fold is left to `skip_closed_folds`
+            Action::ToggleFoldRecursively(line) => {
+                match self
+                    .folds
+                    .toggle_recursively(line, self.buffer.get_normal_text())
+                {
+                    Some((fold, true)) => ok_vec![BaseAction::SetCursor(LineCol {
+                        line: fold.start,
+                        col: 0,
+                    })],
+                    Some((_, false)) => ok_vec!(),
+                    None => {
+                        force_notif_bar_content("No fold found".to_string());
+                        ok_vec!()
+                    }
+                }
+            }
+            Action::CloseAllFolds => {
+                let lines = self.buffer.get_normal_text();
+                self.folds.close_all(lines);
+                // The cursor moves onto the line its closed fold is displayed as
+                let line = self.folds.view(lines).display_line(self.cursor.line());
+                match line == self.cursor.line() {
+                    true => ok_vec!(),
+                    false => ok_vec![BaseAction::SetCursor(LineCol { line, col: 0 })],
+                }
+            }
+            Action::OpenAllFolds => {
+                self.folds.open_all();
+                ok_vec!()
+            }
+            Action::ToggleFold(line) => {
+                if self
+                    .folds
+                    .toggle(line, self.buffer.get_normal_text())
+                    .is_none()
+                {
+                    force_notif_bar_content("No fold found".to_string());
+                }
+                ok_vec!()
+            }
+            Action::InsertNewLine => {
+                let mut actions = self.resolve_abbreviation();
+                actions.extend([
+                    BaseAction::InsertLineAt(lazy!(), 1),
+                    BaseAction::MoveDown(1),
+                ]);
+                Ok(actions)
+            }
+            // Inserted as a single edit, which a single undo takes back, without expanding
+            // abbreviations
+            Action::InsertRegister(name) => {
+                let name = self.registers.paste_source(name);
+                let Some(content) = self.registers.get(name) else {
+                    return ok_vec!();
+                };
+                let mut text = content.lines.join("\n");
+                if content.linewise {
+                    text.push('\n');
+                }
+                let (replace, end) = self.resolve_insert_text(&text);
+                ok_vec![replace, BaseAction::SetCursor(end)]
+            }
+            Action::InsertExpression(expression) => match expression::evaluate(&expression) {
+                Ok(value) => {
+                    let (replace, end) = self.resolve_insert_text(&value.to_string());
+                    ok_vec![replace, BaseAction::SetCursor(end)]
+                }
+                Err(Error::InvalidExpression(reason)) => {
+                    force_notif_bar_content(format!("Invalid expression: {reason}"));
+                    ok_vec!()
                 }
+                Err(e) => Err(e),
+            },
+            Action::FetchFromHistory => ok_vec![BaseAction::FetchFromHistory],
+            Action::ExecuteCommand(c) => self.resolve_command_action(c),
+            Action::CompleteCommand => Ok(self.resolve_completion()),
+            Action::ChangeAllPrompt => {
+                if self.keyword_at(self.cursor.pos).is_none() {
+                    force_notif_bar_content("No word under the cursor".to_string());
+                    return ok_vec!();
+                }
+                self.buffer.replace_command_text(CHANGE_ALL_PROMPT);
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Command),
+                    BaseAction::SetCursor(LineCol {
+                        line: 0,
+                        col: CHANGE_ALL_PROMPT.len()
+                    })
+                ]
+            }
+            Action::CommandOnSelection => {
+                self.buffer.replace_command_text(VISUAL_RANGE);
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Command),
+                    BaseAction::SetCursor(LineCol {
+                        line: 0,
+                        col: VISUAL_RANGE.len()
+                    })
+                ]
+            }
+            Action::RunTerminalPrompt => {
+                let terminal_text = self.buffer.get_terminal_text();
+                let output = terminal::run(&terminal_text[terminal_text.len() - 1]);
+                let prompt = LineCol {
+                    line: terminal_text.len() + output.len(),
+                    col: 0,
+                };
+                ok_vec![
+                    BaseAction::AppendTerminalOutput(output),
+                    BaseAction::SetCursor(prompt)
+                ]
             }
         }
     }
-
-    fn calculate_jump_actions(&self, target: LineCol) -> Result<Vec<BaseAction>> {
-        let mut action_vec = vec![];
-        action_vec.push(BaseAction::ChangeMode(Modal::Normal));
-        let from = self.cursor.last_text_mode_pos;
-
-        action_vec.push(BaseAction::MoveLeft(self.cursor.text_mode_col()));
-
-        match from.line.cmp(&target.line) {
-            std::cmp::Ordering::Less => {
-                action_vec.push(BaseAction::MoveDown(target.line - from.line))
+    fn resolve_command_action(&mut self, c: Command) -> Result<Vec<BaseAction>> {
+        match c {
+            Command::Exit { .. } if self.help.is_some() => {
+                let cursor = self.close_help();
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::SetCursor(cursor)
+                ]
             }
-            std::cmp::Ordering::Greater => {
-                action_vec.push(BaseAction::MoveUp(from.line - target.line))
+            Command::Help(topic) if topic == HELP_KEYS => {
+                self.open_help();
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::SetCursor(LineCol::default())
+                ]
             }
-            std::cmp::Ordering::Equal => (),
-        };
-
-        action_vec.push(BaseAction::MoveRight(target.col));
-        info!(
-            "Movement needs to be done to reach target: {:?}",
-            action_vec
-        );
-
-        Ok(action_vec)
-    }
-    /// Resolves the input action and adds corresponding BaseActions to the queue
-    #[instrument]
+            Command::Help(topic) => {
+                force_notif_bar_content(format!("Sorry, no help for {topic}"));
+                ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+            }
+            Command::Exit { force: false } if self.config.confirm && self.modified => {
+                let prompt = "Quit without saving? (y/n)".to_string();
+                self.confirmation = Some((prompt, Command::Exit { force: true }));
+                ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+            }
+            Command::Exit { .. } => {
+                self.discard_swap_file();
+                Err(Error::ExitCall)
+            }
+            Command::Set(args) => {
+                let fileencoding = self.config.fileencoding.clone();
+                match self.config.set(&args) {
+                    Ok(Some(report)) => force_notif_bar_content(report),
+                    Ok(None) => (),
+                    Err(Error::UnknownOption(name)) => {
+                        force_notif_bar_content(format!("Unknown option: {name}"))
+                    }
+                    Err(Error::InvalidInput) => {
+                        force_notif_bar_content(format!("Invalid argument: {args}"))
+                    }
+                    Err(e) => return Err(e),
+                }
+                self.viewport.set_mouse_capture(self.config.mouse)?;
+                self.viewport.set_fold_column(self.config.foldcolumn);
+                self.viewport.set_sign_column(self.config.signcolumn);
+                self.refresh_change_signs();
+                self.viewport.set_color_column(self.config.colorcolumn);
+                self.viewport.set_cursor_line(self.config.cursorline);
+                self.registers.unnamedplus = self.config.clipboard == UNNAMEDPLUS;
+                if !self.config.swapfile {
+                    self.discard_swap_file();
+                }
+                if self.config.fileencoding != fileencoding {
+                    return self.reread_encoded();
+                }
+                ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+            }
+            Command::Highlight(args) => {
+                match self.config.theme.highlight(&args) {
+                    Ok(Some(report)) => force_notif_bar_content(report),
+                    Ok(None) => (),
+                    Err(Error::UnknownOption(name)) => {
+                        force_notif_bar_content(format!("Unknown highlight group or key: {name}"))
+                    }
+                    Err(Error::InvalidInput) => {
+                        force_notif_bar_content(format!("Invalid argument: {args}"))
+                    }
+                    Err(e) => return Err(e),
+                }
+                self.viewport.set_theme(self.config.theme.clone());
+                ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+            }
+            Command::Todo => {
+                self.todo_signs = true;
+                let found = self.place_todo_signs();
+                force_notif_bar_content(format!("{found} TODO comments"));
+                ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+            }
+            Command::Terminal => {
+                let terminal_text = self.buffer.get_terminal_text();
+                let line = terminal_text.len() - 1;
+                let col = terminal_text[line].len();
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Terminal),
+                    BaseAction::SetCursor(LineCol { line, col })
+                ]
+            }
+            Command::Edit(path) if path.is_dir() => {
+                self.resolve_command_action(Command::Explore(Some(path)))
+            }
+            Command::Explore(dir) => {
+                let parent = self.path.as_deref().and_then(Path::parent);
+                let dir = dir
+                    .or_else(|| parent.map(Path::to_path_buf))
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .unwrap_or_else(|| PathBuf::from("."));
+                match self.explore(&dir) {
+                    Ok(()) => ok_vec![
+                        BaseAction::ChangeMode(Modal::Normal),
+                        BaseAction::SetCursor(LineCol::default())
+                    ],
+                    Err(Error::Io(e)) => {
+                        force_notif_bar_content(format!("Can't list {}: {e}", dir.display()));
+                        ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Command::Edit(path) => match self.load_file(&path) {
+                Ok(()) => ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::SetCursor(LineCol::default())
+                ],
+                Err(Error::Io(e)) => {
+                    force_notif_bar_content(format!("Can't open {}: {e}", path.display()));
+                    ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+                }
+                Err(e) => Err(e),
+            },
+            Command::Write {
+                range,
+                path,
+                force,
+                append,
+            } => {
+                // Written whole, an unnamed buffer takes the name of the file
+                if let (None, Some(path), None, false) = (&self.path, &path, &range, append) {
+                    self.set_path(path.clone());
+                }
+                let Some(target) = path.or_else(|| self.path.clone()) else {
+                    force_notif_bar_content("No file name".to_string());
+                    return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+                };
+                let own_file = self.path.as_ref() == Some(&target);
+                let refusal = match (own_file, range.is_some()) {
+                    _ if force || append => None,
+                    (false, _) if target.exists() => Some("exists, add ! to overwrite"),
+                    (true, true) => Some("is the buffer's file, add ! to write a part of it"),
+                    (true, false) if self.conversion_error => {
+                        Some("has conversion errors, add ! to write anyway")
+                    }
+                    _ => None,
+                };
+                if refusal.is_some() && self.config.confirm {
+                    let prompt = format!("Overwrite \"{}\"? (y/n)", target.display());
+                    let command = Command::Write {
+                        range,
+                        path: Some(target),
+                        force: true,
+                        append,
+                    };
+                    self.confirmation = Some((prompt, command));
+                    return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+                }
+                if let Some(refusal) = refusal {
+                    force_notif_bar_content(format!("\"{}\" {refusal}", target.display()));
+                    return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+                }
+                let lines = match &range {
+                    Some(range) => self.buffer.get_full_lines_buffer_window(
+                        Some(LineCol {
+                            line: range.start,
+                            col: 0,
+                        }),
+                        Some(LineCol {
+                            line: range.end - 1,
+                            col: 0,
+                        }),
+                    )?,
+                    None => self.buffer.get_normal_text().to_vec(),
+                };
+                match write_lines(&target, &lines, append, &self.config.fileencoding) {
+                    Ok(bytes) => {
+                        if own_file && range.is_none() && !append {
+                            self.modified = false;
+                            self.conversion_error = false;
+                            self.discard_swap_file();
+                            self.refresh_change_signs();
+                        }
+                        let written = match append {
+                            true => "appended",
+                            false => "written",
+                        };
+                        force_notif_bar_content(format!(
+                            "\"{}\" {}L, {bytes}B {written}",
+                            target.display(),
+                            lines.len()
+                        ));
+                    }
+                    Err(Error::Unrepresentable(encoding)) => {
+                        force_notif_bar_content(format!(
+                            "Can't write {}: characters not representable in {encoding}",
+                            target.display()
+                        ));
+                    }
+                    Err(e) => {
+                        force_notif_bar_content(format!("Can't write {}: {e}", target.display()));
+                    }
+                }
+                ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+            }
+            Command::Alternate => {
+                let Some(path) = &self.path else {
+                    force_notif_bar_content("No file name".to_string());
+                    return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+                };
+                let candidates = alternate::candidates(path, &self.config.alternates);
+                match candidates.iter().find(|candidate| candidate.is_file()) {
+                    Some(alternate) => {
+                        self.resolve_command_action(Command::Edit(alternate.clone()))
+                    }
+                    None => {
+                        force_notif_bar_content(format!("No alternate file of {}", path.display()));
+                        ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+                    }
+                }
+            }
+            Command::Script(script) => {
+                let requests = self.scripts.run(&script, self.cursor.last_text_mode_pos);
+                self.perform_script_requests(requests)
+            }
+            Command::Source(path) => {
+                let requests = match fs::read_to_string(&path) {
+                    Ok(script) => self.scripts.run(&script, self.cursor.last_text_mode_pos),
+                    Err(e) => Err(Error::Io(e)),
+                };
+                self.perform_script_requests(requests)
+            }
+            Command::User(name, args) => {
+                let pos = self.cursor.last_text_mode_pos;
+                let requests = self.scripts.call_command(&name, &args, pos);
+                self.perform_script_requests(requests)
+            }
+            Command::Recover => {
+                let recovered = self.path.as_deref().map(swap::read);
+                match recovered {
+                    Some(Ok(lines)) => ok_vec![
+                        BaseAction::ChangeMode(Modal::Normal),
+                        BaseAction::ReplaceLines(0..self.buffer.max_line() + 1, lines),
+                        BaseAction::SetCursor(LineCol::default())
+                    ],
+                    Some(Err(_)) | None => {
+                        force_notif_bar_content("No swap file to recover".to_string());
+                        ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+                    }
+                }
+            }
+            Command::Goto(line) => {
+                let _ = self.marks.insert('`', self.cursor.last_text_mode_pos);
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::SetCursor(self.first_non_blank(line))
+                ]
+            }
+            Command::GotoByte(count) => {
+                let _ = self.marks.insert('`', self.cursor.last_text_mode_pos);
+                let offset = count.saturating_sub(1);
+                let target = self.buffer.linecol_at_offset(offset).unwrap_or_else(|| {
+                    force_notif_bar_content(format!("Byte {count} is past the end of the file"));
+                    self.buffer.max_linecol()
+                });
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::SetCursor(target)
+                ]
+            }
+            Command::Substitute(range, sub) => {
+                let lines = &self.buffer.get_normal_text()[range.clone()];
+                let (substituted, count) =
+                    transform::substitute(lines, &sub.pattern, &sub.replacement, sub.global);
+                let Some(last_changed) = (0..lines.len()).rfind(|&i| substituted[i] != lines[i])
+                else {
+                    force_notif_bar_content(format!("Pattern not found: {}", sub.pattern));
+                    return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+                };
+                if count > 1 {
+                    force_notif_bar_content(format!("{count} substitutions"));
+                }
+                // Back in the normal plane first, so the edit is recorded for undo
+                let changed = &substituted[last_changed];
+                let cursor = LineCol {
+                    line: range.start + last_changed,
+                    col: changed.len() - changed.trim_start().len(),
+                };
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::ReplaceLines(range, substituted),
+                    BaseAction::SetCursor(cursor),
+                ]
+            }
+            Command::Sort(range, options) => {
+                let (sorted, removed) = transform::sort_lines(
+                    &self.buffer.get_normal_text()[range.clone()],
+                    options.reverse,
+                    options.unique,
+                    options.numeric,
+                );
+                let report = format!("{} lines sorted", range.len());
+                force_notif_bar_content(match removed {
+                    0 => report,
+                    removed => format!("{report}, {removed} duplicates removed"),
+                });
+                let cursor = LineCol {
+                    line: range.start,
+                    col: 0,
+                };
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::ReplaceLines(range, sorted),
+                    BaseAction::SetCursor(cursor),
+                ]
+            }
+            Command::Align(range, delimiter) => {
+                let aligned = transform::align_lines(
+                    &self.buffer.get_normal_text()[range.clone()],
+                    &delimiter,
+                );
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::ReplaceLines(range.clone(), aligned),
+                    BaseAction::SetCursor(self.first_non_blank(range.start)),
+                ]
+            }
+            Command::Number(range, separator) => {
+                let numbered = transform::number_lines(
+                    &self.buffer.get_normal_text()[range.clone()],
+                    range.start + 1,
+                    &separator,
+                );
+                let cursor = LineCol {
+                    line: range.start,
+                    col: 0,
+                };
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::ReplaceLines(range, numbered),
+                    BaseAction::SetCursor(cursor),
+                ]
+            }
+            Command::ChangeAll(replacement) => {
+                let pos = self.cursor.last_text_mode_pos;
+                let Some(word) = self.keyword_at(pos) else {
+                    force_notif_bar_content("No word under the cursor".to_string());
+                    return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+                };
+                let lines = self.buffer.get_normal_text();
+                let word = &lines[pos.line][word.clone()];
+                let (replaced, count) =
+                    transform::replace_word(lines, word, &replacement, &self.config.iskeyword);
+                if count == 0 {
+                    force_notif_bar_content(format!("Pattern not found: {word}"));
+                    return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+                }
+                force_notif_bar_content(format!("{count} occurrences of {word} replaced"));
+                let line = &replaced[pos.line];
+                let cursor = LineCol {
+                    col: grapheme::cluster_start(line, pos.col.min(line.len())),
+                    ..pos
+                };
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::ReplaceLines(0..lines.len(), replaced),
+                    BaseAction::SetCursor(cursor),
+                ]
+            }
+            Command::Diff => {
+                let Some(path) = self.path.clone() else {
+                    force_notif_bar_content("No file to compare with".to_string());
+                    return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+                };
+                let saved = match fs::read(&path) {
+                    Ok(saved) => encoding::decode(&saved, &self.config.fileencoding).0,
+                    Err(e) => {
+                        force_notif_bar_content(format!("Couldn't read {}: {e}", path.display()));
+                        return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+                    }
+                };
+                let mut current = self.buffer.get_normal_text().join("\n");
+                current.push('\n');
+                let diff = diff::unified(&path.display().to_string(), &saved, &current);
+                if diff.is_empty() {
+                    force_notif_bar_content("No unsaved changes".to_string());
+                    return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+                }
+                self.open_scratch(diff);
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::SetCursor(LineCol::default())
+                ]
+            }
+            Command::Squeeze(range) => {
+                let (squeezed, removed) = transform::squeeze_blank_lines(
+                    &self.buffer.get_normal_text()[range.clone()],
+                    self.config.whitespaceblank,
+                );
+                force_notif_bar_content(format!("{removed} lines removed"));
+                if removed == 0 {
+                    return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+                }
+                let cursor = LineCol {
+                    line: range.start,
+                    col: 0,
+                };
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::ReplaceLines(range, squeezed),
+                    BaseAction::SetCursor(cursor),
+                ]
+            }
+            // The buffer keeps a single line once all of its lines are blank
+            Command::TrimBlank => {
+                let lines = self.buffer.get_normal_text();
+                let trailing = lines
+                    .iter()
+                    .rev()
+                    .take_while(|line| transform::is_blank_line(line, self.config.whitespaceblank))
+                    .count()
+                    .min(lines.len() - 1);
+                force_notif_bar_content(format!("{trailing} lines removed"));
+                if trailing == 0 {
+                    return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+                }
+                let last = lines.len() - trailing - 1;
+                let cursor = self.cursor.last_text_mode_pos;
+                let cursor = match cursor.line > last {
+                    true => LineCol { line: last, col: 0 },
+                    false => cursor,
+                };
+                ok_vec![
+                    BaseAction::ChangeMode(Modal::Normal),
+                    BaseAction::ReplaceLines(last + 1..lines.len(), Vec::new()),
+                    BaseAction::SetCursor(cursor),
+                ]
+            }
+            Command::Abbreviate(Some((trigger, expansion))) => {
+                if trigger.chars().all(|ch| self.config.is_keyword(ch)) {
+                    let _ = self.abbreviations.insert(trigger, expansion);
+                } else {
+                    force_notif_bar_content(format!("Invalid abbreviation: {trigger}"));
+                }
+                ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+            }
+            Command::Abbreviate(None) => {
+                let mut listed: Vec<_> = self
+                    .abbreviations
+                    .iter()
+                    .map(|(trigger, expansion)| format!("{trigger} {expansion}"))
+                    .collect();
+                listed.sort();
+                force_notif_bar_content(match listed.is_empty() {
+                    true => "No abbreviation found".to_string(),
+                    false => listed.join(", "),
+                });
+                ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+            }
+            Command::Unabbreviate(trigger) => {
+                if self.abbreviations.remove(&trigger).is_none() {
+                    force_notif_bar_content(format!("No such abbreviation: {trigger}"));
+                }
+                ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+            }
+            Command::Map(modes, Some((lhs, mapping))) => {
+                for &mode in modes {
+                    self.keymap.insert(mode, lhs.clone(), mapping.clone());
+                }
+                ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+            }
+            Command::Map(modes, None) => {
+                let listed = self.keymap.list(modes);
+                force_notif_bar_content(match listed.is_empty() {
+                    true => "No mapping found".to_string(),
+                    false => listed.join(", "),
+                });
+                ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+            }
+            Command::Unmap(modes, lhs) => {
+                let mut removed = false;
+                for &mode in modes {
+                    removed |= self.keymap.remove(mode, &lhs);
+                }
+                if !removed {
+                    force_notif_bar_content(format!(
+                        "No such mapping: {}",
+                        keymap::key_notation(&lhs)
+                    ));
+                }
+                ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+            }
+            Command::Invalid(message) => {
+                force_notif_bar_content(message);
+                ok_vec![BaseAction::ChangeMode(Modal::Normal)]
+            }
+            Command::None => ok_vec![BaseAction::ChangeMode(Modal::Normal)],
+            Command::Find(pattern) => self.resolve_search(pattern, FindDirection::Forwards),
+            Command::Rfind(pattern) => self.resolve_search(pattern, FindDirection::Backwards),
+        }
+    }
+
+    /// Jumps to the match of the pattern typed into the command line, an empty pattern repeating
+    /// the previous search. The count typed in front of the search selects the match.
+    fn resolve_search(
+        &mut self,
+        pattern: String,
+        direction: FindDirection,
+    ) -> Result<Vec<BaseAction>> {
+        // Scrolling to the match starts out from the view the search started from
+        self.end_search_preview();
+        let count = std::mem::replace(&mut self.search_count, 1);
+        let pattern = match (pattern.is_empty(), &self.last_search) {
+            (false, _) => pattern,
+            (true, Some((last, _))) => last.clone(),
+            (true, None) => {
+                force_notif_bar_content("No previous search pattern".to_string());
+                return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+            }
+        };
+        self.last_search = Some((pattern.clone(), direction));
+
+        let from = self.cursor.last_text_mode_pos;
+        let forwards = direction == FindDirection::Forwards;
+        let lc = self.search_matches(&pattern, from, forwards, count);
+        info!("Found match for search on {:?}", lc);
+        match lc {
+            Err(Error::PatternNotFound) => ok_vec!(BaseAction::ChangeMode(Modal::Normal)),
+            // Moving relatively would start out from the command line position
+            Ok(target) => {
+                let _ = self.marks.insert('`', from);
+                let mut actions = vec![BaseAction::ChangeMode(Modal::Normal)];
+                actions.extend(self.resolve_search_jump(target));
+                Ok(actions)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Fills in the first completion candidate for the command line, or the next one if the
+    /// command line still holds the previous candidate
+    fn resolve_completion(&mut self) -> Vec<BaseAction> {
+        let command_line = self.buffer.get_command_text();
+        match &mut self.completion {
+            Some(completion) if completion.current() == command_line => completion.advance(),
+            _ => self.completion = Completion::new(command_line),
+        }
+        let Some(completion) = &self.completion else {
+            return Vec::new();
+        };
+        let completed = completion.current();
+        let col = completed.len();
+        self.buffer.replace_command_text(completed);
+        vec![BaseAction::SetCursor(LineCol { line: 0, col })]
+    }
+
+    /// Replaces the word in front of the cursor with the next (previous) candidate of the word
+    /// completion, starting one if there is none, and reports which candidate it is
+    fn resolve_word_completion(&mut self, forwards: bool) -> Vec<BaseAction> {
+        let pos = self.cursor.pos;
+        if self.word_completion.is_none() {
+            let lines = self.buffer.get_normal_text();
+            self.word_completion = WordCompletion::new(lines, pos, &self.config.iskeyword);
+        }
+        let Some(completion) = &mut self.word_completion else {
+            force_notif_bar_content("Pattern not found".to_string());
+            return Vec::new();
+        };
+        completion.advance(forwards);
+        force_notif_bar_content(completion.report());
+
+        let start = completion.start;
+        let line = &self.buffer.get_normal_text()[pos.line];
+        let completed = format!("{}{}", &line[..start.col], completion.current());
+        let col = completed.len();
+        vec![
+            BaseAction::ReplaceLines(pos.line..pos.line + 1, vec![completed + &line[pos.col..]]),
+            BaseAction::SetCursor(LineCol { col, ..pos }),
+        ]
+    }
+
+    /// Lines scrolled by `Ctrl-U` and `Ctrl-D`
+    fn half_page(&self) -> usize {
+        match self.config.scroll {
+            0 => (self.viewport.text_rows() / 2).max(1),
+            lines => lines,
+        }
+    }
+    /// Scrolls the view for `line` to be in the middle of it
+    fn resolve_center(&self, line: usize) -> BaseAction {
+        let top = self.viewport.top_border();
+        let new_top = line.saturating_sub(self.viewport.text_rows() / 2);
+        match new_top >= top {
+            true => BaseAction::ScrollDown(new_top - top),
+            false => BaseAction::ScrollUp(top - new_top),
+        }
+    }
+    /// Jumps to a search match, centering it with `searchcenter`
+    fn resolve_search_jump(&self, target: LineCol) -> Vec<BaseAction> {
+        let mut actions = vec![BaseAction::SetCursor(target)];
+        if self.config.searchcenter {
+            actions.push(self.resolve_center(target.line));
+        }
+        actions
+    }
+    /// Scrolls the view by `dist` lines without moving past the last line. The cursor moves by
+    /// `cursor_dist` lines in the same direction and is dragged along if it would leave the screen.
+    /// On its new line it keeps the column it had before scrolling, or goes to the first non-blank
+    /// character if `startofline` is set.
+    fn resolve_scroll(&self, down: bool, dist: usize, cursor_dist: usize) -> Vec<BaseAction> {
+        let top = self.viewport.top_border();
+        let max_line = self.buffer.max_line();
+        let (scroll, new_top) = if down {
+            let new_top = (top + dist).min(max_line.max(top));
+            (BaseAction::ScrollDown(new_top - top), new_top)
+        } else {
+            let new_top = top.saturating_sub(dist);
+            (BaseAction::ScrollUp(top - new_top), new_top)
+        };
+
+        let last_visible = new_top + self.viewport.text_rows().saturating_sub(1);
+        let moved = if down {
+            self.cursor.line() + cursor_dist
+        } else {
+            self.cursor.line().saturating_sub(cursor_dist)
+        };
+        let line = moved.clamp(new_top, last_visible).min(max_line);
+        let mut actions = vec![scroll];
+        if line != self.cursor.line() {
+            let cursor = match self.config.startofline {
+                true => self.first_non_blank(line),
+                false => {
+                    let col = self.desired_col.unwrap_or(self.cursor.col());
+                    let text = &self.buffer.get_normal_text()[line];
+                    let col = grapheme::cluster_start(text, col.min(text.len()));
+                    LineCol { line, col }
+                }
+            };
+            actions.push(BaseAction::SetCursor(cursor));
+        }
+        actions
+    }
+
+    /// Computes the range of a text object at the cursor.
+    /// Returns `Error::PatternNotFound` if there is nothing to act upon.
+    fn text_object_range(&self, object: TextObject) -> Result<TextRange> {
+        let lines = self.buffer.get_normal_text();
+        let pos = self.cursor.pos;
+        match object {
+            TextObject::Line => {
+                let end = (pos.line + self.repeat_action - 1).min(self.buffer.max_line());
+                Ok(TextRange::lines(pos.line, end))
+            }
+            TextObject::Word { inner } => {
+                textobject::word(&lines[pos.line], pos, inner, &self.config.iskeyword)
+                    .ok_or(Error::PatternNotFound)
+            }
+            TextObject::Paragraph { inner } => Ok(textobject::paragraph(lines, pos.line, inner)),
+            TextObject::Tag { inner } => textobject::tag(lines, pos, inner),
+            TextObject::Indent { inner } => {
+                textobject::indent_block(lines, pos.line, inner).ok_or(Error::PatternNotFound)
+            }
+            TextObject::NextWord { big } => {
+                let iskeyword = &self.config.iskeyword;
+                Ok(textobject::next_word(
+                    lines,
+                    pos,
+                    self.repeat_action,
+                    big,
+                    iskeyword,
+                ))
+            }
+            TextObject::Vertical { up: false } => {
+                let end = (pos.line + self.repeat_action).min(self.buffer.max_line());
+                Ok(TextRange::lines(pos.line, end))
+            }
+            TextObject::Vertical { up: true } => Ok(TextRange::lines(
+                pos.line.saturating_sub(self.repeat_action),
+                pos.line,
+            )),
+            TextObject::Percent => {
+                let line = self.percent_line();
+                Ok(TextRange::lines(pos.line.min(line), pos.line.max(line)))
+            }
+        }
+    }
+
+    /// The character the cursor is on, `None` past the end of the line
+    fn char_under_cursor(&self) -> Option<char> {
+        let line = &self.buffer.get_normal_text()[self.cursor.line()];
+        line.get(self.cursor.col()..)?.chars().next()
+    }
+
+    /// Computes the range of a text object at the cursor, reporting malformed markup in the
+    /// notification bar. Returns `None` if there is nothing to act upon.
+    fn text_object_target(&self, object: TextObject) -> Result<Option<TextRange>> {
+        match self.text_object_range(object) {
+            Ok(range) => Ok(Some(range)),
+            Err(Error::PatternNotFound) => Ok(None),
+            Err(Error::MalformedMarkup) => {
+                force_notif_bar_content("Malformed markup, no tag pair to act upon".to_string());
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Replaces the buffer with the contents of the file at `path`, leaving the buffer untouched if
+    /// the file can't be read
+    fn load_file(&mut self, path: &Path) -> Result<()> {
+        let (content, invalid) = encoding::decode(&fs::read(path)?, &self.config.fileencoding);
+        if invalid {
+            force_notif_bar_content(format!(
+                "{} isn't valid {}, invalid bytes were replaced",
+                path.display(),
+                self.config.fileencoding
+            ));
+        }
+        // The file replaces the help buffer, as if it was opened from the buffer behind it
+        self.help = None;
+        self.explored = None;
+        self.discard_swap_file();
+        self.buffer
+            .load(content.lines().map(String::from).collect());
+        self.set_path(path.to_path_buf());
+        self.modified = false;
+        self.conversion_error = invalid;
+        self.last_visual = None;
+        self.refresh_change_signs();
+        Ok(())
+    }
+
+    /// Reads the file of the buffer again in the new `fileencoding`, unless the buffer has unsaved
+    /// changes, which are written in the new encoding instead
+    fn reread_encoded(&mut self) -> Result<Vec<BaseAction>> {
+        let fileencoding = &self.config.fileencoding;
+        let path = match &self.path {
+            Some(path) if !self.modified && self.help.is_none() && path.is_file() => path.clone(),
+            _ => {
+                self.conversion_error = false;
+                force_notif_bar_content(format!("The buffer will be written as {fileencoding}"));
+                return ok_vec![BaseAction::ChangeMode(Modal::Normal)];
+            }
+        };
+        let (content, invalid) = encoding::decode(&fs::read(&path)?, fileencoding);
+        self.conversion_error = invalid;
+        force_notif_bar_content(match invalid {
+            true => format!(
+                "{} isn't valid {fileencoding}, invalid bytes were replaced",
+                path.display()
+            ),
+            false => format!("\"{}\" reread as {fileencoding}", path.display()),
+        });
+        let pos = self.cursor.last_text_mode_pos;
+        self.buffer
+            .load(content.lines().map(String::from).collect());
+        let line = pos.line.min(self.buffer.max_line());
+        ok_vec![
+            BaseAction::ChangeMode(Modal::Normal),
+            BaseAction::SetCursor(LineCol {
+                line,
+                col: pos.col.min(self.buffer.max_normal_col(line)),
+            })
+        ]
+    }
+
+    /// Replaces the buffer by the read-only help listing the keybindings, keeping it to be restored
+    /// by `close_help`. Opening the help while it is open refreshes it.
+    fn open_help(&mut self) {
+        let text = self.help_keys_text();
+        self.open_scratch(text);
+    }
+
+    /// Replaces the buffer by a read-only one holding `text`, like the help. The buffer it replaces
+    /// is kept to be restored by `close_help`, unless another read-only buffer is already open.
+    fn open_scratch(&mut self, text: Vec<String>) {
+        if self.help.is_none() {
+            let cursor = match self.modal.is_text() {
+                true => self.cursor.pos,
+                false => self.cursor.last_text_mode_pos,
+            };
+            self.help = Some(HelpReturn {
+                buffer: std::mem::take(&mut self.buffer),
+                path: self.path.take(),
+                modified: std::mem::take(&mut self.modified),
+                folds: std::mem::take(&mut self.folds),
+                cursor,
+            });
+        }
+        self.buffer.load(text);
+        self.explored = None;
+    }
+
+    /// Lists the entries of `dir` in a read-only buffer like the help, the entry at the cursor
+    /// being opened by Enter
+    pub fn explore(&mut self, dir: &Path) -> Result<()> {
+        let listing = explore::listing(dir)?;
+        self.open_scratch(listing);
+        self.explored = Some(dir.to_path_buf());
+        Ok(())
+    }
+
+    /// Restores the buffer the help replaced, returning the cursor position within it
+    fn close_help(&mut self) -> LineCol {
+        self.explored = None;
+        let Some(help) = self.help.take() else {
+            return self.cursor.pos;
+        };
+        self.buffer = help.buffer;
+        self.path = help.path;
+        self.modified = help.modified;
+        self.folds = help.folds;
+        help.cursor
+    }
+
+    /// The lines of `:help keys`, listing the bindings of the interpreters and the mappings
+    fn help_keys_text(&self) -> Vec<String> {
+        let motions: Vec<_> = MOTIONS
+            .iter()
+            .map(|b| (b.notation(), b.description))
+            .collect();
+        let mut keys: Vec<_> = NORMAL_KEYS
+            .iter()
+            .map(|b| (b.notation(), b.description))
+            .collect();
+        if !self.config.duplicatekey.is_empty() {
+            let duplicate = self.config.duplicatekey.clone();
+            keys.push((duplicate, "Duplicate the line below"));
+        }
+        let sequences: Vec<_> = NORMAL_SEQUENCES
+            .iter()
+            .map(|&(keys, description)| (keys.to_string(), description))
+            .collect();
+        let width = [&motions, &keys, &sequences]
+            .iter()
+            .flat_map(|section| section.iter().map(|(keys, _)| keys.len()))
+            .max()
+            .unwrap_or_default();
+
+        let mut text = vec![
+            "Keybindings, close this buffer with :q".to_string(),
+            String::new(),
+        ];
+        for (title, section) in [
+            ("Motions of normal and visual mode", motions),
+            ("Normal mode", keys),
+            ("Normal mode key sequences", sequences),
+        ] {
+            text.push(title.to_string());
+            text.extend(
+                section
+                    .iter()
+                    .map(|(keys, description)| format!("  {keys:width$}  {description}")),
+            );
+            text.push(String::new());
+        }
+        text.push("Mappings".to_string());
+        let mappings = self
+            .keymap
+            .list(&[MapMode::Normal, MapMode::Visual, MapMode::Insert]);
+        match mappings.is_empty() {
+            true => text.push("  No mapping found".to_string()),
+            false => text.extend(mappings.iter().map(|mapping| format!("  {mapping}"))),
+        }
+        text
+    }
+
+    /// Expands the abbreviation typed right before the cursor in insert mode. The trigger has to
+    /// be a whole word, the expansion replaces it as a single edit.
+    fn resolve_abbreviation(&self) -> Vec<BaseAction> {
+        if !self.modal.is_insert() || self.abbreviations.is_empty() {
+            return Vec::new();
+        }
+        let pos = self.cursor.pos;
+        let line = &self.buffer.get_normal_text()[pos.line];
+        let Some(before) = line.get(..pos.col) else {
+            return Vec::new();
+        };
+        let start = before
+            .char_indices()
+            .rev()
+            .take_while(|&(_, ch)| self.config.is_keyword(ch))
+            .last()
+            .map_or(pos.col, |(i, _)| i);
+        let Some(expansion) = self.abbreviations.get(&before[start..]) else {
+            return Vec::new();
+        };
+
+        let expanded = format!("{}{expansion}{}", &line[..start], &line[pos.col..]);
+        let cursor = LineCol {
+            line: pos.line,
+            col: start + expansion.len(),
+        };
+        vec![
+            BaseAction::ReplaceLines(pos.line..pos.line + 1, vec![expanded]),
+            BaseAction::SetCursor(cursor),
+        ]
+    }
+
+    /// The line `{count}%` jumps to, `count` percent through the file rounded up like in vim
+    fn percent_line(&self) -> usize {
+        let line_count = self.buffer.max_line() + 1;
+        let line = (self.repeat_action.min(100) * line_count).div_ceil(100);
+        line.clamp(1, line_count) - 1
+    }
+
+    /// Position of the first non-blank character of the given line
+    fn first_non_blank(&self, line: usize) -> LineCol {
+        let text = &self.buffer.get_normal_text()[line];
+        LineCol {
+            line,
+            col: text.len() - text.trim_start().len(),
+        }
+    }
+
+    /// Surrounds the range with the delimiters of `ch` (`ys`), linewise ranges are surrounded from
+    /// the first non-blank character on. The cursor lands on the opening delimiter.
+    fn resolve_add_surround(&self, range: TextRange, ch: char) -> Vec<BaseAction> {
+        let lines = self.buffer.get_normal_text();
+        let (start, end) = if range.linewise {
+            let start = self.first_non_blank(range.start.line);
+            let end = LineCol {
+                line: range.end.line,
+                col: lines[range.end.line].len(),
+            };
+            (start, end)
+        } else {
+            (range.start, range.end)
+        };
+        let (open, close) = surround::delimiters(ch);
+        let spans = [(start..start, open.as_str()), (end..end, close.as_str())];
+        let (replaced, surrounded) = transform::replace_spans(lines, &spans);
+        vec![
+            BaseAction::ReplaceLines(replaced, surrounded),
+            BaseAction::SetCursor(start),
+        ]
+    }
+
+    /// Replaces the delimiters of `old` around the cursor with those of `new` (`cs`), or deletes
+    /// them if there is no replacement (`ds`). The cursor lands on the opening delimiter.
+    fn resolve_replace_surround(&self, old: char, new: Option<char>) -> Result<Vec<BaseAction>> {
+        let lines = self.buffer.get_normal_text();
+        let (open_span, close_span) = match surround::find(lines, self.cursor.pos, old) {
+            Ok(spans) => spans,
+            Err(Error::PatternNotFound) => return ok_vec!(),
+            Err(e) => return Err(e),
+        };
+        let (open, close) = new.map(surround::delimiters).unwrap_or_default();
+        let cursor = open_span.start;
+        let spans = [(open_span, open.as_str()), (close_span, close.as_str())];
+        let (replaced, surrounded) = transform::replace_spans(lines, &spans);
+        ok_vec![
+            BaseAction::ReplaceLines(replaced, surrounded),
+            BaseAction::SetCursor(cursor),
+        ]
+    }
+
+    /// Reindents the given lines as a single edit, leaving the cursor on the first non-blank
+    /// character of the first line
+    fn resolve_reindent(&self, range: Range<usize>) -> Vec<BaseAction> {
+        let lines = self.buffer.get_normal_text();
+        let previous = range.start.checked_sub(1).map(|line| lines[line].as_str());
+        let reindented =
+            transform::reindent_lines(&lines[range.clone()], previous, self.config.shiftwidth);
+        let first = &reindented[0];
+        let cursor = LineCol {
+            line: range.start,
+            col: first.len() - first.trim_start().len(),
+        };
+        vec![
+            BaseAction::ReplaceLines(range, reindented),
+            BaseAction::SetCursor(cursor),
+        ]
+    }
+
+    /// Inserts `ch` at the cursor, breaking the line at a blank in front of it when the typed text
+    /// goes past `textwidth`. `None` if the line doesn't need breaking.
+    fn resolve_auto_wrap(&self, ch: char) -> Option<Vec<BaseAction>> {
+        let pos = self.cursor.pos;
+        let line = self.buffer.get_normal_text().get(pos.line)?;
+        let (before, after) = line.split_at(pos.col.min(line.len()));
+        let typed = format!("{before}{ch}{after}");
+        let col = before.len() + ch.len_utf8();
+        let (head, tail, col) = transform::wrap_at_blank(&typed, col, self.config.textwidth)?;
+        Some(vec![
+            BaseAction::ReplaceLines(pos.line..pos.line + 1, vec![head, tail]),
+            BaseAction::SetCursor(LineCol {
+                line: pos.line + 1,
+                col,
+            }),
+        ])
+    }
+
+    /// Rewraps the given lines to `textwidth`, keeping their indentation and comment leaders. The
+    /// cursor moves to the last rewrapped line, unless `keep_cursor` leaves it where it was.
+    fn resolve_reflow(&self, range: Range<usize>, keep_cursor: bool) -> Vec<BaseAction> {
+        let comment = self.config.comment_string(self.path.as_deref()).trim();
+        let reflowed = transform::reflow_lines(
+            &self.buffer.get_normal_text()[range.clone()],
+            self.config.reflow_width(),
+            comment,
+        );
+        let last_line = range.start + reflowed.len() - 1;
+        let cursor = match keep_cursor {
+            true if (range.start..=last_line).contains(&self.cursor.line()) => LineCol {
+                col: self
+                    .cursor
+                    .col()
+                    .min(reflowed[self.cursor.line() - range.start].len()),
+                ..self.cursor.pos
+            },
+            true => self.cursor.pos,
+            false => {
+                let last = &reflowed[reflowed.len() - 1];
+                LineCol {
+                    line: last_line,
+                    col: last.len() - last.trim_start().len(),
+                }
+            }
+        };
+        vec![
+            BaseAction::ReplaceLines(range, reflowed),
+            BaseAction::SetCursor(cursor),
+        ]
+    }
+
+    /// Toggles the line comment on the given lines, leaving the cursor on the first non-blank
+    /// character of the first line
+    fn resolve_comment(&self, range: Range<usize>) -> Vec<BaseAction> {
+        let prefix = self.config.comment_string(self.path.as_deref());
+        let toggled =
+            transform::toggle_comment(&self.buffer.get_normal_text()[range.clone()], prefix);
+        let first = &toggled[0];
+        let cursor = LineCol {
+            line: range.start,
+            col: first.len() - first.trim_start().len(),
+        };
+        vec![
+            BaseAction::ReplaceLines(range, toggled),
+            BaseAction::SetCursor(cursor),
+        ]
+    }
+
+    /// Adds a cursor at the next occurrence of the word under the cursor, after the cursor added
+    /// last. The primary cursor moves to the start of the word, where the others are placed too.
+    fn resolve_add_cursor(&mut self) -> Vec<BaseAction> {
+        let pos = self.cursor.pos;
+        let Some(word) = self.keyword_at(pos) else {
+            force_notif_bar_content("No word under the cursor".to_string());
+            return Vec::new();
+        };
+        let start = LineCol {
+            col: word.start,
+            ..pos
+        };
+        let lines = self.buffer.get_normal_text();
+        let iskeyword = &self.config.iskeyword;
+        let text = &lines[pos.line][word];
+        let mut taken = self.extra_cursors.clone();
+        taken.push(start);
+        let from = self.extra_cursors.last().copied().unwrap_or(start);
+        match multicursor::next_match(lines, text, from, &taken, iskeyword) {
+            Some(found) => self.extra_cursors.push(found),
+            None => force_notif_bar_content(format!("No further match of {text}")),
+        }
+        vec![BaseAction::SetCursor(start)]
+    }
+
+    /// Byte range of the keyword at `pos`, `None` if there is none
+    fn keyword_at(&self, pos: LineCol) -> Option<Range<usize>> {
+        let line = &self.buffer.get_normal_text()[pos.line];
+        textobject::word(line, pos, true, &self.config.iskeyword)
+            .map(|word| word.start.col..word.end.col)
+            .filter(|word| {
+                line[word.clone()]
+                    .chars()
+                    .all(|ch| self.config.is_keyword(ch))
+            })
+    }
+
+    /// Makes the edit at the cursor and at all additional cursors
+    fn resolve_multicursor_edit(&mut self, edit: CursorEdit) -> Vec<BaseAction> {
+        let mut cursors = vec![self.cursor.pos];
+        cursors.extend(&self.extra_cursors);
+        let (range, lines, moved) =
+            multicursor::apply(self.buffer.get_normal_text(), &cursors, edit);
+        self.extra_cursors = moved[1..].to_vec();
+        vec![
+            BaseAction::ReplaceLines(range, lines),
+            BaseAction::SetCursor(moved[0]),
+        ]
+    }
+
+    /// Toggles the comments of the lines at the column `col`, the left edge of a visual block
+    fn resolve_block_comment(&self, range: Range<usize>, col: usize) -> Vec<BaseAction> {
+        let prefix = self.config.comment_string(self.path.as_deref());
+        let lines = &self.buffer.get_normal_text()[range.clone()];
+        let toggled = transform::toggle_comment_at(lines, prefix, col, self.config.blockpad);
+        let cursor = LineCol {
+            line: range.start,
+            col: col.min(toggled[0].len()),
+        };
+        vec![
+            BaseAction::ReplaceLines(range, toggled),
+            BaseAction::SetCursor(cursor),
+        ]
+    }
+
+    /// Pads the cursor line with spaces up to the cursor when it sits in virtual space past the end
+    /// of the line, for text to be inserted there
+    fn resolve_virtual_space_padding(&self) -> Vec<BaseAction> {
+        let pos = self.cursor.pos;
+        let line = &self.buffer.get_normal_text()[pos.line];
+        if !self.modal.is_text() || pos.col <= line.len() {
+            return Vec::new();
+        }
+        let padded = format!("{line}{}", " ".repeat(pos.col - line.len()));
+        vec![BaseAction::ReplaceLines(
+            pos.line..pos.line + 1,
+            vec![padded],
+        )]
+    }
+
+    /// Deletes as many characters as the count asks for after the cursor (`x`) or before it (`X`),
+    /// but no more than the line holds, a grapheme cluster counting as one character. The characters
+    /// go to the selected register, or to the small delete register.
+    fn resolve_delete_chars(&mut self, before: bool) -> Vec<BaseAction> {
+        let pos = self.cursor.pos;
+        let line = &self.buffer.get_normal_text()[pos.line];
+        let col = pos.col.min(line.len());
+        // A vertical move can leave the cursor within a cluster, which then belongs to it
+        let col = match col < line.len() {
+            true => grapheme::previous_boundary(line, col + 1),
+            false => col,
+        };
+        let count = self.repeat_action.max(1);
+        let (start, end) = if before {
+            let start = (0..count).fold(col, |col, _| grapheme::previous_boundary(line, col));
+            (start, col)
+        } else {
+            let end = (0..count).fold(col, |col, _| grapheme::next_boundary(line, col));
+            (col, end)
+        };
+        if start == end {
+            return Vec::new();
+        }
+        let content = RegisterContent::new(vec![line[start..end].to_string()], false);
+        // The cursor stays on the line once its last characters are gone
+        let remaining = format!("{}{}", &line[..start], &line[end..]);
+        let last = grapheme::previous_boundary(&remaining, remaining.len());
+        let cursor = LineCol {
+            line: pos.line,
+            col: start.min(last),
+        };
+        self.registers.delete(self.register, content);
+        // Deleting the whole line with `DeleteAt` would remove the line itself
+        vec![
+            BaseAction::ReplaceLines(pos.line..pos.line + 1, vec![remaining]),
+            BaseAction::SetCursor(cursor),
+        ]
+    }
+
+    /// Yanks the text within the range into the selected register and, for deleting operators,
+    /// removes it from the buffer. Change additionally enters insert mode in place of the text.
+    fn apply_operator(&mut self, op: Operator, range: TextRange) -> Result<Vec<BaseAction>> {
+        match op {
+            Operator::Comment => {
+                return Ok(self.resolve_comment(range.start.line..range.end.line + 1))
+            }
+            Operator::Reindent => {
+                return Ok(self.resolve_reindent(range.start.line..range.end.line + 1))
+            }
+            Operator::Reflow { keep_cursor } => {
+                let lines = range.start.line..range.end.line + 1;
+                return Ok(self.resolve_reflow(lines, keep_cursor));
+            }
+            _ => (),
+        }
+        let lines = self.buffer.get_normal_text();
+        let taken = if range.linewise {
+            lines[range.start.line..=range.end.line].to_vec()
+        } else {
+            self.buffer
+                .get_text(range.start, range.end)?
+                .split('\n')
+                .map(String::from)
+                .collect()
+        };
+        let content = RegisterContent::new(taken, range.linewise);
+
+        if op == Operator::Yank {
+            self.registers.yank(self.register, content);
+            return ok_vec![BaseAction::SetCursor(range.start)];
+        }
+        self.registers.delete(self.register, content);
+
+        let replaced = range.start.line..range.end.line + 1;
+        let mut actions = if range.linewise && op == Operator::Delete {
+            let remaining = lines.len() - replaced.len();
+            // Without lines below the deleted ones, the cursor moves up onto the new last line
+            let line = range.start.line.min(remaining.saturating_sub(1));
+            vec![
+                BaseAction::ReplaceLines(replaced, vec![]),
+                BaseAction::SetCursor(LineCol { line, col: 0 }),
+            ]
+        } else if range.linewise {
+            vec![
+                BaseAction::ReplaceLines(replaced, vec![String::new()]),
+                BaseAction::SetCursor(range.start),
+            ]
+        } else {
+            let joined = format!(
+                "{}{}",
+                &lines[range.start.line][..range.start.col],
+                &lines[range.end.line][range.end.col..]
+            );
+            vec![
+                BaseAction::ReplaceLines(replaced, vec![joined]),
+                BaseAction::SetCursor(range.start),
+            ]
+        };
+        if op == Operator::Change {
+            actions.push(BaseAction::ChangeMode(Modal::Insert));
+        }
+        Ok(actions)
+    }
+
+    /// Pastes the content of a register `count` times after the cursor, or before it if `before`
+    /// is set. Linewise content is pasted as new lines below (above) the cursor line.
+    fn resolve_paste(&self, register: char, before: bool) -> Result<Vec<BaseAction>> {
+        let Some(content) = self.registers.get(register) else {
+            return ok_vec!();
+        };
+        let pos = self.cursor.pos;
+        let count = self.repeat_action;
+
+        if content.linewise {
+            let at = if before { pos.line } else { pos.line + 1 };
+            let pasted = content
+                .lines
+                .iter()
+                .cloned()
+                .cycle()
+                .take(content.lines.len() * count)
+                .collect();
+            return ok_vec![
+                BaseAction::ReplaceLines(at..at, pasted),
+                BaseAction::SetCursor(LineCol { line: at, col: 0 }),
+            ];
+        }
+
+        let mut line = self.buffer.get_normal_text()[pos.line].clone();
+        // In virtual space the line is padded up to the cursor, or over it when pasting after it
+        if pos.col > 0 && pos.col >= line.len() {
+            let width = if before { pos.col } else { pos.col + 1 };
+            line.push_str(&" ".repeat(width - line.len()));
+        }
+        let at = if before {
+            pos.col
+        } else {
+            line[pos.col..]
+                .chars()
+                .next()
+                .map_or(line.len(), |ch| pos.col + ch.len_utf8())
+        };
+        let text = content.lines.join("\n").repeat(count);
+        let pasted: Vec<String> = format!("{}{}{}", &line[..at], text, &line[at..])
+            .split('\n')
+            .map(String::from)
+            .collect();
+
+        let last_pasted = text.rsplit('\n').next().unwrap_or_default();
+        let end = if pasted.len() == 1 {
+            LineCol {
+                line: pos.line,
+                col: (at + text.len()).saturating_sub(1),
+            }
+        } else {
+            LineCol {
+                line: pos.line + pasted.len() - 1,
+                col: last_pasted.len().saturating_sub(1),
+            }
+        };
+        ok_vec![
+            BaseAction::ReplaceLines(pos.line..pos.line + 1, pasted),
+            BaseAction::SetCursor(end),
+        ]
+    }
+
+    /// Adds the count to the number under or after the cursor, leaving the cursor on its last digit
+    fn resolve_increment(&self, delta: i64) -> Result<Vec<BaseAction>> {
+        let pos = self.cursor.pos;
+        let delta = delta * self.repeat_action as i64;
+        let line = &self.buffer.get_normal_text()[pos.line];
+        let Some(incremented) = transform::increment_number(line, pos.col, delta) else {
+            return ok_vec!();
+        };
+        let (number, _) =
+            transform::find_number(&incremented, pos.col).expect("Number was just written");
+        ok_vec![
+            BaseAction::ReplaceLines(pos.line..pos.line + 1, vec![incremented]),
+            BaseAction::SetCursor(LineCol {
+                line: pos.line,
+                col: number.end - 1,
+            }),
+        ]
+    }
+
+    /// Replaces the selected lines with their rewrite as a single edit and leaves visual mode,
+    /// placing the cursor on the first non-blank of the first line. The rewrite also receives the
+    /// line in front of the selection.
+    fn resolve_selection_rewrite(
+        &self,
+        rewrite: impl FnOnce(&[String], Option<&str>) -> Vec<String>,
+    ) -> Vec<BaseAction> {
+        let selection = Selection::from(&self.cursor).normalized();
+        let range = selection.start.line..selection.end.line + 1;
+        let lines = self.buffer.get_normal_text();
+        let previous = range.start.checked_sub(1).map(|line| lines[line].as_str());
+        let rewritten = rewrite(&lines[range.clone()], previous);
+        let first = &rewritten[0];
+        let cursor = LineCol {
+            line: range.start,
+            col: first.len() - first.trim_start().len(),
+        };
+        vec![
+            BaseAction::ChangeMode(Modal::Normal),
+            BaseAction::ReplaceLines(range, rewritten),
+            BaseAction::SetCursor(cursor),
+        ]
+    }
+
+    /// Selects the lines rewritten by the actions of `resolve_selection_rewrite` again, in the
+    /// visual mode they were selected in. The ends of the selection stay on their characters.
+    fn keep_selection(&self, mut actions: Vec<BaseAction>) -> Vec<BaseAction> {
+        let Some((range, rewritten)) = actions.iter().find_map(|action| match action {
+            BaseAction::ReplaceLines(range, rewritten) => Some((range, rewritten)),
+            _ => None,
+        }) else {
+            return actions;
+        };
+        let lines = self.buffer.get_normal_text();
+        let follow = |at: LineCol| LineCol {
+            col: (at.col + rewritten[at.line - range.start].len())
+                .saturating_sub(lines[at.line].len()),
+            ..at
+        };
+        let anchor = follow(self.cursor.last_text_mode_pos);
+        let pos = follow(self.cursor.pos);
+        // The cursor placed on the first rewritten line gives way to the selection
+        actions.retain(|action| !matches!(action, BaseAction::SetCursor(_)));
+        actions.extend([
+            BaseAction::SetCursor(anchor),
+            BaseAction::ChangeMode(self.modal),
+            BaseAction::SetCursor(pos),
+        ]);
+        actions
+    }
+
+    /// Moves the cursor line, or the selected lines in visual modes, `count` lines down or up as a
+    /// single edit, stopping at the edges of the buffer. The cursor and the selection move along.
+    fn resolve_move_lines(&self, down: bool) -> Vec<BaseAction> {
+        let visual = self.modal.is_selecting();
+        let (anchor, pos) = match visual {
+            true => (self.cursor.last_text_mode_pos, self.cursor.pos),
+            false => (self.cursor.pos, self.cursor.pos),
+        };
+        let (first, last) = (anchor.line.min(pos.line), anchor.line.max(pos.line));
+        let lines = self.buffer.get_normal_text();
+        let dist = match down {
+            true => self.repeat_action.min(self.buffer.max_line() - last),
+            false => self.repeat_action.min(first),
+        };
+        if dist == 0 {
+            return vec![];
+        }
+
+        let (range, moved) = match down {
+            true => {
+                let range = first..last + dist + 1;
+                let moved = [&lines[last + 1..range.end], &lines[first..=last]].concat();
+                (range, moved)
+            }
+            false => {
+                let range = first - dist..last + 1;
+                let moved = [&lines[first..=last], &lines[range.start..first]].concat();
+                (range, moved)
+            }
+        };
+        let shift = |at: LineCol| LineCol {
+            line: if down { at.line + dist } else { at.line - dist },
+            ..at
+        };
+        let mut actions = vec![BaseAction::ReplaceLines(range, moved)];
+        if visual {
+            actions.extend([
+                BaseAction::ChangeMode(Modal::Normal),
+                BaseAction::SetCursor(shift(anchor)),
+                BaseAction::ChangeMode(self.modal),
+            ]);
+        }
+        actions.push(BaseAction::SetCursor(shift(pos)));
+        actions
+    }
+
+    /// The visual selection as a range of the text, linewise in visual line mode
+    fn selection_range(&self) -> TextRange {
+        let selection = Selection::from(&self.cursor).normalized();
+        if self.modal.is_visual_line() {
+            return TextRange::lines(selection.start.line, selection.end.line);
+        }
+        // The selection includes the character under its end
+        let line = &self.buffer.get_normal_text()[selection.end.line];
+        let width = line
+            .get(selection.end.col..)
+            .and_then(|rest| rest.chars().next())
+            .map_or(0, char::len_utf8);
+        let mut end = selection.end;
+        end.col = (end.col + width).min(line.len());
+        TextRange {
+            start: selection.start,
+            end,
+            linewise: false,
+        }
+    }
+
+    /// Selects `range`, in visual line mode if it is linewise
+    fn resolve_select(&self, range: TextRange) -> Vec<BaseAction> {
+        let (mode, end) = match range.linewise {
+            true => (Modal::VisualLine, range.end),
+            false => {
+                // The cursor rests on the last selected character
+                let line = &self.buffer.get_normal_text()[range.end.line];
+                let col = match line[..range.end.col].chars().next_back() {
+                    Some(ch) if range.end > range.start => range.end.col - ch.len_utf8(),
+                    _ => range.end.col,
+                };
+                (Modal::Visual, LineCol { col, ..range.end })
+            }
+        };
+        vec![
+            BaseAction::ChangeMode(Modal::Normal),
+            BaseAction::SetCursor(range.start),
+            BaseAction::ChangeMode(mode),
+            BaseAction::SetCursor(end),
+        ]
+    }
+
+    /// Grows the selection to the smallest of the word, line and paragraph around the cursor which
+    /// contains more than the selection, remembering it for `ShrinkSelection`
+    fn resolve_expand_selection(&mut self) -> Vec<BaseAction> {
+        let lines = self.buffer.get_normal_text();
+        let pos = self.cursor.pos;
+        let current = self.selection_range();
+        // Linewise ranges are compared by the text they cover
+        let span = |range: &TextRange| match range.linewise {
+            true => (
+                range.start,
+                LineCol {
+                    line: range.end.line,
+                    col: lines[range.end.line].len(),
+                },
+            ),
+            false => (range.start, range.end),
+        };
+        let (start, end) = span(&current);
+        let candidates = [
+            textobject::word(&lines[pos.line], pos, true, &self.config.iskeyword),
+            Some(TextRange::lines(pos.line, pos.line)),
+            Some(textobject::paragraph(lines, pos.line, true)),
+        ];
+        let Some(expanded) = candidates.into_iter().flatten().find(|candidate| {
+            let (candidate_start, candidate_end) = span(candidate);
+            candidate_start <= start
+                && candidate_end >= end
+                && (candidate_start, candidate_end) != (start, end)
+        }) else {
+            return vec![];
+        };
+
+        if self
+            .expansions
+            .last()
+            .is_some_and(|&(_, last)| last != current)
+        {
+            self.expansions.clear();
+        }
+        self.expansions.push((current, expanded));
+        self.resolve_select(expanded)
+    }
+
+    /// Increments the first number on every selected line and leaves visual mode. In sequential
+    /// mode the step grows with every line containing a number (`g Ctrl-A`).
+    fn resolve_selection_increment(&self, delta: i64, sequential: bool) -> Result<Vec<BaseAction>> {
+        let selection = Selection::from(&self.cursor).normalized();
+        let first_col = if self.modal.is_visual() {
+            selection.start.col
+        } else if self.modal.is_visual_block() {
+            selection.block_cols().start
+        } else {
+            0
+        };
+        let range = selection.start.line..selection.end.line + 1;
+        let lines = &self.buffer.get_normal_text()[range.clone()];
+        let delta = delta * self.repeat_action as i64;
+        ok_vec![
+            BaseAction::ReplaceLines(
+                range,
+                transform::increment_lines(lines, first_col, delta, sequential)
+            ),
+            BaseAction::ChangeMode(Modal::Normal),
+            BaseAction::SetCursor(LineCol {
+                line: selection.start.line,
+                col: first_col,
+            }),
+        ]
+    }
+
+    /// Resolves the input action and adds corresponding BaseActions to the queue
+    #[instrument]
     fn add_to_action_queue(&mut self, api_action: Action) -> Result<()> {
-        let mut base_actions = self.resolve_action(api_action)?;
+        // Keys that only build up a pending command resolve to nothing and keep the count alive
+        if matches!(api_action, Action::Nothing) {
+            return Ok(());
+        }
+        self.repeat_action = self.count.take().unwrap_or(1);
+        self.desired_col = match api_action.is_scroll() {
+            true => Some(self.desired_col.unwrap_or(self.cursor.col())),
+            false => None,
+        };
+        let jump_from = api_action.is_jump().then_some(self.cursor.pos);
+        let base_actions = self.resolve_action(api_action);
+        self.register = None;
+        let mut base_actions = base_actions?;
+        if let Some(from) = jump_from.filter(|_| !base_actions.is_empty()) {
+            let _ = self.marks.insert('`', from);
+        }
+
+        // If repeatable
+        if base_actions.len() == 1 && self.repeat_action != 1 {
+            let a = base_actions
+                .pop()
+                .expect("Checked for len one line prior")
+                .repeat(self.repeat_action);
+            self.action_queue.push_back(a);
+        }
+
+        for action in base_actions {
+            self.action_queue.push_back(action)
+        }
+        Ok(())
+    }
+
+    /// Moves to the `count`th occurrence of `ch` after (or before) the cursor like `f` and `F`, or
+    /// next to it like `t` and `T`. The search stays within the cursor line unless
+    /// `findacrosslines` is set, a character which isn't found leaves the cursor alone.
+    fn resolve_find_char(&self, ch: char, forwards: bool, till: bool) -> Vec<BaseAction> {
+        let lines = self.buffer.get_normal_text();
+        let needle = ch.to_string();
+        let next = |pos: LineCol| -> Option<LineCol> {
+            let line = &lines[pos.line];
+            let on_line = match forwards {
+                true => {
+                    let from = line
+                        .get(pos.col..)?
+                        .chars()
+                        .next()
+                        .map_or(pos.col, |under| pos.col + under.len_utf8());
+                    line[from..].find(ch).map(|col| from + col)
+                }
+                false => line.get(..pos.col)?.rfind(ch),
+            };
+            if let Some(col) = on_line {
+                return Some(LineCol { col, ..pos });
+            }
+            if !self.config.findacrosslines {
+                return None;
+            }
+            match forwards {
+                true if pos.line < self.buffer.max_line() => {
+                    let start = LineCol {
+                        line: pos.line + 1,
+                        col: 0,
+                    };
+                    self.find(needle.as_str(), start).ok()
+                }
+                false if pos.line > 0 => {
+                    let line = pos.line - 1;
+                    let end = LineCol {
+                        line,
+                        col: lines[line].len(),
+                    };
+                    self.rfind(needle.as_str(), end).ok()
+                }
+                _ => None,
+            }
+        };
+
+        let mut target = self.cursor.pos;
+        for _ in 0..self.repeat_action {
+            match next(target) {
+                Some(found) => target = found,
+                None => return vec![],
+            }
+        }
+        // `t` and `T` stop on the character in front of the found one, within its line
+        if till {
+            let line = &lines[target.line];
+            target.col = match forwards {
+                true => line[..target.col]
+                    .chars()
+                    .next_back()
+                    .map_or(target.col, |before| target.col - before.len_utf8()),
+                false => (target.col + ch.len_utf8()).min(line.len()),
+            };
+        }
+        vec![BaseAction::SetCursor(target)]
+    }
+
+    fn resolve_find<F, P>(&self, find_fn: F, pattern: P) -> Result<BaseAction>
+    where
+        F: Fn(P, LineCol) -> Result<LineCol>,
+        P: Pattern,
+    {
+        // TODO: After Regex Implementation this part of the code will decide and convert
+        // pattern to regex if needed
+        let pos = self.cursor.pos;
+        let dest = match find_fn(pattern, pos) {
+            Err(Error::PatternNotFound) => pos,
+            Ok(dest) => dest,
+            e => e?,
+        };
+        Ok(BaseAction::SetCursor(dest))
+    }
+    fn jump_two_boundaries<F1, F2>(
+        &self,
+        direction: Direction,
+        first_boundary: F1,
+        second_boundary: F2,
+    ) -> Result<BaseAction>
+    where
+        F1: Fn(char) -> bool,
+        F2: Fn(char) -> bool,
+    {
+        self.jump_two_boundaries_from(self.cursor.pos, direction, first_boundary, second_boundary)
+    }
+
+    /// Jumps over the two boundaries `repeat_action` times, every jump starting where the previous
+    /// one ended, so a count in front of a word motion moves over as many words
+    fn resolve_word_motion<F1, F2>(
+        &self,
+        direction: Direction,
+        first_boundary: F1,
+        second_boundary: F2,
+    ) -> Result<Vec<BaseAction>>
+    where
+        F1: Fn(char) -> bool,
+        F2: Fn(char) -> bool,
+    {
+        let mut pos = self.cursor.pos;
+        for _ in 0..self.repeat_action {
+            match self.jump_two_boundaries_from(
+                pos,
+                direction,
+                &first_boundary,
+                &second_boundary,
+            )? {
+                BaseAction::SetCursor(dest) if dest != pos => pos = dest,
+                _ => break,
+            }
+        }
+        match pos == self.cursor.pos {
+            true => ok_vec![BaseAction::Nothing],
+            false => ok_vec![BaseAction::SetCursor(pos)],
+        }
+    }
+
+    /// Moves to the start of the word `repeat_action` words ahead or back, words being runs of
+    /// keyword characters or of other non-blank characters. Moving on from the first or last word
+    /// of a line continues on the line before or after it.
+    fn resolve_word_start_motion(&self, direction: Direction) -> Vec<BaseAction> {
+        let lines = self.buffer.get_normal_text();
+        let iskeyword = &self.config.iskeyword;
+        let mut pos = self.cursor.pos;
+        for _ in 0..self.repeat_action {
+            let line = &lines[pos.line];
+            pos = match direction {
+                Direction::Forward => {
+                    match textobject::next_word_start(line, pos.col, false, iskeyword) {
+                        Some(col) => LineCol { col, ..pos },
+                        None if pos.line + 1 < lines.len() => self.first_non_blank(pos.line + 1),
+                        None => break,
+                    }
+                }
+                Direction::Backward => {
+                    let before = line.get(..pos.col).unwrap_or(line);
+                    match before.trim().is_empty() {
+                        false => LineCol {
+                            col: textobject::previous_word_start(line, pos.col, iskeyword),
+                            ..pos
+                        },
+                        true if pos.line > 0 => {
+                            let previous = &lines[pos.line - 1];
+                            LineCol {
+                                line: pos.line - 1,
+                                col: textobject::previous_word_start(
+                                    previous,
+                                    previous.len(),
+                                    iskeyword,
+                                ),
+                            }
+                        }
+                        true => break,
+                    }
+                }
+            };
+        }
+        match pos == self.cursor.pos {
+            true => vec![BaseAction::Nothing],
+            false => vec![BaseAction::SetCursor(pos)],
+        }
+    }
+
+    fn jump_two_boundaries_from<F1, F2>(
+        &self,
+        from: LineCol,
+        direction: Direction,
+        first_boundary: F1,
+        second_boundary: F2,
+    ) -> Result<BaseAction>
+    where
+        F1: Fn(char) -> bool,
+        F2: Fn(char) -> bool,
+    {
+        // let mut pos = self.cursor.pos;
+        // // Avoid getting stuck if jump destination is directly on cursor
+        // if self.buffer.max_normal_col(pos.line) > pos.col {
+        //     pos.col += 1;
+        // };
+
+        // let mut dest = self.find(&first_boundary, pos)?;
+        // // let dest = dest?;
+        // dest = self.find(&second_boundary, dest)?;
+        // Ok(BaseAction::SetCursor(dest))
+
+        //////////////////////////
+
+        let mut pos = from;
+
+        // Avoid getting stuck if jump destination is directly on cursor
+        if self.buffer.max_normal_col(pos.line) > pos.col {
+            pos.col += 1;
+        }
+
+        let dest = match direction {
+            Direction::Forward => {
+                let dest = self.find(&first_boundary, pos);
+
+                if let Err(Error::PatternNotFound) = dest {
+                    warn!("First Destination not found");
+                    return Ok(BaseAction::Nothing);
+                };
+                let dest = dest?;
+
+                info!("First Destination found{:?}", &dest);
+                let dest = self.find(&second_boundary, dest);
+                if let Err(Error::PatternNotFound) = dest {
+                    warn!("Second Destination not found");
+                    return Ok(BaseAction::Nothing);
+                };
+                info!("Second Destination found{:?}", &dest);
+                dest?
+            }
+            Direction::Backward => {
+                let dest = self.rfind(&first_boundary, pos)?;
+                self.rfind(&second_boundary, dest)?
+            }
+        };
+
+        Ok(BaseAction::SetCursor(dest))
+    }
+
+    /// Searches for `query` from `at` like `/` and `?` do. Unless `nowrapscan` is set, a search
+    /// reaching the end of the buffer continues from the other end.
+    fn search(&self, query: &str, at: LineCol, forwards: bool) -> Result<LineCol> {
+        let pattern = SearchPattern::parse(query);
+        let ahead = if forwards {
+            self.find_in_scope(pattern.clone(), at)
+        } else {
+            self.rfind_in_scope(pattern.clone(), at)
+        };
+        if !matches!(ahead, Err(Error::PatternNotFound)) {
+            return ahead;
+        }
+
+        let (edge, other_edge) = if forwards {
+            ("BOTTOM", "TOP")
+        } else {
+            ("TOP", "BOTTOM")
+        };
+        if !self.config.wrapscan {
+            force_notif_bar_content(format!("search hit {edge} without match for: {query}"));
+            return ahead;
+        }
+        let wrapped = match (forwards, self.search_scope) {
+            (true, Some(scope)) => self.find_in_scope(pattern, scope.start),
+            (true, None) => self.find(pattern, LineCol::default()),
+            (false, Some(scope)) => self.rfind_in_scope(pattern, scope.end),
+            (false, None) => self.rfind(pattern, self.buffer.max_linecol()),
+        };
+        match wrapped {
+            Ok(_) => {
+                force_notif_bar_content(format!("search hit {edge}, continuing at {other_edge}"))
+            }
+            Err(Error::PatternNotFound) => {
+                force_notif_bar_content(format!("Pattern not found: {query}"))
+            }
+            Err(_) => (),
+        }
+        wrapped
+    }
+    /// Searches the `count`th match of `query` away from `from`, every search continuing from the
+    /// previous match. A forward search skips a match right at `from`.
+    ///
+    /// If searching runs out of matches, or wraps around to the first match found, before reaching
+    /// the `count`th one, it stops at the last match reached and reports how many were found.
+    fn search_matches(
+        &self,
+        query: &str,
+        from: LineCol,
+        forwards: bool,
+        count: usize,
+    ) -> Result<LineCol> {
+        let mut found: Vec<LineCol> = Vec::new();
+        let mut at = from;
+        while found.len() < count {
+            // Matches start on cluster boundaries, the next search skips the whole cluster
+            let start = match forwards {
+                true => LineCol {
+                    line: at.line,
+                    col: grapheme::next_boundary(&self.buffer.get_normal_text()[at.line], at.col),
+                },
+                false => at,
+            };
+            match self.search(query, start, forwards) {
+                Ok(target) if found.first() == Some(&target) => break,
+                Ok(target) => {
+                    found.push(target);
+                    at = target;
+                }
+                Err(Error::PatternNotFound) if !found.is_empty() => break,
+                Err(e) => return Err(e),
+            }
+        }
+        if found.len() < count {
+            force_notif_bar_content(format!(
+                "Only {} of {count} matches for: {query}",
+                found.len()
+            ));
+        }
+        found.last().copied().ok_or(Error::PatternNotFound)
+    }
+    /// Selection of the current visual mode as a search scope, whole lines in visual line mode
+    fn selection_scope(&self) -> Option<Selection> {
+        let selection = Selection::from(&self.cursor).normalized();
+        Some(match self.modal {
+            Modal::VisualLine => Selection {
+                start: LineCol {
+                    line: selection.start.line,
+                    col: 0,
+                },
+                end: LineCol {
+                    line: selection.end.line,
+                    col: self.buffer.max_normal_col(selection.end.line),
+                },
+            },
+            _ => selection,
+        })
+    }
+    /// Searches forwards like `find`, only accepting matches starting within the search scope
+    fn find_in_scope(&self, query: impl Pattern, at: LineCol) -> Result<LineCol> {
+        let Some(scope) = self.search_scope else {
+            return self.find(query, at);
+        };
+        let at = if at < scope.start { scope.start } else { at };
+        self.find(query, at)
+            .and_then(|target| match target <= scope.end {
+                true => Ok(target),
+                false => Err(Error::PatternNotFound),
+            })
+    }
+    /// Searches backwards like `rfind`, only accepting matches starting within the search scope
+    fn rfind_in_scope(&self, query: impl Pattern, at: LineCol) -> Result<LineCol> {
+        let Some(scope) = self.search_scope else {
+            return self.rfind(query, at);
+        };
+        let at = if at > scope.end { scope.end } else { at };
+        self.rfind(query, at)
+            .and_then(|target| match target >= scope.start {
+                true => Ok(target),
+                false => Err(Error::PatternNotFound),
+            })
+    }
+    /// Searches for a query string in the buffer, starting from a given position.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The string to search for.
+    /// * `at` - The position (line and column) to start the search from.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(LineCol)` - The position (line and column) where the query was found.
+    /// * `Err(BufferError::PatternNotFound)` - If the query string is not found in the buffer.
+    ///
+    /// # Behavior
+    ///
+    /// The search starts at the given position and continues to the end of the buffer.
+    /// It searches the remainder of the starting line, then subsequent lines in their entirety.
+    /// The search is case-sensitive and returns the position of the first occurrence found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let buffer = // ... initialize buffer ...
+    /// let result = buffer.find("example", LineCol{line: 1, col: 5});
+    /// assert_eq!(result, Ok(LineCol{line: 2, col: 10})); // Found on line 2, column 10
+    /// ```
+    fn find(&self, query: impl Pattern, at: LineCol) -> Result<LineCol> {
+        // The cursor may be past the end of the line in virtual space
+        let at = LineCol {
+            col: at.col.min(self.buffer.max_normal_col(at.line)),
+            ..at
+        };
+        // Anchors need whole lines, matches in front of `at` on its line are skipped instead
+        if query.is_anchored() {
+            let lines = &self.buffer.get_normal_text()[at.line..];
+            let (first, rest) = lines.split_at(1.min(lines.len()));
+            return query
+                .find_pattern(first)
+                .filter(|target| target.col >= at.col)
+                .or_else(|| {
+                    query.find_pattern(rest).map(|target| LineCol {
+                        line: target.line + 1,
+                        ..target
+                    })
+                })
+                .map(|target| LineCol {
+                    line: target.line + at.line,
+                    ..target
+                })
+                .ok_or(Error::PatternNotFound);
+        }
+        let buf = &self.buffer.get_buffer_window(Some(at), None)?;
+        info!("{:?}", buf);
+        query
+            .find_pattern(buf)
+            .ok_or(Error::PatternNotFound)
+            .map(|target| LineCol {
+                line: target.line + at.line,
+                col: if target.line == 0 {
+                    target.col + at.col
+                } else {
+                    target.col
+                },
+            })
+    }
+
+    /// Searches backwards for a query string in the buffer, ending at a given position.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The string to search for.
+    /// * `at` - The position (line and column) to start the reverse search from.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(LineCol)` - The position (line and column) where the query was found.
+    /// * `Err(BufferError::PatternNotFound)` - If the query string is not found in the buffer.
+    ///
+    /// # Behavior
+    ///
+    /// The search starts at the given position and continues backwards to the beginning of the buffer.
+    /// It first searches the portion of the starting line from the given position to its start,
+    /// then searches previous lines in their entirety from end to start.
+    /// The search is case-sensitive and returns the position of the last occurrence found
+    /// (i.e., the first occurrence when searching backwards).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let buffer = // ... initialize buffer ...
+    /// let result = buffer.rfind("example", LineCol{line: 2, col: 15});
+    /// assert_eq!(result, Ok(LineCol{line: 1, col: 5})); // Found on line 1, column 5
+    /// ```
+    fn rfind(&self, query: impl Pattern, at: LineCol) -> Result<LineCol> {
+        let at = LineCol {
+            col: at.col.min(self.buffer.max_normal_col(at.line)),
+            ..at
+        };
+        // Anchors need whole lines, matches from `at` on along its line are skipped instead
+        if query.is_anchored() {
+            let lines = self.buffer.get_normal_text();
+            let at_line = at.line.min(lines.len().saturating_sub(1));
+            return query
+                .rfind_pattern(&lines[at_line..=at_line])
+                .filter(|target| target.col < at.col)
+                .map(|target| LineCol {
+                    line: at_line,
+                    ..target
+                })
+                .or_else(|| query.rfind_pattern(&lines[..at_line]))
+                .ok_or(Error::PatternNotFound);
+        }
+        let buf = &self.buffer.get_buffer_window(None, Some(at))?;
+        query
+            .rfind_pattern(buf)
+            .ok_or(Error::PatternNotFound)
+            .map(|v| LineCol {
+                line: v.line,
+                col: v.col,
+            })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Forward,
+    Backward,
+}
+
+/// Work the event loop does once the editor idled for long enough
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Timer {
+    /// Deciding on the keys held back for a mapping, after `timeoutlen`
+    PendingKeys,
+    /// Writing the modified text, after `autosave`
+    Autosave,
+    /// Bringing the change signs up to date after edits, with `changesigns`
+    ChangeSigns,
+}
+
+#[derive(Clone, Debug)]
+enum Action {
+    Quit,
+    Save,
+
+    // Cursor Movement
+    BumpUp,
+    BumpDown,
+    BumpLeft,
+    BumpRight,
+    JumpUp,
+    JumpDown,
+    PageUp,
+    PageDown,
+    JumpToNextWord,
+    JumpToNextSymbol,
+    ReverseJumpToNextWord,
+    ReverseJumpToNextSymbol,
+    JumpSOL,
+    /// `Home`, toggling between the first non-blank character and the start of the line when
+    /// `smarthome` is set
+    SmartHome,
+    JumpEOL,
+    JumpSOF,
+    JumpEOF,
+    JumpNextParagraph,
+    JumpPrevParagraph,
+    ScrollLineUp,
+    ScrollLineDown,
+
+    // Mode Changes
+    ChangeMode(Modal),
+    InsertModeEOL,
+    /// `gi`, entering insert mode where it was left last
+    ResumeInsert,
+
+    // Text Search
+    Find(String),
+    ReverseFind(String),
+    FindChar(char),
+    ReverseFindChar(char),
+    ReverseToChar(char),
+    ToChar(char),
+
+    // Insertions
+
+    // Text Manipulation
+    Replace(char),
+    InsertCharAtCursor(char),
+    InsertNewLine,
+    /// `Ctrl-R` in insert mode, inserting the content of a register as if it was typed
+    InsertRegister(char),
+    /// `Ctrl-R =` in insert mode, inserting the value of the arithmetic expression
+    InsertExpression(String),
+    InsertModeBelow,
+    InsertModeAbove,
+    DeleteBeforeCursor,
+    /// `Ctrl-N`, adding a cursor at the next occurrence of the word under the cursor
+    AddCursorAtNextMatch,
+    /// `gr`, prompting for the replacement of every occurrence of the word under the cursor
+    ChangeAllPrompt,
+    /// `Esc` in normal mode, removing the cursors added with `Ctrl-N`
+    ClearCursors,
+    /// `Ctrl-W` in insert mode, deleting the word in front of the cursor
+    DeleteWordBeforeCursor,
+    /// `Ctrl-U` in insert mode, deleting the text inserted in front of the cursor on its line
+    DeleteInsertedBeforeCursor,
+    /// `Ctrl-N`/`Ctrl-P` in insert mode, completing the word in front of the cursor with the next
+    /// or previous word of the buffer starting like it
+    CompleteWord {
+        forwards: bool,
+    },
+    DeleteAtCursor,
+    Increment(i64),
+    IncrementSelection(i64),
+    SequentialIncrementSelection(i64),
+    CommentSelection,
+
+    // Operators
+    Operate(Operator, TextObject),
+    Surround(TextObject, char),
+    DeleteSurround(char),
+    ChangeSurround(char, char),
+
+    // Clipboard Operations
+    Yank,
+    Paste(char),
+    PasteNewline(char),
+    PasteAbove(char),
+
+    // History Operations
+    FetchFromHistory,
+
+    // Command Execution
+    ExecuteCommand(Command),
+    /// Completes the command line, cycling through the candidates when repeated
+    CompleteCommand,
+    /// Enters the command line prefilled with the range of the visual selection
+    CommandOnSelection,
+    /// Joins the selected lines into one
+    JoinSelection,
+    /// Shifts the selected lines by `shiftwidth` to the right or left
+    ShiftSelection {
+        right: bool,
+    },
+    /// Reindents the selected lines by their bracket nesting
+    ReindentSelection,
+    /// Grows the selection to the word, line or paragraph around the cursor, whichever is the
+    /// smallest one containing it
+    ExpandSelection,
+    /// Undoes the last `ExpandSelection`
+    ShrinkSelection,
+    /// `Alt-j`, swaps the line or the selected lines with the line below
+    MoveLineDown,
+    /// `Alt-k`, swaps the line or the selected lines with the line above
+    MoveLineUp,
+    /// `Alt-d`, inserts `count` copies of the line or the selected lines below them, leaving the
+    /// registers alone
+    DuplicateLines,
+    /// Runs the prompt line of the terminal plane in the shell
+    RunTerminalPrompt,
+
+    // Undo/Redo
+    Undo(u8),
+    Redo,
+
+    /// `n` and `N`, repeating the previous search in its direction or in the reverse one
+    RepeatSearch {
+        reverse: bool,
+    },
+
+    /// `{count}%`, jumping to the line `count` percent through the file
+    JumpToPercent,
+    /// `]d` and `[d`, jumping to the `count`th next or previous line with a sign of the kinds
+    JumpToSign {
+        forwards: bool,
+        kinds: &'static [SignKind],
+    },
+    /// `]]` and `[[`, jumping to the `count`th next or previous line looking like the start of a
+    /// section
+    JumpToSection {
+        forwards: bool,
+    },
+    /// `m`, setting a mark at the cursor
+    SetMark(char),
+    /// `` ` `` and `'`, jumping to a mark or to the first non-blank of its line
+    JumpToMark {
+        mark: char,
+        linewise: bool,
+    },
+
+    // Misc
+    OpenFile,
+    /// Enter in the listing of `:Explore`, opening the file or directory at the cursor
+    OpenEntry,
+    /// `za`, opening or closing the fold containing the line
+    ToggleFold(usize),
+    /// `zA`, opening or closing the fold containing the line along with the folds within it
+    ToggleFoldRecursively(usize),
+    /// `zM`, closing every fold
+    CloseAllFolds,
+    /// `zR`, opening every fold
+    OpenAllFolds,
+    /// `zz`, scrolling the cursor line to the middle of the window
+    CenterLine,
+
+    /// `.`, replaying the keys of the last change
+    RepeatChange,
+    /// `q{reg}`, recording the keys typed from now on into the register
+    StartRecording(char),
+    /// `q` while recording, storing the recorded keys
+    StopRecording,
+    /// `@{reg}`, playing the keys recorded into the register
+    PlayMacro(char),
+
+    Nothing,
+}
+
+impl Action {
+    /// Whether the action scrolls the view, moving the cursor along
+    const fn is_scroll(&self) -> bool {
+        matches!(
+            self,
+            Self::JumpUp
+                | Self::JumpDown
+                | Self::PageUp
+                | Self::PageDown
+                | Self::ScrollLineUp
+                | Self::ScrollLineDown
+        )
+    }
+    /// Whether the action fails when it leaves both the cursor and the text as they are
+    const fn fails_in_place(&self) -> bool {
+        matches!(
+            self,
+            Self::BumpUp
+                | Self::BumpDown
+                | Self::BumpLeft
+                | Self::BumpRight
+                | Self::JumpToNextWord
+                | Self::JumpToNextSymbol
+                | Self::ReverseJumpToNextWord
+                | Self::ReverseJumpToNextSymbol
+                | Self::JumpNextParagraph
+                | Self::JumpPrevParagraph
+                | Self::FindChar(_)
+                | Self::ReverseFindChar(_)
+                | Self::ToChar(_)
+                | Self::ReverseToChar(_)
+                | Self::RepeatSearch { .. }
+                | Self::Replace(_)
+                | Self::DeleteAtCursor
+                | Self::DeleteBeforeCursor
+        )
+    }
+    /// Whether the action is a jump, which remembers the position it left in the `` ` `` mark
+    const fn is_jump(&self) -> bool {
+        matches!(
+            self,
+            Self::JumpSOF
+                | Self::JumpEOF
+                | Self::JumpNextParagraph
+                | Self::JumpPrevParagraph
+                | Self::RepeatSearch { .. }
+                | Self::JumpToMark { .. }
+                | Self::JumpToPercent
+                | Self::JumpToSign { .. }
+                | Self::JumpToSection { .. }
+        )
+    }
+}
+
+/// Writes the lines to the file at `path`, each of them ended by a newline. With `append` they are
+/// added to the end of the file, which has to exist already.
+///
+/// # Returns
+///
+/// The number of bytes written.
+fn write_lines(path: &Path, lines: &[String], append: bool, fileencoding: &str) -> Result<usize> {
+    let mut content = lines.join("\n");
+    content.push('\n');
+    let bytes = encoding::encode(&content, fileencoding)?;
+    match append {
+        true => OpenOptions::new()
+            .append(true)
+            .open(path)?
+            .write_all(&bytes)?,
+        false => fs::write(path, &bytes)?,
+    }
+    Ok(bytes.len())
+}
+
+/// The buffer replaced by the help buffer, along with the state belonging to it
+struct HelpReturn<Buff> {
+    buffer: Buff,
+    path: Option<PathBuf>,
+    modified: bool,
+    folds: Folds,
+    cursor: LineCol,
+}
+
+/// A key bound to an action without depending on the keys typed before or after it. The tables
+/// of bindings are what the interpreters look keys up in, and what `:help keys` lists.
+struct Binding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+    action: Action,
+    description: &'static str,
+}
+
+impl Binding {
+    const fn new(code: KeyCode, action: Action, description: &'static str) -> Self {
+        Self {
+            code,
+            modifiers: KeyModifiers::NONE,
+            action,
+            description,
+        }
+    }
+    const fn ctrl(ch: char, action: Action, description: &'static str) -> Self {
+        Self {
+            code: KeyCode::Char(ch),
+            modifiers: KeyModifiers::CONTROL,
+            action,
+            description,
+        }
+    }
+    const fn alt(ch: char, action: Action, description: &'static str) -> Self {
+        Self {
+            code: KeyCode::Char(ch),
+            modifiers: KeyModifiers::ALT,
+            action,
+            description,
+        }
+    }
+    const fn key(ch: char, action: Action, description: &'static str) -> Self {
+        Self::new(KeyCode::Char(ch), action, description)
+    }
+    /// The action bound to the key, shift is ignored for characters like in mappings
+    fn lookup(bindings: &[Self], key_event: KeyEvent) -> Option<Action> {
+        let key_event = keymap::normalize(key_event);
+        bindings
+            .iter()
+            .find(|b| b.code == key_event.code && b.modifiers == key_event.modifiers)
+            .map(|b| b.action.clone())
+    }
+    fn notation(&self) -> String {
+        keymap::key_notation(&[KeyEvent::new(self.code, self.modifiers)])
+    }
+}
+
+/// Keys moving the cursor in normal and visual mode
+const MOTIONS: &[Binding] = &[
+    Binding::key('k', Action::BumpUp, "Move up"),
+    Binding::key('j', Action::BumpDown, "Move down"),
+    Binding::key('h', Action::BumpLeft, "Move left"),
+    Binding::key('l', Action::BumpRight, "Move right"),
+    Binding::ctrl('u', Action::JumpUp, "Scroll up half a window"),
+    Binding::ctrl('d', Action::JumpDown, "Scroll down half a window"),
+    Binding::ctrl('b', Action::PageUp, "Scroll up a page"),
+    Binding::ctrl('f', Action::PageDown, "Scroll down a page"),
+    Binding::key('W', Action::JumpToNextWord, "Next word"),
+    Binding::key('w', Action::JumpToNextSymbol, "Next symbol"),
+    Binding::key('B', Action::ReverseJumpToNextWord, "Previous word"),
+    Binding::key('b', Action::ReverseJumpToNextSymbol, "Previous symbol"),
+    Binding::key('_', Action::JumpSOL, "Start of the line"),
+    Binding::new(KeyCode::Home, Action::SmartHome, "Start of the line"),
+    Binding::key('$', Action::JumpEOL, "End of the line"),
+    Binding::new(KeyCode::End, Action::JumpEOL, "End of the line"),
+    Binding::key('G', Action::JumpEOF, "Last line"),
+    Binding::key('}', Action::JumpNextParagraph, "Next paragraph"),
+    Binding::key('{', Action::JumpPrevParagraph, "Previous paragraph"),
+];
+
+/// Keys of normal mode carried out right away, besides the motions
+const NORMAL_KEYS: &[Binding] = &[
+    Binding::key('i', Action::ChangeMode(Modal::Insert), "Insert mode"),
+    Binding::key('v', Action::ChangeMode(Modal::Visual), "Visual mode"),
+    Binding::key(
+        'V',
+        Action::ChangeMode(Modal::VisualLine),
+        "Visual line mode",
+    ),
+    Binding::ctrl(
+        'v',
+        Action::ChangeMode(Modal::VisualBlock),
+        "Visual block mode",
+    ),
+    Binding::key(':', Action::ChangeMode(Modal::Command), "Command line"),
+    Binding::key('A', Action::InsertModeEOL, "Insert at the end of the line"),
+    Binding::key('n', Action::RepeatSearch { reverse: false }, "Next match"),
+    Binding::key(
+        'N',
+        Action::RepeatSearch { reverse: true },
+        "Previous match",
+    ),
+    Binding::key('o', Action::InsertModeBelow, "Open a line below"),
+    Binding::key('O', Action::InsertModeAbove, "Open a line above"),
+    Binding::key('X', Action::DeleteBeforeCursor, "Delete before the cursor"),
+    Binding::key('x', Action::DeleteAtCursor, "Delete at the cursor"),
+    Binding::ctrl('a', Action::Increment(1), "Increment the number"),
+    Binding::ctrl('x', Action::Increment(-1), "Decrement the number"),
+    Binding::key('u', Action::Undo(1), "Undo"),
+    Binding::key('.', Action::RepeatChange, "Repeat the last change"),
+    Binding::ctrl(
+        'n',
+        Action::AddCursorAtNextMatch,
+        "Add a cursor at the next match of the word",
+    ),
+    Binding::ctrl('r', Action::Redo, "Redo"),
+    Binding::alt('j', Action::MoveLineDown, "Move the line down"),
+    Binding::alt('k', Action::MoveLineUp, "Move the line up"),
+];
+
+/// Keys of normal mode which depend on the keys around them, interpreted in
+/// `interpret_normal_event` and only described here for `:help keys`
+const NORMAL_SEQUENCES: &[(&str, &str)] = &[
+    ("/ ?", "Search forwards or backwards"),
+    ("{count}%", "Line count percent through the file"),
+    (
+        "]] [[",
+        "Next or previous unindented line starting a section",
+    ),
+    ("]d [d", "Next or previous line with a sign"),
+    ("]t [t", "Next or previous TODO comment found by :todo"),
+    (
+        "d{obj} c{obj} y{obj}",
+        "Delete, change or yank a text object or up to a word with w and W, dd cc yy for lines",
+    ),
+    ("gc{obj}", "Toggle the line comments of a text object"),
+    (
+        "gq{obj} gw{obj}",
+        "Rewrap a text object to textwidth, gw keeping the cursor",
+    ),
+    (
+        "gr",
+        "Replace every occurrence of the word under the cursor",
+    ),
+    ("gi", "Insert where insert mode was left last"),
+    (
+        "={obj}",
+        "Reindent the lines of a text object, == for lines",
+    ),
+    (
+        "ys{obj}{ch} ds{ch} cs{ch}{ch}",
+        "Add, delete or change surrounding delimiters",
+    ),
+    ("p P", "Paste after or before the cursor"),
+    (
+        "\"{reg}",
+        "Use a register for the next yank, delete or paste",
+    ),
+    ("f{ch} F{ch} t{ch} T{ch}", "Find a character on the line"),
+    ("r{ch}", "Replace the character at the cursor"),
+    ("m{a-z}", "Set a mark"),
+    ("q{reg} q", "Record a macro into a register, stop recording"),
+    (
+        "@{reg} @@",
+        "Play the macro in a register, play the last macro again",
+    ),
+    (
+        "`{mark} '{mark}",
+        "Jump to a mark or its line, `` `. `^ for the last jump, change and insert",
+    ),
+    ("gg", "First line"),
+    ("gf", "Open the file under the cursor"),
+    ("g?", "Show this help"),
+    ("za", "Toggle the fold"),
+    ("zA", "Toggle the fold and the folds within it"),
+    ("zM zR", "Close or open every fold"),
+    ("zz", "Scroll the cursor line to the middle of the window"),
+];
+
+/// Topic of `:help` listing the keybindings, the only topic there is
+const HELP_KEYS: &str = "keys";
+
+/// Operators act upon the text covered by a text object
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+    /// `ys`, surrounds the text object with the delimiters typed afterwards
+    Surround,
+    /// `gc`, toggles the line comment on every line of the text object
+    Comment,
+    /// `=`, reindents every line of the text object by its bracket nesting
+    Reindent,
+    /// `gq` and `gw`, rewrapping the lines of the text object to `textwidth`, `gw` leaving the
+    /// cursor where it was
+    Reflow {
+        keep_cursor: bool,
+    },
+}
+
+/// A surround command still missing some of its delimiter characters
+#[derive(Clone, Copy, Debug)]
+enum PendingSurround {
+    Add(TextObject),
+    Delete,
+    /// Holds the delimiter to replace once it was typed
+    Change(Option<char>),
+}
+
+/// An insert mode key sequence consuming the keys typed after it. Only one of them can be pending
+/// at a time, so the keys completing a digraph are never taken literally and vice versa.
+#[derive(Clone, Debug)]
+enum PendingInsert {
+    /// `Ctrl-K`, holding the first character of the digraph once it was typed
+    Digraph(Option<char>),
+    /// `Ctrl-V`, inserting the next key as is
+    Literal,
+    /// `Ctrl-R`, inserting the register named by the next key
+    Register,
+    /// `Ctrl-R =`, holding the expression typed so far until `Enter` inserts its value
+    Expression(String),
+}
+
+impl Operator {
+    /// The key triggering the operator, pressing it twice acts on whole lines
+    const fn key(self) -> char {
+        match self {
+            Self::Delete => 'd',
+            Self::Change => 'c',
+            Self::Yank => 'y',
+            Self::Surround => 's',
+            Self::Comment => 'c',
+            Self::Reindent => '=',
+            Self::Reflow { keep_cursor: false } => 'q',
+            Self::Reflow { keep_cursor: true } => 'w',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lazy<T> {
+    inner: Option<T>,
+}
+impl<T> Lazy<T> {
+    /// Create Type Inferred Lazy Struct
+    pub fn new() -> Self {
+        Lazy { inner: None }
+    }
+    /// Returns the inner evaluated value if there is one
+    pub fn into_inner(self) -> Option<T> {
+        self.inner
+    }
+    /// Initializes a Lazy Type with an already initialized value.
+    pub fn with_inner(v: T) -> Self {
+        Lazy { inner: Some(v) }
+    }
+    /// Set the inner value Of Lazy value
+    pub fn set_inner(&mut self, v: T) {
+        self.inner = Some(v)
+    }
+    /// True if the lazy already contains an inner value
+    pub fn is_evaluated(&self) -> bool {
+        self.inner.is_some()
+    }
+}
+
+impl<T: Clone> Lazy<T> {
+    // Clones the inner type T, panicking if called on an unevaluated lazy
+    pub fn clone_inner(&self) -> T {
+        self.inner.clone().unwrap()
+    }
+}
+
+impl<T> Default for Lazy<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::buffer::VecBuffer;
+    use crate::extension::KeystrokeCounter;
+    use crate::testing::TempDir;
+    use crate::LineCol;
+
+    #[test]
+    fn test_jump_two_boundaries() {
+        // Create a VecBuffer with test content
+        let content = vec![
+            "Hello world! This is a test.".to_string(),
+            "Multiple   spaces   between words.".to_string(),
+            "Symbols: @#$% and _underscores_".to_string(),
+        ];
+        let buffer = VecBuffer::new(content);
+
+        let mut editor = Editor::new(buffer, true);
+
+        // Test forward word jump
+        let result = editor.jump_two_boundaries(Direction::Forward, char::is_whitespace, |ch| {
+            !ch.is_whitespace()
+        });
+        assert_eq!(
+            result.unwrap(),
+            BaseAction::SetCursor(LineCol { line: 0, col: 6 })
+        );
+
+        // Test backward word jump
+        editor.cursor.pos = LineCol { line: 0, col: 20 };
+        let result = editor.jump_two_boundaries(Direction::Backward, char::is_whitespace, |ch| {
+            !ch.is_whitespace()
+        });
+        assert_eq!(
+            result.unwrap(),
+            BaseAction::SetCursor(LineCol { line: 0, col: 13 })
+        );
+
+        // Test forward symbol jump
+        editor.cursor.pos = LineCol { line: 2, col: 0 };
+        let result = editor.jump_two_boundaries(
+            Direction::Forward,
+            |ch| ch.is_alphanumeric() || ch == '_',
+            |ch| !ch.is_alphanumeric() && ch != '_' && !ch.is_whitespace(),
+        );
+        assert_eq!(
+            result.unwrap(),
+            BaseAction::SetCursor(LineCol { line: 2, col: 8 })
+        );
+
+        // Test backward symbol jump
+        editor.cursor.pos = LineCol { line: 2, col: 20 };
+        let result = editor.jump_two_boundaries(
+            Direction::Backward,
+            |ch| ch.is_alphanumeric() || ch == '_',
+            |ch| !ch.is_alphanumeric() && ch != '_' && !ch.is_whitespace(),
+        );
+        assert_eq!(
+            result.unwrap(),
+            BaseAction::SetCursor(LineCol { line: 2, col: 13 })
+        );
+
+        // Test jump at end of buffer
+        editor.cursor.pos = LineCol { line: 2, col: 30 };
+        let result = editor.jump_two_boundaries(Direction::Forward, char::is_whitespace, |ch| {
+            !ch.is_whitespace()
+        });
+        assert_eq!(result.unwrap(), BaseAction::Nothing);
+
+        // Test jump at beginning of buffer
+        editor.cursor.pos = LineCol { line: 0, col: 0 };
+        let result = editor.jump_two_boundaries(Direction::Backward, char::is_whitespace, |ch| {
+            !ch.is_whitespace()
+        });
+        assert_eq!(result.unwrap(), BaseAction::Nothing);
+    }
+
+    fn editor_with_lines(lines: &[&str]) -> Editor<VecBuffer> {
+        let content = lines.iter().map(|line| line.to_string()).collect();
+        Editor::new(VecBuffer::new(content), true)
+    }
+
+    /// An editor whose lines hold their own line numbers, counted from zero
+    fn editor_with_numbered_lines(count: usize) -> Editor<VecBuffer> {
+        let content = (0..count).map(|line| line.to_string()).collect();
+        Editor::new(VecBuffer::new(content), true)
+    }
+
+    #[test]
+    fn test_goto() {
+        let mut editor = editor_with_lines(&["first", "second line", "third"]);
+        assert_eq!(editor.cursor_position(), LineCol { line: 0, col: 0 });
+
+        editor.goto(LineCol { line: 1, col: 7 }).unwrap();
+        assert_eq!(editor.cursor_position(), LineCol { line: 1, col: 7 });
+        assert_eq!(editor.shadow_cursor.line, 1);
+        assert_eq!(editor.shadow_cursor.col, 7);
+    }
+
+    #[test]
+    fn test_goto_clamps_out_of_range_positions() {
+        let mut editor = editor_with_lines(&["first", "second line", "third"]);
+        editor.goto(LineCol { line: 1, col: 50 }).unwrap();
+        assert_eq!(editor.cursor_position(), LineCol { line: 1, col: 11 });
+        editor.goto(LineCol { line: 9, col: 9 }).unwrap();
+        assert_eq!(editor.cursor_position(), LineCol { line: 2, col: 5 });
+    }
+
+    #[test]
+    fn test_goto_scrolls_viewport() {
+        let mut editor = editor_with_numbered_lines(100);
+
+        editor.goto(LineCol { line: 60, col: 0 }).unwrap();
+        let top = editor.viewport.top_border();
+        assert!(top > 0 && top <= 60);
+        editor.goto(LineCol { line: 3, col: 0 }).unwrap();
+        assert_eq!(editor.viewport.top_border(), 3);
+    }
+
+    /// Feeds every character as a key press, `\x1b` standing in for Escape
+    fn press(editor: &mut Editor<VecBuffer>, keys: &str) {
+        for ch in keys.chars() {
+            let code = match ch {
+                '\x1b' => KeyCode::Esc,
+                '\n' => KeyCode::Enter,
+                '\x7f' => KeyCode::Backspace,
+                ch => KeyCode::Char(ch),
+            };
+            editor
+                .process_key(KeyEvent::new(code, KeyModifiers::NONE))
+                .unwrap();
+        }
+    }
+
+    /// The key typing `ch` with control held
+    fn ctrl(ch: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(ch), KeyModifiers::CONTROL)
+    }
+
+    fn text(editor: &Editor<VecBuffer>) -> Vec<String> {
+        editor.buffer.get_normal_text().to_vec()
+    }
+
+    #[test]
+    fn test_process_key_insert() {
+        let mut editor = editor_with_lines(&[""]);
+        press(&mut editor, "iw foo\x1b");
+        assert_eq!(text(&editor), vec!["w foo"]);
+        assert_eq!(editor.modal, Modal::Normal);
+    }
+
+    #[test]
+    fn test_process_key_delete_line_paste_and_undo() {
+        let mut editor = editor_with_lines(&["a", "b", "c"]);
+        press(&mut editor, "dd");
+        assert_eq!(text(&editor), vec!["b", "c"]);
+        press(&mut editor, "p");
+        assert_eq!(text(&editor), vec!["b", "a", "c"]);
+        press(&mut editor, "uu");
+        assert_eq!(text(&editor), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_delete_last_line() {
+        let mut editor = editor_with_lines(&["a", "b", "c"]);
+        press(&mut editor, "Gdd");
+        assert_eq!(text(&editor), vec!["a", "b"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 0 });
+        press(&mut editor, "p");
+        assert_eq!(text(&editor), vec!["a", "b", "c"]);
+        assert_eq!(editor.cursor.line(), 2);
+        press(&mut editor, "dddddd");
+        assert_eq!(text(&editor), vec![""]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_process_key_surround() {
+        let mut editor = editor_with_lines(&["say hello"]);
+        editor.goto(LineCol { line: 0, col: 5 }).unwrap();
+        press(&mut editor, "ysiw\"");
+        assert_eq!(text(&editor), vec!["say \"hello\""]);
+        press(&mut editor, "cs\"(");
+        assert_eq!(text(&editor), vec!["say ( hello )"]);
+        press(&mut editor, "ds(");
+        assert_eq!(text(&editor), vec!["say hello"]);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor), vec!["say ( hello )"]);
+    }
+
+    #[test]
+    fn test_process_key_comment_toggle() {
+        let mut editor = editor_with_lines(&["    let x = 1;", "x"]);
+        editor.config_mut().comment_string = Some("// ".to_string());
+        press(&mut editor, "gcc");
+        assert_eq!(text(&editor), vec!["    // let x = 1;", "x"]);
+        press(&mut editor, "gcip");
+        assert_eq!(text(&editor), vec!["    // // let x = 1;", "// x"]);
+        press(&mut editor, "gcc");
+        assert_eq!(text(&editor), vec!["    // let x = 1;", "// x"]);
+    }
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> MouseEvent {
+        MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn test_mouse_click_positions_cursor() {
+        let mut editor = editor_with_lines(&["first", "second line"]);
+        let click = MouseEventKind::Down(MouseButton::Left);
+        editor.process_mouse(mouse(click, 10, 1)).unwrap();
+        assert_eq!(editor.cursor_position(), LineCol { line: 1, col: 3 });
+        editor.process_mouse(mouse(click, 40, 0)).unwrap();
+        assert_eq!(editor.cursor_position(), LineCol { line: 0, col: 5 });
+        editor.process_mouse(mouse(click, 10, 12)).unwrap();
+        assert_eq!(editor.cursor_position(), LineCol { line: 1, col: 3 });
+    }
+
+    #[test]
+    fn test_mouse_wheel_scrolls_and_drags_cursor_along() {
+        let mut editor = editor_with_numbered_lines(100);
+
+        editor
+            .process_mouse(mouse(MouseEventKind::ScrollDown, 0, 0))
+            .unwrap();
+        assert_eq!(editor.viewport.top_border(), MOUSE_SCROLL_LINES);
+        assert_eq!(editor.cursor_position().line, MOUSE_SCROLL_LINES);
+        editor
+            .process_mouse(mouse(MouseEventKind::ScrollUp, 0, 0))
+            .unwrap();
+        assert_eq!(editor.viewport.top_border(), 0);
+        assert_eq!(editor.cursor_position().line, MOUSE_SCROLL_LINES);
+    }
+
+    #[test]
+    fn test_set_mouse_option() {
+        let mut editor = editor_with_lines(&[""]);
+        press(&mut editor, ":set nomouse\n");
+        assert!(!editor.config.mouse);
+        assert_eq!(editor.modal, Modal::Normal);
+        press(&mut editor, ":set mouse\n");
+        assert!(editor.config.mouse);
+    }
+
+    #[test]
+    fn test_mouse_drag_selects_in_visual_mode() {
+        let mut editor = editor_with_lines(&["first", "second line"]);
+        let left = MouseButton::Left;
+        editor
+            .process_mouse(mouse(MouseEventKind::Down(left), 8, 0))
+            .unwrap();
+        assert_eq!(editor.modal, Modal::Normal);
+        editor
+            .process_mouse(mouse(MouseEventKind::Drag(left), 10, 1))
+            .unwrap();
+        editor
+            .process_mouse(mouse(MouseEventKind::Up(left), 10, 1))
+            .unwrap();
+
+        assert_eq!(editor.modal, Modal::Visual);
+        let selection = Selection::from(&editor.cursor);
+        assert_eq!(selection.start, LineCol { line: 0, col: 1 });
+        assert_eq!(selection.end, LineCol { line: 1, col: 3 });
+
+        editor
+            .process_mouse(mouse(MouseEventKind::Drag(left), 12, 0))
+            .unwrap();
+        assert_eq!(editor.cursor_position(), LineCol { line: 1, col: 3 });
+    }
+
+    #[test]
+    fn test_mouse_drag_past_last_row_scrolls() {
+        let mut editor = editor_with_numbered_lines(100);
+        let left = MouseButton::Left;
+        editor
+            .process_mouse(mouse(MouseEventKind::Down(left), 7, 0))
+            .unwrap();
+        editor
+            .process_mouse(mouse(MouseEventKind::Drag(left), 7, 23))
+            .unwrap();
+        assert_eq!(editor.viewport.top_border(), 1);
+        assert_eq!(editor.cursor_position().line, editor.viewport.text_rows());
+    }
+
+    #[test]
+    fn test_terminal_plane_runs_commands_and_returns() {
+        let mut editor = editor_with_lines(&["text"]);
+        press(&mut editor, "l:terminal\n");
+        assert_eq!(editor.modal, Modal::Terminal);
+        press(&mut editor, "echo hii\x7f\n");
+        assert_eq!(editor.buffer.get_terminal_text(), ["echo hi", "hi", ""]);
+        assert_eq!(editor.cursor_position(), LineCol { line: 2, col: 0 });
+
+        press(&mut editor, "\x1b");
+        assert_eq!(editor.modal, Modal::Normal);
+        assert_eq!(editor.cursor_position(), LineCol { line: 0, col: 1 });
+        assert_eq!(text(&editor), ["text"]);
+
+        press(&mut editor, ":term\n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 2, col: 0 });
+    }
+
+    #[test]
+    fn test_command_line_completion() {
+        let mut editor = editor_with_lines(&["text"]);
+        press(&mut editor, ":te");
+        let tab = KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE);
+        editor.process_key(tab).unwrap();
+        assert_eq!(editor.buffer.get_command_text(), "terminal");
+        assert_eq!(editor.cursor_position(), LineCol { line: 0, col: 8 });
+
+        press(&mut editor, "\x7f\x7f\x7f\x7f\x7f\x7f\x7f\x7f");
+        editor.process_key(tab).unwrap();
+        editor.process_key(tab).unwrap();
+        assert_eq!(
+            editor.buffer.get_command_text(),
+            crate::completion::COMMANDS[1]
+        );
+        assert_eq!(
+            editor.completion.as_ref().unwrap().candidates,
+            crate::completion::COMMANDS
+        );
+        press(&mut editor, " ");
+        assert_eq!(editor.completion, None);
+    }
+
+    #[test]
+    fn test_insert_digraphs_and_literals() {
+        let mut editor = editor_with_lines(&[""]);
+        press(&mut editor, "i");
+        editor.process_key(ctrl('k')).unwrap();
+        press(&mut editor, "a:");
+        editor.process_key(ctrl('k')).unwrap();
+        press(&mut editor, "qqx");
+        editor.process_key(ctrl('v')).unwrap();
+        editor.process_key(ctrl('k')).unwrap();
+        editor.process_key(ctrl('v')).unwrap();
+        editor
+            .process_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+            .unwrap();
+        press(&mut editor, "\x1b");
+
+        assert_eq!(text(&editor), ["äx\x0b\t"]);
+        assert_eq!(editor.modal, Modal::Normal);
+    }
+
+    #[test]
+    fn test_viewport_follows_cursor_after_actions() {
+        let mut editor = editor_with_numbered_lines(100);
+        let last_row = editor.viewport.text_rows() - 1;
+
+        press(&mut editor, "G");
+        assert_eq!(editor.viewport.top_border(), 99 - last_row);
+        press(&mut editor, "gg");
+        assert_eq!(editor.viewport.top_border(), 0);
+
+        // The whole text is a single paragraph
+        editor.goto(LineCol { line: 80, col: 0 }).unwrap();
+        press(&mut editor, "dap");
+        assert_eq!(text(&editor), [""]);
+        assert_eq!(editor.cursor_position().line, 0);
+        assert_eq!(editor.viewport.top_border(), 0);
+    }
+
+    #[test]
+    fn test_half_and_full_page_scrolling() {
+        let mut editor = editor_with_numbered_lines(100);
+        let half = editor.viewport.text_rows() / 2;
+        let page = editor.viewport.text_rows() - PAGE_OVERLAP;
+
+        editor.goto(LineCol { line: 2, col: 0 }).unwrap();
+        editor.process_key(ctrl('d')).unwrap();
+        assert_eq!(editor.viewport.top_border(), half);
+        assert_eq!(editor.cursor_position().line, 2 + half);
+        editor.process_key(ctrl('u')).unwrap();
+        assert_eq!(editor.viewport.top_border(), 0);
+        assert_eq!(editor.cursor_position().line, 2);
+
+        editor.process_key(ctrl('f')).unwrap();
+        assert_eq!(editor.viewport.top_border(), page);
+        assert_eq!(editor.cursor_position().line, 2 + page);
+        editor.process_key(ctrl('b')).unwrap();
+        assert_eq!(editor.viewport.top_border(), 0);
+
+        press(&mut editor, ":set scroll=5\n");
+        editor.process_key(ctrl('d')).unwrap();
+        assert_eq!(editor.viewport.top_border(), 5);
+        assert_eq!(editor.cursor_position().line, 7);
+    }
+
+    #[test]
+    fn test_search_wraps_around() {
+        let mut editor = editor_with_lines(&["foo", "bar", "foo bar"]);
+        editor.goto(LineCol { line: 2, col: 4 }).unwrap();
+        press(&mut editor, "/foo\n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 0, col: 0 });
+        press(&mut editor, "?bar\n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 2, col: 4 });
+
+        press(&mut editor, ":set nowrapscan\n/foo\n");
+        assert_eq!(editor.modal, Modal::Normal);
+        assert_eq!(editor.cursor_position(), LineCol { line: 2, col: 4 });
+    }
+
+    #[test]
+    fn test_search_within_selection() {
+        let mut editor = editor_with_lines(&["foo bar", "bar foo", "foo bar"]);
+        press(&mut editor, "jV/foo\n");
+        assert_eq!(editor.modal, Modal::Normal);
+        assert_eq!(editor.cursor_position(), LineCol { line: 1, col: 4 });
+
+        // Wraps around within the selection instead of continuing past it
+        press(&mut editor, "V/bar\n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 1, col: 0 });
+        press(&mut editor, "V?foo\n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 1, col: 4 });
+
+        // The scope ends with the search
+        press(&mut editor, "/foo\n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 2, col: 0 });
+    }
+
+    #[test]
+    fn test_search_direction_comes_from_the_mode() {
+        let mut editor = editor_with_lines(&["foo", "bar", "foo bar"]);
+        editor.goto(LineCol { line: 1, col: 0 }).unwrap();
+        press(&mut editor, "?foo\n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 0, col: 0 });
+
+        // A command line starting with a slash isn't a search
+        press(&mut editor, ":/bar\n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_visual_command_line_substitutes_selection() {
+        let mut editor = editor_with_lines(&["a a", "a a", "a a", "a a"]);
+        press(&mut editor, "jVj:");
+        assert_eq!(editor.modal, Modal::Command);
+        assert_eq!(editor.buffer.get_command_text(), "'<,'>");
+        press(&mut editor, "s/a/b/\n");
+        assert_eq!(text(&editor), ["a a", "b a", "b a", "a a"]);
+        assert_eq!(editor.cursor_position(), LineCol { line: 2, col: 0 });
+
+        press(&mut editor, ":%s/a/c/g\n");
+        assert_eq!(text(&editor), ["c c", "b c", "b c", "c c"]);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor), ["a a", "b a", "b a", "a a"]);
+
+        press(&mut editor, ":'<,'>s/b/d\n:4\n");
+        assert_eq!(text(&editor), ["a a", "d a", "d a", "a a"]);
+        assert_eq!(editor.cursor_position(), LineCol { line: 3, col: 0 });
+    }
+
+    #[test]
+    fn test_open_file_under_cursor_and_edit() {
+        let dir = TempDir::new("gf");
+        fs::write(dir.join("other.txt"), "hello\nworld\n").unwrap();
+
+        let mut editor = editor_with_lines(&["see other.txt", "missing.txt"]);
+        editor.set_path(dir.join("current.txt"));
+        press(&mut editor, "jgf");
+        assert_eq!(text(&editor), ["see other.txt", "missing.txt"]);
+        editor.goto(LineCol { line: 0, col: 6 }).unwrap();
+        press(&mut editor, "gf");
+        assert_eq!(text(&editor), ["hello", "world"]);
+        assert_eq!(editor.path, Some(dir.join("other.txt")));
+        assert_eq!(editor.cursor_position(), LineCol::default());
+
+        press(&mut editor, ":e /nonexistent/file\n");
+        assert_eq!(text(&editor), ["hello", "world"]);
+        assert_eq!(editor.modal, Modal::Normal);
+    }
+
+    #[test]
+    fn test_alternate_file() {
+        let dir = TempDir::new("alternate");
+        fs::write(dir.join("list.c"), "int x;\n").unwrap();
+        fs::write(dir.join("list.h"), "extern int x;\n").unwrap();
+
+        let mut editor = editor_with_lines(&["text"]);
+        editor.set_path(dir.join("list.c"));
+        press(&mut editor, ":A\n");
+        assert_eq!(text(&editor), ["extern int x;"]);
+        assert_eq!(editor.path, Some(dir.join("list.h")));
+        press(&mut editor, ":A\n");
+        assert_eq!(editor.path, Some(dir.join("list.c")));
+
+        // Nothing is opened without an existing alternate
+        press(&mut editor, ":set alternates=c:cpp\n:A\n");
+        assert_eq!(editor.path, Some(dir.join("list.c")));
+        assert_eq!(editor.modal, Modal::Normal);
+    }
+
+    #[test]
+    fn test_sort_command() {
+        let mut editor = editor_with_lines(&["c", "b 2", "b 10", "c", "a"]);
+        press(&mut editor, ":sort u\n");
+        assert_eq!(text(&editor), ["a", "b 10", "b 2", "c"]);
+        press(&mut editor, "u:2,3sor! n\n");
+        assert_eq!(text(&editor), ["c", "b 10", "b 2", "c", "a"]);
+        assert_eq!(editor.cursor_position(), LineCol { line: 1, col: 0 });
+    }
+
+    #[test]
+    fn test_align_command() {
+        let mut editor = editor_with_lines(&["x = 1", "yy = 2", "z", "", "abc = 3", "d = 4"]);
+        press(&mut editor, ":Tabularize /=\n");
+        assert_eq!(text(&editor)[..3], ["x  = 1", "yy = 2", "z"]);
+        press(&mut editor, "G:align =\n");
+        assert_eq!(text(&editor)[4..], ["abc = 3", "d   = 4"]);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor)[4..], ["abc = 3", "d = 4"]);
+
+        press(&mut editor, "ggVj:align ,\n");
+        assert_eq!(text(&editor)[..2], ["x  = 1", "yy = 2"]);
+    }
+
+    #[test]
+    fn test_squeeze_and_trim_blank_lines() {
+        let mut editor = editor_with_lines(&["a", "", "", "b", "", " ", "", "c", "", "  ", ""]);
+        press(&mut editor, ":1,4squeeze\n");
+        assert_eq!(
+            text(&editor),
+            ["a", "", "b", "", " ", "", "c", "", "  ", ""]
+        );
+        press(&mut editor, ":set nowhitespaceblank\n:squeeze\n");
+        assert_eq!(
+            text(&editor),
+            ["a", "", "b", "", " ", "", "c", "", "  ", ""]
+        );
+        press(&mut editor, ":set whitespaceblank\n:squeeze\n");
+        assert_eq!(text(&editor), ["a", "", "b", "", "c", ""]);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor).len(), 10);
+
+        press(&mut editor, "G:trimblank\n");
+        assert_eq!(text(&editor), ["a", "", "b", "", " ", "", "c"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 6, col: 0 });
+
+        let mut editor = editor_with_lines(&["", " "]);
+        press(&mut editor, ":trimblank\n");
+        assert_eq!(text(&editor), [""]);
+    }
+
+    #[test]
+    fn test_number_lines() {
+        let lines = vec!["line"; 11];
+        let mut editor = editor_with_lines(&lines);
+        press(&mut editor, ":9,11number! :\n");
+        assert_eq!(
+            text(&editor)[7..],
+            ["line", " 9:line", "10:line", "11:line"]
+        );
+        press(&mut editor, "u:number!\n");
+        assert_eq!(text(&editor)[0], " 1 line");
+        assert_eq!(text(&editor)[10], "11 line");
+    }
+
+    #[test]
+    fn test_jump_to_section() {
+        let mut editor =
+            editor_with_lines(&["fn a() {", "    a", "}", "", "fn b() {}", "", "fn c() {}"]);
+        press(&mut editor, "]]");
+        assert_eq!(editor.cursor.pos, LineCol { line: 4, col: 0 });
+        press(&mut editor, "2]]");
+        assert_eq!(editor.cursor.line(), 6);
+        press(&mut editor, "``");
+        assert_eq!(editor.cursor.line(), 4);
+        press(&mut editor, "[[[[");
+        assert_eq!(editor.cursor.line(), 0);
+    }
+
+    #[test]
+    fn test_diff_against_file() {
+        let dir = TempDir::new("diff");
+        let path = dir.join("file.txt");
+        fs::write(&path, "one\ntwo\n").unwrap();
+        let mut editor = editor_with_lines(&["one", "two"]);
+        press(&mut editor, ":diff\n");
+        assert_eq!(text(&editor), ["one", "two"]);
+
+        editor.set_path(path.clone());
+        press(&mut editor, ":diff\n");
+        assert_eq!(text(&editor), ["one", "two"]);
+
+        press(&mut editor, "jx:diff\n");
+        let diff = text(&editor);
+        assert!(diff[0].starts_with("--- "));
+        assert_eq!(diff[2..], ["@@ -1,2 +1,2 @@", " one", "-two", "+wo"]);
+        press(&mut editor, "dd");
+        assert_eq!(text(&editor), diff);
+
+        press(&mut editor, ":q\n");
+        assert_eq!(text(&editor), ["one", "wo"]);
+    }
+
+    #[test]
+    fn test_visual_block_comment() {
+        let lines = ["fn f() {", "    let a = 1;", "  b", "", "      c", "}"];
+        let mut editor = editor_with_lines(&lines);
+        let select_block = |editor: &mut Editor<VecBuffer>| {
+            press(editor, "gg_jllll");
+            editor.process_key(ctrl('v')).unwrap();
+            press(editor, "jjjllll");
+        };
+        select_block(&mut editor);
+        assert_eq!(editor.modal, Modal::VisualBlock);
+        assert_eq!(editor.cursor.pos, LineCol { line: 4, col: 4 });
+        press(&mut editor, "gc");
+        assert_eq!(editor.modal, Modal::Normal);
+        assert_eq!(
+            text(&editor)[1..5],
+            ["    # let a = 1;", "  b", "", "    #   c"]
+        );
+
+        press(&mut editor, "u:set blockpad\n");
+        select_block(&mut editor);
+        press(&mut editor, "gc");
+        assert_eq!(
+            text(&editor)[1..5],
+            ["    # let a = 1;", "  b #", "    #", "    #   c"]
+        );
+        select_block(&mut editor);
+        press(&mut editor, "gc");
+        assert_eq!(text(&editor), lines);
+    }
+
+    #[test]
+    fn test_repeat_change() {
+        let mut editor = editor_with_lines(&["abcdefgh", "one", "two", "three", "four"]);
+        press(&mut editor, "x3.");
+        assert_eq!(text(&editor)[0], "efgh");
+        press(&mut editor, "u");
+        assert_eq!(text(&editor)[0], "bcdefgh");
+
+        // Changes removing lines leave the cursor on the following line for the next repetition
+        press(&mut editor, "jdd2.");
+        assert_eq!(text(&editor), ["bcdefgh", "four"]);
+
+        press(&mut editor, "ggA!\x1bj.");
+        assert_eq!(text(&editor), ["bcdefgh!", "four!"]);
+        press(&mut editor, "k_2.");
+        assert_eq!(text(&editor)[0], "bcdefgh!!!");
+    }
+
+    #[test]
+    fn test_half_page_scroll_keeps_row_and_column() {
+        let lines: Vec<String> = (0..200)
+            .map(|i| match i % 7 {
+                0 => String::new(),
+                _ => format!("    line {i}"),
+            })
+            .collect();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let mut editor = editor_with_lines(&lines);
+        press(&mut editor, "jjjllllll");
+        let (row, col) = (editor.cursor.line(), editor.cursor.col());
+        for _ in 0..6 {
+            editor.process_key(ctrl('d')).unwrap();
+            let line = editor.cursor.line();
+            assert_eq!(line - editor.viewport.top_border(), row);
+            if !lines[line].is_empty() {
+                assert_eq!(editor.cursor.col(), col);
+            }
+        }
+        editor.process_key(ctrl('u')).unwrap();
+        assert_eq!(editor.cursor.line() - editor.viewport.top_border(), row);
+
+        press(&mut editor, ":set sol\n");
+        editor.process_key(ctrl('u')).unwrap();
+        assert_eq!(editor.cursor.col(), 4);
+    }
+
+    #[test]
+    fn test_write_range_to_file() {
+        let dir = TempDir::new("write");
+        let other = dir.join("other.txt");
+        let mut editor = editor_with_lines(&["a", "b", "c", "d"]);
+        press(&mut editor, ":w\n");
+        assert_eq!(editor.path, None);
+
+        press(&mut editor, &format!(":2,3w {}\n", other.display()));
+        assert_eq!(fs::read_to_string(&other).unwrap(), "b\nc\n");
+        assert_eq!(editor.path, None);
+        press(&mut editor, &format!("jVj:w {}\n", other.display()));
+        assert_eq!(fs::read_to_string(&other).unwrap(), "b\nc\n");
+        press(&mut editor, &format!("jVj:w! {}\n", other.display()));
+        assert_eq!(fs::read_to_string(&other).unwrap(), "c\nd\n");
+
+        // Written whole, the buffer takes the name of the file
+        let own = dir.join("own.txt");
+        press(&mut editor, &format!("x:w {}\n", own.display()));
+        assert_eq!(editor.path.as_ref(), Some(&own));
+        assert!(!editor.modified);
+        press(&mut editor, "jx:1,2w\n");
+        assert_eq!(fs::read_to_string(&own).unwrap(), "a\nb\n\nd\n");
+        press(&mut editor, ":w\n");
+        assert_eq!(fs::read_to_string(&own).unwrap(), "a\nb\n\n\n");
+    }
+
+    #[test]
+    fn test_confirm_overwrite_and_quit() {
+        let dir = TempDir::new("confirm");
+        let other = dir.join("other.txt");
+        fs::write(&other, "old\n").unwrap();
+        let mut editor = editor_with_lines(&["a", "b"]);
+        press(&mut editor, &format!(":1w {}\n", other.display()));
+        assert!(editor.confirmation.is_none());
+
+        press(&mut editor, ":set confirm\n");
+        press(&mut editor, &format!(":1w {}\n", other.display()));
+        assert!(editor.confirmation.is_some());
+        press(&mut editor, "n");
+        assert!(editor.confirmation.is_none());
+        assert_eq!(fs::read_to_string(&other).unwrap(), "old\n");
+        press(&mut editor, &format!(":1w {}\ny", other.display()));
+        assert_eq!(fs::read_to_string(&other).unwrap(), "a\n");
+
+        // Quitting with unsaved changes asks as well
+        press(&mut editor, "x:q\n");
+        assert!(editor.confirmation.is_some());
+        press(&mut editor, "n");
+        press(&mut editor, ":q\n");
+        let quit = editor.process_key(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE));
+        assert!(matches!(quit, Err(Error::ExitCall)));
+    }
+
+    #[test]
+    fn test_explore_directory() {
+        let dir = TempDir::new("explore");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), "hello\n").unwrap();
+        let mut editor = editor_with_lines(&["text"]);
+        press(&mut editor, &format!(":Explore {}\n", dir.display()));
+        assert_eq!(text(&editor), ["../", "sub/", "a.txt"]);
+
+        // Enter descends into directories and back up
+        press(&mut editor, "j\n");
+        assert_eq!(text(&editor), ["../"]);
+        press(&mut editor, "\n");
+        assert_eq!(text(&editor), ["../", "sub/", "a.txt"]);
+        press(&mut editor, "x");
+        assert_eq!(text(&editor), ["../", "sub/", "a.txt"]);
+
+        // and opens files
+        press(&mut editor, "jj\n");
+        assert_eq!(text(&editor), ["hello"]);
+        assert_eq!(editor.explored, None);
+        press(&mut editor, &format!(":e {}\n", dir.join("sub").display()));
+        assert_eq!(text(&editor), ["../"]);
+    }
+
+    #[test]
+    fn test_file_encoding() {
+        let dir = TempDir::new("encoding");
+        let path = dir.join("latin1.txt");
+        fs::write(&path, b"caf\xe9\n").unwrap();
+        let mut editor = editor_with_lines(&[""]);
+        press(&mut editor, &format!(":e {}\n", path.display()));
+        assert_eq!(text(&editor), ["caf\u{FFFD}"]);
+        press(&mut editor, ":set fenc=latin1\n");
+        assert_eq!(text(&editor), ["café"]);
+        press(&mut editor, ":set fenc=klingon\n");
+        assert_eq!(editor.config.fileencoding, "latin1");
+
+        // Characters latin1 has no bytes for keep the file as it is
+        press(&mut editor, "A漢\x1b:w\n");
+        assert_eq!(fs::read(&path).unwrap(), b"caf\xe9\n");
+        assert!(editor.modified);
+        press(&mut editor, "u:w\n");
+        assert_eq!(fs::read(&path).unwrap(), b"caf\xe9\n");
+        assert!(!editor.modified);
+
+        // The file is compared with in its encoding
+        press(&mut editor, "_x:diff\n");
+        assert_eq!(text(&editor)[3..], ["-café", "+afé"]);
+    }
+
+    #[test]
+    fn test_write_after_conversion_error() {
+        let dir = TempDir::new("conversion");
+        let path = dir.join("latin1.txt");
+        fs::write(&path, b"caf\xe9\n").unwrap();
+        let mut editor = editor_with_lines(&[""]);
+        press(&mut editor, &format!(":e {}\n", path.display()));
+        press(&mut editor, ":w\n");
+        assert_eq!(fs::read(&path).unwrap(), b"caf\xe9\n");
+        press(&mut editor, ":w!\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "caf\u{FFFD}\n");
+
+        // Once read in the right encoding, nothing is lost by writing the file
+        fs::write(&path, b"caf\xe9\n").unwrap();
+        press(
+            &mut editor,
+            &format!(":e {}\n:set fenc=latin1\n", path.display()),
+        );
+        press(&mut editor, "_x:w\n");
+        assert_eq!(fs::read(&path).unwrap(), b"af\xe9\n");
+    }
+
+    #[test]
+    fn test_append_to_file() {
+        let dir = TempDir::new("append");
+        let log = dir.join("log.txt");
+        let mut editor = editor_with_lines(&["a", "b", "c"]);
+        press(&mut editor, &format!(":w >> {}\n", log.display()));
+        assert!(!log.exists());
+
+        fs::write(&log, "first\n").unwrap();
+        press(&mut editor, &format!(":2,3w >> {}\n", log.display()));
+        press(&mut editor, &format!(":w>>{}\n", log.display()));
+        assert_eq!(fs::read_to_string(&log).unwrap(), "first\nb\nc\na\nb\nc\n");
+        assert_eq!(editor.path, None);
+    }
+
+    #[test]
+    fn test_insert_mode_word_and_line_deletion() {
+        let mut editor = editor_with_lines(&["    let x"]);
+        press(&mut editor, "A = hello world");
+        editor.process_key(ctrl('w')).unwrap();
+        assert_eq!(text(&editor), ["    let x = hello "]);
+        editor.process_key(ctrl('w')).unwrap();
+        assert_eq!(text(&editor), ["    let x = "]);
+
+        press(&mut editor, "1");
+        editor.process_key(ctrl('u')).unwrap();
+        assert_eq!(text(&editor), ["    let x"]);
+        editor.process_key(ctrl('u')).unwrap();
+        assert_eq!(text(&editor), ["    "]);
+        editor.process_key(ctrl('u')).unwrap();
+        assert_eq!(text(&editor), [""]);
+        assert!(editor.modal.is_insert());
+    }
+
+    #[test]
+    fn test_goto_byte() {
+        let mut editor = editor_with_lines(&["one", "two", "three"]);
+        press(&mut editor, ":goto 6\n");
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 1 });
+        press(&mut editor, ":go\n");
+        assert_eq!(editor.cursor.pos, LineCol::default());
+        press(&mut editor, ":goto 100\n");
+        assert_eq!(editor.cursor.line(), 2);
+        press(&mut editor, "``");
+        assert_eq!(editor.cursor.pos, LineCol::default());
+    }
+
+    #[test]
+    fn test_multiple_cursors() {
+        let mut editor = editor_with_lines(&["let foo = foo + 1;", "foo(food)"]);
+        press(&mut editor, "lllll");
+        editor.process_key(ctrl('n')).unwrap();
+        editor.process_key(ctrl('n')).unwrap();
+        editor.process_key(ctrl('n')).unwrap();
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 4 });
+        assert_eq!(editor.extra_cursors.len(), 2);
+
+        press(&mut editor, "imy_\x7f\x1b");
+        assert_eq!(text(&editor), ["let myfoo = myfoo + 1;", "myfoo(food)"]);
+        press(&mut editor, "\x1bix\x1b");
+        assert!(editor.extra_cursors.is_empty());
+        assert_eq!(text(&editor), ["let myxfoo = myfoo + 1;", "myfoo(food)"]);
+    }
+
+    #[test]
+    fn test_edits_drop_additional_cursors() {
+        let mut editor = editor_with_lines(&["foo", "bar", "foo x"]);
+        editor.process_key(ctrl('n')).unwrap();
+        assert_eq!(editor.extra_cursors.len(), 1);
+        press(&mut editor, "jdd");
+        assert!(editor.extra_cursors.is_empty());
+        press(&mut editor, "kix\x1b");
+        assert_eq!(text(&editor), ["xfoo", "foo x"]);
+
+        let mut editor = editor_with_lines(&["foo", "foo"]);
+        editor.process_key(ctrl('n')).unwrap();
+        press(&mut editor, "ix\x1b");
+        assert_eq!(text(&editor), ["xfoo", "xfoo"]);
+        assert_eq!(editor.extra_cursors.len(), 1);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor), ["foo", "foo"]);
+        assert!(editor.extra_cursors.is_empty());
+    }
+
+    #[test]
+    fn test_change_all_occurrences() {
+        let mut editor = editor_with_lines(&["let foo = foo + 1;", "  foo(food)"]);
+        press(&mut editor, "lllllgr");
+        assert_eq!(editor.command_line_content().as_deref(), Some("changeall "));
+        press(&mut editor, "count\n");
+        assert_eq!(text(&editor), ["let count = count + 1;", "  count(food)"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 5 });
+        press(&mut editor, "u");
+        assert_eq!(text(&editor)[0], "let foo = foo + 1;");
+
+        press(&mut editor, "_lllgr");
+        assert!(editor.modal.is_normal());
+
+        // The cursor stays off the middle of a character once the line got shorter
+        let mut editor = editor_with_lines(&["aaaaa é"]);
+        press(&mut editor, "llllgrü\n");
+        assert_eq!(text(&editor), ["ü é"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 3 });
+    }
+
+    #[test]
+    fn test_operator_and_motion_counts_multiply() {
+        let mut editor = editor_with_lines(&["a b c d e f g h"]);
+        press(&mut editor, "2d3w");
+        assert_eq!(text(&editor), ["g h"]);
+
+        let mut editor = editor_with_numbered_lines(9);
+        press(&mut editor, "j3d2j");
+        assert_eq!(text(&editor), ["0", "8"]);
+        press(&mut editor, "dk");
+        assert_eq!(text(&editor), [""]);
+
+        // Without an operator the count repeats the motion
+        let mut editor = editor_with_lines(&["a b c d e"]);
+        press(&mut editor, "www");
+        let thrice = editor.cursor_position();
+        press(&mut editor, "_3w");
+        assert_eq!(editor.cursor_position(), thrice);
+        press(&mut editor, "_d2w");
+        assert_eq!(text(&editor), ["c d e"]);
+    }
+
+    #[test]
+    fn test_record_and_play_macro() {
+        let mut editor = editor_with_lines(&["a", "b", "c", "d"]);
+        press(&mut editor, "qqA!\x1bjq");
+        assert_eq!(editor.recording, None);
+        assert_eq!(editor.registers.get('q').unwrap().lines, ["A!<Esc>j"]);
+        assert_eq!(text(&editor), ["a!", "b", "c", "d"]);
+
+        press(&mut editor, "2@q@@");
+        assert_eq!(text(&editor), ["a!", "b!", "c!", "d!"]);
+    }
 
-        // If repeatable
-        if base_actions.len() == 1 && self.repeat_action != 1 {
-            let a = base_actions
-                .pop()
-                .expect("Checked for len one line prior")
-                .repeat(self.repeat_action);
-            self.action_queue.push_back(a);
-        }
+    #[test]
+    fn test_recursive_macro() {
+        // Playing stops at the `x` which finds the line empty
+        let mut editor = editor_with_lines(&["abc", "abc", "abc"]);
+        press(&mut editor, "qax@aq");
+        press(&mut editor, "@a");
+        assert_eq!(text(&editor), ["", "abc", "abc"]);
+        assert_eq!(editor.macro_depth, 0);
 
-        for action in base_actions {
-            self.action_queue.push_back(action)
-        }
-        Ok(())
+        // A macro which never fails stops playing itself at the maximum depth
+        let mut editor = editor_with_lines(&["a"]);
+        press(&mut editor, "qbA!\x1b@bq");
+        press(&mut editor, "@b");
+        assert_eq!(text(&editor)[0].len(), 2 + MAX_MAP_DEPTH);
+        assert_eq!(editor.macro_depth, 0);
+
+        // The keys following the macro are still carried out
+        press(&mut editor, "_x");
+        assert_eq!(text(&editor)[0].len(), 1 + MAX_MAP_DEPTH);
     }
 
-    fn resolve_find<F, P>(&self, find_fn: F, pattern: P) -> Result<BaseAction>
-    where
-        F: Fn(P, LineCol) -> Result<LineCol>,
-        P: Pattern,
-    {
-        // TODO: After Regex Implementation this part of the code will decide and convert
-        // pattern to regex if needed
-        let pos = self.cursor.pos;
-        let dest = match find_fn(pattern, pos) {
-            Err(Error::PatternNotFound) => pos,
-            Ok(dest) => dest,
-            e => e?,
-        };
-        Ok(BaseAction::SetCursor(dest))
+    #[test]
+    fn test_recording_indicator() {
+        let mut editor = editor_with_lines(&["a"]);
+        press(&mut editor, "qa");
+        assert_eq!(editor.recording, Some(('a', Vec::new())));
+        let pos = LineCol::default();
+        let bar = crate::bars::get_info_bar_content(
+            80,
+            &Modal::Normal,
+            Some('a'),
+            "",
+            pos,
+            &Default::default(),
+        );
+        assert!(bar.starts_with("NORMAL  recording @a"));
+
+        // Recording goes on across modes until `q` is typed in normal mode
+        press(&mut editor, ":\x1biq\x1b");
+        assert!(editor.recording.is_some());
+        press(&mut editor, "q");
+        assert_eq!(editor.recording, None);
+        assert_eq!(editor.registers.get('a').unwrap().lines, [":<Esc>iq<Esc>"]);
     }
-    fn jump_two_boundaries<F1, F2>(
-        &self,
-        direction: Direction,
-        first_boundary: F1,
-        second_boundary: F2,
-    ) -> Result<BaseAction>
-    where
-        F1: Fn(char) -> bool,
-        F2: Fn(char) -> bool,
-    {
-        // let mut pos = self.cursor.pos;
-        // // Avoid getting stuck if jump destination is directly on cursor
-        // if self.buffer.max_normal_col(pos.line) > pos.col {
-        //     pos.col += 1;
-        // };
 
-        // let mut dest = self.find(&first_boundary, pos)?;
-        // // let dest = dest?;
-        // dest = self.find(&second_boundary, dest)?;
-        // Ok(BaseAction::SetCursor(dest))
+    #[test]
+    fn test_resume_insert() {
+        let mut editor = editor_with_lines(&["one", "two", "three"]);
+        press(&mut editor, "jAs\x1bgg");
+        assert_eq!(editor.cursor_position().line, 0);
+        press(&mut editor, "gi!\x1b");
+        assert_eq!(text(&editor), ["one", "twos!", "three"]);
 
-        //////////////////////////
+        // The position is clamped to the text left
+        press(&mut editor, "ddkgi?\x1b");
+        assert_eq!(text(&editor), ["one", "three?"]);
+    }
 
-        let mut pos = self.cursor.pos;
+    #[test]
+    fn test_insert_mode_word_jumps() {
+        let left = KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL);
+        let right = KeyEvent::new(KeyCode::Right, KeyModifiers::CONTROL);
+        let mut editor = editor_with_lines(&["foo bar baz"]);
+        press(&mut editor, "li");
+        editor.process_key(right).unwrap();
+        assert_eq!(editor.modal, Modal::Insert);
+        assert_eq!(editor.cursor_position(), LineCol { line: 0, col: 4 });
+        editor.process_key(right).unwrap();
+        press(&mut editor, "x");
+        editor.process_key(left).unwrap();
+        editor.process_key(left).unwrap();
+        press(&mut editor, "y\x1b");
+        assert_eq!(text(&editor), ["foo ybar xbaz"]);
+    }
 
-        // Avoid getting stuck if jump destination is directly on cursor
-        if self.buffer.max_normal_col(pos.line) > pos.col {
-            pos.col += 1;
-        }
+    #[test]
+    fn test_clipboard_unnamedplus() {
+        let mut editor = editor_with_lines(&["a", "b"]);
+        press(&mut editor, "yy");
+        assert_eq!(editor.registers.get('+'), None);
 
-        let dest = match direction {
-            Direction::Forward => {
-                let dest = self.find(&first_boundary, pos);
+        press(&mut editor, ":set clipboard=unnamedplus\n");
+        assert!(editor.registers.unnamedplus);
+        press(&mut editor, "jyy\"ayyp");
+        assert_eq!(
+            editor.registers.get('+'),
+            Some(&RegisterContent::new(vec!["b".to_string()], true))
+        );
+        assert_eq!(text(&editor), vec!["a", "b", "b"]);
 
-                if let Err(Error::PatternNotFound) = dest {
-                    warn!("First Destination not found");
-                    return Ok(BaseAction::Nothing);
-                };
-                let dest = dest?;
+        press(&mut editor, ":set clipboard=\n");
+        press(&mut editor, "ggyyP");
+        assert_eq!(text(&editor), vec!["a", "a", "b", "b"]);
+        press(&mut editor, ":set clipboard=unnamed\n");
+        assert_eq!(editor.config.clipboard, "");
+    }
 
-                info!("First Destination found{:?}", &dest);
-                let dest = self.find(&second_boundary, dest);
-                if let Err(Error::PatternNotFound) = dest {
-                    warn!("Second Destination not found");
-                    return Ok(BaseAction::Nothing);
-                };
-                info!("Second Destination found{:?}", &dest);
-                dest?
-            }
-            Direction::Backward => {
-                let dest = self.rfind(&first_boundary, pos)?;
-                self.rfind(&second_boundary, dest)?
-            }
-        };
+    #[test]
+    fn test_filetype_detection() {
+        let mut editor = editor_with_lines(&["#!/usr/bin/env python3", "run()"]);
+        // The paths are only named, no swap file is written next to them
+        press(&mut editor, ":set noswapfile\n");
+        editor.set_path(PathBuf::from("tool"));
+        assert_eq!(editor.config.filetype.as_deref(), Some("python"));
+        press(&mut editor, "jgcc");
+        assert_eq!(text(&editor)[1], "# run()");
 
-        Ok(BaseAction::SetCursor(dest))
+        press(&mut editor, "u:set ft=lua\ngcc");
+        assert_eq!(text(&editor)[1], "-- run()");
+
+        editor.set_path(PathBuf::from("tool.rs"));
+        assert_eq!(editor.config.filetype.as_deref(), Some("rust"));
     }
 
-    /// Searches for a query string in the buffer, starting from a given position.
-    ///
-    /// # Arguments
-    ///
-    /// * `query` - The string to search for.
-    /// * `at` - The position (line and column) to start the search from.
-    ///
-    /// # Returns
-    ///
-    /// * `Ok(LineCol)` - The position (line and column) where the query was found.
-    /// * `Err(BufferError::PatternNotFound)` - If the query string is not found in the buffer.
-    ///
-    /// # Behavior
-    ///
-    /// The search starts at the given position and continues to the end of the buffer.
-    /// It searches the remainder of the starting line, then subsequent lines in their entirety.
-    /// The search is case-sensitive and returns the position of the first occurrence found.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// let buffer = // ... initialize buffer ...
-    /// let result = buffer.find("example", LineCol{line: 1, col: 5});
-    /// assert_eq!(result, Ok(LineCol{line: 2, col: 10})); // Found on line 2, column 10
-    /// ```
-    fn find(&self, query: impl Pattern, at: LineCol) -> Result<LineCol> {
-        let buf = &self.buffer.get_buffer_window(Some(at), None)?;
-        info!("{:?}", buf);
-        query
-            .find_pattern(buf)
-            .ok_or(Error::PatternNotFound)
-            .map(|target| LineCol {
-                line: target.line + at.line,
-                col: if target.line == 0 {
-                    target.col + at.col
-                } else {
-                    target.col
-                },
-            })
+    #[test]
+    fn test_insert_word_completion() {
+        let mut editor = editor_with_lines(&["counter count", ""]);
+        press(&mut editor, "jico");
+        editor.process_key(ctrl('n')).unwrap();
+        assert_eq!(text(&editor)[1], "counter");
+        editor.process_key(ctrl('n')).unwrap();
+        assert_eq!(text(&editor)[1], "count");
+        editor.process_key(ctrl('n')).unwrap();
+        assert_eq!(text(&editor)[1], "co");
+        editor.process_key(ctrl('p')).unwrap();
+        assert_eq!(text(&editor)[1], "count");
+        press(&mut editor, "s ");
+        assert_eq!(editor.word_completion, None);
+        assert_eq!(text(&editor)[1], "counts ");
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 7 });
     }
 
-    /// Searches backwards for a query string in the buffer, ending at a given position.
-    ///
-    /// # Arguments
-    ///
-    /// * `query` - The string to search for.
-    /// * `at` - The position (line and column) to start the reverse search from.
-    ///
-    /// # Returns
-    ///
-    /// * `Ok(LineCol)` - The position (line and column) where the query was found.
-    /// * `Err(BufferError::PatternNotFound)` - If the query string is not found in the buffer.
-    ///
-    /// # Behavior
-    ///
-    /// The search starts at the given position and continues backwards to the beginning of the buffer.
-    /// It first searches the portion of the starting line from the given position to its start,
-    /// then searches previous lines in their entirety from end to start.
-    /// The search is case-sensitive and returns the position of the last occurrence found
-    /// (i.e., the first occurrence when searching backwards).
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// let buffer = // ... initialize buffer ...
-    /// let result = buffer.rfind("example", LineCol{line: 2, col: 15});
-    /// assert_eq!(result, Ok(LineCol{line: 1, col: 5})); // Found on line 1, column 5
-    /// ```
-    fn rfind(&self, query: impl Pattern, at: LineCol) -> Result<LineCol> {
-        let buf = &self.buffer.get_buffer_window(None, Some(at))?;
-        query
-            .rfind_pattern(buf)
-            .ok_or(Error::PatternNotFound)
-            .map(|v| LineCol {
-                line: v.line,
-                col: v.col,
-            })
+    #[test]
+    fn test_reflow_paragraph() {
+        let mut editor =
+            editor_with_lines(&["one two three four", "five six", "", "seven eight nine ten"]);
+        press(&mut editor, ":set tw=10\n");
+        press(&mut editor, "4lgwap");
+        assert_eq!(
+            text(&editor),
+            vec![
+                "one two",
+                "three four",
+                "five six",
+                "",
+                "seven eight nine ten"
+            ]
+        );
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 4 });
+
+        press(&mut editor, "Ggqq");
+        assert_eq!(text(&editor)[4..], ["seven", "eight nine", "ten"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 6, col: 0 });
+        press(&mut editor, "u");
+        assert_eq!(text(&editor)[4..], ["seven eight nine ten"]);
     }
-}
 
-enum Direction {
-    Forward,
-    Backward,
-}
+    #[test]
+    fn test_auto_wrap_while_typing() {
+        let mut editor = editor_with_lines(&["    "]);
+        press(&mut editor, "Aone two three four five\x1b");
+        assert_eq!(text(&editor), vec!["    one two three four five"]);
 
-#[derive(Clone, Debug)]
-enum Action {
-    Quit,
-    Save,
+        let mut editor = editor_with_lines(&["    "]);
+        press(&mut editor, ":set tw=14\nAone two three four five\x1b");
+        assert_eq!(
+            text(&editor),
+            vec!["    one two", "    three four", "    five"]
+        );
+        assert_eq!(editor.cursor.pos, LineCol { line: 2, col: 8 });
+    }
 
-    // Cursor Movement
-    BumpUp,
-    BumpDown,
-    BumpLeft,
-    BumpRight,
-    JumpUp,
-    JumpDown,
-    JumpToNextWord,
-    JumpToNextSymbol,
-    ReverseJumpToNextWord,
-    ReverseJumpToNextSymbol,
-    JumpSOL,
-    JumpEOL,
-    JumpSOF,
-    JumpEOF,
+    #[test]
+    fn test_insert_session_is_one_undo_step() {
+        let mut editor = editor_with_lines(&["x"]);
+        press(&mut editor, "iabc\x1b");
+        assert_eq!(text(&editor), vec!["abcx"]);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor), vec!["x"]);
+        assert_eq!(editor.modal, Modal::Normal);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor), vec!["x"]);
+        assert_eq!(editor.modal, Modal::Normal);
+
+        press(&mut editor, "Aa\nb\x1bvl\x1bAc\x1b");
+        assert_eq!(text(&editor), vec!["xa", "bc"]);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor), vec!["xa", "b"]);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor), vec!["x"]);
+        assert_eq!(editor.modal, Modal::Normal);
+
+        // The deletion of a change is undone with the text typed in its place
+        press(&mut editor, "ccyz\x1bu");
+        assert_eq!(text(&editor), vec!["x"]);
+    }
+
+    #[test]
+    fn test_change_signs() {
+        let dir = TempDir::new("signs");
+        let path = dir.join("signs.txt");
+        fs::write(&path, "a\nb\nc\nd\n").unwrap();
+        let mut editor = editor_with_lines(&["a", "b", "c", "d"]);
+        editor.set_path(path);
+
+        press(&mut editor, "jcwB\x1b");
+        assert_eq!(text(&editor), ["a", "B", "c", "d"]);
+        press(&mut editor, "jdd");
+        assert_eq!(text(&editor), ["a", "B", "d"]);
+        press(&mut editor, "konew\x1b");
+        editor.refresh_change_signs();
+        assert_eq!(editor.signs.get(1), None);
+
+        press(&mut editor, ":set changesigns\n");
+        assert_eq!(text(&editor), ["a", "B", "new", "d"]);
+        assert_eq!(editor.signs.get(0), None);
+        assert_eq!(editor.signs.get(1), Some(SignKind::Changed));
+        assert_eq!(editor.signs.get(2), Some(SignKind::Changed));
+
+        press(&mut editor, ":w\n");
+        assert_eq!(editor.signs.next(0, true, SignKind::CHANGES), None);
+        press(&mut editor, "ggddGox\x1b");
+        editor.refresh_change_signs();
+        assert_eq!(editor.signs.get(0), Some(SignKind::Removed));
+        assert_eq!(editor.signs.get(3), Some(SignKind::Added));
+    }
+
+    #[test]
+    fn test_indent_keeps_selection() {
+        let mut editor = editor_with_lines(&["a", "b", "c"]);
+        press(&mut editor, "Vj>");
+        assert_eq!(editor.modal, Modal::Normal);
+
+        let mut editor = editor_with_lines(&["a", "b", "c"]);
+        press(&mut editor, ":set indentkeepselection\nVj>>>");
+        assert_eq!(text(&editor), ["            a", "            b", "c"]);
+        assert_eq!(editor.modal, Modal::VisualLine);
+        press(&mut editor, "<\x1bu");
+        assert_eq!(text(&editor), ["            a", "            b", "c"]);
+
+        press(&mut editor, "ggj$hvl<");
+        assert_eq!(text(&editor)[1], "        b");
+        assert_eq!(
+            (editor.cursor.last_text_mode_pos, editor.cursor.pos),
+            (LineCol { line: 1, col: 8 }, LineCol { line: 1, col: 9 })
+        );
+    }
+
+    #[test]
+    fn test_search_preview() {
+        let mut editor = editor_with_lines(&["foo", "bar", "baz", "bat"]);
+        press(&mut editor, "l/b");
+        assert_eq!(editor.search_preview, Some(LineCol { line: 1, col: 0 }));
+        press(&mut editor, "az");
+        assert_eq!(editor.search_preview, Some(LineCol { line: 2, col: 0 }));
+        press(&mut editor, "q");
+        assert_eq!(editor.search_preview, None);
+        press(&mut editor, "\x7f");
+        assert_eq!(editor.search_preview, Some(LineCol { line: 2, col: 0 }));
+        press(&mut editor, "\x1b");
+        assert_eq!(editor.search_preview, None);
+        assert_eq!(editor.modal, Modal::Normal);
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 1 });
+
+        press(&mut editor, "G?o");
+        assert_eq!(editor.search_preview, Some(LineCol { line: 0, col: 2 }));
+        press(&mut editor, "\n");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 2 });
+    }
+
+    #[test]
+    fn test_search_preview_scrolls_back() {
+        let mut lines = vec!["text"; 60];
+        lines[50] = "far";
+        let mut editor = editor_with_lines(&lines);
+        press(&mut editor, "/fa");
+        assert_eq!(editor.search_preview, Some(LineCol { line: 50, col: 0 }));
+        assert!(editor.viewport.top_border() > 0);
+        press(&mut editor, "x");
+        assert_eq!(editor.viewport.top_border(), 0);
+        press(&mut editor, "\x7f\x1b");
+        assert_eq!(editor.viewport.top_border(), 0);
+        assert_eq!(editor.cursor.pos, LineCol::default());
+    }
+
+    #[test]
+    fn test_abbreviations() {
+        let mut editor = editor_with_lines(&[""]);
+        press(&mut editor, ":ab teh the\n");
+        press(&mut editor, "iteh ateh teh\x1b");
+        assert_eq!(text(&editor), ["the ateh the"]);
+
+        // The expansion is undone along with the rest of the change
+        press(&mut editor, "ccteh.\x1b");
+        assert_eq!(text(&editor), ["the."]);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor), ["the ateh the"]);
+
+        press(&mut editor, ":unab teh\nccteh \x1b");
+        assert_eq!(text(&editor), ["teh "]);
+    }
+
+    #[test]
+    fn test_key_mappings() {
+        let mut editor = editor_with_lines(&["one", "two", "three", "four"]);
+        press(&mut editor, ":nnoremap x dd\n");
+        press(&mut editor, "x");
+        assert_eq!(text(&editor), ["two", "three", "four"]);
+
+        // Recursive mappings are expanded again, noremap ones are not
+        press(&mut editor, ":nmap Q x\n:nnoremap Z x\n");
+        press(&mut editor, "Q");
+        assert_eq!(text(&editor), ["three", "four"]);
+        press(&mut editor, "Z");
+        assert_eq!(text(&editor), ["hree", "four"]);
+
+        press(&mut editor, ":inoremap jk <Esc>\n");
+        press(&mut editor, "Ajk");
+        assert_eq!(editor.modal, Modal::Normal);
+        press(&mut editor, "Ajx\x1b");
+        assert_eq!(text(&editor), ["hreejx", "four"]);
+
+        press(&mut editor, ":nunmap x\n");
+        press(&mut editor, "_x");
+        assert_eq!(text(&editor), ["reejx", "four"]);
+    }
+
+    #[test]
+    fn test_recursive_mapping_is_aborted() {
+        let mut editor = editor_with_lines(&["text"]);
+        press(&mut editor, ":nmap a b\n:nmap b a\n");
+        press(&mut editor, "a");
+        assert!(editor.pending_keys.is_empty());
+        press(&mut editor, ":nmap j jx\n");
+        press(&mut editor, "j");
+        assert_eq!(text(&editor), ["ext"]);
+    }
+
+    #[test]
+    fn test_leader_mappings() {
+        let mut editor = editor_with_lines(&["one", "two"]);
+        press(
+            &mut editor,
+            ":nnoremap <leader>d dd\n:nnoremap <Leader> x\n",
+        );
+        press(&mut editor, " ");
+        assert_eq!(
+            editor.pending_keys_hint().as_deref(),
+            Some("<Space>    d → dd")
+        );
+        press(&mut editor, "d");
+        assert_eq!(text(&editor), ["two"]);
+
+        // Once the next key doesn't follow in time, the shorter mapping applies
+        press(&mut editor, " ");
+        editor.timeout_pending_keys().unwrap();
+        assert_eq!(text(&editor), ["wo"]);
+        assert_eq!(editor.pending_keys_hint(), None);
+
+        // The leader is substituted when a mapping is defined
+        press(&mut editor, ":set mapleader=,\n:nnoremap <leader>x x\n,x");
+        assert_eq!(text(&editor), ["o"]);
+    }
+
+    #[test]
+    fn test_close_and_open_all_folds() {
+        let lines = [
+            "fn a() {",
+            "    if x {",
+            "        y",
+            "    }",
+            "}",
+            "fn b() {",
+            "    z",
+            "}",
+        ];
+        let mut editor = editor_with_lines(&lines);
+        press(&mut editor, "jjzM");
+        assert_eq!(editor.cursor_position(), LineCol { line: 0, col: 0 });
+        press(&mut editor, "j");
+        assert_eq!(editor.cursor.line(), 4);
+        press(&mut editor, "j");
+        assert_eq!(editor.cursor.line(), 5);
+        press(&mut editor, "zRj");
+        assert_eq!(editor.cursor.line(), 6);
+
+        // Opened recursively, the nested fold doesn't stay closed
+        press(&mut editor, "ggjzaggzAzAjj");
+        assert_eq!(editor.cursor.line(), 2);
+        press(&mut editor, "zA");
+        assert_eq!(editor.cursor.line(), 1);
+    }
+
+    #[test]
+    fn test_toggle_folds() {
+        let mut editor = editor_with_lines(&["fn a() {", "    one", "    two", "}", "end"]);
+        press(&mut editor, "jza");
+        assert_eq!(editor.cursor_position(), LineCol { line: 0, col: 0 });
+
+        // Vertical movement steps over the lines hidden by a closed fold
+        press(&mut editor, "j");
+        assert_eq!(editor.cursor.line(), 3);
+        press(&mut editor, "k");
+        assert_eq!(editor.cursor.line(), 0);
+        press(&mut editor, "zaj");
+        assert_eq!(editor.cursor.line(), 1);
+
+        // Clicking a marker in the fold column toggles the fold, rows below it shift up
+        press(&mut editor, ":set fdc=1\n");
+        let click = |column, row| mouse(MouseEventKind::Down(MouseButton::Left), column, row);
+        editor.process_mouse(click(0, 0)).unwrap();
+        assert_eq!(editor.cursor.line(), 0);
+        editor.process_mouse(click(10, 1)).unwrap();
+        assert_eq!(editor.cursor.line(), 3);
+        editor.process_mouse(click(0, 0)).unwrap();
+        editor.process_mouse(click(10, 1)).unwrap();
+        assert_eq!(editor.cursor.line(), 1);
+    }
+
+    #[test]
+    fn test_search_counts_and_repeat() {
+        let mut editor = editor_with_lines(&["a x", "x", "b x", "x"]);
+        press(&mut editor, "3/x\n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 2, col: 2 });
+        press(&mut editor, "N");
+        assert_eq!(editor.cursor_position(), LineCol { line: 1, col: 0 });
+        press(&mut editor, "2n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 3, col: 0 });
 
-    // Mode Changes
-    ChangeMode(Modal),
-    InsertModeEOL,
+        // An empty pattern repeats the previous one, in the direction now typed
+        press(&mut editor, "?\n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 2, col: 2 });
 
-    // Text Search
-    Find(String),
-    ReverseFind(String),
-    FindChar(char),
-    ReverseFindChar(char),
-    ReverseToChar(char),
-    ToChar(char),
+        // Counts beyond the number of matches stop at the last one reached
+        press(&mut editor, "gg9/b\n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 2, col: 0 });
+        press(&mut editor, ":set nows\n5n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 2, col: 0 });
+    }
 
-    // Insertions
+    #[test]
+    fn test_search_multibyte() {
+        let mut editor = editor_with_lines(&["éé", "a é"]);
+        press(&mut editor, "/é\n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 0, col: 2 });
+        press(&mut editor, "n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 1, col: 2 });
+        press(&mut editor, "2/é\n");
+        assert_eq!(editor.cursor_position(), LineCol { line: 0, col: 2 });
 
-    // Text Manipulation
-    Replace(char),
-    InsertCharAtCursor(char),
-    InsertNewLine,
-    InsertModeBelow,
-    InsertModeAbove,
-    DeleteBeforeCursor,
-    DeleteAtCursor,
+        // The preview searches past the cursor the same way
+        press(&mut editor, "/é");
+        assert_eq!(editor.search_preview, Some(LineCol { line: 1, col: 2 }));
+    }
 
-    // Clipboard Operations
-    Yank,
-    Paste(char),
-    PasteNewline(char),
-    PasteAbove(char),
+    #[test]
+    fn test_visual_line_operations() {
+        let mut editor = editor_with_lines(&["fn f() {", "x();", "  y();", "}", "a", "  b"]);
+        press(&mut editor, "jVj=");
+        assert_eq!(text(&editor)[1..3], ["    x();", "    y();"]);
+        assert_eq!(editor.cursor_position(), LineCol { line: 1, col: 4 });
 
-    // History Operations
-    FetchFromHistory,
+        press(&mut editor, "Vj2>");
+        assert_eq!(
+            text(&editor)[1..3],
+            ["            x();", "            y();"]
+        );
+        press(&mut editor, "V<");
+        assert_eq!(text(&editor)[1], "        x();");
 
-    // Command Execution
-    ExecuteCommand(Command),
+        press(&mut editor, "GkVjJ");
+        assert_eq!(text(&editor)[4..], ["a b"]);
+        assert_eq!(editor.cursor_position(), LineCol { line: 4, col: 1 });
 
-    // Undo/Redo
-    Undo(u8),
-    Redo,
+        // Every operation is undone in one step
+        press(&mut editor, "u");
+        assert_eq!(text(&editor)[4..], ["a", "  b"]);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor)[1], "            x();");
+    }
 
-    // Misc
-    OpenFile,
+    #[test]
+    fn test_swap_file_keeps_and_recovers_edits() {
+        let dir = TempDir::new("editor-swap");
+        let path = dir.join("file.txt");
 
-    Nothing,
-}
+        let mut editor = editor_with_lines(&["one", "two"]);
+        editor.set_path(path.clone());
+        press(&mut editor, "x");
+        assert_eq!(swap::read(&path).unwrap(), ["ne", "two"]);
+        // Within insert mode the swap file waits for the edit to be completed
+        press(&mut editor, "ji!");
+        assert_eq!(swap::read(&path).unwrap(), ["ne", "two"]);
+        press(&mut editor, "\x1b");
+        assert_eq!(swap::read(&path).unwrap(), ["ne", "!two"]);
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Lazy<T> {
-    inner: Option<T>,
-}
-impl<T> Lazy<T> {
-    /// Create Type Inferred Lazy Struct
-    pub fn new() -> Self {
-        Lazy { inner: None }
+        let mut recovered = editor_with_lines(&["one", "two"]);
+        recovered.set_path(path.clone());
+        press(&mut recovered, ":recover\n");
+        assert_eq!(text(&recovered), ["ne", "!two"]);
+
+        // Quitting cleanly or disabling swap files removes it
+        let quit = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        press(&mut editor, ":q");
+        assert!(matches!(editor.process_key(quit), Err(Error::ExitCall)));
+        assert!(!swap::exists(&path));
+        press(&mut recovered, "x:set noswf\n");
+        assert!(!swap::exists(&path));
+        press(&mut recovered, "x");
+        assert!(!swap::exists(&path));
     }
-    /// Returns the inner evaluated value if there is one
-    pub fn into_inner(self) -> Option<T> {
-        self.inner
+
+    #[test]
+    fn test_autosave_writes_modified_text() {
+        let dir = TempDir::new("autosave");
+        let path = dir.join("file.txt");
+        fs::write(&path, "one\ntwo\n").unwrap();
+
+        let mut editor = editor_with_lines(&["one", "two"]);
+        editor.set_path(path.clone());
+        press(&mut editor, ":set autosave=500\n");
+        editor.autosave();
+        assert!(!editor.modified);
+
+        press(&mut editor, "dd");
+        assert!(editor.modified && swap::exists(&path));
+        editor.autosave();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "two\n");
+        assert!(!editor.modified && !swap::exists(&path));
     }
-    /// Initializes a Lazy Type with an already initialized value.
-    pub fn with_inner(v: T) -> Self {
-        Lazy { inner: Some(v) }
+
+    #[test]
+    fn test_script_commands_and_mappings() {
+        let mut editor = editor_with_lines(&["one", "two"]);
+        press(
+            &mut editor,
+            ":script fn tag(a) { insert_text(`<${a}>`) } command(\"Tag\", Fn(\"tag\"))\n",
+        );
+        press(&mut editor, "j:Tag b\n");
+        assert_eq!(text(&editor), ["one", "<b>two"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 3 });
+
+        press(
+            &mut editor,
+            ":script map(\"n\", \"Q\", \":Tag i<CR>\"); go_to(0, 1)\n",
+        );
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 1 });
+        press(&mut editor, "Q");
+        assert_eq!(text(&editor), ["o<i>ne", "<b>two"]);
+
+        // A failing script leaves the text alone
+        press(&mut editor, ":script insert_text(\"x\"); throw \"oops\"\n");
+        assert_eq!(text(&editor), ["o<i>ne", "<b>two"]);
+        assert!(matches!(editor.modal, Modal::Normal));
     }
-    /// Set the inner value Of Lazy value
-    pub fn set_inner(&mut self, v: T) {
-        self.inner = Some(v)
+
+    #[test]
+    fn test_extensions_observe_actions() {
+        let mut editor = editor_with_lines(&[""]);
+        let counter = KeystrokeCounter::default();
+        editor.register_extension(Box::new(counter.clone()));
+        press(&mut editor, "ihello\x1bjk");
+        assert_eq!(counter.count(), 5);
+        press(&mut editor, "ia\x1b");
+        assert_eq!(counter.count(), 6);
     }
-    /// True if the lazy already contains an inner value
-    pub fn is_evaluated(&self) -> bool {
-        self.inner.is_some()
+
+    #[test]
+    fn test_marks_and_special_marks() {
+        let mut editor = editor_with_lines(&["  one", "two", "three", "four"]);
+        press(&mut editor, "llmajj`a");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 2 });
+        press(&mut editor, "jj'a");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 2 });
+
+        // `` toggles between the latest jump and the position before it
+        press(&mut editor, "jl");
+        let before = editor.cursor.pos;
+        press(&mut editor, "G");
+        assert_eq!(editor.cursor.line(), 3);
+        press(&mut editor, "``");
+        assert_eq!(editor.cursor.pos, before);
+        press(&mut editor, "``");
+        assert_eq!(editor.cursor.line(), 3);
+        press(&mut editor, ":2\n''");
+        assert_eq!(editor.cursor.line(), 3);
+
+        press(&mut editor, "kx");
+        press(&mut editor, "gg`.");
+        assert_eq!(editor.cursor.line(), 2);
+        // Jumping to a mark which isn't set leaves the cursor alone
+        press(&mut editor, "`b");
+        assert_eq!(editor.cursor.line(), 2);
     }
-}
 
-impl<T: Clone> Lazy<T> {
-    // Clones the inner type T, panicking if called on an unevaluated lazy
-    pub fn clone_inner(&self) -> T {
-        self.inner.clone().unwrap()
+    #[test]
+    fn test_help_lists_keys_and_returns() {
+        let mut editor = editor_with_lines(&["one", "two"]);
+        press(&mut editor, ":nnoremap Q dd\nxj");
+        press(&mut editor, ":help keys\n");
+        let help = text(&editor);
+        assert!(help
+            .iter()
+            .any(|l| l.starts_with("  k ") && l.ends_with("Move up")));
+        assert!(help
+            .iter()
+            .any(|l| l.starts_with("  <C-r> ") && l.ends_with("Redo")));
+        assert!(help.iter().any(|l| l.contains("n Q *dd")));
+
+        // The help is read-only
+        press(&mut editor, "ddiabc\x1b");
+        assert_eq!(text(&editor), help);
+        assert!(!editor.modified);
+
+        press(&mut editor, ":q\n");
+        assert_eq!(text(&editor), ["ne", "two"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 0 });
+        assert!(editor.modified);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor), ["one", "two"]);
+
+        press(&mut editor, "g?");
+        assert_eq!(text(&editor), help);
+        press(&mut editor, ":q\n:help motions\n");
+        assert_eq!(text(&editor), ["one", "two"]);
     }
-}
 
-impl<T> Default for Lazy<T> {
-    fn default() -> Self {
-        Self::new()
+    #[test]
+    fn test_counted_x_and_big_x() {
+        let mut editor = editor_with_lines(&["abcdef", "ghij"]);
+        press(&mut editor, "l3x");
+        assert_eq!(text(&editor), ["aef", "ghij"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 1 });
+        let small = editor
+            .registers
+            .get(crate::register::SMALL_DELETE_REGISTER)
+            .unwrap();
+        assert_eq!(small.lines, ["bcd"]);
+
+        // Fewer characters than requested remain until the end of the line
+        press(&mut editor, "5x");
+        assert_eq!(text(&editor), ["a", "ghij"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 0 });
+
+        press(&mut editor, "jlll2X");
+        assert_eq!(text(&editor), ["a", "gj"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 1 });
+        press(&mut editor, "9X\"ax");
+        assert_eq!(text(&editor), ["a", ""]);
+        assert_eq!(editor.registers.get('a').unwrap().lines, ["j"]);
+        press(&mut editor, "x");
+        assert_eq!(text(&editor), ["a", ""]);
     }
-}
 
-mod test {
-    use super::*;
-    use crate::buffer::VecBuffer;
-    use crate::LineCol;
+    #[test]
+    fn test_backspace_at_start_of_buffer() {
+        let mut editor = editor_with_lines(&["abc", "def"]);
+        press(&mut editor, "i\x7f\x7f");
+        assert_eq!(text(&editor), ["abc", "def"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 0 });
+        press(&mut editor, "\x1bX");
+        assert_eq!(text(&editor), ["abc", "def"]);
+        press(&mut editor, "ji\x7f");
+        assert_eq!(text(&editor), ["abcdef"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 3 });
+
+        let mut editor = editor_with_lines(&[""]);
+        press(&mut editor, "i\x7f\x1bX");
+        assert_eq!(text(&editor), [""]);
+    }
 
     #[test]
-    fn test_jump_two_boundaries() {
-        // Create a VecBuffer with test content
-        let content = vec![
-            "Hello world! This is a test.".to_string(),
-            "Multiple   spaces   between words.".to_string(),
-            "Symbols: @#$% and _underscores_".to_string(),
-        ];
-        let buffer = VecBuffer::new(content);
+    fn test_command_line_only_shown_while_typing_it() {
+        let mut editor = editor_with_lines(&["abc"]);
+        press(&mut editor, ":set nows");
+        assert_eq!(editor.command_line_content().as_deref(), Some("set nows"));
+        press(&mut editor, "\n");
+        assert!(editor.modal.is_normal());
+        assert_eq!(editor.buffer.get_command_text(), "");
+        assert_eq!(editor.command_line_content(), None);
 
-        let mut editor = Editor::new(buffer, false);
+        // Text left in the command line isn't shown outside of it
+        editor.buffer.replace_command_text("w");
+        assert_eq!(editor.command_line_content(), None);
+        press(&mut editor, "/b");
+        assert!(editor.command_line_content().is_some());
+    }
 
-        // Test forward word jump
-        let result = editor.jump_two_boundaries(Direction::Forward, char::is_whitespace, |ch| {
-            !ch.is_whitespace()
-        });
+    #[test]
+    fn test_dw_stops_at_end_of_line() {
+        let mut editor = editor_with_lines(&["foo bar", "baz qux"]);
+        press(&mut editor, "lllldw");
+        assert_eq!(text(&editor), ["foo ", "baz qux"]);
+        press(&mut editor, "u_2dw");
+        assert_eq!(text(&editor), ["", "baz qux"]);
+        press(&mut editor, "u3dw");
+        assert_eq!(text(&editor), ["qux"]);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor), ["foo bar", "baz qux"]);
+
+        // `cw` keeps the whitespace after the word, `yw` takes it along
+        press(&mut editor, "cwnew\x1b");
+        assert_eq!(text(&editor), ["new bar", "baz qux"]);
+        press(&mut editor, "j_yw");
+        assert_eq!(editor.registers.get('0').unwrap().lines, ["baz "]);
+    }
+
+    #[test]
+    fn test_insert_register() {
+        let mut editor = editor_with_lines(&["one two", "three"]);
+        press(&mut editor, "yiwj_i");
+        editor.process_key(ctrl('r')).unwrap();
+        press(&mut editor, "\"-\x1b");
+        assert_eq!(text(&editor), ["one two", "one-three"]);
+
+        // Linewise registers end in a newline
+        press(&mut editor, "kyyj_i");
+        editor.process_key(ctrl('r')).unwrap();
+        press(&mut editor, "0");
+        assert_eq!(text(&editor), ["one two", "one two", "one-three"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 2, col: 0 });
+
+        // An empty register inserts nothing, the key after it is typed as usual
+        editor.process_key(ctrl('r')).unwrap();
+        press(&mut editor, "zx\x1b");
+        assert_eq!(text(&editor), ["one two", "one two", "xone-three"]);
+        press(&mut editor, "u");
+        assert_eq!(text(&editor), ["one two", "one-three"]);
+    }
+
+    #[test]
+    fn test_expand_and_shrink_selection() {
+        let mut editor = editor_with_lines(&["one two", "three", "", "four"]);
+        press(&mut editor, "llllv+");
+        assert_eq!(editor.modal, Modal::Visual);
         assert_eq!(
-            result.unwrap(),
-            BaseAction::SetCursor(LineCol { line: 0, col: 6 })
+            (editor.cursor.last_text_mode_pos, editor.cursor.pos),
+            (LineCol { line: 0, col: 4 }, LineCol { line: 0, col: 6 })
+        );
+        press(&mut editor, "+");
+        assert_eq!(editor.modal, Modal::VisualLine);
+        press(&mut editor, "+");
+        let selection = Selection::from(&editor.cursor).normalized();
+        assert_eq!((selection.start.line, selection.end.line), (0, 1));
+        // Nothing larger than the paragraph
+        press(&mut editor, "+--");
+        assert_eq!(editor.modal, Modal::Visual);
+        assert_eq!(
+            (editor.cursor.last_text_mode_pos, editor.cursor.pos),
+            (LineCol { line: 0, col: 4 }, LineCol { line: 0, col: 6 })
         );
+        press(&mut editor, "-");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 4 });
+        // Shrinking past the selection the expansion started from does nothing
+        press(&mut editor, "-");
+        assert_eq!(editor.modal, Modal::Visual);
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 4 });
+    }
 
-        // Test backward word jump
-        editor.cursor.pos = LineCol { line: 0, col: 20 };
-        let result = editor.jump_two_boundaries(Direction::Backward, char::is_whitespace, |ch| {
-            !ch.is_whitespace()
-        });
+    #[test]
+    fn test_jump_to_percent() {
+        let lines: Vec<String> = (1..=100).map(|i| format!("  {i}")).collect();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let mut editor = editor_with_lines(&lines);
+        press(&mut editor, "50%");
+        assert_eq!(editor.cursor.pos, LineCol { line: 49, col: 2 });
+        press(&mut editor, "1%");
+        assert_eq!(editor.cursor.line(), 0);
+        press(&mut editor, "250%");
+        assert_eq!(editor.cursor.line(), 99);
+        press(&mut editor, "``");
+        assert_eq!(editor.cursor.line(), 0);
+        // Without a count there is nothing to jump to
+        press(&mut editor, "%");
+        assert_eq!(editor.cursor.line(), 0);
+
+        let mut editor = editor_with_lines(&["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"]);
+        press(&mut editor, "jd50%");
+        assert_eq!(text(&editor), ["a", "f", "g", "h", "i", "j"]);
+    }
+
+    #[test]
+    fn test_reindent_operator() {
+        let mut editor = editor_with_lines(&["fn f() {", "if x {", "y();", "}", "}", "", "  z"]);
+        press(&mut editor, "j=ip");
         assert_eq!(
-            result.unwrap(),
-            BaseAction::SetCursor(LineCol { line: 0, col: 13 })
+            text(&editor),
+            [
+                "fn f() {",
+                "    if x {",
+                "        y();",
+                "    }",
+                "}",
+                "",
+                "  z"
+            ]
         );
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 0 });
 
-        // Test forward symbol jump
-        editor.cursor.pos = LineCol { line: 2, col: 0 };
-        let result = editor.jump_two_boundaries(
-            Direction::Forward,
-            |ch| ch.is_alphanumeric() || ch == '_',
-            |ch| !ch.is_alphanumeric() && ch != '_' && !ch.is_whitespace(),
+        press(&mut editor, "G==");
+        assert_eq!(text(&editor)[6], "z");
+        press(&mut editor, "u");
+        assert_eq!(text(&editor)[6], "  z");
+        press(&mut editor, "u");
+        assert_eq!(text(&editor)[1..4], ["if x {", "y();", "}"]);
+    }
+
+    #[test]
+    fn test_move_lines() {
+        let alt = |ch| KeyEvent::new(KeyCode::Char(ch), KeyModifiers::ALT);
+        let mut editor = editor_with_lines(&["a", "b", "c", "d"]);
+        editor.process_key(alt('j')).unwrap();
+        assert_eq!(text(&editor), ["b", "a", "c", "d"]);
+        assert_eq!(editor.cursor.line(), 1);
+        press(&mut editor, "5");
+        editor.process_key(alt('j')).unwrap();
+        assert_eq!(text(&editor), ["b", "c", "d", "a"]);
+        assert_eq!(editor.cursor.line(), 3);
+        // Nothing moves past the edges of the buffer
+        editor.process_key(alt('j')).unwrap();
+        assert_eq!(text(&editor), ["b", "c", "d", "a"]);
+
+        press(&mut editor, "ggVj");
+        editor.process_key(alt('j')).unwrap();
+        assert_eq!(text(&editor), ["d", "b", "c", "a"]);
+        assert_eq!(editor.modal, Modal::VisualLine);
+        let selection = Selection::from(&editor.cursor).normalized();
+        assert_eq!((selection.start.line, selection.end.line), (1, 2));
+        editor.process_key(alt('k')).unwrap();
+        assert_eq!(text(&editor), ["b", "c", "d", "a"]);
+
+        press(&mut editor, "\x1bu");
+        assert_eq!(text(&editor), ["d", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn test_duplicate_lines() {
+        let alt_d = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::ALT);
+        let mut editor = editor_with_lines(&["one", "two", "three"]);
+        press(&mut editor, "yyjl");
+        editor.process_key(alt_d).unwrap();
+        assert_eq!(text(&editor), ["one", "two", "two", "three"]);
+        assert_eq!(editor.cursor.pos, LineCol { line: 2, col: 1 });
+
+        press(&mut editor, "Vj2");
+        editor.process_key(alt_d).unwrap();
+        assert_eq!(
+            text(&editor),
+            ["one", "two", "two", "three", "two", "three", "two", "three"]
         );
+        assert_eq!(editor.modal, Modal::Normal);
+        assert_eq!(editor.cursor.line(), 4);
+
+        // The registers keep what was yanked before
+        press(&mut editor, "ggp");
+        assert_eq!(text(&editor)[..2], ["one", "one"]);
+        press(&mut editor, "uu");
+        assert_eq!(text(&editor), ["one", "two", "two", "three"]);
+
+        // Other keys are bound to it with a mapping
+        press(&mut editor, ":nnoremap <C-j> <M-d>\ngg");
+        editor.process_key(ctrl('j')).unwrap();
+        assert_eq!(text(&editor)[..2], ["one", "one"]);
+    }
+
+    #[test]
+    fn test_rebind_duplicate_lines() {
+        let alt_d = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::ALT);
+        let mut editor = editor_with_lines(&["one", "two"]);
+        press(&mut editor, ":set duplicatekey=<C-y>\n");
+        editor.process_key(alt_d).unwrap();
+        assert_eq!(text(&editor), ["one", "two"]);
+        editor.process_key(ctrl('y')).unwrap();
+        assert_eq!(text(&editor), ["one", "one", "two"]);
+
+        // A plain key is taken over from what it usually does
+        press(&mut editor, ":set dupk=D\nVjD");
+        assert_eq!(text(&editor), ["one", "one", "two", "one", "two"]);
+        assert!(editor
+            .help_keys_text()
+            .iter()
+            .any(|line| line.contains("D  ") && line.ends_with("Duplicate the line below")));
+    }
+
+    #[test]
+    fn test_iskeyword() {
+        let mut editor = editor_with_lines(&["a my-var b"]);
+        press(&mut editor, "llldiw");
+        assert_eq!(text(&editor), ["a -var b"]);
+        press(&mut editor, "u:set isk+=-\nlldiw");
+        assert_eq!(text(&editor), ["a  b"]);
+    }
+
+    #[test]
+    fn test_jump_between_signs() {
+        let mut editor = editor_with_lines(&["a", "  b", "c", "d", "e"]);
+        editor.place_sign(1, SignKind::Error);
+        editor.place_sign(3, SignKind::Warning);
+        press(&mut editor, "]d");
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 2 });
+        press(&mut editor, "]d]d");
+        assert_eq!(editor.cursor.line(), 3);
+        press(&mut editor, "``");
+        assert_eq!(editor.cursor.line(), 1);
+        press(&mut editor, "G2[d");
+        assert_eq!(editor.cursor.line(), 1);
+
+        editor.clear_signs(&[SignKind::Error]);
+        press(&mut editor, "gg]d");
+        assert_eq!(editor.cursor.line(), 3);
+    }
+
+    #[test]
+    fn test_todo_signs() {
+        let mut editor = editor_with_lines(&["// TODO: a", "b", "c // FIXME", "d", "XXX"]);
+        press(&mut editor, "]t");
+        assert_eq!(editor.cursor.line(), 0);
+        press(&mut editor, ":todo\n]t");
+        assert_eq!(editor.cursor.line(), 2);
+        press(&mut editor, "]t[t[t");
+        assert_eq!(editor.cursor.line(), 0);
+
+        // The signs follow the edits of the buffer
+        press(&mut editor, "dd");
+        assert_eq!(editor.signs.get(0), None);
+        assert_eq!(editor.signs.get(1), Some(SignKind::Todo));
+        press(&mut editor, "2]t");
+        assert_eq!(editor.cursor.line(), 3);
+    }
+
+    #[test]
+    fn test_find_char_across_lines() {
+        let mut editor = editor_with_lines(&["a,b,c", "d,e", "f"]);
+        press(&mut editor, "f,");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 1 });
+        press(&mut editor, "2f,");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 1 });
+        press(&mut editor, "t,");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 2 });
+        press(&mut editor, "T,");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 2 });
+        // By default the search stays within the line
+        press(&mut editor, "fe");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 2 });
+
+        press(&mut editor, ":set findacrosslines\nfe");
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 2 });
+        press(&mut editor, "Fb");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 2 });
+        press(&mut editor, "2f,");
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 1 });
+    }
+
+    #[test]
+    fn test_grapheme_clusters() {
+        let family = "a👨‍👩‍👧b";
+        let mut editor = editor_with_lines(&[family, "e\u{301}x"]);
+        press(&mut editor, "l");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 1 });
+        press(&mut editor, "l");
         assert_eq!(
-            result.unwrap(),
-            BaseAction::SetCursor(LineCol { line: 2, col: 8 })
+            editor.cursor.pos,
+            LineCol {
+                line: 0,
+                col: family.len() - 1
+            }
         );
+        press(&mut editor, "h");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 1 });
+        press(&mut editor, "x");
+        assert_eq!(text(&editor)[0], "ab");
 
-        // Test backward symbol jump
-        editor.cursor.pos = LineCol { line: 2, col: 20 };
-        let result = editor.jump_two_boundaries(
-            Direction::Backward,
-            |ch| ch.is_alphanumeric() || ch == '_',
-            |ch| !ch.is_alphanumeric() && ch != '_' && !ch.is_whitespace(),
+        press(&mut editor, "jx");
+        assert_eq!(text(&editor)[1], "x");
+    }
+
+    #[test]
+    fn test_vertical_moves_land_on_clusters() {
+        let mut editor = editor_with_lines(&["abc", "é"]);
+        press(&mut editor, "lj");
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 0 });
+        press(&mut editor, "ix\x1b");
+        assert_eq!(text(&editor), ["abc", "xé"]);
+
+        let mut lines = vec!["é"; 100];
+        lines[0] = "abc";
+        let mut editor = editor_with_lines(&lines);
+        press(&mut editor, "l");
+        editor.process_key(ctrl('d')).unwrap();
+        assert_ne!(editor.cursor.line(), 0);
+        assert_eq!(editor.cursor.col(), 0);
+    }
+
+    #[test]
+    fn test_virtual_edit() {
+        let mut editor = editor_with_lines(&["long line", "ab"]);
+        press(&mut editor, "$jl");
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 2 });
+
+        press(&mut editor, ":set virtualedit\nk$j");
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 9 });
+        press(&mut editor, "lix\x1b");
+        assert_eq!(text(&editor)[1], "ab        x");
+    }
+
+    #[test]
+    fn test_virtual_edit_replace_and_paste() {
+        let mut editor = editor_with_lines(&["long line", "ab"]);
+        press(&mut editor, ":set virtualedit\n$jlrx");
+        assert_eq!(text(&editor)[1], "ab        x");
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 10 });
+
+        let mut editor = editor_with_lines(&["long line", "ab"]);
+        press(&mut editor, ":set virtualedit\nyiw$jlp");
+        assert_eq!(text(&editor)[1], "ab         long");
+
+        let mut editor = editor_with_lines(&["long line", "ab"]);
+        press(&mut editor, ":set virtualedit\nyiw$jlP");
+        assert_eq!(text(&editor)[1], "ab        long");
+    }
+
+    #[test]
+    fn test_indent_text_object() {
+        let mut editor = editor_with_lines(&["if a:", "    b()", "    c()", "d()"]);
+        press(&mut editor, "jdii");
+        assert_eq!(text(&editor), ["if a:", "d()"]);
+
+        let mut editor = editor_with_lines(&["if a:", "    b()", "    c()", "d()"]);
+        press(&mut editor, "jjciipass\x1b");
+        assert_eq!(text(&editor), ["if a:", "pass", "d()"]);
+
+        press(&mut editor, "jdai");
+        assert_eq!(text(&editor), [""]);
+    }
+
+    #[test]
+    fn test_pending_command() {
+        let mut editor = editor_with_lines(&["one two three"]);
+        press(&mut editor, "2d");
+        assert_eq!(editor.pending_command(), "2d");
+        press(&mut editor, "w");
+        assert_eq!(editor.pending_command(), "");
+        assert_eq!(text(&editor), ["three"]);
+
+        press(&mut editor, "\"a3");
+        assert_eq!(editor.pending_command(), "\"a3");
+        press(&mut editor, "\x1b");
+        assert_eq!(editor.pending_command(), "");
+
+        press(&mut editor, "Ft");
+        assert_eq!(editor.pending_command(), "");
+        press(&mut editor, "f");
+        assert_eq!(editor.pending_command(), "f");
+    }
+
+    #[test]
+    fn test_smart_home() {
+        let mut editor = editor_with_lines(&["    indented"]);
+        let home = KeyEvent::new(KeyCode::Home, KeyModifiers::NONE);
+        press(&mut editor, "$");
+        editor.process_key(home).unwrap();
+        assert_eq!(editor.cursor.col(), 0);
+
+        press(&mut editor, ":set smarthome\n$");
+        editor.process_key(home).unwrap();
+        assert_eq!(editor.cursor.col(), 4);
+        editor.process_key(home).unwrap();
+        assert_eq!(editor.cursor.col(), 0);
+        editor.process_key(home).unwrap();
+        assert_eq!(editor.cursor.col(), 4);
+        press(&mut editor, "_");
+        assert_eq!(editor.cursor.col(), 0);
+    }
+
+    #[test]
+    fn test_anchored_search() {
+        let mut editor = editor_with_lines(&["foo foo", "a foo", "foo bar", "bar foo"]);
+        press(&mut editor, "/^foo\n");
+        assert_eq!(editor.cursor.pos, LineCol { line: 2, col: 0 });
+        press(&mut editor, "n");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 0 });
+
+        press(&mut editor, "/foo$\n");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 4 });
+        press(&mut editor, "n");
+        assert_eq!(editor.cursor.pos, LineCol { line: 1, col: 2 });
+        press(&mut editor, "N");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 4 });
+        press(&mut editor, "?^foo\n");
+        assert_eq!(editor.cursor.pos, LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_find_from_mid_line() {
+        let editor = editor_with_lines(&["abc abc", "xx abc", "", "zz", "  abc", "é x"]);
+        let at = |line, col| LineCol { line, col };
+        assert_eq!(editor.find("abc", at(0, 2)).ok(), Some(at(0, 4)));
+        assert_eq!(editor.find("abc", at(0, 5)).ok(), Some(at(1, 3)));
+        assert_eq!(editor.find("zz", at(1, 4)).ok(), Some(at(3, 0)));
+        assert_eq!(editor.find("abc", at(1, 4)).ok(), Some(at(4, 2)));
+        assert_eq!(editor.find("abc", at(4, 3)).ok(), None);
+        assert_eq!(editor.find('x', at(5, 0)).ok(), Some(at(5, 3)));
+        assert_eq!(
+            editor.find(char::is_whitespace, at(5, 0)).ok(),
+            Some(at(5, 2))
         );
         assert_eq!(
-            result.unwrap(),
-            BaseAction::SetCursor(LineCol { line: 2, col: 13 })
+            editor.find(char::is_whitespace, at(4, 2)).ok(),
+            Some(at(5, 2))
         );
+        // Past the end of the line, as the cursor may be with `virtualedit`
+        assert_eq!(editor.find("zz", at(2, 4)).ok(), Some(at(3, 0)));
+        assert_eq!(editor.rfind("xx", at(1, 9)).ok(), Some(at(1, 0)));
+    }
 
-        // Test jump at end of buffer
-        editor.cursor.pos = LineCol { line: 2, col: 30 };
-        let result = editor.jump_two_boundaries(Direction::Forward, char::is_whitespace, |ch| {
-            !ch.is_whitespace()
-        });
-        assert_eq!(result.unwrap(), BaseAction::Nothing);
+    #[test]
+    fn test_center_on_search_result() {
+        let mut lines = vec!["line"; 100];
+        lines[60] = "target";
+        lines[90] = "target";
+        let mut editor = editor_with_lines(&lines);
+        let rows = editor.viewport.text_rows();
+        press(&mut editor, "/target\n");
+        assert_eq!(editor.cursor.line(), 60);
+        assert_eq!(editor.viewport.top_border(), 60 + 1 - rows);
+        press(&mut editor, "zz");
+        assert_eq!(editor.viewport.top_border(), 60 - rows / 2);
 
-        // Test jump at beginning of buffer
-        editor.cursor.pos = LineCol { line: 0, col: 0 };
-        let result = editor.jump_two_boundaries(Direction::Backward, char::is_whitespace, |ch| {
-            !ch.is_whitespace()
-        });
-        assert_eq!(result.unwrap(), BaseAction::Nothing);
+        press(&mut editor, ":set searchcenter\nn");
+        assert_eq!(editor.cursor.line(), 90);
+        assert_eq!(editor.viewport.top_border(), 90 - rows / 2);
+        press(&mut editor, "gg/target\n");
+        assert_eq!(editor.viewport.top_border(), 60 - rows / 2);
+    }
+
+    #[test]
+    fn test_insert_expression() {
+        let mut editor = editor_with_lines(&[""]);
+        press(&mut editor, "ix = ");
+        editor.process_key(ctrl('r')).unwrap();
+        press(&mut editor, "=(2 + 4) * 77\x7f\n;");
+        assert_eq!(text(&editor), ["x = 42;"]);
+
+        editor.process_key(ctrl('r')).unwrap();
+        press(&mut editor, "=1 / 0\n!");
+        assert_eq!(text(&editor), ["x = 42;!"]);
+    }
+
+    #[test]
+    fn test_counted_commands_undo_at_once() {
+        let mut editor = editor_with_lines(&["one", "two", "three", "four"]);
+        press(&mut editor, "3ddu");
+        assert_eq!(text(&editor), ["one", "two", "three", "four"]);
+
+        press(&mut editor, "yiw3p");
+        assert_eq!(text(&editor)[0], "ooneoneonene");
+        press(&mut editor, "u");
+        assert_eq!(text(&editor)[0], "one");
+        press(&mut editor, "_3x");
+        assert_eq!(text(&editor)[0], "");
+        press(&mut editor, "u");
+        assert_eq!(text(&editor)[0], "one");
+    }
+
+    #[test]
+    fn test_timers_run_once_due() {
+        let mut editor = editor_with_lines(&["abc"]);
+        press(&mut editor, ":nnoremap xy dd\n:set tm=1000\nx");
+        assert_eq!(editor.poll_timeout(), POLL_INTERVAL);
+        assert!(!editor.service_timers().unwrap());
+        assert_eq!(text(&editor), ["abc"]);
+
+        editor.last_event = Instant::now() - Duration::from_millis(900);
+        assert!(editor.poll_timeout() <= Duration::from_millis(100));
+        editor.last_event = Instant::now() - Duration::from_millis(1000);
+        assert!(editor.service_timers().unwrap());
+        assert_eq!(text(&editor), ["bc"]);
+        assert!(editor.timers().is_empty());
     }
 }
@@ -0,0 +1,62 @@
+//! Encodings files are read and written in, chosen with `:set fileencoding`. The text is kept as
+//! UTF-8 within the editor, files are converted from their encoding as they are loaded and back
+//! into it as they are written.
+use encoding_rs::{Encoding, UTF_8};
+
+use crate::{Error, Result};
+
+/// Encoding of files unless `fileencoding` names another one
+pub const DEFAULT_ENCODING: &str = "utf-8";
+
+/// The encoding called `label`, like `utf-8` or `latin1`. `None` if there is none by that name, or
+/// if text can't be written in it, which is the case for UTF-16.
+pub fn lookup(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.as_bytes()).filter(|encoding| encoding.output_encoding() == *encoding)
+}
+
+/// Decodes the contents of a file written in the encoding called `label`, UTF-8 if there is none
+/// by that name. Bytes invalid in the encoding are replaced by `U+FFFD`.
+///
+/// # Returns
+///
+/// The text and whether some of its bytes were invalid.
+pub fn decode(bytes: &[u8], label: &str) -> (String, bool) {
+    let encoding = lookup(label).unwrap_or(UTF_8);
+    let (text, invalid) = encoding.decode_without_bom_handling(bytes);
+    (text.into_owned(), invalid)
+}
+
+/// Encodes `text` in the encoding called `label`, UTF-8 if there is none by that name. Fails with
+/// `Error::Unrepresentable` instead of dropping the characters the encoding has no bytes for.
+pub fn encode(text: &str, label: &str) -> Result<Vec<u8>> {
+    let encoding = lookup(label).unwrap_or(UTF_8);
+    let (bytes, _, unmappable) = encoding.encode(text);
+    match unmappable {
+        true => Err(Error::Unrepresentable(label.to_string())),
+        false => Ok(bytes.into_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_and_encode() {
+        assert!(lookup("latin1").is_some());
+        assert!(lookup("utf-16le").is_none());
+        assert!(lookup("klingon").is_none());
+
+        assert_eq!(decode(b"caf\xe9", "latin1"), ("café".to_string(), false));
+        assert_eq!(
+            decode(b"caf\xe9", "utf-8"),
+            ("caf\u{FFFD}".to_string(), true)
+        );
+        assert_eq!(encode("café", "latin1").unwrap(), b"caf\xe9");
+        assert_eq!(encode("café", "utf-8").unwrap(), "café".as_bytes());
+        assert!(matches!(
+            encode("漢", "latin1"),
+            Err(Error::Unrepresentable(_))
+        ));
+    }
+}
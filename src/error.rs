@@ -14,13 +14,23 @@ pub enum Error {
     InvalidLineNumber,
     InvalidInput,
     PatternNotFound,
+    MalformedMarkup,
+    UnknownOption(String),
     NoCommandAvailable,
     UnexpectedRegisterData,
     ProgrammingBug {
         descr: String,
     },
     NowhereToGo,
-    ImATeacup,
+    RecursiveMapping,
+    /// A macro stopped playing at a key which failed or played itself too deeply
+    MacroAborted,
+    /// A script failed to compile or run, with the message of the scripting engine
+    Script(String),
+    /// An expression of the expression register couldn't be evaluated, with the reason why
+    InvalidExpression(String),
+    /// Text holds characters the file encoding has no bytes for, with the name of the encoding
+    Unrepresentable(String),
 
     #[from]
     Io(std::io::Error),
@@ -0,0 +1,260 @@
+//! Parsing of the line ranges and arguments of Ex commands typed into the command line.
+use std::ops::Range;
+
+use crate::{Error, Result};
+
+/// The lines addresses of a range are resolved against, all of them zero based
+#[derive(Clone, Copy, Debug)]
+pub struct AddressContext {
+    /// Line of the cursor, addressed by `.`
+    pub current: usize,
+    /// Last line of the buffer, addressed by `$`
+    pub last: usize,
+    /// First and last line of the previous visual selection, addressed by `'<` and `'>`
+    pub visual: Option<(usize, usize)>,
+}
+
+/// Splits the line range off the front of an Ex command.
+///
+/// A range is either `%` for the whole buffer or one or two addresses separated by `,` or `;`.
+/// An address is a line number, `.`, `$`, `'<` or `'>`, optionally followed by `+N`/`-N` offsets.
+/// A lone offset is relative to the cursor line. Reversed ranges are swapped.
+///
+/// # Returns
+///
+/// The zero based, end exclusive range of lines, if the command starts with one, and the rest of
+/// the command.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidLineNumber` for addresses past the end of the buffer or visual marks
+/// which aren't set yet, and `Error::InvalidInput` for marks other than the visual ones.
+pub fn parse_range<'a>(
+    command: &'a str,
+    ctx: &AddressContext,
+) -> Result<(Option<Range<usize>>, &'a str)> {
+    if let Some(rest) = command.strip_prefix('%') {
+        return Ok((Some(0..ctx.last + 1), rest));
+    }
+    let (first, rest) = parse_address(command, ctx)?;
+    let Some(first) = first else {
+        return Ok((None, command));
+    };
+    let (last, rest) = match rest.strip_prefix([',', ';']) {
+        Some(rest) => match parse_address(rest, ctx)? {
+            (Some(last), rest) => (last, rest),
+            (None, rest) => (ctx.current, rest),
+        },
+        None => (first, rest),
+    };
+
+    if first.max(last) > ctx.last {
+        return Err(Error::InvalidLineNumber);
+    }
+    Ok((Some(first.min(last)..first.max(last) + 1), rest))
+}
+
+/// Parses a single address with its offsets, `None` if the command doesn't start with one
+fn parse_address<'a>(command: &'a str, ctx: &AddressContext) -> Result<(Option<usize>, &'a str)> {
+    let (base, mut rest) = if let Some(rest) = command.strip_prefix('.') {
+        (Some(ctx.current as i64), rest)
+    } else if let Some(rest) = command.strip_prefix('$') {
+        (Some(ctx.last as i64), rest)
+    } else if let Some(rest) = command.strip_prefix('\'') {
+        let mark = match rest.chars().next() {
+            Some('<') => |(first, _)| first,
+            Some('>') => |(_, last)| last,
+            _ => return Err(Error::InvalidInput),
+        };
+        let line = ctx.visual.map(mark).ok_or(Error::InvalidLineNumber)?;
+        (Some(line as i64), &rest[1..])
+    } else {
+        match take_number(command) {
+            // Line numbers are one based, line zero is clamped to the first line
+            (Some(number), rest) => (Some(number.saturating_sub(1) as i64), rest),
+            (None, rest) => (None, rest),
+        }
+    };
+
+    let mut offset = 0;
+    let mut has_offset = false;
+    while let Some(sign) = rest.chars().next().filter(|ch| matches!(ch, '+' | '-')) {
+        let (number, after) = take_number(&rest[1..]);
+        let number = number.unwrap_or(1) as i64;
+        offset += if sign == '+' { number } else { -number };
+        has_offset = true;
+        rest = after;
+    }
+    let line = match (base, has_offset) {
+        (None, false) => return Ok((None, rest)),
+        (None, true) => ctx.current as i64 + offset,
+        (Some(base), _) => base + offset,
+    };
+    let line = usize::try_from(line).map_err(|_| Error::InvalidLineNumber)?;
+    Ok((Some(line), rest))
+}
+
+/// Splits leading decimal digits off `s`
+fn take_number(s: &str) -> (Option<usize>, &str) {
+    let digits = s.bytes().take_while(u8::is_ascii_digit).count();
+    (s[..digits].parse().ok(), &s[digits..])
+}
+
+/// The arguments of `:s/pattern/replacement/flags`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Substitution {
+    pub pattern: String,
+    pub replacement: String,
+    /// Replace every match on a line instead of only the first one
+    pub global: bool,
+}
+
+/// Parses the arguments of a substitute command, `args` starting with the delimiter. Any
+/// character other than a letter, digit, space, backslash or `"` may be used as the delimiter,
+/// and a backslash escapes it within the pattern and the replacement. The trailing delimiter may
+/// be omitted.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidInput` for a missing or invalid delimiter, an empty pattern or unknown
+/// flags.
+pub fn parse_substitution(args: &str) -> Result<Substitution> {
+    let mut chars = args.chars();
+    let delimiter = chars
+        .next()
+        .filter(|ch| !ch.is_alphanumeric() && !matches!(ch, ' ' | '\\' | '"'))
+        .ok_or(Error::InvalidInput)?;
+
+    let mut parts = vec![String::new()];
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some(escaped) if escaped == delimiter => parts.last_mut().unwrap().push(escaped),
+                Some(escaped) => parts.last_mut().unwrap().extend(['\\', escaped]),
+                None => parts.last_mut().unwrap().push('\\'),
+            },
+            ch if ch == delimiter && parts.len() < 3 => parts.push(String::new()),
+            ch => parts.last_mut().unwrap().push(ch),
+        }
+    }
+
+    let mut parts = parts.into_iter();
+    let pattern = parts.next().unwrap_or_default();
+    let replacement = parts.next().unwrap_or_default();
+    let flags = parts.next().unwrap_or_default();
+    if pattern.is_empty() || flags.chars().any(|flag| flag != 'g') {
+        return Err(Error::InvalidInput);
+    }
+    Ok(Substitution {
+        pattern,
+        replacement,
+        global: flags.contains('g'),
+    })
+}
+
+/// The options of `:sort[!] [n][u]`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SortOptions {
+    /// `!`, sorting in reverse order
+    pub reverse: bool,
+    /// `u`, keeping only the first of equal lines
+    pub unique: bool,
+    /// `n`, comparing the first number of each line
+    pub numeric: bool,
+}
+
+/// Parses the arguments of a sort command, `args` following right after the command name.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidInput` for unknown options.
+pub fn parse_sort_options(args: &str) -> Result<SortOptions> {
+    let (reverse, flags) = match args.strip_prefix('!') {
+        Some(flags) => (true, flags),
+        None => (false, args),
+    };
+    let mut options = SortOptions {
+        reverse,
+        ..SortOptions::default()
+    };
+    for flag in flags.chars().filter(|ch| !ch.is_whitespace()) {
+        match flag {
+            'u' => options.unique = true,
+            'n' => options.numeric = true,
+            _ => return Err(Error::InvalidInput),
+        }
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CTX: AddressContext = AddressContext {
+        current: 4,
+        last: 9,
+        visual: Some((2, 3)),
+    };
+
+    fn range(command: &str) -> Result<(Option<Range<usize>>, &str)> {
+        parse_range(command, &CTX)
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(range("s/a/b/").unwrap(), (None, "s/a/b/"));
+        assert_eq!(range("%s").unwrap(), (Some(0..10), "s"));
+        assert_eq!(range("3").unwrap(), (Some(2..3), ""));
+        assert_eq!(range("2,$d").unwrap(), (Some(1..10), "d"));
+        assert_eq!(range("'<,'>s").unwrap(), (Some(2..4), "s"));
+        assert_eq!(range(".,+2").unwrap(), (Some(4..7), ""));
+        assert_eq!(range("-,.").unwrap(), (Some(3..5), ""));
+        assert_eq!(range("$-1").unwrap(), (Some(8..9), ""));
+        assert_eq!(range("5,2").unwrap(), (Some(1..5), ""));
+    }
+
+    #[test]
+    fn test_parse_invalid_range() {
+        assert!(matches!(range("11"), Err(Error::InvalidLineNumber)));
+        assert!(matches!(range("'a"), Err(Error::InvalidInput)));
+        let no_marks = AddressContext {
+            visual: None,
+            ..CTX
+        };
+        assert!(matches!(
+            parse_range("'<,'>", &no_marks),
+            Err(Error::InvalidLineNumber)
+        ));
+    }
+
+    #[test]
+    fn test_parse_sort_options() {
+        assert_eq!(parse_sort_options("").unwrap(), SortOptions::default());
+        let options = parse_sort_options("! nu").unwrap();
+        assert!(options.reverse && options.numeric && options.unique);
+        assert!(parse_sort_options(" x").is_err());
+    }
+
+    #[test]
+    fn test_parse_substitution() {
+        assert_eq!(
+            parse_substitution("/a/b/g").unwrap(),
+            Substitution {
+                pattern: "a".to_string(),
+                replacement: "b".to_string(),
+                global: true,
+            }
+        );
+        let sub = parse_substitution(r"#a\#b#c").unwrap();
+        assert_eq!(
+            (sub.pattern.as_str(), sub.replacement.as_str()),
+            ("a#b", "c")
+        );
+        assert!(!sub.global);
+        assert_eq!(parse_substitution("/x").unwrap().replacement, "");
+        assert!(parse_substitution("//b/").is_err());
+        assert!(parse_substitution("/a/b/q").is_err());
+        assert!(parse_substitution("ab").is_err());
+    }
+}
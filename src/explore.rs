@@ -0,0 +1,42 @@
+//! Listings of directories shown by `:Explore`, a read-only buffer of entries opened with Enter.
+use std::{fs, io, path::Path};
+
+/// Entry of a listing leading to the parent directory
+pub const PARENT_ENTRY: &str = "../";
+
+/// Lines listing the entries of `dir`: the parent directory first, then the subdirectories marked
+/// by a trailing slash and then the files, each sorted by name
+pub fn listing(dir: &Path) -> io::Result<Vec<String>> {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        match entry.path().is_dir() {
+            true => dirs.push(format!("{name}/")),
+            false => files.push(name),
+        }
+    }
+    dirs.sort();
+    files.sort();
+    Ok(std::iter::once(PARENT_ENTRY.to_string())
+        .chain(dirs)
+        .chain(files)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_listing() {
+        let dir = TempDir::new("listing");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("b.txt"), "").unwrap();
+        fs::write(dir.join("a.txt"), "").unwrap();
+        assert_eq!(listing(&dir).unwrap(), ["../", "sub/", "a.txt", "b.txt"]);
+        assert!(listing(&dir.join("missing")).is_err());
+    }
+}
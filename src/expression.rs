@@ -0,0 +1,125 @@
+//! Integer arithmetic of the expression register, evaluated by `Ctrl-R =` in insert mode. Only
+//! integers, `+`, `-`, `*`, `/` and parentheses are understood, with the usual precedence.
+use std::{iter::Peekable, str::Chars};
+
+use crate::{Error, Result};
+
+/// Evaluates `expression`, whitespace between the numbers and operators is ignored
+pub fn evaluate(expression: &str) -> Result<i64> {
+    let mut chars = expression.chars().peekable();
+    let value = sum(&mut chars)?;
+    match next_token(&mut chars) {
+        None => Ok(value),
+        Some(ch) => Err(invalid(format!("Unexpected character: {ch}"))),
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::InvalidExpression(reason.into())
+}
+
+/// Skips whitespace and returns the next character without taking it
+fn next_token(chars: &mut Peekable<Chars>) -> Option<char> {
+    while chars.next_if(|ch| ch.is_whitespace()).is_some() {}
+    chars.peek().copied()
+}
+
+/// Terms added or subtracted from each other
+fn sum(chars: &mut Peekable<Chars>) -> Result<i64> {
+    let mut value = product(chars)?;
+    while let Some(op @ ('+' | '-')) = next_token(chars) {
+        let _ = chars.next();
+        let rhs = product(chars)?;
+        value = match op {
+            '+' => value.checked_add(rhs),
+            _ => value.checked_sub(rhs),
+        }
+        .ok_or_else(|| invalid("Overflow"))?;
+    }
+    Ok(value)
+}
+
+/// Factors multiplied or divided by each other, division rounding towards zero
+fn product(chars: &mut Peekable<Chars>) -> Result<i64> {
+    let mut value = factor(chars)?;
+    while let Some(op @ ('*' | '/')) = next_token(chars) {
+        let _ = chars.next();
+        let rhs = factor(chars)?;
+        value = match op {
+            '*' => value.checked_mul(rhs).ok_or_else(|| invalid("Overflow"))?,
+            _ if rhs == 0 => return Err(invalid("Division by zero")),
+            _ => value.checked_div(rhs).ok_or_else(|| invalid("Overflow"))?,
+        };
+    }
+    Ok(value)
+}
+
+/// A number, a parenthesized sum or a factor with a sign in front
+fn factor(chars: &mut Peekable<Chars>) -> Result<i64> {
+    match next_token(chars) {
+        Some('-') => {
+            let _ = chars.next();
+            factor(chars)?
+                .checked_neg()
+                .ok_or_else(|| invalid("Overflow"))
+        }
+        Some('+') => {
+            let _ = chars.next();
+            factor(chars)
+        }
+        Some('(') => {
+            let _ = chars.next();
+            let value = sum(chars)?;
+            match next_token(chars) {
+                Some(')') => {
+                    let _ = chars.next();
+                    Ok(value)
+                }
+                _ => Err(invalid("Missing )")),
+            }
+        }
+        Some(ch) if ch.is_ascii_digit() => {
+            let mut digits = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                digits.push(digit);
+            }
+            digits.parse().map_err(|_| invalid("Overflow"))
+        }
+        Some(ch) => Err(invalid(format!("Unexpected character: {ch}"))),
+        None => Err(invalid("Missing operand")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precedence_and_parentheses() {
+        assert_eq!(evaluate("1 + 2 * 3").unwrap(), 7);
+        assert_eq!(evaluate("(1 + 2) * 3").unwrap(), 9);
+        assert_eq!(evaluate("10 - 4 - 3").unwrap(), 3);
+        assert_eq!(evaluate("7 / 2").unwrap(), 3);
+        assert_eq!(evaluate("-7 / 2").unwrap(), -3);
+        assert_eq!(evaluate("2 * -(3 + +1)").unwrap(), -8);
+        assert_eq!(evaluate(" 42 ").unwrap(), 42);
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        for expression in [
+            "",
+            "1 +",
+            "(1 + 2",
+            "1 2",
+            "1 / 0",
+            "2 ^ 3",
+            "99999999999999999999",
+        ] {
+            assert!(
+                matches!(evaluate(expression), Err(Error::InvalidExpression(_))),
+                "{expression}"
+            );
+        }
+    }
+}
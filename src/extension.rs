@@ -0,0 +1,27 @@
+//! Extensions hooked into the editor with `Editor::register_extension`.
+use std::{cell::Cell, rc::Rc};
+
+use crate::{BaseAction, Component, Result};
+
+/// Counts the characters typed into the text. Clones share their count, so one clone can be
+/// registered while another one is kept to read the count.
+#[derive(Clone, Debug, Default)]
+pub struct KeystrokeCounter {
+    count: Rc<Cell<usize>>,
+}
+
+impl KeystrokeCounter {
+    /// The characters typed since the counter was created
+    pub fn count(&self) -> usize {
+        self.count.get()
+    }
+}
+
+impl Component for KeystrokeCounter {
+    fn execute_action(&mut self, a: &BaseAction) -> Result<()> {
+        if let BaseAction::InsertAt(_, _) = a {
+            self.count.set(self.count.get() + 1);
+        }
+        Ok(())
+    }
+}
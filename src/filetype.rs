@@ -0,0 +1,120 @@
+//! Kinds of files, like `rust` or `python`, detected from the name of a file or the interpreter
+//! named by its shebang line. Features depending on the language of a file consult the filetype
+//! rather than the extension, so `:set filetype` adjusts all of them at once.
+use std::path::Path;
+
+/// Filetypes of the file extensions, the extensions of a filetype following it
+const EXTENSIONS: &[(&str, &[&str])] = &[
+    ("rust", &["rs"]),
+    ("c", &["c", "h"]),
+    ("cpp", &["cpp", "hpp", "cc", "cxx", "hxx"]),
+    ("java", &["java"]),
+    ("javascript", &["js", "jsx", "mjs"]),
+    ("typescript", &["ts", "tsx"]),
+    ("go", &["go"]),
+    ("kotlin", &["kt"]),
+    ("swift", &["swift"]),
+    ("cs", &["cs"]),
+    ("scala", &["scala"]),
+    ("zig", &["zig"]),
+    ("dart", &["dart"]),
+    ("python", &["py"]),
+    ("sh", &["sh", "bash", "zsh"]),
+    ("ruby", &["rb"]),
+    ("perl", &["pl"]),
+    ("toml", &["toml"]),
+    ("yaml", &["yaml", "yml"]),
+    ("r", &["r"]),
+    ("lua", &["lua"]),
+    ("sql", &["sql"]),
+    ("haskell", &["hs"]),
+    ("elm", &["elm"]),
+    ("vim", &["vim"]),
+    ("tex", &["tex"]),
+    ("erlang", &["erl"]),
+    ("lisp", &["lisp", "el"]),
+    ("clojure", &["clj"]),
+    ("scheme", &["scm"]),
+    ("markdown", &["md"]),
+    ("json", &["json"]),
+    ("html", &["html", "htm"]),
+    ("css", &["css"]),
+];
+
+/// Filetypes of the interpreters named by shebang lines, versions like in `python3` stripped
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("sh", "sh"),
+    ("bash", "sh"),
+    ("zsh", "sh"),
+    ("python", "python"),
+    ("ruby", "ruby"),
+    ("perl", "perl"),
+    ("node", "javascript"),
+    ("lua", "lua"),
+];
+
+/// Detects the filetype of the file at `path` from its extension or, failing that, from the
+/// shebang line its text starts with
+pub fn detect(path: &Path, first_line: Option<&str>) -> Option<String> {
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    let by_extension = extension.and_then(|extension| {
+        EXTENSIONS
+            .iter()
+            .find(|(_, extensions)| extensions.contains(&extension))
+    });
+    match by_extension {
+        Some((filetype, _)) => Some(filetype.to_string()),
+        None => first_line.and_then(from_shebang).map(String::from),
+    }
+}
+
+/// Filetype of the interpreter a `#!` line runs, through `env` or directly
+fn from_shebang(line: &str) -> Option<&'static str> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+    let program = program.trim_end_matches(|ch: char| ch.is_ascii_digit() || ch == '.');
+    INTERPRETERS
+        .iter()
+        .find(|(interpreter, _)| *interpreter == program)
+        .map(|(_, filetype)| *filetype)
+}
+
+/// Line comment prefix of files of `filetype`, `None` for filetypes without line comments
+pub fn comment_string(filetype: &str) -> Option<&'static str> {
+    match filetype {
+        "rust" | "c" | "cpp" | "java" | "javascript" | "typescript" | "go" | "kotlin" | "swift"
+        | "cs" | "scala" | "zig" | "dart" => Some("// "),
+        "python" | "sh" | "ruby" | "perl" | "toml" | "yaml" | "r" => Some("# "),
+        "lua" | "sql" | "haskell" | "elm" => Some("-- "),
+        "vim" => Some("\" "),
+        "tex" | "erlang" => Some("% "),
+        "lisp" | "clojure" | "scheme" => Some(";; "),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let detect = |path: &str, line| detect(Path::new(path), line);
+        assert_eq!(detect("src/main.rs", None).as_deref(), Some("rust"));
+        assert_eq!(
+            detect("lib/list.h", Some("#!/bin/sh")).as_deref(),
+            Some("c")
+        );
+        assert_eq!(detect("notes", None), None);
+        assert_eq!(detect("build", Some("#!/bin/bash")).as_deref(), Some("sh"));
+        assert_eq!(
+            detect("run", Some("#!/usr/bin/env -S python3.12 -u")).as_deref(),
+            Some("python")
+        );
+        assert_eq!(detect("run", Some("#!/usr/bin/env awk")), None);
+        assert_eq!(detect("run", Some("# not a shebang")), None);
+    }
+}
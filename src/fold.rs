@@ -0,0 +1,200 @@
+//! Folds collapsing indented blocks into a single screen row. Like vim's `foldmethod=indent`,
+//! every line followed by lines indented deeper than itself starts a fold spanning those lines.
+use std::{collections::BTreeSet, ops::Range};
+
+/// Marker of the fold column for a line starting a closed fold
+pub const CLOSED_MARKER: char = '+';
+/// Marker of the fold column for a line starting an open fold
+pub const OPEN_MARKER: char = '-';
+
+/// Returns the foldable regions of `lines`, ordered by their first line. A region includes the
+/// line starting it and the blank lines within it, but not the blank lines trailing it.
+pub fn regions(lines: &[String]) -> Vec<Range<usize>> {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let mut regions = Vec::new();
+    for (start, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let depth = indent(line);
+        let end = lines[start + 1..]
+            .iter()
+            .take_while(|line| line.trim().is_empty() || indent(line) > depth)
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .last()
+            .map_or(start + 1, |(i, _)| start + i + 2);
+        if end > start + 1 {
+            regions.push(start..end);
+        }
+    }
+    regions
+}
+
+/// The folds which are closed, identified by their first line
+#[derive(Clone, Debug, Default)]
+pub struct Folds {
+    closed: BTreeSet<usize>,
+}
+
+impl Folds {
+    /// Opens the closed fold hiding `line`, or closes the innermost fold containing it.
+    ///
+    /// # Returns
+    ///
+    /// The toggled fold and whether it is closed now, `None` if `line` isn't within any fold.
+    pub fn toggle(&mut self, line: usize, lines: &[String]) -> Option<(Range<usize>, bool)> {
+        let view = self.view(lines);
+        if let Some(fold) = view.closed_fold(line) {
+            let _ = self.closed.remove(&fold.start);
+            return Some((fold.clone(), false));
+        }
+        let fold = view.regions.iter().rfind(|region| region.contains(&line))?;
+        let _ = self.closed.insert(fold.start);
+        Some((fold.clone(), true))
+    }
+    /// Opens the closed fold hiding `line` along with the folds nested within it, or closes the
+    /// innermost fold containing it along with those nested within it, like `toggle` does.
+    pub fn toggle_recursively(
+        &mut self,
+        line: usize,
+        lines: &[String],
+    ) -> Option<(Range<usize>, bool)> {
+        let view = self.view(lines);
+        let (fold, close) = match view.closed_fold(line) {
+            Some(fold) => (fold.clone(), false),
+            None => (
+                view.regions
+                    .iter()
+                    .rfind(|region| region.contains(&line))?
+                    .clone(),
+                true,
+            ),
+        };
+        for region in view
+            .regions
+            .iter()
+            .filter(|region| fold.start <= region.start && region.end <= fold.end)
+        {
+            match close {
+                true => self.closed.insert(region.start),
+                false => self.closed.remove(&region.start),
+            };
+        }
+        Some((fold, close))
+    }
+    /// Closes every fold of `lines`
+    pub fn close_all(&mut self, lines: &[String]) {
+        self.closed = regions(lines).iter().map(|region| region.start).collect();
+    }
+    /// Opens every fold
+    pub fn open_all(&mut self) {
+        self.closed.clear();
+    }
+    /// Resolves the closed folds against the current regions of `lines`, closed folds whose
+    /// region vanished through edits are ignored
+    pub fn view(&self, lines: &[String]) -> FoldView {
+        let regions = regions(lines);
+        let mut closed: Vec<Range<usize>> = Vec::new();
+        for region in regions
+            .iter()
+            .filter(|region| self.closed.contains(&region.start))
+        {
+            // Closed folds nested within another closed fold are hidden by it
+            if closed.last().is_none_or(|outer| outer.end <= region.start) {
+                closed.push(region.clone());
+            }
+        }
+        FoldView { regions, closed }
+    }
+}
+
+/// Regions and closed folds of a text as it is displayed
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FoldView {
+    regions: Vec<Range<usize>>,
+    /// The outermost closed folds, ordered by their first line
+    closed: Vec<Range<usize>>,
+}
+
+impl FoldView {
+    /// The closed fold hiding `line`, including the line a closed fold is displayed as
+    pub fn closed_fold(&self, line: usize) -> Option<&Range<usize>> {
+        self.closed.iter().find(|fold| fold.contains(&line))
+    }
+    /// The line the screen row of `line` is displayed as
+    pub fn display_line(&self, line: usize) -> usize {
+        self.closed_fold(line).map_or(line, |fold| fold.start)
+    }
+    /// The line displayed on the screen row after the one of `line`
+    pub fn next_line(&self, line: usize) -> usize {
+        self.closed_fold(line).map_or(line + 1, |fold| fold.end)
+    }
+    /// The fold column marker of `line`, if it starts a fold
+    pub fn marker(&self, line: usize) -> Option<char> {
+        if self.closed.iter().any(|fold| fold.start == line) {
+            Some(CLOSED_MARKER)
+        } else if self.regions.iter().any(|region| region.start == line) {
+            Some(OPEN_MARKER)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_regions() {
+        let text = lines("fn a() {\n    if x {\n        y\n\n    }\n}\n\nfn b() {}");
+        assert_eq!(regions(&text), [0..5, 1..3]);
+        assert_eq!(regions(&lines("a\n\n  b\n\nc")), vec![0..3]);
+        assert!(regions(&lines("a\nb")).is_empty());
+    }
+
+    #[test]
+    fn test_toggle_and_view() {
+        let text = lines("a\n  b\n    c\n  d\ne");
+        let mut folds = Folds::default();
+        assert_eq!(folds.toggle(2, &text), Some((1..3, true)));
+        assert_eq!(folds.toggle(0, &text), Some((0..4, true)));
+
+        let view = folds.view(&text);
+        assert_eq!(view.closed_fold(2), Some(&(0..4)));
+        assert_eq!(view.display_line(3), 0);
+        assert_eq!(view.next_line(0), 4);
+        assert_eq!(view.marker(0), Some(CLOSED_MARKER));
+        assert_eq!(view.marker(4), None);
+
+        // Opening the outer fold reveals the still closed inner one
+        assert_eq!(folds.toggle(3, &text), Some((0..4, false)));
+        let view = folds.view(&text);
+        assert_eq!(view.marker(0), Some(OPEN_MARKER));
+        assert_eq!(view.next_line(1), 3);
+        assert_eq!(folds.toggle(4, &text), None);
+    }
+
+    #[test]
+    fn test_toggle_recursively_and_all() {
+        let text = lines("a\n  b\n    c\n  d\ne\n  f");
+        let mut folds = Folds::default();
+        assert_eq!(folds.toggle_recursively(2, &text), Some((1..3, true)));
+        assert_eq!(folds.toggle_recursively(3, &text), Some((0..4, true)));
+        // Opening the outer fold recursively opens the inner one as well
+        assert_eq!(folds.toggle_recursively(0, &text), Some((0..4, false)));
+        assert_eq!(folds.view(&text).closed_fold(2), None);
+
+        folds.close_all(&text);
+        let view = folds.view(&text);
+        assert_eq!(view.closed_fold(2), Some(&(0..4)));
+        assert_eq!(view.closed_fold(5), Some(&(4..6)));
+        folds.open_all();
+        assert_eq!(folds.view(&text).closed_fold(5), None);
+    }
+}
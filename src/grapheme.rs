@@ -0,0 +1,130 @@
+//! Boundaries of grapheme clusters, the characters as the user perceives them. Emoji sequences
+//! joined by zero width joiners and letters followed by combining marks span several code points,
+//! but the cursor moves over them and `x` deletes them as a single unit. The clusters are the
+//! extended grapheme clusters of Unicode Standard Annex #29.
+//!
+//! The widths of the clusters on the screen are the ones terminals give them: CJK characters and
+//! emoji take two columns, extending characters none.
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Byte offset of the end of the cluster starting at `col`, the line's length at its end.
+/// `col` has to be a cluster boundary.
+pub fn next_boundary(line: &str, col: usize) -> usize {
+    line.get(col..)
+        .and_then(|rest| rest.graphemes(true).next())
+        .map_or(line.len(), |cluster| col + cluster.len())
+}
+
+/// Byte offset of the start of the cluster in front of `col`, zero at the start of the line.
+/// Offsets within a cluster belong to it.
+pub fn previous_boundary(line: &str, col: usize) -> usize {
+    line.grapheme_indices(true)
+        .map(|(start, _)| start)
+        .take_while(|&start| start < col)
+        .last()
+        .unwrap_or(0)
+}
+
+/// Byte offset of the start of the cluster `col` lies within, `col` itself on a boundary or past
+/// the end of the line
+pub fn cluster_start(line: &str, col: usize) -> usize {
+    match col < line.len() {
+        true => previous_boundary(line, col + 1),
+        false => col,
+    }
+}
+
+/// Clusters of the line as their byte offsets and widths
+fn clusters(line: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+    line.grapheme_indices(true)
+        .map(|(col, cluster)| (col, cluster.width()))
+}
+
+/// Terminal columns taken by `text`
+pub fn display_width(text: &str) -> usize {
+    clusters(text).map(|(_, width)| width).sum()
+}
+
+/// Byte offset of the cluster displayed at the terminal column `column` of the line, the line's
+/// length past its end
+pub fn col_at_width(line: &str, column: usize) -> usize {
+    let mut start = 0;
+    for (col, width) in clusters(line) {
+        if start + width > column {
+            return col;
+        }
+        start += width;
+    }
+    line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_width_joiner_sequences() {
+        let family = "a👨‍👩‍👧b";
+        let end = family.len() - 1;
+        assert_eq!(next_boundary(family, 0), 1);
+        assert_eq!(next_boundary(family, 1), end);
+        assert_eq!(next_boundary(family, end), family.len());
+        assert_eq!(previous_boundary(family, end), 1);
+        assert_eq!(previous_boundary(family, 1), 0);
+        assert_eq!(previous_boundary(family, 0), 0);
+    }
+
+    #[test]
+    fn test_combining_marks_and_modifiers() {
+        // `e` followed by a combining acute accent
+        let accented = "e\u{301}x";
+        assert_eq!(next_boundary(accented, 0), 3);
+        assert_eq!(previous_boundary(accented, 3), 0);
+
+        let waving = "👋🏽!";
+        assert_eq!(next_boundary(waving, 0), 8);
+        assert_eq!(next_boundary(waving, 8), 9);
+
+        assert_eq!(cluster_start(accented, 2), 0);
+        assert_eq!(cluster_start(accented, 3), 3);
+        assert_eq!(cluster_start(waving, 5), 0);
+        assert_eq!(cluster_start(waving, 12), 12);
+    }
+
+    #[test]
+    fn test_flags_pair_up() {
+        let flags = "🇩🇪🇫🇷";
+        assert_eq!(next_boundary(flags, 0), 8);
+        assert_eq!(next_boundary(flags, 8), 16);
+        assert_eq!(previous_boundary(flags, 16), 8);
+    }
+
+    #[test]
+    fn test_scripts_and_line_breaks() {
+        // Decomposed hangul syllable, thai and bengali letters with their vowel signs
+        for cluster in ["\u{1100}\u{1161}\u{11A8}", "กำ", "কি", "\r\n"] {
+            let line = format!("{cluster}a");
+            assert_eq!(next_boundary(&line, 0), cluster.len());
+            assert_eq!(previous_boundary(&line, cluster.len()), 0);
+        }
+    }
+
+    #[test]
+    fn test_display_width() {
+        let mixed = "a漢字b";
+        assert_eq!(display_width(mixed), 6);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width("👨‍👩‍👧🇩🇪"), 4);
+        // The hangul filler and the thai vowel sign take no column of their own
+        assert_eq!(display_width("\u{1100}\u{1160}"), 2);
+        assert_eq!(display_width("\u{0E01}\u{0E31}"), 1);
+
+        assert_eq!(col_at_width(mixed, 0), 0);
+        assert_eq!(col_at_width(mixed, 1), 1);
+        assert_eq!(col_at_width(mixed, 2), 1);
+        assert_eq!(col_at_width(mixed, 3), 4);
+        assert_eq!(col_at_width(mixed, 5), 7);
+        assert_eq!(col_at_width(mixed, 6), mixed.len());
+    }
+}
@@ -0,0 +1,340 @@
+//! Key mappings defined at runtime with the `:map` family of commands, replacing a sequence of
+//! typed keys by another one before the keys reach the interpreters.
+use std::collections::HashMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::Modal;
+
+/// Modes mappings are defined for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MapMode {
+    Normal,
+    Visual,
+    Insert,
+}
+
+impl MapMode {
+    /// The mappings applying in a mode, `None` for the modes which are never remapped
+    pub const fn of(modal: Modal) -> Option<Self> {
+        match modal {
+            Modal::Normal => Some(Self::Normal),
+            Modal::Visual | Modal::VisualLine | Modal::VisualBlock => Some(Self::Visual),
+            Modal::Insert => Some(Self::Insert),
+            _ => None,
+        }
+    }
+    const fn prefix(self) -> char {
+        match self {
+            Self::Normal => 'n',
+            Self::Visual => 'v',
+            Self::Insert => 'i',
+        }
+    }
+}
+
+const NORMAL_VISUAL: &[MapMode] = &[MapMode::Normal, MapMode::Visual];
+const NORMAL: &[MapMode] = &[MapMode::Normal];
+const VISUAL: &[MapMode] = &[MapMode::Visual];
+const INSERT: &[MapMode] = &[MapMode::Insert];
+
+/// The keys a mapped key sequence is replaced by
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mapping {
+    pub rhs: Vec<KeyEvent>,
+    /// The rhs is interpreted as typed, without applying mappings to it again
+    pub noremap: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Keymap {
+    mappings: HashMap<MapMode, HashMap<Vec<KeyEvent>, Mapping>>,
+}
+
+impl Keymap {
+    pub fn insert(&mut self, mode: MapMode, lhs: Vec<KeyEvent>, mapping: Mapping) {
+        let _ = self.mappings.entry(mode).or_default().insert(lhs, mapping);
+    }
+    /// Removes the mapping of `lhs`, returns whether there was one
+    pub fn remove(&mut self, mode: MapMode, lhs: &[KeyEvent]) -> bool {
+        self.mappings
+            .get_mut(&mode)
+            .is_some_and(|mappings| mappings.remove(lhs).is_some())
+    }
+    /// The mappings whose lhs continues the typed `keys`, with the keys still missing from it
+    pub fn continuations(&self, mode: MapMode, keys: &[KeyEvent]) -> Vec<(&[KeyEvent], &Mapping)> {
+        let keys: Vec<_> = keys.iter().map(|&key| normalize(key)).collect();
+        let mut continuations: Vec<_> = self
+            .mappings
+            .get(&mode)
+            .into_iter()
+            .flatten()
+            .filter(|(lhs, _)| lhs.len() > keys.len() && lhs.starts_with(&keys))
+            .map(|(lhs, mapping)| (&lhs[keys.len()..], mapping))
+            .collect();
+        continuations.sort_by_key(|(rest, _)| key_notation(rest));
+        continuations
+    }
+    /// Whether the typed `keys` start a lhs longer than them, requiring more keys to decide
+    pub fn is_pending(&self, mode: MapMode, keys: &[KeyEvent]) -> bool {
+        !self.continuations(mode, keys).is_empty()
+    }
+    /// Returns the mapping with the longest lhs the typed `keys` start with, together with the
+    /// length of its lhs
+    pub fn lookup(&self, mode: MapMode, keys: &[KeyEvent]) -> Option<(usize, &Mapping)> {
+        let keys: Vec<_> = keys.iter().map(|&key| normalize(key)).collect();
+        self.mappings
+            .get(&mode)?
+            .iter()
+            .filter(|(lhs, _)| keys.starts_with(lhs))
+            .max_by_key(|(lhs, _)| lhs.len())
+            .map(|(lhs, mapping)| (lhs.len(), mapping))
+    }
+    /// Describes the mappings of the modes, sorted by their lhs
+    pub fn list(&self, modes: &[MapMode]) -> Vec<String> {
+        let mut listed: Vec<_> = modes
+            .iter()
+            .filter_map(|mode| Some((mode, self.mappings.get(mode)?)))
+            .flat_map(|(mode, mappings)| {
+                mappings.iter().map(|(lhs, mapping)| {
+                    let noremap = if mapping.noremap { "*" } else { "" };
+                    format!(
+                        "{} {} {noremap}{}",
+                        mode.prefix(),
+                        key_notation(lhs),
+                        key_notation(&mapping.rhs)
+                    )
+                })
+            })
+            .collect();
+        listed.sort_by(|a, b| a[2..].cmp(&b[2..]));
+        listed
+    }
+}
+
+/// The modes and recursiveness of the map command called `name`
+pub fn map_command(name: &str) -> Option<(&'static [MapMode], bool)> {
+    let command = match name {
+        "map" => (NORMAL_VISUAL, false),
+        "no" | "noremap" => (NORMAL_VISUAL, true),
+        "nm" | "nmap" => (NORMAL, false),
+        "nn" | "nnoremap" => (NORMAL, true),
+        "vm" | "vmap" => (VISUAL, false),
+        "vn" | "vnoremap" => (VISUAL, true),
+        "im" | "imap" => (INSERT, false),
+        "ino" | "inoremap" => (INSERT, true),
+        _ => return None,
+    };
+    Some(command)
+}
+
+/// The modes of the unmap command called `name`
+pub fn unmap_command(name: &str) -> Option<&'static [MapMode]> {
+    let modes = match name {
+        "unm" | "unmap" => NORMAL_VISUAL,
+        "nun" | "nunmap" => NORMAL,
+        "vu" | "vunmap" => VISUAL,
+        "iu" | "iunmap" => INSERT,
+        _ => return None,
+    };
+    Some(modes)
+}
+
+/// Parses keys written in vim's key notation. Special keys are written in angle brackets, like
+/// `<Esc>`, `<CR>` or `<Space>`, optionally with modifiers as in `<C-x>` or `<M-CR>`. Brackets
+/// which don't name a key stand for themselves, `<lt>` is a literal `<` and `<leader>` is the
+/// `leader` key.
+pub fn parse_keys(notation: &str, leader: char) -> Vec<KeyEvent> {
+    let mut keys = Vec::new();
+    let mut rest = notation;
+    while let Some(ch) = rest.chars().next() {
+        let special = rest
+            .strip_prefix('<')
+            .and_then(|after| after.split_once('>'))
+            .and_then(|(name, after)| match name.eq_ignore_ascii_case("leader") {
+                true => Some((plain_key(leader), after)),
+                false => Some((special_key(name)?, after)),
+            });
+        match special {
+            Some((key, after)) => {
+                keys.push(key);
+                rest = after;
+            }
+            None => {
+                keys.push(plain_key(ch));
+                rest = &rest[ch.len_utf8()..];
+            }
+        }
+    }
+    keys
+}
+
+/// Parses the name within angle brackets of a special key
+fn special_key(name: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = name;
+    while let Some((modifier, key)) = name.split_once('-').filter(|(_, key)| !key.is_empty()) {
+        modifiers |= match modifier.to_ascii_lowercase().as_str() {
+            "c" => KeyModifiers::CONTROL,
+            "s" => KeyModifiers::SHIFT,
+            "a" | "m" => KeyModifiers::ALT,
+            _ => return None,
+        };
+        name = key;
+    }
+
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(ch), None) if modifiers != KeyModifiers::NONE => match ch {
+            ch if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::Char(ch.to_ascii_uppercase()),
+            ch => KeyCode::Char(ch.to_ascii_lowercase()),
+        },
+        _ => match name.to_ascii_lowercase().as_str() {
+            "esc" => KeyCode::Esc,
+            "cr" | "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "bs" | "backspace" => KeyCode::Backspace,
+            "del" => KeyCode::Delete,
+            "space" => KeyCode::Char(' '),
+            "lt" => KeyCode::Char('<'),
+            "bar" => KeyCode::Char('|'),
+            "bslash" => KeyCode::Char('\\'),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            function => KeyCode::F(function.strip_prefix('f')?.parse().ok()?),
+        },
+    };
+    Some(normalize(KeyEvent::new(code, modifiers)))
+}
+
+/// The key typing `ch` without modifiers
+fn plain_key(ch: char) -> KeyEvent {
+    KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE)
+}
+
+/// Writes keys in the notation read by `parse_keys`
+pub fn key_notation(keys: &[KeyEvent]) -> String {
+    keys.iter()
+        .map(|key| {
+            let name = match key.code {
+                KeyCode::Char(' ') => "Space".to_string(),
+                KeyCode::Char('<') => "lt".to_string(),
+                KeyCode::Char(ch) if key.modifiers.is_empty() => return ch.to_string(),
+                KeyCode::Char(ch) => ch.to_string(),
+                KeyCode::Enter => "CR".to_string(),
+                KeyCode::Backspace => "BS".to_string(),
+                KeyCode::Delete => "Del".to_string(),
+                KeyCode::F(n) => format!("F{n}"),
+                code => format!("{code:?}"),
+            };
+            let mut modifiers = String::new();
+            for (modifier, prefix) in [
+                (KeyModifiers::CONTROL, "C-"),
+                (KeyModifiers::SHIFT, "S-"),
+                (KeyModifiers::ALT, "M-"),
+            ] {
+                if key.modifiers.contains(modifier) {
+                    modifiers.push_str(prefix);
+                }
+            }
+            format!("<{modifiers}{name}>")
+        })
+        .collect()
+}
+
+/// Brings a key into the form mappings are stored in. Terminals report typed characters with
+/// `SHIFT` inconsistently, the character itself already tells whether shift was held.
+pub fn normalize(key: KeyEvent) -> KeyEvent {
+    let modifiers = match key.code {
+        KeyCode::Char(_) => key.modifiers - KeyModifiers::SHIFT,
+        _ => key.modifiers,
+    };
+    KeyEvent::new(key.code, modifiers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    fn keys(notation: &str) -> Vec<KeyEvent> {
+        parse_keys(notation, '\\')
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(keys("dd"), [plain_key('d'), plain_key('d')]);
+        assert_eq!(
+            keys(":w<CR>"),
+            [
+                plain_key(':'),
+                plain_key('w'),
+                key(KeyCode::Enter, KeyModifiers::NONE)
+            ]
+        );
+        assert_eq!(
+            keys("<C-x><esc><Space>"),
+            [
+                key(KeyCode::Char('x'), KeyModifiers::CONTROL),
+                key(KeyCode::Esc, KeyModifiers::NONE),
+                plain_key(' ')
+            ]
+        );
+        assert_eq!(keys("<S-a>"), [plain_key('A')]);
+        assert_eq!(
+            keys("<lt>x>"),
+            [plain_key('<'), plain_key('x'), plain_key('>')]
+        );
+        assert_eq!(keys("<foo>").len(), 5);
+        assert_eq!(keys("<Leader>w"), [plain_key('\\'), plain_key('w')]);
+        assert_eq!(parse_keys("<leader>", ' '), [plain_key(' ')]);
+    }
+
+    #[test]
+    fn test_key_notation_round_trips() {
+        for notation in ["dd", ":w<CR>", "<C-x><Esc><Space>", "<lt>a<M-CR><F5>"] {
+            assert_eq!(key_notation(&keys(notation)), notation);
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut keymap = Keymap::default();
+        let mapping = |rhs| Mapping {
+            rhs: keys(rhs),
+            noremap: true,
+        };
+        keymap.insert(MapMode::Normal, keys("g"), mapping("x"));
+        keymap.insert(MapMode::Normal, keys("gq"), mapping("y"));
+        keymap.insert(MapMode::Normal, keys("gr"), mapping("z"));
+        let lookup = |typed| keymap.lookup(MapMode::Normal, &keys(typed));
+
+        assert_eq!(lookup("gq"), Some((2, &mapping("y"))));
+        assert_eq!(lookup("gx"), Some((1, &mapping("x"))));
+        assert_eq!(lookup("x"), None);
+        assert!(keymap.is_pending(MapMode::Normal, &keys("g")));
+        assert!(!keymap.is_pending(MapMode::Normal, &keys("gq")));
+        assert!(!keymap.is_pending(MapMode::Insert, &keys("g")));
+        let continuations = keymap.continuations(MapMode::Normal, &keys("g"));
+        assert_eq!(
+            continuations,
+            [
+                (&keys("q")[..], &mapping("y")),
+                (&keys("r")[..], &mapping("z"))
+            ]
+        );
+
+        assert!(keymap.remove(MapMode::Normal, &keys("gq")));
+        assert!(!keymap.remove(MapMode::Normal, &keys("gq")));
+        assert_eq!(keymap.continuations(MapMode::Normal, &keys("g")).len(), 1);
+    }
+}
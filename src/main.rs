@@ -1,12 +1,40 @@
 #![allow(dead_code, unused_variables)]
+mod alternate;
 mod bars;
 mod buffer;
+mod clipboard;
 mod common;
+mod completion;
+mod config;
 mod cursor;
+mod diff;
+mod digraph;
 mod editor;
+mod encoding;
 mod error;
+mod ex;
+mod explore;
+mod expression;
+mod extension;
+mod filetype;
+mod fold;
+mod grapheme;
+mod keymap;
+mod multicursor;
+mod register;
+mod script;
+mod search;
+mod sign;
+mod surround;
+mod swap;
+mod terminal;
+#[cfg(test)]
+mod testing;
+mod textobject;
+mod theme;
+mod transform;
 mod viewport;
-use std::{fs::File, panic};
+use std::{fs::File, panic, path::Path};
 
 use buffer::VecBuffer;
 use clap::Parser;
@@ -26,9 +54,37 @@ struct Cli {
     #[arg(short = 't', long)]
     test: bool,
 
-    // Read File on given path, this argument is the default argument being passed
-    #[arg(default_value = "")]
-    file: String,
+    // Read File on given path, this argument is the default argument being passed. The line to
+    // open at is given as `+N` in front of the path or as its `:N` suffix, `+` opens at the end.
+    #[arg(num_args = 0..=2)]
+    file: Vec<String>,
+}
+
+impl Cli {
+    /// The path to open together with the line to place the cursor on, counted from zero
+    fn target(&self) -> (String, Option<usize>) {
+        let mut path = String::new();
+        let mut line = None;
+        for arg in &self.file {
+            match arg.strip_prefix('+') {
+                Some("") => line = Some(usize::MAX),
+                Some(number) if number.parse::<usize>().is_ok() => {
+                    line = number.parse::<usize>().ok().map(|n| n.saturating_sub(1));
+                }
+                _ => path = arg.clone(),
+            }
+        }
+        // A path actually containing the suffix is opened as is
+        if let Some((file, number)) = path.rsplit_once(':') {
+            if let Ok(number) = number.parse::<usize>() {
+                if !Path::new(&path).exists() {
+                    line = Some(number.saturating_sub(1));
+                    path = file.to_string();
+                }
+            }
+        }
+        (path, line)
+    }
 }
 
 fn main() {
@@ -68,28 +124,49 @@ fn initialize_editor(cli: &Cli) -> Editor<VecBuffer> {
         return new_from_file(&"./test_file.neotext".into());
     }
 
-    if cli.file.is_empty() {
+    let (path, line) = cli.target();
+    let mut editor = if path.is_empty() {
         editor::Editor::new(VecBuffer::new(vec![" ".to_string()]), false)
     } else {
-        new_from_file(&cli.file.clone().into())
+        new_from_file(&path.into())
+    };
+    if let Some(line) = line {
+        if let Err(e) = editor.goto(LineCol { line, col: 0 }) {
+            error!("Couldn't open at line {}: {:?}", line, e);
+        }
     }
+    editor
 }
 
 pub fn new_from_file(p: &std::path::PathBuf) -> Editor<VecBuffer> {
+    if p.is_dir() {
+        let mut editor = Editor::new(VecBuffer::new(vec![" ".to_string()]), false);
+        if let Err(e) = editor.explore(p) {
+            panic!("Invalid path: {:?}, exception: {}", p, e);
+        }
+        return editor;
+    }
     let content = match std::fs::read(p) {
         Err(e) => panic!("Invalid path: {:?}, exception: {}", p, e),
         Ok(content) => content,
     };
-    Editor::new(
-        VecBuffer::new(
-            String::from_utf8(content)
-                .expect("Invalid utf8 file")
-                .lines()
-                .map(String::from)
-                .collect(),
-        ),
+    // Invalid bytes are replaced rather than refusing the file, `:set fileencoding` rereads it
+    let (text, invalid) = encoding::decode(&content, encoding::DEFAULT_ENCODING);
+    if invalid {
+        bars::force_notif_bar_content(format!(
+            "{} isn't valid UTF-8, set fileencoding to reread it",
+            p.display()
+        ));
+    }
+    let mut editor = Editor::new(
+        VecBuffer::new(text.lines().map(String::from).collect()),
         false,
-    )
+    );
+    editor.set_path(p.clone());
+    if invalid {
+        editor.set_conversion_error();
+    }
+    editor
 }
 fn setup_tracing(debug: bool) {
     let filter = EnvFilter::try_new("info, neotext = trace, crossterm = off")
@@ -118,3 +195,28 @@ fn setup_tracing(debug: bool) {
         subscriber.init();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(args: &[&str]) -> (String, Option<usize>) {
+        Cli::parse_from(std::iter::once("neotext").chain(args.iter().copied())).target()
+    }
+
+    #[test]
+    fn test_cli_target_line() {
+        assert_eq!(target(&[]), (String::new(), None));
+        assert_eq!(target(&["src/lib.rs"]), ("src/lib.rs".to_string(), None));
+        assert_eq!(
+            target(&["+42", "src/lib.rs"]),
+            ("src/lib.rs".to_string(), Some(41))
+        );
+        assert_eq!(
+            target(&["src/lib.rs:7"]),
+            ("src/lib.rs".to_string(), Some(6))
+        );
+        assert_eq!(target(&["+", "a"]), ("a".to_string(), Some(usize::MAX)));
+        assert_eq!(target(&["a:b"]), ("a:b".to_string(), None));
+    }
+}
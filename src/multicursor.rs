@@ -0,0 +1,147 @@
+//! Additional cursors placed with `Ctrl-N` at the next occurrences of the word under the cursor.
+//! Text typed in insert mode, and deleted with Backspace, is edited at all cursors at once.
+use std::ops::Range;
+
+use crate::{textobject::is_whole_word, LineCol};
+
+/// An edit made at every cursor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CursorEdit {
+    Insert(char),
+    /// Deleting the character in front of the cursor, nothing at the start of a line
+    DeleteBefore,
+}
+
+/// Makes the edit at all `cursors`, which are moved along with the text.
+///
+/// # Returns
+///
+/// The range of lines touched, the lines replacing them and the new cursor positions in the order
+/// of `cursors`. Cursors outside of the text are left where they are.
+pub fn apply(
+    lines: &[String],
+    cursors: &[LineCol],
+    edit: CursorEdit,
+) -> (Range<usize>, Vec<String>, Vec<LineCol>) {
+    let valid = |pos: &LineCol| {
+        lines
+            .get(pos.line)
+            .is_some_and(|line| line.is_char_boundary(pos.col))
+    };
+    let touched = cursors.iter().filter(|pos| valid(pos)).map(|pos| pos.line);
+    let (Some(first), Some(last)) = (touched.clone().min(), touched.max()) else {
+        return (0..0, Vec::new(), cursors.to_vec());
+    };
+
+    let mut moved = cursors.to_vec();
+    let mut edited = Vec::new();
+    for (line, text) in lines.iter().enumerate().take(last + 1).skip(first) {
+        // Indices of the cursors on the line, from left to right
+        let mut on_line: Vec<usize> = (0..cursors.len())
+            .filter(|&i| cursors[i].line == line && valid(&cursors[i]))
+            .collect();
+        on_line.sort_by_key(|&i| cursors[i].col);
+
+        let mut result = String::with_capacity(text.len());
+        let mut written = 0;
+        for i in on_line {
+            let col = cursors[i].col;
+            match edit {
+                CursorEdit::Insert(ch) => {
+                    result.push_str(&text[written..col]);
+                    result.push(ch);
+                    written = col;
+                }
+                CursorEdit::DeleteBefore => {
+                    let start = text[written..col]
+                        .char_indices()
+                        .next_back()
+                        .map_or(col, |(offset, _)| written + offset);
+                    result.push_str(&text[written..start]);
+                    written = col;
+                }
+            }
+            moved[i].col = result.len();
+        }
+        result.push_str(&text[written..]);
+        edited.push(result);
+    }
+    (first..last + 1, edited, moved)
+}
+
+/// Start of the next occurrence of `word` as a whole word after `from`, wrapping around the end of
+/// the text. Occurrences starting at one of the `taken` positions are skipped.
+pub fn next_match(
+    lines: &[String],
+    word: &str,
+    from: LineCol,
+    taken: &[LineCol],
+    iskeyword: &str,
+) -> Option<LineCol> {
+    if word.is_empty() {
+        return None;
+    }
+    let mut found: Vec<LineCol> = Vec::new();
+    for (line, text) in lines.iter().enumerate() {
+        found.extend(
+            text.match_indices(word)
+                .filter(|&(col, _)| is_whole_word(text, col..col + word.len(), iskeyword))
+                .map(|(col, _)| LineCol { line, col }),
+        );
+    }
+    found.retain(|pos| !taken.contains(pos));
+    found
+        .iter()
+        .find(|&&pos| pos > from)
+        .or_else(|| found.first())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_apply_insert_and_delete() {
+        let text = lines(&["ab ab", "x", "ab"]);
+        let cursors = [
+            LineCol { line: 0, col: 3 },
+            LineCol { line: 0, col: 0 },
+            LineCol { line: 2, col: 0 },
+        ];
+        let (range, edited, moved) = apply(&text, &cursors, CursorEdit::Insert('é'));
+        assert_eq!(range, 0..3);
+        assert_eq!(edited, ["éab éab", "x", "éab"]);
+        assert_eq!(
+            moved,
+            [
+                LineCol { line: 0, col: 7 },
+                LineCol { line: 0, col: 2 },
+                LineCol { line: 2, col: 2 },
+            ]
+        );
+
+        let (_, edited, moved) = apply(&edited, &moved, CursorEdit::DeleteBefore);
+        assert_eq!(edited, text);
+        assert_eq!(moved, cursors);
+
+        let (_, edited, _) = apply(&text, &cursors, CursorEdit::DeleteBefore);
+        assert_eq!(edited, ["abab", "x", "ab"]);
+    }
+
+    #[test]
+    fn test_next_match() {
+        let text = lines(&["foo foobar foo", "bar_foo foo"]);
+        let start = LineCol { line: 0, col: 0 };
+        let second = next_match(&text, "foo", start, &[start], "_");
+        assert_eq!(second, Some(LineCol { line: 0, col: 11 }));
+        let third = next_match(&text, "foo", second.unwrap(), &[start], "_");
+        assert_eq!(third, Some(LineCol { line: 1, col: 8 }));
+        let taken = [start, second.unwrap(), third.unwrap()];
+        assert_eq!(next_match(&text, "foo", third.unwrap(), &taken, "_"), None);
+    }
+}
@@ -0,0 +1,206 @@
+//! Storage for yanked and deleted text, addressed by single characters like in vim.
+use std::collections::HashMap;
+
+use crate::clipboard::{self, Clipboard, CLIPBOARD_REGISTER};
+
+/// Always holds the most recently yanked or deleted text
+pub const UNNAMED_REGISTER: char = '"';
+/// Holds the most recent yank that didn't name a register
+pub const YANK_REGISTER: char = '0';
+/// Holds the most recent delete within a single line that didn't name a register
+pub const SMALL_DELETE_REGISTER: char = '-';
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegisterContent {
+    /// The stored text split into lines, text taken from within a single line is a single line
+    pub lines: Vec<String>,
+    /// Whether whole lines were taken, linewise content is pasted as new lines
+    pub linewise: bool,
+}
+
+impl RegisterContent {
+    pub fn new(lines: Vec<String>, linewise: bool) -> Self {
+        Self { lines, linewise }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Registers {
+    content: HashMap<char, RegisterContent>,
+    /// The system clipboard behind the `+` register, without one it's an ordinary register
+    clipboard: Option<Box<dyn Clipboard>>,
+    /// Whether yanks, deletes and pastes without a register go through the clipboard register
+    pub unnamedplus: bool,
+}
+
+impl Registers {
+    pub fn with_clipboard(clipboard: Box<dyn Clipboard>) -> Self {
+        Self {
+            clipboard: Some(clipboard),
+            ..Self::default()
+        }
+    }
+
+    pub fn get(&self, name: char) -> Option<&RegisterContent> {
+        self.content.get(&name)
+    }
+
+    /// Stores yanked text in the named register, or the yank register `0` if none was given.
+    /// The unnamed register always receives a copy, as does the clipboard with `unnamedplus`.
+    pub fn yank(&mut self, name: Option<char>, content: RegisterContent) {
+        if name.is_none() && self.unnamedplus {
+            self.store(CLIPBOARD_REGISTER, content.clone());
+        }
+        self.store(name.unwrap_or(YANK_REGISTER), content);
+    }
+
+    /// Stores deleted text in the named register. Without a name, deletes within a line go to the
+    /// small delete register `-`, while larger deletes shift through the numbered registers `1`-`9`.
+    /// The unnamed register always receives a copy, as does the clipboard with `unnamedplus`.
+    pub fn delete(&mut self, name: Option<char>, content: RegisterContent) {
+        if name.is_none() && self.unnamedplus {
+            self.store(CLIPBOARD_REGISTER, content.clone());
+        }
+        match name {
+            Some(name) => self.store(name, content),
+            None if !content.linewise && content.lines.len() == 1 => {
+                self.store(SMALL_DELETE_REGISTER, content)
+            }
+            None => {
+                for n in (1..9).rev() {
+                    let from = char::from_digit(n, 10).expect("Single digit");
+                    let to = char::from_digit(n + 1, 10).expect("Single digit");
+                    if let Some(shifted) = self.content.remove(&from) {
+                        self.content.insert(to, shifted);
+                    }
+                }
+                self.store('1', content)
+            }
+        }
+    }
+
+    /// The register a paste from `name` reads, the clipboard register for `"+` and, with
+    /// `unnamedplus`, for pastes without a register. Text put on the clipboard outside the editor
+    /// is taken into the register first, the clipboard being read only here.
+    pub fn paste_source(&mut self, name: char) -> char {
+        if name != CLIPBOARD_REGISTER && !(name == UNNAMED_REGISTER && self.unnamedplus) {
+            return name;
+        }
+        let text = self
+            .clipboard
+            .as_mut()
+            .and_then(|clipboard| clipboard.read());
+        if let Some(text) = text.filter(|text| !text.is_empty()) {
+            // Unchanged text keeps the register linewise or not, as it was yanked
+            let stored = self
+                .content
+                .get(&CLIPBOARD_REGISTER)
+                .map(clipboard::to_text);
+            if stored.as_ref() != Some(&text) {
+                self.content
+                    .insert(CLIPBOARD_REGISTER, clipboard::from_text(&text));
+            }
+        }
+        match self.content.contains_key(&CLIPBOARD_REGISTER) {
+            true => CLIPBOARD_REGISTER,
+            false => name,
+        }
+    }
+
+    fn store(&mut self, name: char, content: RegisterContent) {
+        if let (CLIPBOARD_REGISTER, Some(clipboard)) = (name, &mut self.clipboard) {
+            clipboard.write(&clipboard::to_text(&content));
+        }
+        if name != UNNAMED_REGISTER {
+            self.content.insert(UNNAMED_REGISTER, content.clone());
+        }
+        self.content.insert(name, content);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    fn content(text: &str, linewise: bool) -> RegisterContent {
+        RegisterContent::new(text.lines().map(String::from).collect(), linewise)
+    }
+
+    #[test]
+    fn test_yank_fills_yank_and_unnamed_register() {
+        let mut registers = Registers::default();
+        registers.yank(None, content("word", false));
+        assert_eq!(registers.get(YANK_REGISTER), Some(&content("word", false)));
+        assert_eq!(
+            registers.get(UNNAMED_REGISTER),
+            Some(&content("word", false))
+        );
+    }
+
+    #[test]
+    fn test_delete_shifts_numbered_registers() {
+        let mut registers = Registers::default();
+        registers.delete(None, content("first", true));
+        registers.delete(None, content("second", true));
+        registers.delete(None, content("small", false));
+        assert_eq!(registers.get('1'), Some(&content("second", true)));
+        assert_eq!(registers.get('2'), Some(&content("first", true)));
+        assert_eq!(
+            registers.get(SMALL_DELETE_REGISTER),
+            Some(&content("small", false))
+        );
+        assert_eq!(
+            registers.get(UNNAMED_REGISTER),
+            Some(&content("small", false))
+        );
+    }
+
+    /// Clipboard kept in memory, shared with the test to look at and change it
+    #[derive(Debug, Default)]
+    struct MemoryClipboard(Rc<RefCell<Option<String>>>);
+
+    impl Clipboard for MemoryClipboard {
+        fn read(&mut self) -> Option<String> {
+            self.0.borrow().clone()
+        }
+        fn write(&mut self, text: &str) {
+            *self.0.borrow_mut() = Some(text.to_string());
+        }
+    }
+
+    #[test]
+    fn test_unnamedplus_goes_through_clipboard() {
+        let text = Rc::default();
+        let mut registers = Registers::with_clipboard(Box::new(MemoryClipboard(Rc::clone(&text))));
+        registers.yank(None, content("internal", false));
+        assert_eq!(*text.borrow(), None);
+        assert_eq!(registers.paste_source(UNNAMED_REGISTER), UNNAMED_REGISTER);
+
+        registers.unnamedplus = true;
+        registers.delete(None, content("line", true));
+        assert_eq!(text.borrow().as_deref(), Some("line\n"));
+        assert_eq!(registers.paste_source(UNNAMED_REGISTER), CLIPBOARD_REGISTER);
+        assert_eq!(
+            registers.get(CLIPBOARD_REGISTER),
+            Some(&content("line", true))
+        );
+
+        *text.borrow_mut() = Some("copied elsewhere".to_string());
+        assert_eq!(registers.paste_source(UNNAMED_REGISTER), CLIPBOARD_REGISTER);
+        assert_eq!(
+            registers.get(CLIPBOARD_REGISTER),
+            Some(&content("copied elsewhere", false))
+        );
+        assert_eq!(registers.paste_source('a'), 'a');
+    }
+
+    #[test]
+    fn test_named_register() {
+        let mut registers = Registers::default();
+        registers.yank(Some('a'), content("named", false));
+        assert_eq!(registers.get('a'), Some(&content("named", false)));
+        assert_eq!(registers.get(YANK_REGISTER), None);
+    }
+}
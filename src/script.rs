@@ -0,0 +1,266 @@
+//! Scripting of the editor in rhai. Scripts run by `:script` and `:source` define Ex commands and
+//! mappings, calling back into a small API:
+//!
+//! - `insert_text(text)` inserts text at the cursor
+//! - `cursor_position()` returns the cursor as `#{ line, col }`, counted from zero
+//! - `go_to(line, col)` moves the cursor there, clamped to the text (`goto` is a rhai keyword)
+//! - `notify(message)` shows a message in the notification bar
+//! - `command(name, Fn("callback"))` defines `:name`, which calls the callback with its arguments
+//!   if the callback takes a parameter. Like in vim, the name has to start with a capital letter.
+//! - `map(mode, lhs, rhs)` maps keys like `:{mode}noremap lhs rhs`, `""` maps in every mode
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, Map, Scope, AST, INT};
+
+use crate::{Error, LineCol, Result};
+
+/// Operations a single run may take, which stops scripts stuck in a loop
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Requests of a script to the editor, carried out in order once the script has run
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Request {
+    InsertText(String),
+    Goto(LineCol),
+    Notify(String),
+    Map {
+        mode: String,
+        lhs: String,
+        rhs: String,
+    },
+}
+
+/// State shared with the functions exposed to scripts
+#[derive(Debug, Default)]
+struct State {
+    /// The cursor position when the script started, moved along by `go_to`
+    cursor: LineCol,
+    requests: Vec<Request>,
+    /// Commands defined by scripts, mapping their names to the called functions
+    commands: HashMap<String, String>,
+}
+
+/// Runs scripts and the commands they define, keeping the functions and variables of every script
+/// run so far
+pub struct ScriptHost {
+    engine: Engine,
+    scope: Scope<'static>,
+    /// Functions defined by the scripts run so far
+    functions: AST,
+    state: Rc<RefCell<State>>,
+}
+
+impl std::fmt::Debug for ScriptHost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptHost")
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ScriptHost {
+    fn default() -> Self {
+        let state = Rc::new(RefCell::new(State::default()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let shared = Rc::clone(&state);
+        engine.register_fn("insert_text", move |text: &str| {
+            shared
+                .borrow_mut()
+                .requests
+                .push(Request::InsertText(text.to_string()));
+        });
+        let shared = Rc::clone(&state);
+        engine.register_fn("cursor_position", move || {
+            let cursor = shared.borrow().cursor;
+            let mut position = Map::new();
+            let _ = position.insert("line".into(), Dynamic::from(cursor.line as INT));
+            let _ = position.insert("col".into(), Dynamic::from(cursor.col as INT));
+            position
+        });
+        let shared = Rc::clone(&state);
+        engine.register_fn("go_to", move |line: INT, col: INT| {
+            let target = LineCol {
+                line: line.max(0) as usize,
+                col: col.max(0) as usize,
+            };
+            let mut state = shared.borrow_mut();
+            state.cursor = target;
+            state.requests.push(Request::Goto(target));
+        });
+        let shared = Rc::clone(&state);
+        engine.register_fn("notify", move |message: &str| {
+            shared
+                .borrow_mut()
+                .requests
+                .push(Request::Notify(message.to_string()));
+        });
+        let shared = Rc::clone(&state);
+        engine.register_fn(
+            "command",
+            move |name: &str, callback: FnPtr| -> std::result::Result<(), Box<EvalAltResult>> {
+                if !name.starts_with(|ch: char| ch.is_ascii_uppercase())
+                    || !name.chars().all(|ch| ch.is_ascii_alphanumeric())
+                {
+                    return Err(format!("Invalid command name: {name}").into());
+                }
+                let _ = shared
+                    .borrow_mut()
+                    .commands
+                    .insert(name.to_string(), callback.fn_name().to_string());
+                Ok(())
+            },
+        );
+        let shared = Rc::clone(&state);
+        engine.register_fn("map", move |mode: &str, lhs: &str, rhs: &str| {
+            shared.borrow_mut().requests.push(Request::Map {
+                mode: mode.to_string(),
+                lhs: lhs.to_string(),
+                rhs: rhs.to_string(),
+            });
+        });
+
+        Self {
+            engine,
+            scope: Scope::new(),
+            functions: AST::empty(),
+            state,
+        }
+    }
+}
+
+impl ScriptHost {
+    /// Runs a script with the cursor at `cursor`, returning the requests it made
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Script` if the script doesn't compile or fails while running, the requests
+    /// made up to the failure are dropped.
+    pub fn run(&mut self, script: &str, cursor: LineCol) -> Result<Vec<Request>> {
+        let ast = self.engine.compile(script).map_err(script_error)?;
+        // The script can call the functions of earlier scripts, and later ones its functions
+        let mut program = self.functions.clone_functions_only();
+        let _ = program.combine(ast);
+
+        self.state.borrow_mut().cursor = cursor;
+        let result = self.engine.run_ast_with_scope(&mut self.scope, &program);
+        let requests = std::mem::take(&mut self.state.borrow_mut().requests);
+        result.map_err(script_error)?;
+        self.functions = program.clone_functions_only();
+        Ok(requests)
+    }
+
+    /// Whether a script defined the command `name`
+    pub fn has_command(&self, name: &str) -> bool {
+        self.state.borrow().commands.contains_key(name)
+    }
+
+    /// Runs the command `name` defined by a script, returning the requests it made
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Script` if the command isn't defined or its function fails.
+    pub fn call_command(
+        &mut self,
+        name: &str,
+        args: &str,
+        cursor: LineCol,
+    ) -> Result<Vec<Request>> {
+        let function = self.state.borrow().commands.get(name).cloned();
+        let Some(function) = function else {
+            return Err(Error::Script(format!("Not an editor command: {name}")));
+        };
+        let takes_args = self
+            .functions
+            .iter_functions()
+            .any(|f| f.name == function && f.params.len() == 1);
+
+        self.state.borrow_mut().cursor = cursor;
+        let result = match takes_args {
+            true => self.engine.call_fn::<Dynamic>(
+                &mut self.scope,
+                &self.functions,
+                &function,
+                (args.to_string(),),
+            ),
+            false => {
+                self.engine
+                    .call_fn::<Dynamic>(&mut self.scope, &self.functions, &function, ())
+            }
+        };
+        let requests = std::mem::take(&mut self.state.borrow_mut().requests);
+        let _ = result.map_err(script_error)?;
+        Ok(requests)
+    }
+}
+
+fn script_error(error: impl std::fmt::Display) -> Error {
+    Error::Script(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_script_requests() {
+        let mut host = ScriptHost::default();
+        let requests = host
+            .run(
+                r#"
+                let at = cursor_position();
+                go_to(at.line + 1, 0);
+                notify(`${cursor_position().line}`);
+                "#,
+                LineCol { line: 2, col: 4 },
+            )
+            .unwrap();
+        assert_eq!(
+            requests,
+            [
+                Request::Goto(LineCol { line: 3, col: 0 }),
+                Request::Notify("3".to_string())
+            ]
+        );
+        assert!(matches!(
+            host.run("go_to(", LineCol::default()),
+            Err(Error::Script(_))
+        ));
+        assert!(matches!(
+            host.run("loop {}", LineCol::default()),
+            Err(Error::Script(_))
+        ));
+    }
+
+    #[test]
+    fn test_script_commands() {
+        let mut host = ScriptHost::default();
+        host.run(
+            r#"
+            fn greet(name) { insert_text(`hello ${name}`); }
+            fn bye() { notify("bye"); }
+            command("Greet", Fn("greet"));
+            command("Bye", Fn("bye"));
+            "#,
+            LineCol::default(),
+        )
+        .unwrap();
+        assert!(host.has_command("Greet") && !host.has_command("greet"));
+        assert_eq!(
+            host.call_command("Greet", "you", LineCol::default())
+                .unwrap(),
+            [Request::InsertText("hello you".to_string())]
+        );
+        assert_eq!(
+            host.call_command("Bye", "", LineCol::default()).unwrap(),
+            [Request::Notify("bye".to_string())]
+        );
+        assert!(host
+            .run(r#"command("lower", Fn("bye"))"#, LineCol::default())
+            .is_err());
+        assert!(host
+            .call_command("Missing", "", LineCol::default())
+            .is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Patterns typed after `/` and `?`. They match literally, except that a leading `^` anchors them
+//! to the start of a line and a trailing `$` to its end. Preceded by a backslash, `^` and `$` match
+//! themselves.
+use std::ops::Range;
+
+use crate::{LineCol, Pattern};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchPattern {
+    text: String,
+    /// Whether matches have to start at the start of a line
+    start: bool,
+    /// Whether matches have to end at the end of a line
+    end: bool,
+}
+
+impl SearchPattern {
+    pub fn parse(query: &str) -> Self {
+        let (start, text) = match query.strip_prefix('^') {
+            Some(rest) => (true, rest),
+            None => (
+                false,
+                query
+                    .strip_prefix('\\')
+                    .filter(|rest| rest.starts_with('^'))
+                    .unwrap_or(query),
+            ),
+        };
+        let (end, text) = match text.strip_suffix("\\$") {
+            Some(rest) => (false, format!("{rest}$")),
+            None => match text.strip_suffix('$') {
+                Some(rest) => (true, rest.to_string()),
+                None => (false, text.to_string()),
+            },
+        };
+        Self { text, start, end }
+    }
+
+    /// Column of the first match within `line`, the last one with `last`
+    fn match_in(&self, line: &str, last: bool) -> Option<usize> {
+        match (self.start, self.end) {
+            (true, true) => (line == self.text).then_some(0),
+            (true, false) => line.starts_with(&self.text).then_some(0),
+            (false, true) => line.strip_suffix(self.text.as_str()).map(str::len),
+            (false, false) if last => line.rfind(&self.text),
+            (false, false) => line.find(&self.text),
+        }
+    }
+
+    /// Byte range of the match starting at `col` within `line`, if there is one
+    pub fn match_at(&self, line: &str, col: usize) -> Option<Range<usize>> {
+        let end = col + self.text.len();
+        let matched = line.get(col..end) == Some(self.text.as_str())
+            && (!self.start || col == 0)
+            && (!self.end || end == line.len());
+        matched.then_some(col..end)
+    }
+
+    /// Byte ranges of the matches within `line`
+    pub fn matches(&self, line: &str) -> Vec<Range<usize>> {
+        if self.is_anchored() {
+            return self
+                .match_in(line, false)
+                .map(|col| col..col + self.text.len())
+                .into_iter()
+                .collect();
+        }
+        line.match_indices(&self.text)
+            .map(|(col, found)| col..col + found.len())
+            .collect()
+    }
+}
+
+impl Pattern for SearchPattern {
+    fn find_pattern(&self, haystack: &[String]) -> Option<LineCol> {
+        haystack.iter().enumerate().find_map(|(line, content)| {
+            self.match_in(content, false)
+                .map(|col| LineCol { line, col })
+        })
+    }
+    fn rfind_pattern(&self, haystack: &[String]) -> Option<LineCol> {
+        haystack
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(line, content)| {
+                self.match_in(content, true)
+                    .map(|col| LineCol { line, col })
+            })
+    }
+    fn is_anchored(&self) -> bool {
+        self.start || self.end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_anchors() {
+        let pattern = SearchPattern::parse("^foo");
+        assert!(pattern.start && !pattern.end);
+        assert_eq!(pattern.text, "foo");
+        let pattern = SearchPattern::parse("bar$");
+        assert!(!pattern.start && pattern.end);
+        assert_eq!(SearchPattern::parse("\\^a\\$").text, "^a$");
+        assert!(!SearchPattern::parse("\\^a\\$").is_anchored());
+    }
+
+    #[test]
+    fn test_anchored_matches() {
+        let start = SearchPattern::parse("^ab");
+        assert_eq!(start.matches("abab"), vec![0..2]);
+        assert!(start.matches(" ab").is_empty());
+        let end = SearchPattern::parse("ab$");
+        assert_eq!(end.matches("abab"), vec![2..4]);
+        assert_eq!(SearchPattern::parse("^ab$").matches("ab"), vec![0..2]);
+        assert_eq!(SearchPattern::parse("ab").matches("abab"), [0..2, 2..4]);
+    }
+
+    #[test]
+    fn test_match_at() {
+        assert_eq!(SearchPattern::parse("aa").match_at("baaa", 2), Some(2..4));
+        assert_eq!(SearchPattern::parse("aa").match_at("baaa", 3), None);
+        assert_eq!(SearchPattern::parse("^aa").match_at("baaa", 1), None);
+        assert_eq!(SearchPattern::parse("aa$").match_at("baaa", 1), None);
+    }
+
+    #[test]
+    fn test_all_matches() {
+        let lines = ["aaa".to_string(), "baa".to_string()];
+        let at = |line, col| LineCol { line, col };
+        assert_eq!(
+            SearchPattern::parse("aa").all_matches(&lines),
+            [at(0, 0), at(0, 1), at(1, 1)]
+        );
+        assert_eq!(SearchPattern::parse("^aa").all_matches(&lines), [at(0, 0)]);
+        assert_eq!(
+            SearchPattern::parse("aa$").all_matches(&lines),
+            [at(0, 1), at(1, 1)]
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! Signs marking lines in the gutter, like vim's `:sign`. They are placed through the editor's API,
+//! for linters and other tools to point at lines, and jumped between with `]d` and `[d`. With
+//! `changesigns` the editor places signs of its own on the lines differing from the file on disk.
+use std::collections::BTreeMap;
+
+use crossterm::style::Color;
+
+/// What a sign points out, deciding its symbol and color
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignKind {
+    Error,
+    Warning,
+    Info,
+    /// A `TODO`, `FIXME` or `XXX` comment found by `:todo`
+    Todo,
+    /// A line missing from the file on disk
+    Added,
+    /// A line differing from its counterpart in the file on disk
+    Changed,
+    /// Lines of the file on disk deleted below this line
+    Removed,
+}
+
+impl SignKind {
+    /// Kinds jumped between by `]d` and `[d`
+    pub const DIAGNOSTICS: &'static [Self] = &[Self::Error, Self::Warning, Self::Info];
+    /// Kinds jumped between by `]t` and `[t`
+    pub const TODOS: &'static [Self] = &[Self::Todo];
+    /// Kinds marking the unsaved changes, placed with `changesigns`
+    pub const CHANGES: &'static [Self] = &[Self::Added, Self::Changed, Self::Removed];
+
+    /// The character drawn in the gutter
+    pub const fn symbol(self) -> char {
+        match self {
+            Self::Error => 'E',
+            Self::Warning => 'W',
+            Self::Info => 'I',
+            Self::Todo => 'T',
+            Self::Added => '+',
+            Self::Changed => '~',
+            Self::Removed => '-',
+        }
+    }
+
+    pub const fn color(self) -> Color {
+        match self {
+            Self::Error => Color::Red,
+            Self::Warning => Color::Yellow,
+            Self::Info => Color::Blue,
+            Self::Todo => Color::Magenta,
+            Self::Added => Color::Green,
+            Self::Changed => Color::Cyan,
+            Self::Removed => Color::Red,
+        }
+    }
+}
+
+/// The signs placed on the lines of the buffer, at most one per line
+#[derive(Clone, Debug, Default)]
+pub struct Signs {
+    signs: BTreeMap<usize, SignKind>,
+}
+
+impl Signs {
+    /// Places a sign on `line`, replacing the sign already there
+    pub fn place(&mut self, line: usize, kind: SignKind) {
+        let _ = self.signs.insert(line, kind);
+    }
+    /// Removes the sign of `line`, if any
+    pub fn unplace(&mut self, line: usize) {
+        let _ = self.signs.remove(&line);
+    }
+    /// Removes the signs of the given kinds
+    pub fn clear(&mut self, kinds: &[SignKind]) {
+        self.signs.retain(|_, kind| !kinds.contains(kind));
+    }
+    /// The sign on `line`
+    pub fn get(&self, line: usize) -> Option<SignKind> {
+        self.signs.get(&line).copied()
+    }
+    /// The closest line after (or before) `line` with a sign of one of the given kinds
+    pub fn next(&self, line: usize, forwards: bool, kinds: &[SignKind]) -> Option<usize> {
+        let matching = |(&line, kind): (&usize, &SignKind)| kinds.contains(kind).then_some(line);
+        match forwards {
+            true => self.signs.range(line + 1..).find_map(matching),
+            false => self.signs.range(..line).rev().find_map(matching),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_place_and_find_signs() {
+        let mut signs = Signs::default();
+        signs.place(4, SignKind::Warning);
+        signs.place(9, SignKind::Error);
+        signs.place(2, SignKind::Info);
+        signs.place(4, SignKind::Error);
+        assert_eq!(signs.get(4), Some(SignKind::Error));
+
+        let errors = &[SignKind::Error];
+        assert_eq!(signs.next(0, true, SignKind::DIAGNOSTICS), Some(2));
+        assert_eq!(signs.next(0, true, errors), Some(4));
+        assert_eq!(signs.next(4, true, errors), Some(9));
+        assert_eq!(signs.next(9, true, errors), None);
+        assert_eq!(signs.next(9, false, SignKind::DIAGNOSTICS), Some(4));
+
+        signs.unplace(4);
+        signs.clear(&[SignKind::Info]);
+        assert_eq!(signs.next(0, true, SignKind::DIAGNOSTICS), Some(9));
+    }
+}
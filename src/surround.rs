@@ -0,0 +1,171 @@
+//! Locating the delimiters around text for the surround commands `ys`, `cs` and `ds`.
+use std::ops::Range;
+
+use crate::{textobject::JoinedText, Error, LineCol, Result};
+
+/// Returns the bracket pair named by `ch`, `b` and `B` being aliases of `()` and `{}`.
+const fn bracket_pair(ch: char) -> Option<(char, char)> {
+    match ch {
+        '(' | ')' | 'b' => Some(('(', ')')),
+        '[' | ']' => Some(('[', ']')),
+        '{' | '}' | 'B' => Some(('{', '}')),
+        '<' | '>' => Some(('<', '>')),
+        _ => None,
+    }
+}
+
+/// Opening brackets pad the surrounded text with a space, closing brackets don't
+const fn is_padded(ch: char) -> bool {
+    matches!(ch, '(' | '[' | '{' | '<')
+}
+
+/// Returns the opening and closing delimiter `ch` surrounds text with. Any character not naming a
+/// bracket pair, like a quote, is used on both sides.
+pub fn delimiters(ch: char) -> (String, String) {
+    match bracket_pair(ch) {
+        Some((open, close)) if is_padded(ch) => (format!("{open} "), format!(" {close}")),
+        Some((open, close)) => (open.to_string(), close.to_string()),
+        None => (ch.to_string(), ch.to_string()),
+    }
+}
+
+/// Finds the innermost pair of delimiters named by `ch` enclosing `pos`.
+///
+/// Brackets are matched across lines while respecting nesting, other characters (quotes) are paired
+/// up from the start of the cursor line. If `ch` is an opening bracket, the whitespace inside the
+/// brackets is considered part of the delimiters.
+///
+/// # Returns
+///
+/// The spans of the opening and the closing delimiter.
+///
+/// # Errors
+///
+/// Returns `Error::PatternNotFound` if no such pair encloses the position.
+pub fn find(lines: &[String], pos: LineCol, ch: char) -> Result<(Range<LineCol>, Range<LineCol>)> {
+    let text = JoinedText::new(lines);
+    let (open, close) = match bracket_pair(ch) {
+        Some((open, close)) => find_brackets(&text.text, text.offset(pos), open, close),
+        None => {
+            let line_start = text.offset(LineCol {
+                line: pos.line,
+                col: 0,
+            });
+            find_quotes(&lines[pos.line], pos.col, ch)
+                .map(|(open, close)| (line_start + open, line_start + close))
+        }
+    }
+    .ok_or(Error::PatternNotFound)?;
+
+    let mut open_span = open..open + 1;
+    let mut close_span = close..close + 1;
+    if is_padded(ch) {
+        let bytes = text.text.as_bytes();
+        while open_span.end < close_span.start && matches!(bytes[open_span.end], b' ' | b'\t') {
+            open_span.end += 1;
+        }
+        while close_span.start > open_span.end
+            && matches!(bytes[close_span.start - 1], b' ' | b'\t')
+        {
+            close_span.start -= 1;
+        }
+    }
+    let to_linecol = |span: Range<usize>| text.linecol(span.start)..text.linecol(span.end);
+    Ok((to_linecol(open_span), to_linecol(close_span)))
+}
+
+/// Returns the byte offsets of the innermost bracket pair enclosing `cursor`
+fn find_brackets(text: &str, cursor: usize, open: char, close: char) -> Option<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let (open, close) = (open as u8, close as u8);
+    let cursor = cursor.min(bytes.len().checked_sub(1)?);
+
+    let mut depth = 0;
+    let mut opening = None;
+    for i in (0..=cursor).rev() {
+        if bytes[i] == open && depth == 0 {
+            opening = Some(i);
+            break;
+        } else if bytes[i] == open {
+            depth -= 1;
+        } else if bytes[i] == close && i != cursor {
+            depth += 1;
+        }
+    }
+    let opening = opening?;
+
+    let mut depth = 0;
+    for (i, &byte) in bytes.iter().enumerate().skip(opening + 1) {
+        if byte == open {
+            depth += 1;
+        } else if byte == close && depth == 0 {
+            return Some((opening, i));
+        } else if byte == close {
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// Returns the columns of the quote pair around `col`, quotes are paired up in order of appearance
+fn find_quotes(line: &str, col: usize, quote: char) -> Option<(usize, usize)> {
+    let quotes: Vec<usize> = line.match_indices(quote).map(|(i, _)| i).collect();
+    quotes
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|&(open, close)| open <= col && col <= close)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_cols(line: &str, col: usize, ch: char) -> Result<(Range<usize>, Range<usize>)> {
+        let (open, close) = find(&[line.to_string()], LineCol { line: 0, col }, ch)?;
+        Ok((open.start.col..open.end.col, close.start.col..close.end.col))
+    }
+
+    #[test]
+    fn test_delimiters() {
+        assert_eq!(delimiters('('), ("( ".to_string(), " )".to_string()));
+        assert_eq!(delimiters(')'), ("(".to_string(), ")".to_string()));
+        assert_eq!(delimiters('"'), ("\"".to_string(), "\"".to_string()));
+    }
+
+    #[test]
+    fn test_find_nested_brackets() {
+        let line = "f(a, (b), c)";
+        assert_eq!(find_cols(line, 3, ')').unwrap(), (1..2, 11..12));
+        assert_eq!(find_cols(line, 6, ')').unwrap(), (5..6, 7..8));
+        assert_eq!(find_cols(line, 11, ')').unwrap(), (1..2, 11..12));
+        assert!(matches!(
+            find_cols(line, 0, ')'),
+            Err(Error::PatternNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_find_opening_bracket_takes_inner_whitespace() {
+        assert_eq!(find_cols("[ x ]", 2, '[').unwrap(), (0..2, 3..5));
+        assert_eq!(find_cols("[ x ]", 2, ']').unwrap(), (0..1, 4..5));
+    }
+
+    #[test]
+    fn test_find_brackets_across_lines() {
+        let lines = ["fn f() {".to_string(), "    x".to_string(), "}".to_string()];
+        let (open, close) = find(&lines, LineCol { line: 1, col: 4 }, 'B').unwrap();
+        assert_eq!(open.start, LineCol { line: 0, col: 7 });
+        assert_eq!(close.start, LineCol { line: 2, col: 0 });
+    }
+
+    #[test]
+    fn test_find_quotes() {
+        let line = r#"say "hi" and "bye""#;
+        assert_eq!(find_cols(line, 5, '"').unwrap(), (4..5, 7..8));
+        assert_eq!(find_cols(line, 17, '"').unwrap(), (13..14, 17..18));
+        assert!(matches!(
+            find_cols(line, 10, '"'),
+            Err(Error::PatternNotFound)
+        ));
+    }
+}
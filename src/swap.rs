@@ -0,0 +1,74 @@
+//! Swap files keeping a copy of the edited text next to its file, like vim's `.name.swp`, so the
+//! changes can be recovered with `:recover` after the editor crashed.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::Result;
+
+/// Edits made in insert mode after which the swap file is written without leaving insert mode
+pub const UPDATE_COUNT: usize = 200;
+
+/// The swap file of `path`, a hidden file named after it in the same directory
+pub fn swap_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map_or_else(|| "noname".into(), |name| name.to_string_lossy());
+    path.with_file_name(format!(".{name}.swp"))
+}
+
+/// Whether a swap file of `path` exists
+pub fn exists(path: &Path) -> bool {
+    swap_path(path).is_file()
+}
+
+/// Writes `lines` to the swap file of `path`
+pub fn write(path: &Path, lines: &[String]) -> Result<()> {
+    fs::write(swap_path(path), lines.join("\n"))?;
+    Ok(())
+}
+
+/// Reads the lines kept in the swap file of `path`
+pub fn read(path: &Path) -> Result<Vec<String>> {
+    let content = fs::read_to_string(swap_path(path))?;
+    Ok(content.split('\n').map(String::from).collect())
+}
+
+/// Removes the swap file of `path`, a missing swap file is not an error
+pub fn remove(path: &Path) -> Result<()> {
+    match fs::remove_file(swap_path(path)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_swap_path() {
+        assert_eq!(
+            swap_path(Path::new("src/main.rs")),
+            Path::new("src/.main.rs.swp")
+        );
+        assert_eq!(swap_path(Path::new("notes")), Path::new(".notes.swp"));
+    }
+
+    #[test]
+    fn test_write_read_and_remove() {
+        let dir = TempDir::new("swap");
+        let path = dir.join("file.txt");
+        let lines = vec!["first".to_string(), String::new(), "third".to_string()];
+
+        assert!(!exists(&path));
+        write(&path, &lines).unwrap();
+        assert!(exists(&path));
+        assert_eq!(read(&path).unwrap(), lines);
+        remove(&path).unwrap();
+        assert!(!exists(&path));
+        remove(&path).unwrap();
+    }
+}
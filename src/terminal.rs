@@ -0,0 +1,49 @@
+//! Running the commands typed into the terminal plane.
+//!
+//! Every prompt line is handed to the user's shell on its own and its output is collected once the
+//! command exits. Interactive programs need a pseudo terminal, which isn't supported yet.
+use std::{env, process::Command};
+
+/// Shell used when `$SHELL` isn't set
+const DEFAULT_SHELL: &str = "sh";
+
+/// Runs `command` with the user's shell and returns the lines it printed, standard output first.
+/// A command which couldn't be started or exited unsuccessfully reports so in its output.
+pub fn run(command: &str) -> Vec<String> {
+    if command.trim().is_empty() {
+        return Vec::new();
+    }
+    let shell = env::var("SHELL").unwrap_or_else(|_| DEFAULT_SHELL.to_string());
+    let output = match Command::new(&shell).arg("-c").arg(command).output() {
+        Ok(output) => output,
+        Err(e) => return vec![format!("{shell}: {e}")],
+    };
+
+    let mut lines: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .chain(String::from_utf8_lossy(&output.stderr).lines())
+        .map(|line| line.replace('\t', "    "))
+        .collect();
+    if !output.status.success() {
+        lines.push(format!("[{}]", output.status));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_collects_output() {
+        assert_eq!(run("echo one; echo two >&2"), vec!["one", "two"]);
+        assert!(run("").is_empty());
+    }
+
+    #[test]
+    fn test_run_reports_failure() {
+        let lines = run("exit 3");
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains('3'));
+    }
+}
@@ -0,0 +1,35 @@
+//! Helpers shared by the tests of several modules.
+use std::{
+    env, fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+/// Directory a test works in, removed with everything in it once the guard is dropped, which
+/// also happens when an assertion of the test fails
+#[derive(Debug)]
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates the empty directory `neotext-{name}-{pid}` within the system's temporary directory
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("neotext-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
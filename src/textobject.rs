@@ -0,0 +1,730 @@
+//! Range computations for text objects and the motions sharing their scanning logic.
+use std::ops::Range;
+
+use crate::{Error, LineCol, Result};
+
+/// A span of the buffer an operator acts upon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextRange {
+    pub start: LineCol,
+    /// Exclusive end position for charwise ranges, for linewise ranges only the line matters
+    pub end: LineCol,
+    /// Whether the range covers the whole lines from `start.line` to `end.line`
+    pub linewise: bool,
+}
+
+impl TextRange {
+    pub const fn lines(start: usize, end: usize) -> Self {
+        Self {
+            start: LineCol {
+                line: start,
+                col: 0,
+            },
+            end: LineCol { line: end, col: 0 },
+            linewise: true,
+        }
+    }
+}
+
+/// Text objects selectable after an operator, `inner` excludes the surrounding whitespace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextObject {
+    /// `count` lines starting at the cursor line, used by doubled operators like `dd`
+    Line,
+    Word {
+        inner: bool,
+    },
+    Paragraph {
+        inner: bool,
+    },
+    Tag {
+        inner: bool,
+    },
+    /// The block of lines indented at least as deep as the cursor line, `ii` and `ai`. Around
+    /// additionally takes the less indented line in front of the block, like a Python `def`.
+    Indent {
+        inner: bool,
+    },
+    /// The `w` and `W` motions after an operator, from the cursor to the start of the word `count`
+    /// words ahead. `big` moves by runs of non-whitespace like `W`.
+    NextWord {
+        big: bool,
+    },
+    /// The `j` and `k` motions after an operator, the cursor line along with the `count` lines
+    /// below it, or above it if `up`
+    Vertical {
+        up: bool,
+    },
+    /// The `{count}%` motion after an operator, the lines from the cursor to the one `count`
+    /// percent through the file
+    Percent,
+}
+
+/// Lines containing only whitespace separate paragraphs
+pub fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+/// Returns the first and last line of the block of lines around `line` sharing its blankness.
+pub fn paragraph_block(lines: &[String], line: usize) -> (usize, usize) {
+    let blank = is_blank(&lines[line]);
+    let mut start = line;
+    while start > 0 && is_blank(&lines[start - 1]) == blank {
+        start -= 1;
+    }
+    let mut end = line;
+    while end + 1 < lines.len() && is_blank(&lines[end + 1]) == blank {
+        end += 1;
+    }
+    (start, end)
+}
+
+/// Computes the `ip`/`ap` text object around `line`.
+///
+/// The inner paragraph is the block of non-blank lines around the cursor, or the block of blank
+/// lines if the cursor sits on one. Around additionally takes the following block, falling back to
+/// the preceding block of blank lines when the paragraph is the last one in the buffer.
+pub fn paragraph(lines: &[String], line: usize, inner: bool) -> TextRange {
+    let (start, end) = paragraph_block(lines, line);
+    if inner {
+        return TextRange::lines(start, end);
+    }
+
+    if end + 1 < lines.len() {
+        let (_, next_end) = paragraph_block(lines, end + 1);
+        TextRange::lines(start, next_end)
+    } else if start > 0 && !is_blank(&lines[line]) {
+        let (previous_start, _) = paragraph_block(lines, start - 1);
+        TextRange::lines(previous_start, end)
+    } else {
+        TextRange::lines(start, end)
+    }
+}
+
+/// Finds the line the `}` (forwards) or `{` (backwards) motion lands on from `line`: the next blank
+/// line past the current paragraph, or the first/last line of the buffer if there is none.
+pub fn paragraph_boundary(lines: &[String], line: usize, forwards: bool) -> usize {
+    let last = lines.len().saturating_sub(1);
+    let step = |l: usize| if forwards { l + 1 } else { l - 1 };
+    let at_edge = |l: usize| if forwards { l >= last } else { l == 0 };
+
+    let mut current = line;
+    while !at_edge(current) && is_blank(&lines[current]) {
+        current = step(current);
+    }
+    while !at_edge(current) {
+        current = step(current);
+        if is_blank(&lines[current]) {
+            break;
+        }
+    }
+    current
+}
+
+/// Whether `line` looks like the start of a section for `]]` and `[[`: it starts at column 0 with
+/// anything but a closing bracket, right after a blank or indented line. This is a heuristic which
+/// finds functions and top level items in most languages without knowing about any of them.
+fn is_section_start(lines: &[String], line: usize) -> bool {
+    let starts_item = lines[line]
+        .chars()
+        .next()
+        .is_some_and(|ch| !ch.is_whitespace() && !matches!(ch, '}' | ')' | ']'));
+    starts_item && (line == 0 || is_blank(&lines[line - 1]) || indentation(&lines[line - 1]) > 0)
+}
+
+/// Finds the next (forwards) or previous section start from `line` for `]]` and `[[`, see
+/// [`is_section_start`]
+pub fn section_start(lines: &[String], line: usize, forwards: bool) -> Option<usize> {
+    match forwards {
+        true => (line + 1..lines.len()).find(|&l| is_section_start(lines, l)),
+        false => (0..line.min(lines.len()))
+            .rev()
+            .find(|&l| is_section_start(lines, l)),
+    }
+}
+
+/// Width of the leading whitespace of a line
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Computes the `ii`/`ai` text object around `line`.
+///
+/// The block spans the lines around the cursor indented at least as deep as it, blank lines within
+/// it included and blank lines at its edges left out. On a blank line the indentation of the next
+/// non-blank line counts. Returns `None` if there is no non-blank line to take it from.
+pub fn indent_block(lines: &[String], line: usize, inner: bool) -> Option<TextRange> {
+    let base = (line..lines.len())
+        .chain((0..line).rev())
+        .find(|&l| !is_blank(&lines[l]))?;
+    let level = indentation(&lines[base]);
+    let within = |l: usize| is_blank(&lines[l]) || indentation(&lines[l]) >= level;
+
+    let mut start = base;
+    while start > 0 && within(start - 1) {
+        start -= 1;
+    }
+    while is_blank(&lines[start]) {
+        start += 1;
+    }
+    let mut end = base;
+    while end + 1 < lines.len() && within(end + 1) {
+        end += 1;
+    }
+    while is_blank(&lines[end]) {
+        end -= 1;
+    }
+    if !inner && start > 0 {
+        start -= 1;
+    }
+    Some(TextRange::lines(start, end))
+}
+
+/// Characters keywords (words in the narrow sense) consist of: letters, digits and the characters
+/// of the `iskeyword` option
+pub fn is_keyword_char(ch: char, iskeyword: &str) -> bool {
+    ch.is_alphanumeric() || iskeyword.contains(ch)
+}
+
+/// Whether the byte range of `line` is a whole word, not preceded or followed by keyword characters
+pub fn is_whole_word(line: &str, range: Range<usize>, iskeyword: &str) -> bool {
+    let before = line[..range.start].chars().next_back();
+    let after = line[range.end..].chars().next();
+    !before.is_some_and(|ch| is_keyword_char(ch, iskeyword))
+        && !after.is_some_and(|ch| is_keyword_char(ch, iskeyword))
+}
+
+/// Computes the `iw`/`aw` text object under `pos`.
+///
+/// A word is a run of keyword characters, any other run of non-whitespace characters, or a run of
+/// whitespace. Around additionally takes the whitespace following the
+/// word, or the whitespace preceding it if the word ends the line.
+/// Returns `None` on an empty line.
+pub fn word(line: &str, pos: LineCol, inner: bool, iskeyword: &str) -> Option<TextRange> {
+    let (offsets, chars): (Vec<usize>, Vec<char>) = line.char_indices().unzip();
+    let col = offsets.iter().rposition(|&offset| offset <= pos.col)?;
+    let byte_col = |i: usize| offsets.get(i).copied().unwrap_or(line.len());
+    let class = |ch: char| {
+        if ch.is_whitespace() {
+            0
+        } else if is_keyword_char(ch, iskeyword) {
+            1
+        } else {
+            2
+        }
+    };
+    let run = |at: usize| {
+        let mut start = at;
+        while start > 0 && class(chars[start - 1]) == class(chars[at]) {
+            start -= 1;
+        }
+        let mut end = at + 1;
+        while end < chars.len() && class(chars[end]) == class(chars[at]) {
+            end += 1;
+        }
+        (start, end)
+    };
+
+    let (mut start, mut end) = run(col);
+    if !inner {
+        if end < chars.len() && chars[end].is_whitespace() != chars[col].is_whitespace() {
+            end = run(end).1;
+        } else if start > 0 && chars[start - 1].is_whitespace() {
+            start = run(start - 1).0;
+        }
+    }
+
+    Some(TextRange {
+        start: LineCol {
+            line: pos.line,
+            col: byte_col(start),
+        },
+        end: LineCol {
+            line: pos.line,
+            col: byte_col(end),
+        },
+        linewise: false,
+    })
+}
+
+/// Byte column of the start of the word in front of `col`, past the whitespace between them, which
+/// `Ctrl-W` deletes back to in insert mode. Zero if there is only whitespace in front of `col`.
+pub fn previous_word_start(line: &str, col: usize, iskeyword: &str) -> usize {
+    let class = |ch: char| match ch {
+        ch if ch.is_whitespace() => 0,
+        ch if is_keyword_char(ch, iskeyword) => 1,
+        _ => 2,
+    };
+    let before = line.get(..col).unwrap_or(line);
+    let mut chars = before
+        .char_indices()
+        .rev()
+        .skip_while(|(_, ch)| ch.is_whitespace())
+        .peekable();
+    let Some(&(_, last)) = chars.peek() else {
+        return 0;
+    };
+    chars
+        .take_while(|&(_, ch)| class(ch) == class(last))
+        .last()
+        .map_or(0, |(start, _)| start)
+}
+
+/// Byte column of the next word start after `col` on the line, `None` if no word follows on it
+pub fn next_word_start(line: &str, col: usize, big: bool, iskeyword: &str) -> Option<usize> {
+    let class = |ch: char| match ch {
+        ch if ch.is_whitespace() => 0,
+        ch if big || is_keyword_char(ch, iskeyword) => 1,
+        _ => 2,
+    };
+    let mut chars = line.get(col..)?.char_indices().peekable();
+    let (_, first) = *chars.peek()?;
+    if class(first) != 0 {
+        while chars
+            .next_if(|&(_, ch)| class(ch) == class(first))
+            .is_some()
+        {}
+    }
+    while chars.next_if(|&(_, ch)| ch.is_whitespace()).is_some() {}
+    chars.peek().map(|&(i, _)| col + i)
+}
+
+/// Computes the range `dw` acts upon, from `pos` to the start of the word `count` words ahead.
+///
+/// Like in vim, the newline ending a line is never part of the range when the last word moved
+/// over ends the line: `dw` on the last word of a line deletes up to the end of the line instead
+/// of joining the next line. Words crossed on the way to the last one may span lines, the range
+/// then ends at the start of the last word on a following line. Empty lines count as words.
+pub fn next_word(
+    lines: &[String],
+    pos: LineCol,
+    count: usize,
+    big: bool,
+    iskeyword: &str,
+) -> TextRange {
+    let mut end = pos;
+    for i in 1..=count.max(1) {
+        let line = &lines[end.line];
+        if let Some(col) = next_word_start(line, end.col, big, iskeyword) {
+            end.col = col;
+            continue;
+        }
+        // The last word moved over ends the line, or there is no line left to move to
+        if i == count.max(1) || end.line + 1 >= lines.len() {
+            end.col = line.len();
+            break;
+        }
+        let next = &lines[end.line + 1];
+        end = LineCol {
+            line: end.line + 1,
+            col: next.len() - next.trim_start().len(),
+        };
+    }
+    TextRange {
+        start: pos,
+        end,
+        linewise: false,
+    }
+}
+
+/// Shrinks a `dw` range to the end of its last word like vim does for `cw`, keeping the whitespace
+/// which followed the word
+pub fn trim_trailing_whitespace(lines: &[String], mut range: TextRange) -> TextRange {
+    loop {
+        let line = &lines[range.end.line];
+        match line[..range.end.col].chars().next_back() {
+            None if range.end.line > range.start.line => {
+                range.end.line -= 1;
+                range.end.col = lines[range.end.line].len();
+            }
+            Some(ch) if ch.is_whitespace() && range.end > range.start => {
+                range.end.col -= ch.len_utf8();
+            }
+            _ => return range,
+        }
+    }
+}
+
+/// Returns the byte range of the file path under `col`, a run of alphanumeric characters and any
+/// of `/`, `.`, `-`, `_` and `~`. Returns `None` if the character under `col` can't be part of a
+/// path.
+pub fn path(line: &str, col: usize) -> Option<Range<usize>> {
+    let is_path_char = |ch: char| ch.is_alphanumeric() || matches!(ch, '/' | '.' | '-' | '_' | '~');
+    let under = line.get(col..)?.chars().next()?;
+    if !is_path_char(under) {
+        return None;
+    }
+    let start = line[..col]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, ch)| is_path_char(ch))
+        .last()
+        .map_or(col, |(i, _)| i);
+    let end = line[col..]
+        .char_indices()
+        .find(|&(_, ch)| !is_path_char(ch))
+        .map_or(line.len(), |(i, _)| col + i);
+    Some(start..end)
+}
+
+/// Lines joined by newlines, for scans that cross line boundaries. Translates between positions in
+/// the buffer and byte offsets into the joined text.
+pub struct JoinedText {
+    pub text: String,
+    line_starts: Vec<usize>,
+}
+
+impl JoinedText {
+    pub fn new(lines: &[String]) -> Self {
+        let line_starts = lines
+            .iter()
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len() + 1;
+                Some(start)
+            })
+            .collect();
+        Self {
+            text: lines.join("\n"),
+            line_starts,
+        }
+    }
+
+    pub fn offset(&self, pos: LineCol) -> usize {
+        self.line_starts[pos.line] + pos.col
+    }
+
+    pub fn linecol(&self, offset: usize) -> LineCol {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        LineCol {
+            line,
+            col: offset - self.line_starts[line],
+        }
+    }
+}
+
+/// A markup tag found while scanning, spanning the byte offsets `start..end` of the joined text
+struct Tag<'a> {
+    name: &'a str,
+    closing: bool,
+    start: usize,
+    end: usize,
+}
+
+/// Scans the text for opening and closing tags, skipping comments, declarations and
+/// self-closing tags. A `<` not followed by a tag name is treated as text.
+fn scan_tags(text: &str) -> Result<Vec<Tag<'_>>> {
+    let mut tags = Vec::new();
+    let mut at = 0;
+    while let Some(offset) = text[at..].find('<') {
+        let start = at + offset;
+        if text[start..].starts_with("<!--") {
+            let close = text[start..].find("-->").ok_or(Error::MalformedMarkup)?;
+            at = start + close + 3;
+            continue;
+        }
+        let after = &text[start + 1..];
+        let closing = after.starts_with('/');
+        let after = after.strip_prefix('/').unwrap_or(after);
+        let name_len = after
+            .find(|ch: char| !(ch.is_alphanumeric() || matches!(ch, '-' | '_' | ':' | '.')))
+            .unwrap_or(after.len());
+        if name_len == 0 {
+            at = start + 1;
+            continue;
+        }
+        let name = &after[..name_len];
+        let end = start + text[start..].find('>').ok_or(Error::MalformedMarkup)? + 1;
+        let body = &text[start + 1..end - 1];
+        if !body.ends_with('/') {
+            tags.push(Tag {
+                name,
+                closing,
+                start,
+                end,
+            });
+        }
+        at = end;
+    }
+    Ok(tags)
+}
+
+/// Computes the `it`/`at` text object: the contents of (inner) or the whole of (around) the
+/// innermost `<tag>…</tag>` pair enclosing `pos`. Opening tags never closed (like `<br>`) are
+/// ignored, tags are matched by name.
+///
+/// # Errors
+///
+/// Returns `Error::MalformedMarkup` if a tag is never terminated or a closing tag has no opening
+/// counterpart, and `Error::PatternNotFound` if no tag pair encloses the position.
+pub fn tag(lines: &[String], pos: LineCol, inner: bool) -> Result<TextRange> {
+    let text = JoinedText::new(lines);
+    let cursor = text.offset(pos);
+
+    let mut open: Vec<Tag> = Vec::new();
+    let mut innermost: Option<(Tag, Tag)> = None;
+    for tag in scan_tags(&text.text)? {
+        if !tag.closing {
+            open.push(tag);
+            continue;
+        }
+        let opening_index = open
+            .iter()
+            .rposition(|opening| opening.name == tag.name)
+            .ok_or(Error::MalformedMarkup)?;
+        let opening = open.swap_remove(opening_index);
+        open.truncate(opening_index);
+
+        let encloses = opening.start <= cursor && cursor < tag.end;
+        let is_inner = innermost
+            .as_ref()
+            .is_none_or(|(current, _)| opening.start > current.start);
+        if encloses && is_inner {
+            innermost = Some((opening, tag));
+        }
+    }
+
+    let (opening, closing) = innermost.ok_or(Error::PatternNotFound)?;
+    let (start, end) = if inner {
+        (opening.end, closing.start)
+    } else {
+        (opening.start, closing.end)
+    };
+    Ok(TextRange {
+        start: text.linecol(start),
+        end: text.linecol(end),
+        linewise: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// "First paragraph"
+    /// "still first"
+    /// ""
+    /// "   "
+    /// "Second paragraph"
+    fn paragraphs() -> Vec<String> {
+        vec![
+            "First paragraph".to_string(),
+            "still first".to_string(),
+            "".to_string(),
+            "   ".to_string(),
+            "Second paragraph".to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_inner_paragraph() {
+        let lines = paragraphs();
+        assert_eq!(paragraph(&lines, 1, true), TextRange::lines(0, 1));
+        assert_eq!(paragraph(&lines, 4, true), TextRange::lines(4, 4));
+    }
+
+    #[test]
+    fn test_inner_paragraph_on_blank_line_selects_blank_block() {
+        let lines = paragraphs();
+        assert_eq!(paragraph(&lines, 3, true), TextRange::lines(2, 3));
+    }
+
+    #[test]
+    fn test_around_paragraph_takes_trailing_blank_lines() {
+        let lines = paragraphs();
+        assert_eq!(paragraph(&lines, 0, false), TextRange::lines(0, 3));
+    }
+
+    #[test]
+    fn test_around_last_paragraph_takes_preceding_blank_lines() {
+        let lines = paragraphs();
+        assert_eq!(paragraph(&lines, 4, false), TextRange::lines(2, 4));
+    }
+
+    #[test]
+    fn test_indent_block() {
+        let lines: Vec<String> = [
+            "def f():",
+            "    a = 1",
+            "",
+            "    if a:",
+            "        b()",
+            "    return a",
+            "",
+            "x = f()",
+        ]
+        .map(String::from)
+        .to_vec();
+        assert_eq!(indent_block(&lines, 1, true), Some(TextRange::lines(1, 5)));
+        assert_eq!(indent_block(&lines, 2, true), Some(TextRange::lines(1, 5)));
+        assert_eq!(indent_block(&lines, 4, true), Some(TextRange::lines(4, 4)));
+        assert_eq!(indent_block(&lines, 4, false), Some(TextRange::lines(3, 4)));
+        assert_eq!(indent_block(&lines, 5, false), Some(TextRange::lines(0, 5)));
+        assert_eq!(indent_block(&[String::new()], 0, true), None);
+    }
+
+    #[test]
+    fn test_paragraph_boundary() {
+        let lines = paragraphs();
+        assert_eq!(paragraph_boundary(&lines, 0, true), 2);
+        assert_eq!(paragraph_boundary(&lines, 2, true), 4);
+        assert_eq!(paragraph_boundary(&lines, 4, false), 3);
+        assert_eq!(paragraph_boundary(&lines, 1, false), 0);
+    }
+
+    #[test]
+    fn test_section_start() {
+        let lines: Vec<String> = [
+            "use std::io;",
+            "use std::fs;",
+            "",
+            "fn main() {",
+            "    run();",
+            "}",
+            "#[test]",
+            "fn test() {}",
+            "struct Unit;",
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+        assert_eq!(section_start(&lines, 0, true), Some(3));
+        assert_eq!(section_start(&lines, 3, true), None);
+        assert_eq!(section_start(&lines, 8, false), Some(3));
+        assert_eq!(section_start(&lines, 3, false), Some(0));
+        assert_eq!(section_start(&lines, 0, false), None);
+    }
+
+    #[test]
+    fn test_word() {
+        let pos = LineCol { line: 0, col: 7 };
+        let range = word("let my_var = 1;", pos, true, "_").unwrap();
+        assert_eq!((range.start.col, range.end.col), (4, 10));
+        let range = word("let my_var = 1;", pos, false, "_").unwrap();
+        assert_eq!((range.start.col, range.end.col), (4, 11));
+        let range = word("end word", LineCol { line: 0, col: 5 }, false, "_").unwrap();
+        assert_eq!((range.start.col, range.end.col), (3, 8));
+        assert_eq!(word("", pos, true, "_"), None);
+
+        // Characters of `iskeyword` join words
+        let range = word("a my-var b", LineCol { line: 0, col: 3 }, true, "_").unwrap();
+        assert_eq!((range.start.col, range.end.col), (2, 4));
+        let range = word("a my-var b", LineCol { line: 0, col: 3 }, true, "_-").unwrap();
+        assert_eq!((range.start.col, range.end.col), (2, 8));
+    }
+
+    #[test]
+    fn test_previous_word_start() {
+        assert_eq!(previous_word_start("hello world", 11, "_"), 6);
+        assert_eq!(previous_word_start("hello world  ", 13, "_"), 6);
+        assert_eq!(previous_word_start("foo.bar_baz", 11, "_"), 4);
+        assert_eq!(previous_word_start("foo.", 4, "_"), 3);
+        assert_eq!(previous_word_start("  x", 2, "_"), 0);
+        assert_eq!(previous_word_start("x", 5, "_"), 0);
+    }
+
+    #[test]
+    fn test_next_word() {
+        let lines: Vec<String> = ["foo bar", "  baz qux", "", "end"]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        let end =
+            |pos: LineCol, count: usize, big: bool| next_word(&lines, pos, count, big, "_").end;
+        let at = |line: usize, col: usize| LineCol { line, col };
+
+        assert_eq!(end(at(0, 0), 1, false), at(0, 4));
+        // The last word of a line stops at its end instead of joining the next line
+        assert_eq!(end(at(0, 4), 1, false), at(0, 7));
+        assert_eq!(end(at(0, 0), 2, false), at(0, 7));
+        assert_eq!(end(at(0, 0), 3, false), at(1, 6));
+        // Empty lines are words of their own
+        assert_eq!(end(at(1, 6), 2, false), at(2, 0));
+        assert_eq!(end(at(3, 0), 5, false), at(3, 3));
+
+        let lines = vec!["a.b c".to_string()];
+        assert_eq!(next_word(&lines, at(0, 0), 1, false, "_").end, at(0, 1));
+        assert_eq!(next_word(&lines, at(0, 0), 1, true, "_").end, at(0, 4));
+        assert_eq!(next_word(&lines, at(0, 0), 1, false, ".").end, at(0, 4));
+
+        let range = next_word(&lines, at(0, 0), 1, true, "_");
+        assert_eq!(trim_trailing_whitespace(&lines, range).end, at(0, 3));
+    }
+
+    fn tag_range(lines: &[&str], pos: LineCol, inner: bool) -> Result<(LineCol, LineCol)> {
+        let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        tag(&lines, pos, inner).map(|range| (range.start, range.end))
+    }
+
+    #[test]
+    fn test_path() {
+        let line = "see (src/main.rs), ok";
+        assert_eq!(path(line, 8), Some(5..16));
+        assert_eq!(path(line, 5), Some(5..16));
+        assert_eq!(path(line, 4), None);
+        assert_eq!(path("../a-b_c.txt", 11), Some(0..12));
+        assert_eq!(path("", 0), None);
+    }
+
+    #[test]
+    fn test_inner_tag_picks_innermost_pair() {
+        let lines = ["<div><b>bold</b> text</div>"];
+        let range = tag_range(&lines, LineCol { line: 0, col: 9 }, true).unwrap();
+        assert_eq!(
+            range,
+            (LineCol { line: 0, col: 8 }, LineCol { line: 0, col: 12 })
+        );
+        let range = tag_range(&lines, LineCol { line: 0, col: 18 }, true).unwrap();
+        assert_eq!(
+            range,
+            (LineCol { line: 0, col: 5 }, LineCol { line: 0, col: 21 })
+        );
+    }
+
+    #[test]
+    fn test_around_tag_across_lines() {
+        let lines = ["<ul>", "  <li>item<br></li>", "</ul>"];
+        let range = tag_range(&lines, LineCol { line: 1, col: 1 }, false).unwrap();
+        assert_eq!(
+            range,
+            (LineCol { line: 0, col: 0 }, LineCol { line: 2, col: 5 })
+        );
+        let range = tag_range(&lines, LineCol { line: 1, col: 7 }, true).unwrap();
+        assert_eq!(
+            range,
+            (LineCol { line: 1, col: 6 }, LineCol { line: 1, col: 14 })
+        );
+    }
+
+    #[test]
+    fn test_tag_on_the_tag_itself_and_nested_same_name() {
+        let lines = ["<a><a>x</a></a>"];
+        let range = tag_range(&lines, LineCol { line: 0, col: 1 }, false).unwrap();
+        assert_eq!(
+            range,
+            (LineCol { line: 0, col: 0 }, LineCol { line: 0, col: 15 })
+        );
+        let range = tag_range(&lines, LineCol { line: 0, col: 6 }, true).unwrap();
+        assert_eq!(
+            range,
+            (LineCol { line: 0, col: 6 }, LineCol { line: 0, col: 7 })
+        );
+    }
+
+    #[test]
+    fn test_tag_malformed_or_missing() {
+        let pos = LineCol { line: 0, col: 2 };
+        assert!(matches!(
+            tag_range(&["<a>text</b>"], pos, true),
+            Err(Error::MalformedMarkup)
+        ));
+        assert!(matches!(
+            tag_range(&["<a text"], pos, true),
+            Err(Error::MalformedMarkup)
+        ));
+        assert!(matches!(
+            tag_range(&["if a < b { x }"], pos, true),
+            Err(Error::PatternNotFound)
+        ));
+    }
+}
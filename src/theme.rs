@@ -0,0 +1,232 @@
+//! Colors of the editor by their role, changed at runtime through `:highlight`.
+use crossterm::style::Color;
+
+use crate::{Error, Result};
+
+/// Foreground and background color of a highlight group
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Highlight {
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl Highlight {
+    /// The terminal's own colors
+    pub const PLAIN: Self = Self::new(Color::Reset, Color::Reset);
+
+    const fn new(fg: Color, bg: Color) -> Self {
+        Self { fg, bg }
+    }
+}
+
+/// Highlight groups of the editor, named like their vim counterparts in `:highlight`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    /// The info bar showing the mode and cursor position, `StatusLine`
+    pub info_bar: Highlight,
+    /// Selected text in visual modes, `Visual`
+    pub selection: Highlight,
+    /// The line numbers in front of the text, `LineNr`
+    pub line_number: Highlight,
+    /// The line the cursor is on while `cursorline` is set, `CursorLine`
+    pub cursor_line: Highlight,
+    /// The number of the line the cursor is on while `cursorline` is set, `CursorLineNr`
+    pub cursor_line_number: Highlight,
+    /// Closed folds and the fold column, `Folded`
+    pub fold: Highlight,
+    /// Matches of the last search while `hlsearch` is set, `Search`
+    pub search: Highlight,
+    /// The match previewed while typing a search, `IncSearch`
+    pub inc_search: Highlight,
+    /// The cell at `colorcolumn`, of which only the background is used, `ColorColumn`
+    pub color_column: Highlight,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            info_bar: Highlight::new(Color::Reset, Color::DarkGrey),
+            selection: Highlight::new(Color::Black, Color::White),
+            line_number: Highlight::new(Color::Green, Color::Reset),
+            cursor_line: Highlight::new(Color::Reset, Color::AnsiValue(236)),
+            cursor_line_number: Highlight::new(Color::Yellow, Color::Reset),
+            fold: Highlight::new(Color::DarkGrey, Color::Reset),
+            search: Highlight::new(Color::Black, Color::Yellow),
+            inc_search: Highlight::new(Color::Black, Color::Cyan),
+            color_column: Highlight::new(Color::Reset, Color::DarkRed),
+        }
+    }
+}
+
+impl Theme {
+    /// Applies the arguments of a `:highlight` command, `{group} [guifg={color}] [guibg={color}]`.
+    /// Without colors to assign the current colors of the group are reported. `ctermfg` and
+    /// `ctermbg` are accepted in place of `guifg` and `guibg`.
+    ///
+    /// # Returns
+    ///
+    /// The colors of the group if they were queried.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UnknownOption` for unknown groups or keys, and `Error::InvalidInput` for
+    /// colors which can't be parsed.
+    pub fn highlight(&mut self, args: &str) -> Result<Option<String>> {
+        let mut args = args.split_whitespace();
+        let Some(name) = args.next() else {
+            return Err(Error::InvalidInput);
+        };
+        let group = self.group(name)?;
+        let mut assigned = *group;
+        let mut queried = true;
+        for arg in args {
+            let (key, value) = arg.split_once('=').ok_or(Error::InvalidInput)?;
+            let color = parse_color(value)?;
+            match key.to_ascii_lowercase().as_str() {
+                "guifg" | "ctermfg" => assigned.fg = color,
+                "guibg" | "ctermbg" => assigned.bg = color,
+                _ => return Err(Error::UnknownOption(key.to_string())),
+            }
+            queried = false;
+        }
+        // Only assigned once every argument turned out valid
+        *group = assigned;
+        Ok(queried.then(|| {
+            format!(
+                "{name} guifg={} guibg={}",
+                color_name(assigned.fg),
+                color_name(assigned.bg)
+            )
+        }))
+    }
+
+    fn group(&mut self, name: &str) -> Result<&mut Highlight> {
+        match name.to_ascii_lowercase().as_str() {
+            "statusline" => Ok(&mut self.info_bar),
+            "visual" => Ok(&mut self.selection),
+            "linenr" => Ok(&mut self.line_number),
+            "cursorline" => Ok(&mut self.cursor_line),
+            "cursorlinenr" => Ok(&mut self.cursor_line_number),
+            "folded" => Ok(&mut self.fold),
+            "search" => Ok(&mut self.search),
+            "incsearch" => Ok(&mut self.inc_search),
+            "colorcolumn" => Ok(&mut self.color_column),
+            _ => Err(Error::UnknownOption(name.to_string())),
+        }
+    }
+}
+
+/// Named colors, the bright variants are named without a prefix like in crossterm
+const COLOR_NAMES: &[(&str, Color)] = &[
+    ("none", Color::Reset),
+    ("black", Color::Black),
+    ("darkgrey", Color::DarkGrey),
+    ("red", Color::Red),
+    ("darkred", Color::DarkRed),
+    ("green", Color::Green),
+    ("darkgreen", Color::DarkGreen),
+    ("yellow", Color::Yellow),
+    ("darkyellow", Color::DarkYellow),
+    ("blue", Color::Blue),
+    ("darkblue", Color::DarkBlue),
+    ("magenta", Color::Magenta),
+    ("darkmagenta", Color::DarkMagenta),
+    ("cyan", Color::Cyan),
+    ("darkcyan", Color::DarkCyan),
+    ("white", Color::White),
+    ("grey", Color::Grey),
+];
+
+/// Parses a color from its name, a `#rrggbb` hex triple or a 256 color palette index. Names are
+/// matched ignoring case, underscores and the `gray` spelling.
+pub fn parse_color(value: &str) -> Result<Color> {
+    if let Some(hex) = value.strip_prefix('#') {
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+                .ok_or(Error::InvalidInput)
+        };
+        if hex.len() != 6 {
+            return Err(Error::InvalidInput);
+        }
+        return Ok(Color::Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        });
+    }
+    if let Ok(index) = value.parse() {
+        return Ok(Color::AnsiValue(index));
+    }
+    let name = value
+        .to_ascii_lowercase()
+        .replace('_', "")
+        .replace("gray", "grey");
+    COLOR_NAMES
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|&(_, color)| color)
+        .ok_or(Error::InvalidInput)
+}
+
+/// Formats a color the way `parse_color` reads it back
+fn color_name(color: Color) -> String {
+    match color {
+        Color::Rgb { r, g, b } => format!("#{r:02x}{g:02x}{b:02x}"),
+        Color::AnsiValue(index) => index.to_string(),
+        color => COLOR_NAMES
+            .iter()
+            .find(|(_, known)| *known == color)
+            .map_or_else(|| format!("{color:?}"), |(name, _)| name.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("DarkGray").unwrap(), Color::DarkGrey);
+        assert_eq!(parse_color("dark_grey").unwrap(), Color::DarkGrey);
+        assert_eq!(
+            parse_color("#ff8000").unwrap(),
+            Color::Rgb {
+                r: 255,
+                g: 128,
+                b: 0
+            }
+        );
+        assert_eq!(parse_color("208").unwrap(), Color::AnsiValue(208));
+        assert!(matches!(parse_color("#ff80"), Err(Error::InvalidInput)));
+        assert!(matches!(parse_color("#gg8000"), Err(Error::InvalidInput)));
+        assert!(matches!(parse_color("mauve"), Err(Error::InvalidInput)));
+    }
+
+    #[test]
+    fn test_highlight() {
+        let mut theme = Theme::default();
+        assert_eq!(
+            theme.highlight("StatusLine").unwrap(),
+            Some("StatusLine guifg=none guibg=darkgrey".to_string())
+        );
+        assert_eq!(
+            theme
+                .highlight("Visual guifg=#000000 ctermbg=cyan")
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            theme.selection,
+            Highlight::new(Color::Rgb { r: 0, g: 0, b: 0 }, Color::Cyan)
+        );
+
+        // Nothing is assigned if any argument is invalid
+        assert!(theme.highlight("LineNr guifg=red guibg=nope").is_err());
+        assert_eq!(theme.line_number, Theme::default().line_number);
+        assert!(matches!(
+            theme.highlight("Cursor guifg=red"),
+            Err(Error::UnknownOption(_))
+        ));
+    }
+}
@@ -0,0 +1,838 @@
+//! Pure line transformations backing the text manipulation commands of the editor.
+//!
+//! Every function here takes lines by reference and returns the rewritten lines, leaving it up to
+//! the editor to splice the result back into the buffer as a single edit.
+use std::ops::Range;
+
+use crate::{
+    textobject::{self, JoinedText},
+    LineCol,
+};
+
+/// Locates the decimal number under or after `from` on a line.
+///
+/// If `from` points into the middle of a number, the whole number is returned. A minus sign
+/// directly preceding the digits is treated as part of the number.
+///
+/// # Returns
+///
+/// The byte range the number occupies within the line together with its parsed value, or `None`
+/// if there is no number at or after `from` (or it doesn't fit into an `i64`).
+pub fn find_number(line: &str, from: usize) -> Option<(Range<usize>, i64)> {
+    let bytes = line.as_bytes();
+    let mut start = from.min(bytes.len());
+    if bytes.get(start).is_some_and(u8::is_ascii_digit) {
+        while start > 0 && bytes[start - 1].is_ascii_digit() {
+            start -= 1;
+        }
+    }
+    let start = start + bytes[start..].iter().position(u8::is_ascii_digit)?;
+    let end = start
+        + bytes[start..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+    let start = if start > 0 && bytes[start - 1] == b'-' {
+        start - 1
+    } else {
+        start
+    };
+
+    let value = line[start..end].parse().ok()?;
+    Some((start..end, value))
+}
+
+/// Adds `delta` to the number under or after `from`, returning the rewritten line.
+/// Returns `None` if the line contains no number to increment.
+pub fn increment_number(line: &str, from: usize, delta: i64) -> Option<String> {
+    let (range, value) = find_number(line, from)?;
+    let mut incremented = line.to_string();
+    incremented.replace_range(range, &value.saturating_add(delta).to_string());
+    Some(incremented)
+}
+
+/// Increments the first number on each of the given lines.
+///
+/// The search starts at `first_col` on the first line and at the beginning of every other line.
+/// Lines without a number are left untouched. If `sequential` is set, the n-th line containing a
+/// number is incremented by `n * delta` instead of `delta`, turning equal numbers into a sequence.
+pub fn increment_lines(
+    lines: &[String],
+    first_col: usize,
+    delta: i64,
+    sequential: bool,
+) -> Vec<String> {
+    let mut step = 0;
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let from = if i == 0 { first_col } else { 0 };
+            if find_number(line, from).is_none() {
+                return line.clone();
+            }
+            step += 1;
+            let delta = if sequential { delta * step } else { delta };
+            increment_number(line, from, delta).expect("Checked for a number above")
+        })
+        .collect()
+}
+
+/// Toggles the line comment `prefix` on the given lines, inserting it after the indentation.
+///
+/// If every non-blank line is already commented the comments are removed, otherwise all non-blank
+/// lines get commented. A commented line may omit the trailing whitespace of the prefix.
+pub fn toggle_comment(lines: &[String], prefix: &str) -> Vec<String> {
+    let marker = prefix.trim_end();
+    fn split_indent(line: &str) -> (&str, &str) {
+        line.split_at(line.len() - line.trim_start().len())
+    }
+    let commented = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| split_indent(line).1.starts_with(marker));
+
+    lines
+        .iter()
+        .map(|line| {
+            let (indent, rest) = split_indent(line);
+            if rest.is_empty() {
+                line.clone()
+            } else if commented {
+                let rest = rest
+                    .strip_prefix(prefix)
+                    .or_else(|| rest.strip_prefix(marker))
+                    .unwrap_or(rest);
+                format!("{indent}{rest}")
+            } else {
+                format!("{indent}{prefix}{rest}")
+            }
+        })
+        .collect()
+}
+
+/// Toggles the comment `prefix` at the byte column `col` of the lines, the left edge of a visual
+/// block, instead of after their indentation. Lines ending left of the column are padded with spaces
+/// when `pad` is set and left alone otherwise. The lines are uncommented if all of them reaching the
+/// column have the comment marker there.
+pub fn toggle_comment_at(lines: &[String], prefix: &str, col: usize, pad: bool) -> Vec<String> {
+    let marker = prefix.trim_end();
+    let reaching = || {
+        lines
+            .iter()
+            .filter_map(|line| line.get(col..))
+            .filter(|rest| !rest.is_empty())
+    };
+    let commented = reaching().next().is_some() && reaching().all(|rest| rest.starts_with(marker));
+
+    lines
+        .iter()
+        .map(|line| {
+            let Some(rest) = line.get(col..) else {
+                // Only the marker is added to padded lines, which have no text following it
+                return match pad && !commented && line.len() < col {
+                    true => format!("{line}{}{marker}", " ".repeat(col - line.len())),
+                    false => line.clone(),
+                };
+            };
+            let head = &line[..col];
+            match (commented, rest.is_empty()) {
+                (true, _) => match rest
+                    .strip_prefix(prefix)
+                    .or_else(|| rest.strip_prefix(marker))
+                {
+                    Some("") => head.trim_end().to_string(),
+                    Some(rest) => format!("{head}{rest}"),
+                    None => line.clone(),
+                },
+                (false, true) if pad => format!("{line}{marker}"),
+                (false, true) => line.clone(),
+                (false, false) => format!("{head}{prefix}{rest}"),
+            }
+        })
+        .collect()
+}
+
+/// Replaces non-overlapping spans of text with new text.
+///
+/// # Returns
+///
+/// The range of lines touched by the spans together with the lines replacing them.
+pub fn replace_spans(
+    lines: &[String],
+    spans: &[(Range<LineCol>, &str)],
+) -> (Range<usize>, Vec<String>) {
+    let first = spans
+        .iter()
+        .map(|(span, _)| span.start.line)
+        .min()
+        .unwrap_or(0);
+    let last = spans
+        .iter()
+        .map(|(span, _)| span.end.line)
+        .max()
+        .unwrap_or(0);
+    let mut text = JoinedText::new(&lines[first..=last]);
+    let relative = |pos: LineCol| LineCol {
+        line: pos.line - first,
+        col: pos.col,
+    };
+
+    let mut offsets: Vec<(Range<usize>, &str)> = spans
+        .iter()
+        .map(|(span, new)| {
+            let start = text.offset(relative(span.start));
+            (start..text.offset(relative(span.end)), *new)
+        })
+        .collect();
+    offsets.sort_by_key(|(span, _)| std::cmp::Reverse(span.start));
+    for (span, new) in offsets {
+        text.text.replace_range(span, new);
+    }
+    (
+        first..last + 1,
+        text.text.split('\n').map(String::from).collect(),
+    )
+}
+
+/// Replaces `pattern` with `replacement` on every line, only its first occurrence per line unless
+/// `global` is set.
+///
+/// # Returns
+///
+/// The rewritten lines together with the number of replacements made.
+pub fn substitute(
+    lines: &[String],
+    pattern: &str,
+    replacement: &str,
+    global: bool,
+) -> (Vec<String>, usize) {
+    let mut count = 0;
+    let substituted = lines
+        .iter()
+        .map(|line| {
+            let matches = line.matches(pattern).count();
+            if matches == 0 {
+                return line.clone();
+            }
+            if global {
+                count += matches;
+                line.replace(pattern, replacement)
+            } else {
+                count += 1;
+                line.replacen(pattern, replacement, 1)
+            }
+        })
+        .collect();
+    (substituted, count)
+}
+
+/// Sorts lines, comparing them as strings or by their first number if `numeric` is set. Lines
+/// without a number sort before all others. The sort is stable, also when `reverse`d.
+///
+/// # Returns
+///
+/// The sorted lines together with the number of lines dropped for comparing equal to the line
+/// before them, which only happens if `unique` is set.
+pub fn sort_lines(
+    lines: &[String],
+    reverse: bool,
+    unique: bool,
+    numeric: bool,
+) -> (Vec<String>, usize) {
+    let key = |line: &String| find_number(line, 0).map(|(_, value)| value);
+    let compare = |a: &String, b: &String| {
+        if numeric {
+            key(a).cmp(&key(b))
+        } else {
+            a.cmp(b)
+        }
+    };
+
+    let mut sorted = lines.to_vec();
+    if reverse {
+        sorted.sort_by(|a, b| compare(b, a));
+    } else {
+        sorted.sort_by(compare);
+    }
+    if unique {
+        sorted.dedup_by(|a, b| compare(a, b).is_eq());
+    }
+    let removed = lines.len() - sorted.len();
+    (sorted, removed)
+}
+
+/// Aligns the lines on the first occurrence of `delimiter` in them, padding the text in front of
+/// it with spaces until the delimiters line up in one column. Lines without the delimiter are
+/// left as they are.
+pub fn align_lines(lines: &[String], delimiter: &str) -> Vec<String> {
+    let column = |line: &str, at: usize| line[..at].chars().count();
+    let Some(width) = lines
+        .iter()
+        .filter_map(|line| line.find(delimiter).map(|at| column(line, at)))
+        .max()
+    else {
+        return lines.to_vec();
+    };
+    lines
+        .iter()
+        .map(|line| match line.find(delimiter) {
+            Some(at) => {
+                let padding = " ".repeat(width - column(line, at));
+                format!("{}{padding}{}", &line[..at], &line[at..])
+            }
+            None => line.clone(),
+        })
+        .collect()
+}
+
+/// Joins lines into one like `J`, replacing the indentation of every following line with a single
+/// space. No space is inserted in front of an empty line or a line starting with `)`.
+///
+/// # Returns
+///
+/// The joined line together with the column where the last line was joined.
+pub fn join_lines(lines: &[String]) -> (String, usize) {
+    let mut joined = lines.first().cloned().unwrap_or_default();
+    let mut last_join = 0;
+    for line in lines.iter().skip(1) {
+        let line = line.trim_start();
+        joined.truncate(joined.trim_end().len());
+        last_join = joined.len();
+        if !joined.is_empty() && !line.is_empty() && !line.starts_with(')') {
+            joined.push(' ');
+        }
+        joined.push_str(line);
+    }
+    (joined, last_join)
+}
+
+/// Shifts the non-blank lines right by `width` spaces, or left by up to `width` columns of
+/// indentation. A tab counts as `width` columns when shifting left.
+pub fn shift_lines(lines: &[String], width: usize, right: bool) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                return line.clone();
+            }
+            if right {
+                return format!("{}{line}", " ".repeat(width));
+            }
+            let mut removed = 0;
+            let start = line
+                .char_indices()
+                .find(|&(_, ch)| {
+                    let columns = match ch {
+                        ' ' => 1,
+                        '\t' => width,
+                        _ => return true,
+                    };
+                    if removed + columns > width {
+                        return true;
+                    }
+                    removed += columns;
+                    false
+                })
+                .map_or(line.len(), |(i, _)| i);
+            line[start..].to_string()
+        })
+        .collect()
+}
+
+/// Counts the closing brackets a line starts with, and the balance of the brackets following
+/// them. Brackets within double quoted strings are skipped.
+fn bracket_balance(content: &str) -> (usize, isize) {
+    let leading = content
+        .chars()
+        .take_while(|ch| matches!(ch, '}' | ')' | ']'))
+        .count();
+    let mut balance = 0;
+    let mut in_string = false;
+    let mut chars = content[leading..].chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if in_string => {
+                let _ = chars.next();
+            }
+            '"' => in_string = !in_string,
+            '{' | '(' | '[' if !in_string => balance += 1,
+            '}' | ')' | ']' if !in_string => balance -= 1,
+            _ => (),
+        }
+    }
+    (leading, balance)
+}
+
+/// Rewraps the words of the lines into lines at most `width` characters wide, never breaking a
+/// word. Paragraphs separated by blank lines are rewrapped on their own, every line keeping the
+/// indentation and the `comment` leader the first line of its paragraph starts with. Blank lines
+/// are kept as they are.
+pub fn reflow_lines(lines: &[String], width: usize, comment: &str) -> Vec<String> {
+    let mut reflowed = Vec::new();
+    let mut leader = None;
+    let mut words = Vec::new();
+    for line in lines {
+        let own_leader = line_leader(line, comment);
+        let content = &line[own_leader.len()..];
+        if content.trim().is_empty() {
+            wrap_words(
+                leader.take().unwrap_or_default(),
+                &words,
+                width,
+                &mut reflowed,
+            );
+            words.clear();
+            reflowed.push(line.clone());
+            continue;
+        }
+        leader.get_or_insert(own_leader);
+        words.extend(content.split_whitespace());
+    }
+    wrap_words(leader.unwrap_or_default(), &words, width, &mut reflowed);
+    reflowed
+}
+
+/// Breaks `line` for the text in front of `col` to fit within `width` characters, at the last run
+/// of blanks leaving the first part within the width. The second part is indented like the line.
+///
+/// # Returns
+///
+/// The two parts and the column `col` ends up at in the second one. `None` if the text fits, or if
+/// no blanks past the indentation allow breaking it.
+pub fn wrap_at_blank(line: &str, col: usize, width: usize) -> Option<(String, String, usize)> {
+    if line[..col].chars().count() <= width {
+        return None;
+    }
+    let indent = line.len() - line.trim_start().len();
+    let mut blanks = None;
+    let mut run_start = None;
+    for (i, ch) in line[..col].char_indices().skip_while(|&(i, _)| i < indent) {
+        match (ch.is_whitespace(), run_start) {
+            (true, None) => run_start = Some(i),
+            (false, Some(start)) => {
+                if line[..start].chars().count() <= width {
+                    blanks = Some((start, i));
+                }
+                run_start = None;
+            }
+            _ => (),
+        }
+    }
+    let (start, end) = blanks?;
+    let tail = format!("{}{}", &line[..indent], &line[end..]);
+    Some((line[..start].to_string(), tail, indent + col - end))
+}
+
+/// The indentation of `line` followed by the `comment` leader and the whitespace after it, if
+/// the line starts with it
+fn line_leader<'a>(line: &'a str, comment: &str) -> &'a str {
+    let indent = line.len() - line.trim_start().len();
+    match line[indent..].strip_prefix(comment) {
+        Some(rest) if !comment.is_empty() => {
+            let spaces = rest.len() - rest.trim_start().len();
+            &line[..line.len() - rest.len() + spaces]
+        }
+        _ => &line[..indent],
+    }
+}
+
+/// Fills lines starting with `leader` with as many of the words as fit within `width`
+fn wrap_words(leader: &str, words: &[&str], width: usize, wrapped: &mut Vec<String>) {
+    let mut line = leader.to_string();
+    let mut filled = false;
+    for word in words {
+        if filled && line.chars().count() + 1 + word.chars().count() > width {
+            wrapped.push(std::mem::replace(&mut line, leader.to_string()));
+            filled = false;
+        }
+        if filled {
+            line.push(' ');
+        }
+        line.push_str(word);
+        filled = true;
+    }
+    if filled {
+        wrapped.push(line);
+    }
+}
+
+/// Reindents lines by their bracket nesting, a level being `width` spaces deep.
+///
+/// `previous` is the line in front of the reindented ones, the first line is indented like it
+/// plus the brackets it leaves open. Every bracket left open by a line indents the following
+/// lines one level deeper, lines starting with closing brackets are dedented by them and blank
+/// lines lose their whitespace.
+pub fn reindent_lines(lines: &[String], previous: Option<&str>, width: usize) -> Vec<String> {
+    let mut depth = previous.map_or(0, |line| {
+        let indent = line.len() - line.trim_start().len();
+        let (_, balance) = bracket_balance(line.trim());
+        (indent / width.max(1)).saturating_add_signed(balance)
+    });
+    lines
+        .iter()
+        .map(|line| {
+            let content = line.trim();
+            if content.is_empty() {
+                return String::new();
+            }
+            let (leading, balance) = bracket_balance(content);
+            depth = depth.saturating_sub(leading);
+            let reindented = format!("{}{content}", " ".repeat(depth * width));
+            depth = depth.saturating_add_signed(balance);
+            reindented
+        })
+        .collect()
+}
+
+/// Replaces every whole word occurrence of `word` by `replacement`, working from the end of each
+/// line towards its start so the columns of the remaining occurrences stay valid.
+///
+/// # Returns
+///
+/// The lines with the replacements made and the number of occurrences replaced.
+pub fn replace_word(
+    lines: &[String],
+    word: &str,
+    replacement: &str,
+    iskeyword: &str,
+) -> (Vec<String>, usize) {
+    let mut count = 0;
+    let replaced = lines
+        .iter()
+        .map(|line| {
+            let starts: Vec<usize> = line
+                .match_indices(word)
+                .map(|(col, _)| col)
+                .filter(|&col| textobject::is_whole_word(line, col..col + word.len(), iskeyword))
+                .collect();
+            count += starts.len();
+            let mut line = line.clone();
+            for col in starts.into_iter().rev() {
+                line.replace_range(col..col + word.len(), replacement);
+            }
+            line
+        })
+        .collect();
+    (replaced, count)
+}
+
+/// Prefixes the lines with their line numbers, counting from `first`, and `separator`. The
+/// numbers are right aligned to the width of the largest one.
+pub fn number_lines(lines: &[String], first: usize, separator: &str) -> Vec<String> {
+    let width = (first + lines.len().saturating_sub(1)).to_string().len();
+    lines
+        .iter()
+        .zip(first..)
+        .map(|(line, number)| format!("{number:>width$}{separator}{line}"))
+        .collect()
+}
+
+/// Whether `line` counts as blank for `:squeeze` and `:trimblank`: empty, or only whitespace as
+/// well if `whitespace` is set
+pub fn is_blank_line(line: &str, whitespace: bool) -> bool {
+    match whitespace {
+        true => line.trim().is_empty(),
+        false => line.is_empty(),
+    }
+}
+
+/// Collapses every run of consecutive blank lines into its first line.
+///
+/// # Returns
+///
+/// The remaining lines together with the number of lines removed.
+pub fn squeeze_blank_lines(lines: &[String], whitespace: bool) -> (Vec<String>, usize) {
+    let mut squeezed: Vec<String> = Vec::with_capacity(lines.len());
+    for line in lines {
+        let follows_blank = squeezed
+            .last()
+            .is_some_and(|previous| is_blank_line(previous, whitespace));
+        if !(follows_blank && is_blank_line(line, whitespace)) {
+            squeezed.push(line.clone());
+        }
+    }
+    let removed = lines.len() - squeezed.len();
+    (squeezed, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_number_under_cursor() {
+        assert_eq!(find_number("item 123 here", 6), Some((5..8, 123)));
+    }
+
+    #[test]
+    fn test_find_number_after_cursor() {
+        assert_eq!(find_number("abc 42", 0), Some((4..6, 42)));
+        assert_eq!(find_number("abc", 0), None);
+    }
+
+    #[test]
+    fn test_find_negative_number() {
+        assert_eq!(find_number("x = -7;", 0), Some((4..6, -7)));
+    }
+
+    #[test]
+    fn test_increment_number() {
+        assert_eq!(increment_number("v9", 0, 1), Some("v10".to_string()));
+        assert_eq!(increment_number("1 -1", 1, 3), Some("1 2".to_string()));
+        assert_eq!(increment_number("none", 0, 1), None);
+    }
+
+    #[test]
+    fn test_increment_lines_sequential_skips_lines_without_number() {
+        let lines = vec![
+            "0. first".to_string(),
+            "no number".to_string(),
+            "0. second".to_string(),
+            "0. third".to_string(),
+        ];
+        assert_eq!(
+            increment_lines(&lines, 0, 2, true),
+            vec!["2. first", "no number", "4. second", "6. third"]
+        );
+        assert_eq!(
+            increment_lines(&lines, 0, 1, false),
+            vec!["1. first", "no number", "1. second", "1. third"]
+        );
+    }
+
+    #[test]
+    fn test_toggle_comment_keeps_indentation() {
+        let lines = vec![
+            "fn main() {".to_string(),
+            "".to_string(),
+            "    // done".to_string(),
+        ];
+        let commented = toggle_comment(&lines, "// ");
+        assert_eq!(commented, vec!["// fn main() {", "", "    // // done"]);
+        assert_eq!(toggle_comment(&commented, "// "), lines);
+        assert_eq!(toggle_comment(&["  //x".to_string()], "// "), vec!["  x"]);
+    }
+
+    #[test]
+    fn test_replace_spans() {
+        let lines = vec!["keep".to_string(), "(a".to_string(), "b)".to_string()];
+        let at = |line, col| LineCol { line, col };
+        let spans = [(at(1, 0)..at(1, 1), "\""), (at(2, 1)..at(2, 2), "\"")];
+        assert_eq!(
+            replace_spans(&lines, &spans),
+            (1..3, vec!["\"a".to_string(), "b\"".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_substitute() {
+        let lines = vec!["a-a".to_string(), "b".to_string(), "a".to_string()];
+        let (first, count) = substitute(&lines, "a", "x", false);
+        assert_eq!(first, ["x-a", "b", "x"]);
+        assert_eq!(count, 2);
+        let (global, count) = substitute(&lines, "a", "", true);
+        assert_eq!(global, ["-", "b", ""]);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_sort_lines() {
+        let lines: Vec<String> = ["b10", "a", "b9", "a", "x"]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            sort_lines(&lines, false, false, false).0,
+            ["a", "a", "b10", "b9", "x"]
+        );
+        assert_eq!(
+            sort_lines(&lines, true, true, false),
+            (vec!["x".into(), "b9".into(), "b10".into(), "a".into()], 1)
+        );
+        // Lines without numbers compare equal and keep their order
+        assert_eq!(
+            sort_lines(&lines, false, false, true).0,
+            ["a", "a", "x", "b9", "b10"]
+        );
+        assert_eq!(sort_lines(&lines, false, true, true).0, ["a", "b9", "b10"]);
+    }
+
+    #[test]
+    fn test_replace_word() {
+        let lines: Vec<String> = ["foo(foo_bar, foo)", "food foo"]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        let (replaced, count) = replace_word(&lines, "foo", "value", "_");
+        assert_eq!(replaced, ["value(foo_bar, value)", "food value"]);
+        assert_eq!(count, 3);
+        assert_eq!(replace_word(&lines, "fo", "x", "_").1, 0);
+    }
+
+    #[test]
+    fn test_number_lines() {
+        let lines: Vec<String> = ["a", "", "c"].iter().map(ToString::to_string).collect();
+        assert_eq!(number_lines(&lines, 1, ". "), ["1. a", "2. ", "3. c"]);
+        assert_eq!(number_lines(&lines, 8, " "), [" 8 a", " 9 ", "10 c"]);
+    }
+
+    #[test]
+    fn test_toggle_comment_at_column() {
+        let ragged: Vec<String> = ["    let a = 1;", "  b", "", "      c"]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        let skipped = toggle_comment_at(&ragged, "// ", 4, false);
+        assert_eq!(skipped, ["    // let a = 1;", "  b", "", "    //   c"]);
+        assert_eq!(toggle_comment_at(&skipped, "// ", 4, false), ragged);
+
+        let padded = toggle_comment_at(&ragged, "// ", 4, true);
+        assert_eq!(
+            padded,
+            ["    // let a = 1;", "  b //", "    //", "    //   c"]
+        );
+        assert_eq!(toggle_comment_at(&padded, "// ", 4, true), ragged);
+    }
+
+    #[test]
+    fn test_squeeze_blank_lines() {
+        let lines: Vec<String> = ["a", "", "", "  ", "b", " ", "", "c", ""]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            squeeze_blank_lines(&lines, true),
+            (
+                vec![
+                    "a".into(),
+                    "".into(),
+                    "b".into(),
+                    " ".into(),
+                    "c".into(),
+                    "".into()
+                ],
+                3
+            )
+        );
+        assert_eq!(
+            squeeze_blank_lines(&lines, false).0,
+            ["a", "", "  ", "b", " ", "", "c", ""]
+        );
+    }
+
+    #[test]
+    fn test_align_lines() {
+        let lines: Vec<String> = ["a = 1", "long_name = 2", "none", "é=3 = 4"]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            align_lines(&lines, "="),
+            ["a         = 1", "long_name = 2", "none", "é         =3 = 4"]
+        );
+        assert_eq!(align_lines(&lines, "=>"), lines);
+    }
+
+    #[test]
+    fn test_join_lines() {
+        let lines = vec![
+            "fn f(  ".to_string(),
+            "    x".to_string(),
+            "  )".to_string(),
+        ];
+        assert_eq!(join_lines(&lines), ("fn f( x)".to_string(), 7));
+        let lines = vec!["a".to_string(), String::new(), "b".to_string()];
+        assert_eq!(join_lines(&lines), ("a b".to_string(), 1));
+    }
+
+    #[test]
+    fn test_shift_lines() {
+        let lines = vec!["a".to_string(), String::new(), "\t  b".to_string()];
+        assert_eq!(shift_lines(&lines, 2, true), vec!["  a", "", "  \t  b"]);
+        assert_eq!(shift_lines(&lines, 2, false), vec!["a", "", "  b"]);
+        assert_eq!(shift_lines(&["   c".to_string()], 2, false), vec![" c"]);
+    }
+
+    #[test]
+    fn test_wrap_at_blank() {
+        assert_eq!(wrap_at_blank("  one two", 9, 10), None);
+        assert_eq!(
+            wrap_at_blank("  one two three", 15, 10),
+            Some(("  one two".to_string(), "  three".to_string(), 7))
+        );
+        assert_eq!(
+            wrap_at_blank("  one  two|", 10, 9),
+            Some(("  one".to_string(), "  two|".to_string(), 5))
+        );
+        assert_eq!(wrap_at_blank("  unbreakable", 13, 5), None);
+    }
+
+    #[test]
+    fn test_reflow_lines() {
+        let lines: Vec<String> = [
+            "    // the quick brown",
+            "    // fox jumps over the lazy dog",
+            "    //",
+            "  a longwordthatdoesnotfit b",
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+        assert_eq!(
+            reflow_lines(&lines, 20, "//"),
+            vec![
+                "    // the quick",
+                "    // brown fox",
+                "    // jumps over",
+                "    // the lazy dog",
+                "    //",
+                "  a",
+                "  longwordthatdoesnotfit",
+                "  b",
+            ]
+        );
+        assert_eq!(
+            reflow_lines(&lines[3..], 80, "//"),
+            vec!["  a longwordthatdoesnotfit b"]
+        );
+    }
+
+    #[test]
+    fn test_reindent_lines() {
+        let lines: Vec<String> = ["if x {", "y();", "  ", "}", "z"]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            reindent_lines(&lines, Some("  fn f() {"), 4),
+            vec!["    if x {", "        y();", "", "    }", "    z"]
+        );
+        assert_eq!(reindent_lines(&lines[3..], None, 4), vec!["}", "z"]);
+
+        let lines: Vec<String> = [
+            "match x {",
+            "Some(y) => f(",
+            "y, \"(\",",
+            "),",
+            "} else {",
+            "g()",
+            "}}",
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+        assert_eq!(
+            reindent_lines(&lines, None, 2),
+            vec![
+                "match x {",
+                "  Some(y) => f(",
+                "    y, \"(\",",
+                "  ),",
+                "} else {",
+                "  g()",
+                "}}"
+            ]
+        );
+    }
+}
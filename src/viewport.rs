@@ -1,16 +1,25 @@
 use crate::{
     bars::{
-        draw_bar, get_info_bar_content, get_notif_bar_content, INFO_BAR, NOTIFICATION_BAR,
-        NOTIFICATION_BAR_TEXT_X_LOCATION,
+        draw_bar, get_info_bar_content, get_notif_bar_content, info_bar, FileStatus,
+        NOTIFICATION_BAR, NOTIFICATION_BAR_TEXT_X_LOCATION,
     },
     cursor::Cursor,
-    BaseAction, Component, Modal, Result, Selection,
+    fold::FoldView,
+    grapheme,
+    search::SearchPattern,
+    sign::{SignKind, Signs},
+    theme::{Highlight, Theme},
+    BaseAction, Component, LineCol, Modal, Result, Selection,
+};
+use std::{
+    io::{self, Stdout, Write},
+    ops::Range,
 };
-use std::io::{self, Stdout, Write};
 
 use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
     execute,
-    style::{Color, ResetColor, SetBackgroundColor, SetForegroundColor},
+    style::{ResetColor, SetBackgroundColor, SetForegroundColor},
     terminal::{self, ClearType, LeaveAlternateScreen},
 };
 
@@ -18,6 +27,8 @@ const NO_OF_BARS: u8 = 2;
 pub const LINE_NUMBER_SEPARATOR_EMPTY_COLUMNS: usize = 2;
 pub const LINE_NUMBER_RESERVED_COLUMNS: usize = 5;
 pub const FIND_MODE_DIRECTION_SYMBOL_GAP: u16 = 1;
+/// Prompt shown in front of the command line
+pub const COMMAND_MODE_PROMPT: char = ':';
 
 #[derive(Debug)]
 pub struct ViewPort {
@@ -27,6 +38,26 @@ pub struct ViewPort {
     top_border: usize,
     bottom_border: usize,
     mode: Modal,
+    /// A headless viewport never touches the terminal, it only keeps track of the visible area
+    headless: bool,
+    /// Width of the column showing fold markers in front of the line numbers, zero hides it
+    fold_column: usize,
+    /// Width of the sign column in front of the line numbers, zero shows signs after them
+    sign_column: usize,
+    theme: Theme,
+    /// Column tinted on every line by `colorcolumn`, counted from one, zero disables it
+    color_column: usize,
+    /// Whether the line the cursor is on is highlighted
+    cursor_line: bool,
+    /// Keys of the command being typed, shown in the info bar
+    pending_command: String,
+    /// Positions of the cursors added besides the primary one, drawn as selected cells
+    extra_cursors: Vec<LineCol>,
+    /// Register a macro is being recorded into, shown in the info bar
+    recording: Option<char>,
+    status: FileStatus,
+    /// Line and byte range of the match previewed while typing a search
+    search_preview: Option<(usize, Range<usize>)>,
 }
 
 impl Component for ViewPort {
@@ -34,9 +65,9 @@ impl Component for ViewPort {
         println!("Executing Action at Viewport: {:?}", a);
         match a {
             BaseAction::ChangeMode(modal) => self.mode = *modal,
+            BaseAction::ScrollUp(dist) => self.scroll_up(*dist),
+            BaseAction::ScrollDown(dist) => self.scroll_down(*dist),
             _ => (),
-            BaseAction::MoveUp(dist) => self.scroll_up(*dist),
-            BaseAction::MoveDown(dist) => self.scroll_down(*dist),
         };
         Ok(())
     }
@@ -57,13 +88,153 @@ impl ViewPort {
         self.bottom_border += dist;
         self.top_border += dist;
     }
+    /// Number of rows displaying text, the remaining rows are taken by the bars
+    pub const fn text_rows(&self) -> usize {
+        (self.height as usize).saturating_sub(NO_OF_BARS as usize)
+    }
+    /// Scrolls the least distance required for `line` to be displayed
+    pub fn scroll_into_view(&mut self, line: usize) {
+        let last_visible = self.top_border + self.text_rows().saturating_sub(1);
+        if line < self.top_border {
+            self.scroll_up(self.top_border - line);
+        } else if line > last_visible {
+            self.scroll_down(line - last_visible);
+        }
+    }
+    /// Adapts the visible area to a new terminal size, keeping the top line in place
+    pub fn resize(&mut self, width: u16, height: u16, cursor_line: usize) {
+        self.width = width;
+        self.height = height;
+        self.bottom_border = self.top_border + height as usize;
+        self.scroll_into_view(cursor_line);
+    }
+    pub const fn top_border(&self) -> usize {
+        self.top_border
+    }
+    /// Scrolls the view for `top` to be its first line
+    pub fn scroll_to(&mut self, top: usize) {
+        match top >= self.top_border {
+            true => self.scroll_down(top - self.top_border),
+            false => self.scroll_up(self.top_border - top),
+        }
+    }
+    pub fn set_fold_column(&mut self, width: usize) {
+        self.fold_column = width;
+    }
+    pub fn set_sign_column(&mut self, width: usize) {
+        self.sign_column = width;
+    }
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+    pub fn set_color_column(&mut self, column: usize) {
+        self.color_column = column;
+    }
+    pub fn set_cursor_line(&mut self, enabled: bool) {
+        self.cursor_line = enabled;
+    }
+    pub fn set_pending_command(&mut self, keys: String) {
+        self.pending_command = keys;
+    }
+    pub fn set_extra_cursors(&mut self, cursors: Vec<LineCol>) {
+        self.extra_cursors = cursors;
+    }
+    pub fn set_recording(&mut self, register: Option<char>) {
+        self.recording = register;
+    }
+    pub fn set_search_preview(&mut self, preview: Option<(usize, Range<usize>)>) {
+        self.search_preview = preview;
+    }
+    pub fn set_status(&mut self, status: FileStatus) {
+        self.status = status;
+    }
+    /// Whether `line` is drawn as the cursor line, which gives way to visual selections and isn't
+    /// drawn while the cursor is on the bars
+    fn is_cursor_line(&self, line: usize, cursor: &Cursor) -> bool {
+        self.cursor_line
+            && (self.mode.is_normal() || self.mode.is_insert())
+            && line == cursor.line()
+    }
+    /// Screen column of the `colorcolumn` cell, `None` if it is disabled or off the screen
+    fn color_column_position(&self) -> Option<u16> {
+        let column = self.color_column.checked_sub(1)? + self.gutter_width();
+        u16::try_from(column)
+            .ok()
+            .filter(|&column| column < self.width)
+    }
+    /// Columns in front of the text, taken by the fold and sign columns and the line numbers
+    const fn gutter_width(&self) -> usize {
+        self.fold_column
+            + self.sign_column
+            + LINE_NUMBER_RESERVED_COLUMNS
+            + LINE_NUMBER_SEPARATOR_EMPTY_COLUMNS
+    }
+    /// Translates a position on the screen into the text position displayed there, positions within
+    /// the line number gutter map to the start of the line. Returns `None` for rows of the bars.
+    pub fn buffer_position(
+        &self,
+        column: u16,
+        row: u16,
+        buf: &[String],
+        folds: &FoldView,
+    ) -> Option<LineCol> {
+        if row as usize >= self.text_rows() {
+            return None;
+        }
+        let start = folds.display_line(self.top_border);
+        let line = (0..row).fold(start, |line, _| folds.next_line(line));
+        let column = (column as usize).saturating_sub(self.gutter_width());
+        let col = match buf.get(line) {
+            Some(text) => grapheme::col_at_width(text, column),
+            None => column,
+        };
+        Some(LineCol { line, col })
+    }
+    /// Starts or stops receiving mouse events from the terminal
+    pub fn set_mouse_capture(&mut self, enabled: bool) -> Result<()> {
+        if self.headless {
+            return Ok(());
+        }
+        if enabled {
+            execute!(self.terminal, EnableMouseCapture)?;
+        } else {
+            execute!(self.terminal, DisableMouseCapture)?;
+        }
+        Ok(())
+    }
+    /// Creates a viewport of a fixed size which isn't attached to the terminal
+    pub fn headless(width: u16, height: u16) -> Self {
+        Self {
+            terminal: io::stdout(),
+            width,
+            height,
+            top_border: 0,
+            bottom_border: height as usize,
+            mode: Modal::Normal,
+            headless: true,
+            fold_column: 0,
+            sign_column: 0,
+            theme: Theme::default(),
+            color_column: 0,
+            cursor_line: false,
+            pending_command: String::new(),
+            extra_cursors: Vec::new(),
+            recording: None,
+            status: FileStatus::default(),
+            search_preview: None,
+        }
+    }
 }
 
 impl Drop for ViewPort {
     fn drop(&mut self) {
+        if self.headless {
+            return;
+        }
         let _ = terminal::disable_raw_mode();
         let _ = execute!(
             self.terminal,
+            DisableMouseCapture,
             terminal::Clear(ClearType::All),
             LeaveAlternateScreen
         );
@@ -73,7 +244,8 @@ impl Drop for ViewPort {
 impl Default for ViewPort {
     fn default() -> Self {
         terminal::enable_raw_mode().expect("Couldn't start up terminal in raw mode.");
-        let terminal = io::stdout();
+        let mut terminal = io::stdout();
+        execute!(terminal, EnableMouseCapture).expect("Couldn't enable mouse capture.");
         let (width, height) = terminal::size().expect("Failed reading terminal information");
         Self {
             terminal,
@@ -82,65 +254,103 @@ impl Default for ViewPort {
             top_border: 0,
             bottom_border: height as usize,
             mode: Modal::Normal,
+            headless: false,
+            fold_column: 0,
+            sign_column: 0,
+            theme: Theme::default(),
+            color_column: 0,
+            cursor_line: false,
+            pending_command: String::new(),
+            extra_cursors: Vec::new(),
+            recording: None,
+            status: FileStatus::default(),
+            search_preview: None,
         }
     }
 }
 
 impl ViewPort {
-    pub fn update_viewport(&mut self, buf: &[String], cursor: &Cursor) -> Result<()> {
+    /// Redraws the text, the bars and the cursor. Matches of `search` are highlighted on lines
+    /// outside of the visual selection.
+    pub fn update_viewport(
+        &mut self,
+        buf: &[String],
+        cursor: &Cursor,
+        folds: &FoldView,
+        signs: &Signs,
+        search: Option<&str>,
+    ) -> Result<()> {
+        if self.headless {
+            return Ok(());
+        }
         // Prepare Viewport
-        (self.width, self.height) = terminal::size().expect("Failed reading terminal information");
         execute!(
             self.terminal,
             terminal::Clear(ClearType::All),
             crossterm::cursor::MoveTo(0, 0),
         )?;
 
-        // Calculate the range of lines to display
-        let start = self.top_border;
-        let end = self.bottom_border.saturating_sub(NO_OF_BARS as usize);
-        let visible_lines = end.saturating_sub(start) + 1;
-
-        // Create an iterator that pads with empty strings if out of bounds
-        let padded_iter = buf[start..]
-            .iter()
-            .map(|s| s.as_str())
-            .chain(std::iter::repeat(""))
-            .take(visible_lines);
-
-        // Write Content
-        for (i, line) in padded_iter.enumerate() {
-            let line_number = start + i;
+        // Calculate the first line to display, the terminal always shows its latest output
+        let start = if self.mode.is_terminal() {
+            buf.len().saturating_sub(self.text_rows())
+        } else {
+            folds.display_line(self.top_border)
+        };
+
+        // Write Content, a closed fold takes a single row and rows past the end stay empty
+        let mut line_number = start;
+        let mut cursor_row = 0;
+        for _ in 0..self.text_rows() {
             execute!(self.terminal, terminal::Clear(ClearType::CurrentLine))?;
-            self.create_line_numbers(line_number + 1, cursor.line())?;
-            self.draw_line(line, line_number, cursor)?;
+            self.create_fold_column(folds.marker(line_number))?;
+            let sign = signs.get(line_number);
+            self.create_sign_column(sign)?;
+            self.create_line_numbers(line_number, cursor, sign.filter(|_| self.sign_column == 0))?;
+            match (buf.get(line_number), folds.closed_fold(line_number)) {
+                (Some(line), Some(fold)) => self.draw_fold(line, fold.len())?,
+                (Some(line), None) => self.draw_line(line, line_number, cursor, search)?,
+                (None, _) => writeln!(self.terminal, "\r")?,
+            }
+            if line_number < cursor.line() {
+                cursor_row += 1;
+            }
+            line_number = folds.next_line(line_number);
         }
 
-        draw_bar(&INFO_BAR, |term_width, term_height| {
-            get_info_bar_content(term_width, &self.mode, cursor.pos)
+        draw_bar(&info_bar(self.theme.info_bar), |term_width, term_height| {
+            get_info_bar_content(
+                term_width,
+                &self.mode,
+                self.recording,
+                &self.pending_command,
+                cursor.pos,
+                &self.status,
+            )
         })?;
+        let on_command_line = self.mode.is_command() || self.mode.is_find();
+        let mut command_line_col = 0;
         draw_bar(&NOTIFICATION_BAR, |term_width, term_height| {
-            get_notif_bar_content()
+            let content = get_notif_bar_content();
+            if !on_command_line {
+                return content;
+            }
+            // Both the command prompt and the find direction symbol lead the text the cursor is in
+            let width = term_width.saturating_sub(NOTIFICATION_BAR_TEXT_X_LOCATION as usize);
+            let cursor_byte = cursor.col() + FIND_MODE_DIRECTION_SYMBOL_GAP as usize;
+            let (shown, col) = command_line_window(&content, cursor_byte, width);
+            command_line_col = col;
+            shown.to_string()
         })?;
 
         let (line, col) = match self.mode {
-            Modal::Find(_) => (
+            Modal::Find(_) | Modal::Command => (
                 self.bottom_border as u16,
-                cursor.col() as u16
-                    + NOTIFICATION_BAR_TEXT_X_LOCATION
-                    + FIND_MODE_DIRECTION_SYMBOL_GAP,
+                command_line_col as u16 + NOTIFICATION_BAR_TEXT_X_LOCATION,
             ),
-            Modal::Command => (
-                self.bottom_border as u16,
-                cursor.col() as u16 + NOTIFICATION_BAR_TEXT_X_LOCATION,
+            _ => (
+                cursor_row,
+                (cursor_column(buf, cursor) + self.gutter_width()) as u16,
             ),
-            _ => {
-                let line = (cursor.line().saturating_sub(self.top_border)) as u16;
-                let col = cursor.col() as u16
-                    + LINE_NUMBER_RESERVED_COLUMNS as u16
-                    + LINE_NUMBER_SEPARATOR_EMPTY_COLUMNS as u16;
-                (line, col)
-            }
         };
 
         execute!(self.terminal, crossterm::cursor::MoveTo(col, line))?;
@@ -148,9 +358,51 @@ impl ViewPort {
         Ok(())
     }
 
-    fn create_line_numbers(&mut self, line_number: usize, cursor_line: usize) -> Result<()> {
-        execute!(self.terminal, SetForegroundColor(Color::Green))?;
-        let rel_line_number = (line_number as i64 - cursor_line as i64 - 1).abs();
+    fn create_fold_column(&mut self, marker: Option<char>) -> Result<()> {
+        if self.fold_column == 0 {
+            return Ok(());
+        }
+        self.set_colors(self.theme.fold)?;
+        print!(
+            "{marker:<width$}",
+            marker = marker.unwrap_or(' '),
+            width = self.fold_column
+        );
+        execute!(self.terminal, ResetColor)?;
+        Ok(())
+    }
+
+    fn create_sign_column(&mut self, sign: Option<SignKind>) -> Result<()> {
+        if self.sign_column == 0 {
+            return Ok(());
+        }
+        if let Some(sign) = sign {
+            execute!(self.terminal, SetForegroundColor(sign.color()))?;
+        }
+        print!(
+            "{symbol:<width$}",
+            symbol = sign.map_or(' ', SignKind::symbol),
+            width = self.sign_column
+        );
+        execute!(self.terminal, ResetColor)?;
+        Ok(())
+    }
+
+    /// Draws the line number followed by the separator, the sign of the line takes the first
+    /// column of the separator
+    fn create_line_numbers(
+        &mut self,
+        line: usize,
+        cursor: &Cursor,
+        sign: Option<SignKind>,
+    ) -> Result<()> {
+        if self.is_cursor_line(line, cursor) {
+            self.set_colors(self.theme.cursor_line_number)?;
+        } else {
+            self.set_colors(self.theme.line_number)?;
+        }
+        let line_number = line + 1;
+        let rel_line_number = (line_number as i64 - cursor.line() as i64 - 1).abs();
         let line_number = if rel_line_number == 0 {
             line_number as i64
         } else {
@@ -158,12 +410,96 @@ impl ViewPort {
         };
 
         print!(
-            "{line_number:>width$}{separator}",
+            "{line_number:>width$}",
             line_number = line_number,
             width = LINE_NUMBER_RESERVED_COLUMNS,
-            separator = " ".repeat(LINE_NUMBER_SEPARATOR_EMPTY_COLUMNS)
         );
         execute!(self.terminal, ResetColor)?;
+        let mut separator = LINE_NUMBER_SEPARATOR_EMPTY_COLUMNS;
+        if let Some(sign) = sign {
+            execute!(self.terminal, SetForegroundColor(sign.color()))?;
+            print!("{}", sign.symbol());
+            execute!(self.terminal, ResetColor)?;
+            separator -= 1;
+        }
+        print!("{}", " ".repeat(separator));
+        Ok(())
+    }
+
+    /// Draws the row standing in for a closed fold, its first line followed by the hidden ones
+    fn draw_fold(&mut self, first_line: &str, len: usize) -> Result<()> {
+        self.set_colors(self.theme.fold)?;
+        write!(self.terminal, "+--{len:>3} lines: {}\r", first_line.trim())?;
+        execute!(self.terminal, ResetColor)?;
+        writeln!(self.terminal)?;
+        Ok(())
+    }
+
+    fn set_colors(&mut self, colors: Highlight) -> Result<()> {
+        execute!(
+            self.terminal,
+            SetForegroundColor(colors.fg),
+            SetBackgroundColor(colors.bg)
+        )?;
+        Ok(())
+    }
+
+    /// Tints the background of the `colorcolumn` cell of the line just written, the character
+    /// within it is written again to keep it
+    fn draw_color_column(&mut self, line: &str) -> Result<()> {
+        let Some(position) = self.color_column_position() else {
+            return Ok(());
+        };
+        let col = grapheme::col_at_width(line, self.color_column - 1);
+        let cell = match &line[col..] {
+            "" => " ",
+            // Within a wide character, which is left alone
+            _ if grapheme::display_width(&line[..col]) < self.color_column - 1 => return Ok(()),
+            rest => &rest[..grapheme::next_boundary(rest, 0)],
+        };
+        execute!(
+            self.terminal,
+            crossterm::cursor::MoveToColumn(position),
+            SetBackgroundColor(self.theme.color_column.bg)
+        )?;
+        write!(self.terminal, "{cell}")?;
+        execute!(self.terminal, ResetColor)?;
+        write!(self.terminal, "\r")?;
+        Ok(())
+    }
+
+    /// Writes the line in the `base` colors with the `matches` highlighted in the `highlight` ones
+    fn draw_matches(
+        &mut self,
+        line: &str,
+        matches: &[Range<usize>],
+        highlight: Highlight,
+        base: Highlight,
+    ) -> Result<()> {
+        let mut written = 0;
+        for found in matches {
+            write!(self.terminal, "{}", &line[written..found.start])?;
+            self.set_colors(highlight)?;
+            write!(self.terminal, "{}", &line[found.clone()])?;
+            self.set_colors(base)?;
+            written = found.end;
+        }
+        write!(self.terminal, "{}", &line[written..])?;
+        Ok(())
+    }
+
+    /// Draws the line with the byte range `selected` in the colors of the selection
+    fn draw_selected(&mut self, line: &str, selected: Range<usize>) -> Result<()> {
+        // Write line - before Selection
+        write!(self.terminal, "{}", &line[..selected.start])?;
+
+        // Write Whole Selection
+        self.set_colors(self.theme.selection)?;
+        write!(self.terminal, "{}", &line[selected.clone()])?;
+        execute!(self.terminal, ResetColor)?;
+
+        // Print last line - after selection
+        write!(self.terminal, "{}\r", &line[selected.end..])?;
         Ok(())
     }
 
@@ -172,6 +508,7 @@ impl ViewPort {
         line: impl AsRef<str>,
         absolute_ln: usize,
         cursor: &Cursor,
+        search: Option<&str>,
     ) -> Result<()> {
         let line = line.as_ref();
         let selection = Selection::from(cursor).normalized();
@@ -182,15 +519,29 @@ impl ViewPort {
             || absolute_ln > selection.start.line
                 && (absolute_ln < selection.end.line.saturating_sub(1) && self.mode.is_visual());
 
+        // Selections and search matches keep their own colors over the color column
+        let column = self.color_column.wrapping_sub(1);
+        let mut column_covered = self.color_column == 0;
+
         // Decide on which parts to highlight
         if highlight_whole_line {
-            execute!(
-                self.terminal,
-                SetBackgroundColor(Color::White),
-                SetForegroundColor(Color::Black)
-            )?;
+            column_covered = true;
+            self.set_colors(self.theme.selection)?;
             write!(self.terminal, "{}\r", line)?;
             execute!(self.terminal, ResetColor)?;
+        } else if self.mode.is_visual_block() && line_in_highlight_bounds {
+            // The columns of the block, moved out of characters they cut through
+            let cols = selection.block_cols();
+            let mut start_col = cols.start.min(line.len());
+            let mut end_col = cols.end.min(line.len());
+            while !line.is_char_boundary(start_col) {
+                start_col -= 1;
+            }
+            while !line.is_char_boundary(end_col) {
+                end_col += 1;
+            }
+            column_covered |= (start_col..end_col).contains(&column);
+            self.draw_selected(line, start_col..end_col)?;
         } else if self.mode.is_visual() && line_in_highlight_bounds {
             let start_col = if absolute_ln == selection.start.line {
                 selection.start.col
@@ -203,25 +554,192 @@ impl ViewPort {
                 line.len()
             };
 
-            // Write line - before Selection
-            write!(self.terminal, "{}", &line[..start_col])?;
-
-            // Write Whole Selection
-            execute!(
-                self.terminal,
-                SetBackgroundColor(Color::White),
-                SetForegroundColor(Color::Black)
-            )?;
-            write!(self.terminal, "{}", &line[start_col..end_col])?;
-            execute!(self.terminal, ResetColor)?;
-
-            // Print last line - after selection
-            write!(self.terminal, "{}\r", &line[end_col..])?;
+            column_covered |= (start_col..end_col).contains(&column);
+            self.draw_selected(line, start_col..end_col)?;
         } else {
-            write!(self.terminal, "{}\r", line)?;
+            // The cursor line is tinted up to the end of the row
+            let (base, fill) = if self.is_cursor_line(absolute_ln, cursor) {
+                let text_width = (self.width as usize).saturating_sub(self.gutter_width());
+                (
+                    self.theme.cursor_line,
+                    text_width.saturating_sub(grapheme::display_width(line)),
+                )
+            } else {
+                (Highlight::PLAIN, 0)
+            };
+            self.set_colors(base)?;
+            let mut cursor_cells: Vec<_> = self
+                .extra_cursors
+                .iter()
+                .filter(|pos| pos.line == absolute_ln && pos.col < line.len())
+                .filter(|pos| line.is_char_boundary(pos.col))
+                .map(|pos| pos.col..grapheme::next_boundary(line, pos.col))
+                .collect();
+            cursor_cells.sort_by_key(|cell| cell.start);
+            let preview = self
+                .search_preview
+                .clone()
+                .filter(|(line, _)| *line == absolute_ln);
+            if !cursor_cells.is_empty() {
+                self.draw_matches(line, &cursor_cells, self.theme.selection, base)?;
+            } else if let Some((_, found)) = preview {
+                column_covered |= found.contains(&column);
+                self.draw_matches(line, &[found], self.theme.inc_search, base)?;
+            } else if let Some(pattern) = search.filter(|pattern| !pattern.is_empty()) {
+                let matches = SearchPattern::parse(pattern).matches(line);
+                column_covered |= matches.iter().any(|found| found.contains(&column));
+                self.draw_matches(line, &matches, self.theme.search, base)?;
+            } else {
+                write!(self.terminal, "{line}")?;
+            }
+            write!(self.terminal, "{}", " ".repeat(fill))?;
+            execute!(self.terminal, ResetColor)?;
+            write!(self.terminal, "\r")?;
+        }
+        if !column_covered {
+            self.draw_color_column(line)?;
         }
 
         writeln!(self.terminal)?;
         Ok(())
     }
 }
+
+/// Terminal column of the cursor within the text, wide characters in front of it taking two. Past
+/// the end of the line, in virtual space, every column counts as one.
+fn cursor_column(buf: &[String], cursor: &Cursor) -> usize {
+    match buf.get(cursor.line()) {
+        Some(line) if cursor.col() > line.len() => {
+            grapheme::display_width(line) + cursor.col() - line.len()
+        }
+        Some(line) => line
+            .get(..cursor.col())
+            .map_or(cursor.col(), grapheme::display_width),
+        None => cursor.col(),
+    }
+}
+
+/// The part of the command line shown in a bar `width` columns wide, and the column of the cursor
+/// at byte `cursor` within it. A line too long for the bar scrolls horizontally, keeping the cursor
+/// and the column it takes past the end of the text in view.
+fn command_line_window(line: &str, cursor: usize, width: usize) -> (&str, usize) {
+    let cursor = cursor.min(line.len());
+    let cursor_width = grapheme::display_width(&line[..cursor]);
+    let start = grapheme::col_at_width(line, (cursor_width + 1).saturating_sub(width));
+    let start = match grapheme::display_width(&line[..start]) + width > cursor_width {
+        true => start,
+        false => grapheme::next_boundary(line, start),
+    };
+    let shown = &line[start..];
+    let shown = &shown[..grapheme::col_at_width(shown, width)];
+    (
+        shown,
+        cursor_width - grapheme::display_width(&line[..start]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_line_window() {
+        assert_eq!(command_line_window(":w", 2, 20), (":w", 2));
+        assert_eq!(command_line_window("/abc", 2, 20), ("/abc", 2));
+
+        let command = ":s/a long pattern/its replacement/g";
+        let (shown, col) = command_line_window(command, command.len(), 20);
+        assert_eq!((shown, col), ("n/its replacement/g", 19));
+        assert!(command.ends_with(shown));
+
+        let (shown, col) = command_line_window(command, 3, 20);
+        assert_eq!((shown, col), (":s/a long pattern/it", 3));
+    }
+
+    #[test]
+    fn test_resize_keeps_cursor_line_visible() {
+        let mut viewport = ViewPort::headless(80, 24);
+        viewport.scroll_into_view(30);
+        let top = viewport.top_border();
+
+        viewport.resize(100, 40, 30);
+        assert_eq!((viewport.width, viewport.height), (100, 40));
+        assert_eq!(viewport.top_border(), top);
+
+        viewport.resize(100, 5, 30);
+        assert_eq!(viewport.top_border() + viewport.text_rows() - 1, 30);
+    }
+
+    #[test]
+    fn test_cursor_line_only_in_normal_and_insert() {
+        let mut viewport = ViewPort::headless(80, 24);
+        let mut cursor = Cursor::default();
+        cursor.set_line(3);
+        assert!(!viewport.is_cursor_line(3, &cursor));
+        viewport.set_cursor_line(true);
+        assert!(viewport.is_cursor_line(3, &cursor));
+        assert!(!viewport.is_cursor_line(2, &cursor));
+        viewport
+            .execute_action(&BaseAction::ChangeMode(Modal::VisualLine))
+            .unwrap();
+        assert!(!viewport.is_cursor_line(3, &cursor));
+    }
+
+    #[test]
+    fn test_color_column_position() {
+        let mut viewport = ViewPort::headless(80, 24);
+        assert_eq!(viewport.color_column_position(), None);
+        viewport.set_color_column(1);
+        assert_eq!(viewport.color_column_position(), Some(7));
+        viewport.set_color_column(74);
+        assert_eq!(viewport.color_column_position(), None);
+    }
+
+    #[test]
+    fn test_buffer_position() {
+        let mut viewport = ViewPort::headless(80, 24);
+        viewport.scroll_into_view(40);
+        let top = viewport.top_border();
+        let folds = FoldView::default();
+        assert_eq!(
+            viewport.buffer_position(10, 2, &[], &folds),
+            Some(LineCol {
+                line: top + 2,
+                col: 3
+            })
+        );
+        assert_eq!(
+            viewport
+                .buffer_position(3, 0, &[], &folds)
+                .map(|pos| pos.col),
+            Some(0)
+        );
+        assert_eq!(viewport.buffer_position(10, 22, &[], &folds), None);
+
+        viewport.set_fold_column(2);
+        assert_eq!(
+            viewport
+                .buffer_position(10, 0, &[], &folds)
+                .map(|pos| pos.col),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_wide_characters() {
+        let viewport = ViewPort::headless(80, 24);
+        let folds = FoldView::default();
+        let buf = vec!["ab漢字c".to_string()];
+        let gutter = viewport.gutter_width() as u16;
+        let col_at = |column| viewport.buffer_position(gutter + column, 0, &buf, &folds);
+        assert_eq!(col_at(3).map(|pos| pos.col), Some(2));
+        assert_eq!(col_at(4).map(|pos| pos.col), Some(5));
+        assert_eq!(col_at(6).map(|pos| pos.col), Some(8));
+
+        let mut cursor = Cursor::default();
+        cursor.set_col(5);
+        assert_eq!(cursor_column(&buf, &cursor), 4);
+        cursor.set_col(8);
+        assert_eq!(cursor_column(&buf, &cursor), 6);
+    }
+}